@@ -0,0 +1,121 @@
+//! Benchmarks of the core cipher.
+//!
+//! Run them with `cargo bench --features bench`,
+//! the `bench` feature exposes the round transformations measured in isolation.
+//! Criterion keeps the results of the previous run in `target/criterion` and reports the change,
+//! so run the benchmarks before and after a change to catch performance regressions.
+
+use std::hint::black_box;
+use criterion::{
+    criterion_group,
+    criterion_main,
+    BenchmarkId,
+    Criterion,
+    Throughput,
+};
+use tinyaes::{
+    Aes128,
+    AESCore,
+    AESKey,
+};
+use tinyaes::aes_core::bench;
+
+fn keys() -> [(&'static str, AESKey); 3] {
+    [
+        ("AES-128", AESKey::AES128([0x2b; 16])),
+        ("AES-192", AESKey::AES192([0x8e; 24])),
+        ("AES-256", AESKey::AES256([0x60; 32])),
+    ]
+}
+
+fn block(c: &mut Criterion) {
+    let mut group = c.benchmark_group("block");
+    group.throughput(Throughput::Bytes(16));
+
+    for (name, key) in keys() {
+        let core = AESCore::new(key);
+        let block: [u8; 16] = [0x32; 16];
+
+        group.bench_with_input(BenchmarkId::new("encrypt", name), &block, |b, block| {
+            b.iter(|| core.encrypt(black_box(block)))
+        });
+        group.bench_with_input(BenchmarkId::new("decrypt", name), &block, |b, block| {
+            b.iter(|| core.decrypt(black_box(block)))
+        });
+    }
+
+    group.finish();
+}
+
+fn aes128(c: &mut Criterion) {
+    // the specialized AES-128 against the generic core with the same key
+    let mut group = c.benchmark_group("aes128");
+    group.throughput(Throughput::Bytes(16));
+
+    let aes = Aes128::new([0x2b; 16]);
+    let core = AESCore::new(AESKey::AES128([0x2b; 16]));
+    let block: [u8; 16] = [0x32; 16];
+
+    group.bench_with_input("encrypt/specialized", &block, |b, block| b.iter(|| aes.encrypt(black_box(block))));
+    group.bench_with_input("encrypt/generic", &block, |b, block| b.iter(|| core.encrypt(black_box(block))));
+    group.bench_with_input("decrypt/specialized", &block, |b, block| b.iter(|| aes.decrypt(black_box(block))));
+    group.bench_with_input("decrypt/generic", &block, |b, block| b.iter(|| core.decrypt(black_box(block))));
+
+    group.finish();
+}
+
+fn bulk_ecb(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ecb_1mib");
+    group.throughput(Throughput::Bytes(1 << 20));
+
+    for (name, key) in keys() {
+        let core = AESCore::new(key);
+        let mut data: Vec<u8> = vec![0x5a; 1 << 20];
+
+        group.bench_function(BenchmarkId::new("encrypt", name), |b| {
+            b.iter(|| {
+                for block in data.chunks_exact_mut(16) {
+                    core.encrypt_in_place(block.try_into().unwrap());
+                }
+            })
+        });
+        group.bench_function(BenchmarkId::new("decrypt", name), |b| {
+            b.iter(|| {
+                for block in data.chunks_exact_mut(16) {
+                    core.decrypt_in_place(block.try_into().unwrap());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+fn key_expansion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("key_expansion");
+
+    for (name, key) in keys() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &key, |b, key| {
+            b.iter(|| AESCore::new(black_box(*key)))
+        });
+    }
+
+    group.finish();
+}
+
+fn round_transformations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("round");
+    // the columns of the state, each a big-endian word
+    let mut state: [u32; 4] = [0xd4bf5d30, 0xe0b452ae, 0xb84111f1, 0x1e2798e5];
+
+    group.bench_function("mix_columns", |b| b.iter(|| bench::mix_columns(black_box(&mut state))));
+    group.bench_function("inv_mix_columns", |b| b.iter(|| bench::inv_mix_columns(black_box(&mut state))));
+    group.bench_function("shift_rows", |b| b.iter(|| bench::shift_rows(black_box(&mut state))));
+    group.bench_function("sub_bytes", |b| b.iter(|| bench::sub_bytes(black_box(&mut state))));
+    group.bench_function("sub_shift", |b| b.iter(|| bench::sub_shift(black_box(&mut state))));
+
+    group.finish();
+}
+
+criterion_group!(benches, block, aes128, bulk_ecb, key_expansion, round_transformations);
+criterion_main!(benches);
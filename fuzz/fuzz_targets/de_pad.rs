@@ -0,0 +1,38 @@
+//! A fuzz target feeding arbitrary inputs to the de-padding functions of every padding type.
+//!
+//! The de-padding must never panic on malformed input, only return `Ok` or `Err`.
+//! Run it from the repository root with `cargo +nightly fuzz run de_pad`
+//! (requires `cargo install cargo-fuzz`), crashing inputs are saved to `fuzz/artifacts/de_pad`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tinyaes::{
+    Padding,
+    PaddingTypes,
+};
+
+fuzz_target!(|data: &[u8]| {
+    for padding_type in [
+        PaddingTypes::PKCS7,
+        PaddingTypes::ISO78164,
+        PaddingTypes::X923,
+        PaddingTypes::ZeroPadding,
+        PaddingTypes::None,
+    ] {
+        let padding = Padding::new(padding_type);
+
+        // the de-padded data is always a strict prefix of the input
+        if let Ok(de_padded) = padding.de_pad(data) {
+            assert!(de_padded.len() < data.len() && data.starts_with(de_padded));
+        }
+        if let Ok(de_padded) = padding.de_pad_buffer(data) {
+            assert!(de_padded.len() < data.len() && data.starts_with(de_padded));
+        }
+        if let Some((&len, block)) = data.split_first() {
+            if let Ok(de_padded) = padding.de_pad_with_len(block, len as usize) {
+                assert!(de_padded.len() == len as usize && block.starts_with(de_padded));
+            }
+        }
+    }
+});
@@ -0,0 +1,293 @@
+//! A module containing the trait shared by the authenticated encryption modes.
+
+
+
+
+
+// IMPORTS
+
+use crate::gcm::{
+    AeadError,
+    Gcm,
+};
+use crate::gcm_siv::GcmSiv;
+use crate::ocb::Ocb;
+use crate::siv::Siv;
+use crate::util::{
+    ct_eq,
+    wipe,
+};
+
+
+
+
+
+// TRAITS
+
+/// An authenticated encryption with associated data (AEAD) algorithm.
+/// It allows code to be written once for every authenticated mode.
+/// The layout of the sealed output (where the tag is placed) is defined by the mode.
+pub trait Aead {
+    /// The length of the nonce in bytes.
+    const NONCE_LEN: usize;
+    /// The length of the authentication tag in bytes.
+    const TAG_LEN: usize;
+
+    /// Encrypts and authenticates the plaintext, and authenticates the associated data.
+    /// Returns the ciphertext together with the tag, or `AeadError::InvalidNonceLength`
+    /// if the nonce isn't `NONCE_LEN` bytes long.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError>;
+
+    /// Verifies and decrypts the output of `Aead::seal`.
+    /// Returns the plaintext, `AeadError::InvalidNonceLength` if the nonce isn't `NONCE_LEN` bytes long,
+    /// `AeadError::InvalidLength` if the input is shorter than the tag
+    /// or `AeadError::AuthenticationFailed` if the tag doesn't match.
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError>;
+}
+
+impl Aead for Gcm {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+
+    /// The output is the ciphertext followed by the tag.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let nonce: [u8; 12] = nonce.try_into().map_err(|_| AeadError::InvalidNonceLength)?;
+        self.encrypt(nonce, aad, plaintext)
+    }
+
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let nonce: [u8; 12] = nonce.try_into().map_err(|_| AeadError::InvalidNonceLength)?;
+        if ciphertext.len() < 16 {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+        self.decrypt(nonce, aad, ciphertext, tag.try_into().unwrap())
+    }
+}
+
+impl Aead for GcmSiv {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+
+    /// The output is the ciphertext followed by the tag.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let nonce: [u8; 12] = nonce.try_into().map_err(|_| AeadError::InvalidNonceLength)?;
+        self.encrypt(nonce, aad, plaintext)
+    }
+
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        let nonce: [u8; 12] = nonce.try_into().map_err(|_| AeadError::InvalidNonceLength)?;
+        self.decrypt(nonce, aad, ciphertext)
+    }
+}
+
+impl Aead for Ocb {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+
+    /// The output is the ciphertext followed by the full 16-byte tag, whatever the tag length of the instance is.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        if nonce.len() != 12 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        self.with_full_tag().encrypt(nonce, aad, plaintext)
+    }
+
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        if nonce.len() != 12 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        self.with_full_tag().decrypt(nonce, aad, ciphertext)
+    }
+}
+
+impl Aead for Siv {
+    const NONCE_LEN: usize = 16;
+    const TAG_LEN: usize = 16;
+
+    /// The nonce is the last associated data component, and the output is the synthetic IV followed by the ciphertext.
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        if nonce.len() != 16 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        self.encrypt(&[aad, nonce], plaintext)
+    }
+
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        if nonce.len() != 16 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        self.decrypt(&[aad, nonce], ciphertext)
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub(crate) fn verify_or_wipe(expected_tag: &[u8], received_tag: &[u8], buffer: &mut [u8]) -> Result<(), AeadError> {
+    //! Compares the tags in constant time and wipes the buffer if they don't match.
+    //! Every authenticated mode ends its decryption in place with it, so none of them can leave
+    //! unverified plaintext (or a ciphertext that is decrypted only after the verification) in the buffer.
+    //! # Arguments
+    //! * `expected_tag` - The tag computed by the mode.
+    //! * `received_tag` - The tag received with the message.
+    //! * `buffer` - The buffer being decrypted in place.
+    //! # Errors
+    //! * AeadError::AuthenticationFailed - The tags don't match, the buffer was wiped.
+
+    if !ct_eq(expected_tag, received_tag) {
+        wipe(buffer);
+        return Err(AeadError::AuthenticationFailed);
+    }
+
+    Ok(())
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::{
+        AESCore,
+        AESKey,
+    };
+
+    fn check_aead<A: Aead>(aead: &A) {
+        //! Checks the round trip, the output length and the rejection of tampered inputs with any AEAD.
+
+        let nonce: Vec<u8> = (0..A::NONCE_LEN as u8).collect();
+        let aad: &[u8] = b"header";
+        let plaintext: &[u8] = b"a message of a few blocks, sealed generically";
+
+        let sealed = aead.seal(&nonce, aad, plaintext).unwrap();
+        assert_eq!(sealed.len(), plaintext.len() + A::TAG_LEN);
+        assert_eq!(aead.open(&nonce, aad, &sealed).unwrap(), plaintext);
+        assert!(aead.open(&nonce, aad, &aead.seal(&nonce, aad, &[]).unwrap()).unwrap().is_empty());
+
+        let mut tampered = sealed.clone();
+        tampered[3] ^= 0x01;
+        assert_eq!(aead.open(&nonce, aad, &tampered), Err(AeadError::AuthenticationFailed));
+        assert_eq!(aead.open(&nonce, b"other", &sealed), Err(AeadError::AuthenticationFailed));
+        let mut other_nonce = nonce.clone();
+        other_nonce[0] ^= 0x01;
+        assert_eq!(aead.open(&other_nonce, aad, &sealed), Err(AeadError::AuthenticationFailed));
+
+        assert_eq!(aead.open(&nonce, aad, &sealed[..(A::TAG_LEN - 1)]), Err(AeadError::InvalidLength));
+        assert_eq!(aead.seal(&nonce[1..], aad, plaintext), Err(AeadError::InvalidNonceLength));
+        assert_eq!(aead.open(&[0; 20], aad, &sealed), Err(AeadError::InvalidNonceLength));
+    }
+
+    #[test]
+    fn verify_or_wipe_buffer() {
+        //! Test that the buffer is kept when the tags match and wiped when they don't
+
+        let mut buffer: [u8; 20] = [0x5c; 20];
+        assert_eq!(verify_or_wipe(&[0x01; 16], &[0x01; 16], &mut buffer), Ok(()));
+        assert_eq!(buffer, [0x5c; 20]);
+
+        let mut received_tag: [u8; 16] = [0x01; 16];
+        received_tag[15] ^= 0x80;
+        assert_eq!(verify_or_wipe(&[0x01; 16], &received_tag, &mut buffer), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 20]);
+
+        // a truncated tag doesn't match the full one
+        let mut buffer: [u8; 20] = [0x5c; 20];
+        assert_eq!(verify_or_wipe(&[0x01; 16], &[0x01; 12], &mut buffer), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 20]);
+    }
+
+    #[test]
+    fn tampered_in_place() {
+        //! Test that every AEAD mode returns an error for a tampered ciphertext decrypted in place
+        //! and leaves the buffer zeroed
+
+        let plaintext: &[u8] = b"a message longer than a block";
+        let tamper = |buffer: &[u8]| {
+            let mut tampered = buffer.to_vec();
+            tampered[3] ^= 0x01;
+            tampered
+        };
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128([0x11; 16])));
+        let sealed = gcm.seal([0x22; 12], b"header", plaintext).unwrap();
+        let mut buffer = tamper(&sealed.ciphertext);
+        assert_eq!(gcm.decrypt_in_place_detached([0x22; 12], b"header", &mut buffer, &sealed.tag), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 29]);
+
+        let gcm_siv = GcmSiv::new(AESCore::new(AESKey::AES256([0x66; 32]))).unwrap();
+        let output = gcm_siv.encrypt([0x77; 12], b"header", plaintext).unwrap();
+        let mut buffer = tamper(&output[..29]);
+        assert_eq!(gcm_siv.decrypt_in_place_detached([0x77; 12], b"header", &mut buffer, &output[29..].try_into().unwrap()), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 29]);
+
+        let ocb = Ocb::new(AESCore::new(AESKey::AES128([0x88; 16])), 16);
+        let output = ocb.encrypt(&[0x99; 12], b"header", plaintext).unwrap();
+        let mut buffer = tamper(&output[..29]);
+        assert_eq!(ocb.decrypt_in_place_detached(&[0x99; 12], b"header", &mut buffer, &output[29..]), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 29]);
+
+        let siv = Siv::new(AESCore::new(AESKey::AES128([0xaa; 16])), AESCore::new(AESKey::AES128([0xbb; 16])));
+        let output = siv.encrypt(&[b"header"], plaintext).unwrap();
+        let mut buffer = tamper(&output[16..]);
+        assert_eq!(siv.decrypt_in_place_detached(&[b"header"], &output[..16].try_into().unwrap(), &mut buffer), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 29]);
+    }
+
+    #[test]
+    fn gcm() {
+        //! Test the AEAD trait with GCM, the output being the ciphertext followed by the tag
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128([0x11; 16])));
+        check_aead(&gcm);
+
+        let message = Gcm::seal(&gcm, [0x22; 12], b"aad", b"plaintext").unwrap();
+        assert_eq!(Aead::seal(&gcm, &[0x22; 12], b"aad", b"plaintext").unwrap(), [message.ciphertext, message.tag.to_vec()].concat());
+    }
+
+    #[test]
+    fn gcm_siv() {
+        //! Test the AEAD trait with AES-GCM-SIV, the output being the ciphertext followed by the tag
+
+        let gcm_siv = GcmSiv::new(AESCore::new(AESKey::AES256([0x66; 32]))).unwrap();
+        check_aead(&gcm_siv);
+
+        assert_eq!(gcm_siv.seal(&[0x77; 12], b"aad", b"plaintext").unwrap(), gcm_siv.encrypt([0x77; 12], b"aad", b"plaintext").unwrap());
+    }
+
+    #[test]
+    fn ocb() {
+        //! Test the AEAD trait with AES-OCB3, the output being the ciphertext followed by the full tag
+
+        let ocb = Ocb::new(AESCore::new(AESKey::AES128([0x88; 16])), 16);
+        check_aead(&ocb);
+        assert_eq!(ocb.seal(&[0x99; 12], b"aad", b"plaintext").unwrap(), ocb.encrypt(&[0x99; 12], b"aad", b"plaintext").unwrap());
+
+        // an instance with a truncated tag still seals with the full tag
+        let truncated = Ocb::new(AESCore::new(AESKey::AES128([0x88; 16])), 8);
+        check_aead(&truncated);
+        assert_eq!(truncated.seal(&[0x99; 12], b"aad", b"plaintext").unwrap(), ocb.seal(&[0x99; 12], b"aad", b"plaintext").unwrap());
+    }
+
+    #[test]
+    fn siv() {
+        //! Test the AEAD trait with AES-SIV, the nonce being the last associated data component
+
+        let siv = Siv::new(AESCore::new(AESKey::AES128([0x33; 16])), AESCore::new(AESKey::AES128([0x44; 16])));
+        check_aead(&siv);
+
+        assert_eq!(siv.seal(&[0x55; 16], b"aad", b"plaintext").unwrap(), siv.encrypt(&[b"aad", &[0x55; 16]], b"plaintext").unwrap());
+    }
+}
@@ -1,4 +1,7 @@
 //! A module containing the core of the AES algorithm.
+//!
+//! The S-Box is looked up in a table (the `sbox-table` feature) or computed in constant time (the `sbox-computed` feature).
+//! The computed S-Box takes precedence, it is used whenever `sbox-computed` is enabled, even alongside `sbox-table`.
 
 
 
@@ -7,7 +10,6 @@
 // DISABLED LINTS
 
 #![allow(clippy::needless_range_loop)]  // better readability
-#![allow(clippy::mut_range_bound)]  // we are aware that we aren't mutating the range bound, that was never the intention
 
 
 
@@ -15,12 +17,252 @@
 
 // IMPORTS
 
+use core::fmt;
+use core::hash::{
+    Hash,
+    Hasher,
+};
+use core::mem;
 use core::ops::{
     Index,
     IndexMut,
     Range,
     RangeFrom
 };
+use crate::kdf::derive_subkey;
+use crate::util::{
+    blocks,
+    blocks_mut,
+    ct_eq,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use crate::simd;
+
+
+
+
+
+// MODULES
+
+/// The internal round transformations, exposed for benchmarking.
+/// Not a part of the stable API.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench {
+    use super::AESCore;
+
+    pub fn mix_columns(state: &mut [u32; 4]) {
+        //! Mixes the columns of the state.
+
+        AESCore::mix_columns(state);
+    }
+
+    pub fn inv_mix_columns(state: &mut [u32; 4]) {
+        //! Inverse mixes the columns of the state.
+
+        AESCore::inv_mix_columns(state);
+    }
+
+    pub fn shift_rows(state: &mut [u32; 4]) {
+        //! Shifts the rows of the state.
+
+        AESCore::shift_rows(state);
+    }
+
+    pub fn sub_bytes(state: &mut [u32; 4]) {
+        //! Substitutes the bytes of the state with the S-Box.
+
+        AESCore::sub_bytes(state);
+    }
+
+    pub fn sub_shift(state: &mut [u32; 4]) {
+        //! Substitutes the bytes of the state with the S-Box and shifts the rows in a single pass.
+
+        AESCore::sub_shift(state);
+    }
+}
+
+/// The individual AES round transformations on a public state, for building non-standard variants of the cipher.
+///
+/// The transformations are the ones from FIPS-197, applied one at a time,
+/// so they are slower than the fused transformations used by `AESCore`.
+/// Standard AES is never assembled from them by this crate.
+///
+/// **Example:** The first round of the FIPS-197 example (Appendix B)
+/// ```
+/// use tinyaes::{AESCore, AESKey};
+/// use tinyaes::aes_core::primitives::{self, State};
+///
+/// let key: [u8; 16] = [0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c];
+/// let round_keys = AESCore::new(AESKey::AES128(key)).round_keys().to_vec();
+///
+/// // the state at the start of the first round
+/// let mut state = State::from([0x19, 0x3d, 0xe3, 0xbe, 0xa0, 0xf4, 0xe2, 0x2b, 0x9a, 0xc6, 0x8d, 0x2a, 0xe9, 0xf8, 0x48, 0x08]);
+///
+/// primitives::sub_bytes(&mut state);
+/// primitives::shift_rows(&mut state);
+/// primitives::mix_columns(&mut state);
+/// primitives::add_round_key(&mut state, &round_keys[4..8].try_into().unwrap());
+///
+/// let expected: [u8; 16] = [0xa4, 0x9c, 0x7f, 0xf2, 0x68, 0x9f, 0x35, 0x2b, 0x6b, 0x5b, 0xea, 0x43, 0x02, 0x6a, 0x50, 0x49];
+/// assert_eq!(<[u8; 16]>::from(state), expected);
+///
+/// // and undoing it
+/// primitives::add_round_key(&mut state, &round_keys[4..8].try_into().unwrap());
+/// primitives::inv_mix_columns(&mut state);
+/// primitives::inv_shift_rows(&mut state);
+/// primitives::inv_sub_bytes(&mut state);
+/// assert_eq!(state.0[0], [0x19, 0x3d, 0xe3, 0xbe]);
+/// ```
+pub mod primitives {
+    use super::AESCore;
+
+    /// The AES state, as four columns of four bytes (`state.0[c][r]` is row r of column c).
+    /// The columns are the consecutive 4 bytes of the block, the same as in FIPS-197.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct State(pub [[u8; 4]; 4]);
+
+    impl From<[u8; 16]> for State {
+        fn from(block: [u8; 16]) -> Self {
+            Self(*block.as_chunks::<4>().0.first_chunk::<4>().expect("the block has 4 columns"))
+        }
+    }
+
+    impl From<State> for [u8; 16] {
+        fn from(state: State) -> Self {
+            *state.0.as_flattened().first_chunk::<16>().expect("the state has 16 bytes")
+        }
+    }
+
+    /// Loading and storing the state in different byte orders.
+    /// AES is natively big-endian and column-major: byte `r + 4 * c` of the block is row r of column c,
+    /// which is what the `From` conversions do.
+    impl State {
+        pub fn from_bytes_be(block: [u8; 16]) -> Self {
+            //! Loads the state from a block in the FIPS-197 order (column-major, the first byte is the most significant).
+            //! # Arguments
+            //! * `block` - The block to be loaded.
+            //! # Returns
+            //! * `State` - The state.
+
+            Self::from(block)
+        }
+
+        pub fn to_bytes_be(self) -> [u8; 16] {
+            //! Stores the state as a block in the FIPS-197 order (column-major, the first byte is the most significant).
+            //! # Returns
+            //! * `[u8; 16]` - The block.
+
+            self.into()
+        }
+
+        pub fn from_bytes_le(block: [u8; 16]) -> Self {
+            //! Loads the state from a block in the reversed byte order,
+            //! e.g. a block held in a `u128` and stored with `u128::to_le_bytes`.
+            //! # Arguments
+            //! * `block` - The block to be loaded, with the last byte of the FIPS-197 block first.
+            //! # Returns
+            //! * `State` - The state.
+
+            let mut block = block;
+            block.reverse();
+            Self::from(block)
+        }
+
+        pub fn to_bytes_le(self) -> [u8; 16] {
+            //! Stores the state as a block in the reversed byte order, see `from_bytes_le`.
+            //! # Returns
+            //! * `[u8; 16]` - The block, with the last byte of the FIPS-197 block first.
+
+            let mut block = self.to_bytes_be();
+            block.reverse();
+            block
+        }
+
+        pub fn from_rows(bytes: [u8; 16]) -> Self {
+            //! Loads the state from the bytes in row-major order, the way the state is printed in FIPS-197
+            //! (byte `4 * r + c` is row r of column c).
+            //! # Arguments
+            //! * `bytes` - The rows of the state, one after another.
+            //! # Returns
+            //! * `State` - The state.
+
+            let mut state = Self::default();
+            for (i, byte) in bytes.into_iter().enumerate() {
+                state.0[i % 4][i / 4] = byte;
+            }
+            state
+        }
+
+        pub fn to_rows(self) -> [u8; 16] {
+            //! Stores the state in row-major order, see `from_rows`.
+            //! # Returns
+            //! * `[u8; 16]` - The rows of the state, one after another.
+
+            let mut bytes: [u8; 16] = [0; 16];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = self.0[i % 4][i / 4];
+            }
+            bytes
+        }
+    }
+
+    pub fn sub_bytes(state: &mut State) {
+        //! Substitutes the bytes of the state with the S-Box (SubBytes).
+
+        apply(state, AESCore::sub_bytes);
+    }
+
+    pub fn inv_sub_bytes(state: &mut State) {
+        //! Substitutes the bytes of the state with the inverse S-Box (InvSubBytes).
+
+        apply(state, AESCore::inv_sub_bytes);
+    }
+
+    pub fn shift_rows(state: &mut State) {
+        //! Shifts row r of the state cyclically left by r bytes (ShiftRows).
+
+        apply(state, AESCore::shift_rows);
+    }
+
+    pub fn inv_shift_rows(state: &mut State) {
+        //! Shifts row r of the state cyclically right by r bytes (InvShiftRows).
+
+        apply(state, AESCore::inv_shift_rows);
+    }
+
+    pub fn mix_columns(state: &mut State) {
+        //! Multiplies each column of the state by the fixed polynomial {03}x^3 + {01}x^2 + {01}x + {02} (MixColumns).
+
+        apply(state, AESCore::mix_columns);
+    }
+
+    pub fn inv_mix_columns(state: &mut State) {
+        //! Multiplies each column of the state by the inverse polynomial {0b}x^3 + {0d}x^2 + {09}x + {0e} (InvMixColumns).
+
+        apply(state, AESCore::inv_mix_columns);
+    }
+
+    pub fn add_round_key(state: &mut State, round_key: &[[u8; 4]; 4]) {
+        //! XORs the round key into the state (AddRoundKey), its own inverse.
+        //! # Arguments
+        //! * `state` - The state.
+        //! * `round_key` - The four words of the round key, e.g. a part of `AESCore::round_keys`.
+
+        apply(state, |words| AESCore::add_round_key(words, round_key));
+    }
+
+    fn apply(state: &mut State, transformation: impl FnOnce(&mut [u32; 4])) {
+        //! Applies the internal transformation on the column words to the state.
+
+        let mut words: [u32; 4] = state.0.map(u32::from_be_bytes);
+        transformation(&mut words);
+        state.0 = words.map(u32::to_be_bytes);
+    }
+}
 
 
 
@@ -29,13 +271,131 @@ use core::ops::{
 // ENUMS
 
 /// The AES key used to encrypt and decrypt data.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Keys are compared in constant time with respect to the key bytes,
+/// only the variant (the key length) may be leaked through timing.
+#[derive(Debug, Clone, Copy, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AESKey {
     AES128([u8; 16]),
     AES192([u8; 24]),
     AES256([u8; 32]),
 }
 
+impl AESKey {
+    fn as_bytes(&self) -> &[u8] {
+        //! Returns the key bytes.
+
+        match self {
+            AESKey::AES128(key) => key,
+            AESKey::AES192(key) => key,
+            AESKey::AES256(key) => key,
+        }
+    }
+}
+impl PartialEq for AESKey {
+    fn eq(&self, other: &Self) -> bool {
+        mem::discriminant(self) == mem::discriminant(other) && ct_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+impl Hash for AESKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        mem::discriminant(self).hash(state);
+        self.as_bytes().hash(state);
+    }
+}
+impl From<[u8; 16]> for AESKey {
+    fn from(key: [u8; 16]) -> Self {
+        AESKey::AES128(key)
+    }
+}
+impl From<[u8; 24]> for AESKey {
+    fn from(key: [u8; 24]) -> Self {
+        AESKey::AES192(key)
+    }
+}
+impl From<[u8; 32]> for AESKey {
+    fn from(key: [u8; 32]) -> Self {
+        AESKey::AES256(key)
+    }
+}
+impl TryFrom<&[u8]> for AESKey {
+    type Error = KeyError;
+
+    fn try_from(key: &[u8]) -> Result<Self, Self::Error> {
+        match key.len() {
+            16 => Ok(AESKey::AES128(key.try_into().unwrap())),
+            24 => Ok(AESKey::AES192(key.try_into().unwrap())),
+            32 => Ok(AESKey::AES256(key.try_into().unwrap())),
+            _ => Err(KeyError::InvalidLength),
+        }
+    }
+}
+
+/// The enum with AES key sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyVariant {
+    /// A 128-bit key.
+    AES128,
+    /// A 192-bit key.
+    AES192,
+    /// A 256-bit key.
+    AES256,
+}
+
+/// The enum with key errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyError {
+    /// The key isn't 16, 24 or 32 bytes long.
+    InvalidLength,
+    /// The key schedule doesn't have 44, 52 or 60 words for AES-128, AES-192 or AES-256.
+    InvalidScheduleLength,
+    /// The password-based derivation was asked for zero iterations.
+    ZeroIterations,
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::InvalidLength => write!(f, "key must be 16, 24 or 32 bytes long"),
+            KeyError::InvalidScheduleLength => write!(f, "key schedule length doesn't match the key size"),
+            KeyError::ZeroIterations => write!(f, "number of iterations must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// The enum with data length errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LenError {
+    /// The data isn't a multiple of the block size (16 bytes) long.
+    NotBlockMultiple,
+    /// The input and the output aren't the same length.
+    LengthMismatch,
+    /// The data isn't exactly one block (16 bytes) long.
+    NotOneBlock,
+    /// The data is shorter than one block (16 bytes).
+    TooShort,
+    /// An incomplete block is still buffered, the data before it wasn't a multiple of 16 bytes long.
+    IncompleteBlock,
+}
+
+impl fmt::Display for LenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LenError::NotBlockMultiple => write!(f, "data length must be a multiple of 16 bytes"),
+            LenError::LengthMismatch => write!(f, "input and output must be the same length"),
+            LenError::NotOneBlock => write!(f, "block must be exactly 16 bytes long"),
+            LenError::TooShort => write!(f, "data must be at least 16 bytes long"),
+            LenError::IncompleteBlock => write!(f, "an incomplete block is still buffered"),
+        }
+    }
+}
+
+impl std::error::Error for LenError {}
+
 /// The round keys used in the AES algorithm.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum RoundKeys {
@@ -121,12 +481,13 @@ impl IndexMut<RangeFrom<usize>> for RoundKeys {
 // STRUCTS
 
 /// The AES core algorithm.
+/// Only the round keys are stored, the key (the first words of the key schedule) and its size are recovered from them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AESCore {
-    /// The AES key used to encrypt and decrypt data.
-    key: AESKey,
     /// The round keys used in the AES algorithm.
     round_keys: RoundKeys,
+    /// The round keys used in the equivalent inverse cipher.
+    inv_round_keys: RoundKeys,
 }
 
 /// Public functions for encrypting and decrypting data.
@@ -134,241 +495,524 @@ impl AESCore {
     pub fn new(key: AESKey) -> AESCore {
         //! Creates a new AES instance with the given key.
 
+        Self::new_borrowed(&key)
+    }
+
+    pub fn new_borrowed(key: &AESKey) -> AESCore {
+        //! Creates a new AES instance with the borrowed key.
+        //! The key is only read to expand the key schedule, so the caller can keep it elsewhere (e.g. in a protected wrapper).
+
+        let round_keys = Self::key_expansion(key);
+
         Self {
-            key,
-            round_keys: Self::key_expansion(&key),
+            round_keys,
+            inv_round_keys: Self::inv_key_expansion(&round_keys),
         }
     }
 
+    pub fn from_schedule(schedule: &[[u8; 4]]) -> Result<AESCore, KeyError> {
+        //! Creates a new AES instance from a precomputed key schedule (see `AESCore::round_keys`), skipping the key expansion.
+        //! A schedule shared between many instances (e.g. an `Arc<[[u8; 4]]>`) is passed by reference and only copied.
+        //! The key size follows from the length of the schedule, and the key from its first words.
+        //! Only the inverse cipher's schedule is derived from it (with InvMixColumns), the schedule itself isn't verified,
+        //! so it must come from `AESCore::round_keys`.
+        //! # Arguments
+        //! * `schedule` - The expanded key schedule, 44, 52 or 60 words.
+        //! # Returns
+        //! * `Result<AESCore, KeyError>` - The AES instance or an error.
+        //! # Errors
+        //! * KeyError::InvalidScheduleLength - The schedule doesn't have 44, 52 or 60 words.
+
+        let round_keys = match schedule.len() {
+            44 => schedule.try_into().map(RoundKeys::AES128),
+            52 => schedule.try_into().map(RoundKeys::AES192),
+            60 => schedule.try_into().map(RoundKeys::AES256),
+            _ => return Err(KeyError::InvalidScheduleLength),
+        }.expect("the length was checked");
+
+        Ok(Self {
+            round_keys,
+            inv_round_keys: Self::inv_key_expansion(&round_keys),
+        })
+    }
+
+    pub fn derived(root: &AESKey, salt: &[u8]) -> AESCore {
+        //! Creates a new AES instance with a key derived from the root key and the salt.
+        //! Useful for deriving independent ciphers for distinct purposes from a single root key.
+        //! The derived key is of the same variant as the root key (see `derive_subkey`).
+
+        Self::new(derive_subkey(root, salt))
+    }
+
     pub fn key(&self) -> AESKey {
-        //! Returns the key used by this AES instance.
+        //! Returns the key used by this AES instance, recovered from the first words of the key schedule.
 
-        self.key
+        let key_words = &self.round_keys[0..(self.round_keys.len() / 4 - 7)];
+        AESKey::try_from(key_words.as_flattened()).expect("the key is 16, 24 or 32 bytes long")
+    }
+
+    pub fn round_keys(&self) -> &[[u8; 4]] {
+        //! Returns the expanded key schedule, as words `w[0]` to `w[4 * (Nr + 1) - 1]` (FIPS-197, section 5.2).
+
+        &self.round_keys[0..]
+    }
+
+    pub fn inv_round_keys(&self) -> &[[u8; 4]] {
+        //! Returns the key schedule of the equivalent inverse cipher, `dw[0]` to `dw[4 * (Nr + 1) - 1]` (FIPS-197, section 5.3.5).
+
+        &self.inv_round_keys[0..]
+    }
+
+    pub fn key_size_bits(&self) -> usize {
+        //! Returns the size of the key in bits (128, 192 or 256).
+
+        match self.round_keys {
+            RoundKeys::AES128(_) => 128,
+            RoundKeys::AES192(_) => 192,
+            RoundKeys::AES256(_) => 256,
+        }
+    }
+
+    pub fn num_rounds(&self) -> usize {
+        //! Returns the number of rounds of the cipher (10, 12 or 14).
+
+        match self.round_keys {
+            RoundKeys::AES128(_) => 10,
+            RoundKeys::AES192(_) => 12,
+            RoundKeys::AES256(_) => 14,
+        }
     }
 
     pub fn set_key(&mut self, key: AESKey) {
         //! Changes the key used by this AES instance.
 
-        self.key = key;
         self.round_keys = Self::key_expansion(&key);
+        self.inv_round_keys = Self::inv_key_expansion(&self.round_keys);
     }
 
-    pub fn encrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+    pub fn encrypt(&self, block: &Block) -> Block {
         //! Encrypts the given block of data.
 
+        let mut out_block: [u8; 16] = *block;
+        self.encrypt_in_place(&mut out_block);
+        out_block
+    }
+
+    pub fn encrypt_in_place(&self, block: &mut Block) {
+        //! Encrypts the given block of data in place.
+
         // convert block to state
-        let mut state: [[u8; 4]; 4] = [[0; 4]; 4];
-        for r in 0..4 {
-            for c in 0..4 {
-                state[r][c] = block[r + c * 4];
-            }
-        }
+        let mut state: [u32; 4] = Self::load_state(block);
 
         // encryption starts here
         Self::add_round_key(&mut state, &self.round_keys[0..4]);
-        for round in 1..(match self.key {
-            AESKey::AES128(_) => 10,
-            AESKey::AES192(_) => 12,
-            AESKey::AES256(_) => 14,
-        }) {
-            Self::sub_bytes(&mut state);
-            Self::shift_rows(&mut state);
+        for round in 1..self.num_rounds() {
+            Self::sub_shift(&mut state);
             Self::mix_columns(&mut state);
             Self::add_round_key(&mut state, &self.round_keys[round * 4..(round + 1) * 4]);
         }
-        Self::sub_bytes(&mut state);
-        Self::shift_rows(&mut state);
+        Self::sub_shift(&mut state);
         Self::add_round_key(&mut state, &self.round_keys[(self.round_keys.len() - 4)..]);
         // encryption ends here
 
         // convert state to output block
-        let mut out_block: [u8; 16] = [0; 16];
-        for r in 0..4 {
-            for c in 0..4 {
-                out_block[r + c * 4] = state[r][c];
-            }
+        Self::store_state(&state, block);
+    }
+
+    pub fn encrypt_to(&self, block: &Block, out: &mut Block) {
+        //! Encrypts the given block of data into the output block.
+
+        *out = *block;
+        self.encrypt_in_place(out);
+    }
+
+    pub fn encrypt_slice_to(&self, input: &[u8], output: &mut [u8]) -> Result<(), LenError> {
+        //! Encrypts every block of the input into the output, block by block.
+        //! # Arguments
+        //! * `input` - The data to be encrypted, a multiple of 16 bytes long.
+        //! * `output` - The output buffer, the same length as the input.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The input isn't a multiple of 16 bytes long.
+        //! * LenError::LengthMismatch - The output isn't the same length as the input.
+
+        if !input.len().is_multiple_of(16) {
+            return Err(LenError::NotBlockMultiple);
+        }
+        if input.len() != output.len() {
+            return Err(LenError::LengthMismatch);
+        }
+
+        for (block, out) in blocks(input).0.zip(blocks_mut(output).0) {
+            self.encrypt_to(block, out);
+        }
+
+        Ok(())
+    }
+
+    pub fn encrypt_slice(&self, block: &[u8]) -> Result<Block, LenError> {
+        //! Encrypts the given block of data, checking its length instead of panicking.
+        //! # Arguments
+        //! * `block` - The data to be encrypted, exactly 16 bytes long.
+        //! # Returns
+        //! * `Result<Block, LenError>` - The encrypted block or an error.
+        //! # Errors
+        //! * LenError::NotOneBlock - The data isn't exactly 16 bytes long.
+
+        let block: &[u8; 16] = block.try_into().map_err(|_| LenError::NotOneBlock)?;
+        Ok(self.encrypt(block))
+    }
+
+    pub fn encrypt_array<const N: usize>(&self, data: &mut [u8; N]) {
+        //! Encrypts the array in place in ECB mode, block by block.
+        //! The length is checked at compile time, so there is no runtime check or allocation.
+        //! # Arguments
+        //! * `data` - The data to be encrypted, a multiple of 16 bytes long.
+        //!
+        //! An array which isn't a multiple of 16 bytes long doesn't compile:
+        //! ```compile_fail
+        //! use tinyaes::{AESCore, AESKey};
+        //!
+        //! AESCore::new(AESKey::AES128([0; 16])).encrypt_array(&mut [0_u8; 20]);
+        //! ```
+
+        const { assert!(N.is_multiple_of(16), "the array must be a multiple of 16 bytes long") };
+
+        for block in data.as_chunks_mut::<16>().0 {
+            self.encrypt_in_place(block);
         }
-        out_block
     }
 
-    pub fn decrypt(&self, block: &[u8; 16]) -> [u8; 16] {
+    pub fn encrypt_with(&self, blocks: &mut [Block], mut pre: impl FnMut(usize, &mut Block), mut post: impl FnMut(usize, &mut Block)) {
+        //! Encrypts the blocks in place, calling the closures before and after the encryption of each block.
+        //! A building block for modes of operation which aren't provided by the crate,
+        //! e.g. CBC XORs the previous ciphertext block in `pre` and remembers the new one in `post`.
+        //! # Arguments
+        //! * `blocks` - The blocks to be encrypted.
+        //! * `pre` - Called with the index of the block and the block before it is encrypted.
+        //! * `post` - Called with the index of the block and the encrypted block.
+
+        for (index, block) in blocks.iter_mut().enumerate() {
+            pre(index, block);
+            self.encrypt_in_place(block);
+            post(index, block);
+        }
+    }
+
+    pub fn decrypt(&self, block: &Block) -> Block {
         //! Decrypts the given block of data.
 
+        let mut out_block: [u8; 16] = *block;
+        self.decrypt_in_place(&mut out_block);
+        out_block
+    }
+
+    pub fn decrypt_in_place(&self, block: &mut Block) {
+        //! Decrypts the given block of data in place.
+
         // convert block to state
-        let mut state: [[u8; 4]; 4] = [[0; 4]; 4];
-        for r in 0..4 {
-            for c in 0..4 {
-                state[r][c] = block[r + c * 4];
-            }
-        }
+        let mut state: [u32; 4] = Self::load_state(block);
 
         // decryption starts here
         Self::add_round_key(&mut state, &self.round_keys[(self.round_keys.len() - 4)..]);
-        for round in (1..(match self.key {
-            AESKey::AES128(_) => 10,
-            AESKey::AES192(_) => 12,
-            AESKey::AES256(_) => 14,
-        })).rev() {
-            Self::inv_shift_rows(&mut state);
-            Self::inv_sub_bytes(&mut state);
+        for round in (1..self.num_rounds()).rev() {
+            Self::inv_shift_sub(&mut state);
             Self::add_round_key(&mut state, &self.round_keys[round * 4..(round + 1) * 4]);
             Self::inv_mix_columns(&mut state);
         }
-        Self::inv_shift_rows(&mut state);
-        Self::inv_sub_bytes(&mut state);
+        Self::inv_shift_sub(&mut state);
         Self::add_round_key(&mut state, &self.round_keys[0..4]);
         // decryption ends here
 
+        // convert state to output block
+        Self::store_state(&state, block);
+    }
+
+    pub fn decrypt_slice(&self, block: &[u8]) -> Result<Block, LenError> {
+        //! Decrypts the given block of data, checking its length instead of panicking.
+        //! # Arguments
+        //! * `block` - The data to be decrypted, exactly 16 bytes long.
+        //! # Returns
+        //! * `Result<Block, LenError>` - The decrypted block or an error.
+        //! # Errors
+        //! * LenError::NotOneBlock - The data isn't exactly 16 bytes long.
+
+        let block: &[u8; 16] = block.try_into().map_err(|_| LenError::NotOneBlock)?;
+        Ok(self.decrypt(block))
+    }
+
+    pub fn decrypt_eqinv(&self, block: &Block) -> Block {
+        //! Decrypts the given block of data using the equivalent inverse cipher.
+        //! The transformations are applied in the same order as in the forward cipher,
+        //! using the round keys transformed with InvMixColumns (FIPS-197, section 5.3.5).
+        //! The result is identical to `decrypt`.
+
+        // convert block to state
+        let mut state: [u32; 4] = Self::load_state(block);
+
+        // decryption starts here
+        Self::add_round_key(&mut state, &self.inv_round_keys[(self.inv_round_keys.len() - 4)..]);
+        for round in (1..self.num_rounds()).rev() {
+            Self::inv_shift_sub(&mut state);
+            Self::inv_mix_columns(&mut state);
+            Self::add_round_key(&mut state, &self.inv_round_keys[round * 4..(round + 1) * 4]);
+        }
+        Self::inv_shift_sub(&mut state);
+        Self::add_round_key(&mut state, &self.inv_round_keys[0..4]);
+        // decryption ends here
+
         // convert state to output block
         let mut out_block: [u8; 16] = [0; 16];
-        for r in 0..4 {
-            for c in 0..4 {
-                out_block[r + c * 4] = state[r][c];
+        Self::store_state(&state, &mut out_block);
+        out_block
+    }
+}
+
+/// Parallel functions for encrypting and decrypting data in bulk.
+#[cfg(feature = "rayon")]
+impl AESCore {
+    pub fn par_encrypt_ecb(&self, data: &mut [u8]) -> Result<(), LenError> {
+        //! Encrypts every block of the data in place, splitting the blocks across the rayon thread pool.
+        //! # Arguments
+        //! * `data` - The data to be encrypted, a multiple of 16 bytes long.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long.
+
+        if !data.len().is_multiple_of(16) {
+            return Err(LenError::NotBlockMultiple);
+        }
+
+        data.as_chunks_mut::<16>().0.par_iter_mut().for_each(|block| self.encrypt_in_place(block));
+        Ok(())
+    }
+
+    pub fn par_decrypt_ecb(&self, data: &mut [u8]) -> Result<(), LenError> {
+        //! Decrypts every block of the data in place, splitting the blocks across the rayon thread pool.
+        //! # Arguments
+        //! * `data` - The data to be decrypted, a multiple of 16 bytes long.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long.
+
+        if !data.len().is_multiple_of(16) {
+            return Err(LenError::NotBlockMultiple);
+        }
+
+        data.as_chunks_mut::<16>().0.par_iter_mut().for_each(|block| self.decrypt_in_place(block));
+        Ok(())
+    }
+}
+
+/// Functions for inspecting the intermediate states of the encryption.
+#[cfg(feature = "trace")]
+impl AESCore {
+    pub fn encrypt_trace(&self, block: &Block) -> Vec<[[u8; 4]; 4]> {
+        //! Encrypts the given block of data, recording the state after every transformation.
+        //! Intended for learning and for checking test vectors, it is much slower than `encrypt`.
+        //! The states are recorded after the initial AddRoundKey, and then after SubBytes, ShiftRows,
+        //! MixColumns (except in the last round) and AddRoundKey of every round, so the last state is the ciphertext.
+        //! # Arguments
+        //! * `block` - The block to be encrypted.
+        //! # Returns
+        //! * `Vec<[[u8; 4]; 4]>` - The recorded states, each stored column by column (`state[c][r]`, like the round key words).
+
+        let mut trace: Vec<[[u8; 4]; 4]> = Vec::new();
+        let record = |state: &[u32; 4], trace: &mut Vec<[[u8; 4]; 4]>| {
+            trace.push([state[0].to_be_bytes(), state[1].to_be_bytes(), state[2].to_be_bytes(), state[3].to_be_bytes()]);
+        };
+
+        let mut state: [u32; 4] = Self::load_state(block);
+        let rounds: usize = self.num_rounds();
+
+        Self::add_round_key(&mut state, &self.round_keys[0..4]);
+        record(&state, &mut trace);
+        for round in 1..=rounds {
+            Self::sub_bytes(&mut state);
+            record(&state, &mut trace);
+            Self::shift_rows(&mut state);
+            record(&state, &mut trace);
+            if round != rounds {
+                Self::mix_columns(&mut state);
+                record(&state, &mut trace);
             }
+            Self::add_round_key(&mut state, &self.round_keys[round * 4..(round + 1) * 4]);
+            record(&state, &mut trace);
         }
-        out_block
+
+        trace
     }
 }
 
 /// Functions for encrypting and decrypting used in the AES algorithm.
+/// The state is stored as four big-endian column words, the first row being the most significant byte.
 impl AESCore {
-    fn add_round_key(state: &mut [[u8; 4]; 4], round_keys: &[[u8; 4]]) {
-        //! Adds the given round key to the state.
+    #[inline(always)]
+    fn load_state(block: &[u8; 16]) -> [u32; 4] {
+        //! Loads the block into the state, each 4 consecutive bytes forming a column.
 
-        for r in 0..4 {
-            for c in 0..4 {
-                state[r][c] ^= round_keys[c][r];
-            }
+        let mut state: [u32; 4] = [0; 4];
+        for c in 0..4 {
+            state[c] = u32::from_be_bytes([block[c * 4], block[c * 4 + 1], block[c * 4 + 2], block[c * 4 + 3]]);
         }
+        state
     }
 
-    fn mix_columns(state: &mut [[u8; 4]; 4]) {
-        //! Mixes the columns of the state.
+    #[inline(always)]
+    fn store_state(state: &[u32; 4], block: &mut [u8; 16]) {
+        //! Stores the state into the block, column by column.
 
-        let mut temp_column: [u8; 4] = [0; 4];
         for c in 0..4 {
-            temp_column[0] =
-                (if (state[0][c] >> 7) == 1 {(state[0][c] << 1) ^ 0x1b} else {state[0][c] << 1}) ^
-                ((if (state[1][c] >> 7) == 1 {(state[1][c] << 1) ^ 0x1b} else {state[1][c] << 1}) ^ state[1][c]) ^
-                state[2][c] ^
-                state[3][c];
+            block[(c * 4)..(c * 4 + 4)].copy_from_slice(&state[c].to_be_bytes());
+        }
+    }
 
-            temp_column[1] =
-                state[0][c] ^
-                (if (state[1][c] >> 7) == 1 {(state[1][c] << 1) ^ 0x1b} else {state[1][c] << 1}) ^
-                ((if (state[2][c] >> 7) == 1 {(state[2][c] << 1) ^ 0x1b} else {state[2][c] << 1}) ^ state[2][c]) ^
-                state[3][c];
+    #[inline(always)]
+    fn add_round_key(state: &mut [u32; 4], round_keys: &[[u8; 4]]) {
+        //! Adds the given round key to the state.
+        //! With the `simd` feature on x86-64, the SIMD version is used.
 
-            temp_column[2] =
-                state[0][c] ^
-                state[1][c] ^
-                (if (state[2][c] >> 7) == 1 {(state[2][c] << 1) ^ 0x1b} else {state[2][c] << 1}) ^
-                ((if (state[3][c] >> 7) == 1 {(state[3][c] << 1) ^ 0x1b} else {state[3][c] << 1}) ^ state[3][c]);
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        simd::add_round_key(state, round_keys);
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        Self::add_round_key_scalar(state, round_keys);
+    }
 
+    #[cfg(any(test, not(all(feature = "simd", target_arch = "x86_64"))))]
+    #[inline(always)]
+    fn add_round_key_scalar(state: &mut [u32; 4], round_keys: &[[u8; 4]]) {
+        //! Adds the given round key to the state, one column at a time.
 
-            temp_column[3] =
-                ((if (state[0][c] >> 7) == 1 {(state[0][c] << 1) ^ 0x1b} else {state[0][c] << 1}) ^ state[0][c]) ^
-                state[1][c] ^
-                state[2][c] ^
-                (if (state[3][c] >> 7) == 1 {(state[3][c] << 1) ^ 0x1b} else {state[3][c] << 1});
+        for c in 0..4 {
+            state[c] ^= u32::from_be_bytes(round_keys[c]);
+        }
+    }
+
+    #[inline(always)]
+    const fn xtime(word: u32) -> u32 {
+        //! Multiplies each byte of the word by 2 in GF(2^8).
+        //! The reduction by 0x1b is done with shifts of the carried out bits, without branches or multiplication
+        //! (which isn't constant-time on some processors), so the time doesn't depend on the data.
+
+        let carry: u32 = (word >> 7) & 0x01010101;
+        ((word & 0x7f7f7f7f) << 1) ^ carry ^ (carry << 1) ^ (carry << 3) ^ (carry << 4)
+    }
 
-            state[0][c] = temp_column[0];
-            state[1][c] = temp_column[1];
-            state[2][c] = temp_column[2];
-            state[3][c] = temp_column[3];
+    #[inline(always)]
+    fn mix_columns(state: &mut [u32; 4]) {
+        //! Mixes the columns of the state.
+        //! With the `simd` feature on x86-64, the SIMD version is used.
+
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        simd::mix_columns(state);
+        #[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+        Self::mix_columns_scalar(state);
+    }
+
+    #[cfg(any(test, not(all(feature = "simd", target_arch = "x86_64"))))]
+    #[inline(always)]
+    fn mix_columns_scalar(state: &mut [u32; 4]) {
+        //! Mixes the columns of the state, one column at a time.
+        //! Each byte becomes 2 * a0 + 3 * a1 + a2 + a3, where a1 - a3 are the following bytes of the column (cyclically).
+
+        for c in 0..4 {
+            let column = state[c];
+            let rotated = column.rotate_left(8);
+            state[c] = Self::xtime(column ^ rotated) ^ rotated ^ column.rotate_left(16) ^ column.rotate_left(24);
         }
     }
 
-    fn shift_rows(state: &mut [[u8; 4]; 4]) {
+    fn shift_rows(state: &mut [u32; 4]) {
         //! Shifts the rows of the state.
+        //! Row r of column c is taken from column c + r.
 
-        state[1].rotate_left(1);
-        state[2].rotate_left(2);
-        state[3].rotate_left(3);
+        let old_state: [u32; 4] = *state;
+        for c in 0..4 {
+            state[c] =
+                (old_state[c] & 0xff000000) |
+                (old_state[(c + 1) % 4] & 0x00ff0000) |
+                (old_state[(c + 2) % 4] & 0x0000ff00) |
+                (old_state[(c + 3) % 4] & 0x000000ff);
+        }
     }
 
-    fn sub_bytes(state: &mut [[u8; 4]; 4]) {
+    fn sub_bytes(state: &mut [u32; 4]) {
         //! Substitutes the bytes of the state with the S-Box.
 
-        for r in 0..4 {
-            for c in 0..4 {
-                state[r][c] = S_BOX[(state[r][c] >> 4) as usize][(state[r][c] & 0b00001111) as usize];
+        for c in 0..4 {
+            let mut column: [u8; 4] = state[c].to_be_bytes();
+            for r in 0..4 {
+                column[r] = sub_byte(column[r]);
             }
+            state[c] = u32::from_be_bytes(column);
         }
     }
 
-    fn inv_mix_columns(state: &mut [[u8; 4]; 4]) {
-        //! Inverse mixes the columns of the state.
-        
-        let mut temp_column: [u8; 4] = [0; 4];
-        let mut temp_mul: [[u8; 3]; 4] = [[0; 3]; 4];
+    #[inline(always)]
+    fn sub_shift(state: &mut [u32; 4]) {
+        //! Substitutes the bytes of the state with the S-Box and shifts the rows in a single pass.
+        //! Each substituted byte is written directly to its shifted position (row r of column c is taken from column c + r).
 
+        let old_state: [u32; 4] = *state;
         for c in 0..4 {
-            for i in 0..4 {
-                temp_mul[i][0] = if (state[i][c] >> 7) == 1 {(state[i][c] << 1) ^ 0x1b} else {state[i][c] << 1};
-            }
-            for i in 0..4 {
-                for j in 1..3 {
-                    temp_mul[i][j] = if (temp_mul[i][j - 1] >> 7) == 1 {
-                        (temp_mul[i][j - 1] << 1) ^ 0x1b
-                    } else {
-                        temp_mul[i][j - 1] << 1
-                    };
-                }
+            let mut column: [u8; 4] = [0; 4];
+            for r in 0..4 {
+                let byte = old_state[(c + r) % 4].to_be_bytes()[r];
+                column[r] = sub_byte(byte);
             }
+            state[c] = u32::from_be_bytes(column);
+        }
+    }
 
-            // 09 = 01 + 08
-            // 0b = 01 + 02 + 08
-            // 0d = 01 + 04 + 08
-            // 0e = 02 + 04 + 08
-            // temp_mul = [[02, 04, 08]]
-
-            temp_column[0] = 
-                (temp_mul[0][0] ^ temp_mul[0][1] ^ temp_mul[0][2]) ^
-                (state[1][c] ^ temp_mul[1][0] ^ temp_mul[1][2]) ^
-                (state[2][c] ^ temp_mul[2][1] ^ temp_mul[2][2]) ^
-                (state[3][c] ^ temp_mul[3][2]);
-
-            temp_column[1] =
-                (state[0][c] ^ temp_mul[0][2]) ^
-                (temp_mul[1][0] ^ temp_mul[1][1] ^ temp_mul[1][2]) ^
-                (state[2][c] ^ temp_mul[2][0] ^ temp_mul[2][2]) ^
-                (state[3][c] ^ temp_mul[3][1] ^ temp_mul[3][2]);
-            
-            temp_column[2] =
-                (state[0][c] ^ temp_mul[0][1] ^ temp_mul[0][2]) ^
-                (state[1][c] ^ temp_mul[1][2]) ^
-                (temp_mul[2][0] ^ temp_mul[2][1] ^ temp_mul[2][2]) ^
-                (state[3][c] ^ temp_mul[3][0] ^ temp_mul[3][2]);
-            
-            temp_column[3] =
-                (state[0][c] ^ temp_mul[0][0] ^ temp_mul[0][2]) ^
-                (state[1][c] ^ temp_mul[1][1] ^ temp_mul[1][2]) ^
-                (state[2][c] ^ temp_mul[2][2]) ^
-                (temp_mul[3][0] ^ temp_mul[3][1] ^ temp_mul[3][2]);
-
-            state[0][c] = temp_column[0];
-            state[1][c] = temp_column[1];
-            state[2][c] = temp_column[2];
-            state[3][c] = temp_column[3];
-        }
-    }
-
-    fn inv_shift_rows(state: &mut [[u8; 4]; 4]) {
+    #[inline(always)]
+    fn inv_mix_columns(state: &mut [u32; 4]) {
+        //! Inverse mixes the columns of the state.
+        //! The inverse matrix is the forward one multiplied by a matrix adding 4 * (a0 + a2) to a0 and a2,
+        //! and 4 * (a1 + a3) to a1 and a3.
+
+        for c in 0..4 {
+            let quadrupled = Self::xtime(Self::xtime(state[c]));
+            state[c] ^= quadrupled ^ quadrupled.rotate_left(16);
+        }
+        Self::mix_columns(state);
+    }
+
+    fn inv_shift_rows(state: &mut [u32; 4]) {
         //! Inverse shifts the rows of the state.
+        //! Row r of column c is taken from column c - r.
+
+        let old_state: [u32; 4] = *state;
+        for c in 0..4 {
+            state[c] =
+                (old_state[c] & 0xff000000) |
+                (old_state[(c + 3) % 4] & 0x00ff0000) |
+                (old_state[(c + 2) % 4] & 0x0000ff00) |
+                (old_state[(c + 1) % 4] & 0x000000ff);
+        }
+    }
+
+    #[inline(always)]
+    fn inv_shift_sub(state: &mut [u32; 4]) {
+        //! Inverse shifts the rows of the state and inverse substitutes the bytes in a single pass.
+        //! Each byte is read from its position before the shift (row r of column c is taken from column c - r).
 
-        state[1].rotate_right(1);
-        state[2].rotate_right(2);
-        state[3].rotate_right(3);
+        let old_state: [u32; 4] = *state;
+        for c in 0..4 {
+            let mut column: [u8; 4] = [0; 4];
+            for r in 0..4 {
+                let byte = old_state[(c + 4 - r) % 4].to_be_bytes()[r];
+                column[r] = inv_sub_byte(byte);
+            }
+            state[c] = u32::from_be_bytes(column);
+        }
     }
 
-    fn inv_sub_bytes(state: &mut [[u8; 4]; 4]) {
+    fn inv_sub_bytes(state: &mut [u32; 4]) {
         //! Inverse substitutes the bytes of the state with the inverse S-Box.
 
-        for r in 0..4 {
-            for c in 0..4 {
-                state[r][c] = INV_S_BOX[(state[r][c] >> 4) as usize][(state[r][c] & 0b00001111) as usize];
+        for c in 0..4 {
+            let mut column: [u8; 4] = state[c].to_be_bytes();
+            for r in 0..4 {
+                column[r] = inv_sub_byte(column[r]);
             }
+            state[c] = u32::from_be_bytes(column);
         }
     }
 }
@@ -378,90 +1022,360 @@ impl AESCore {
     fn key_expansion(key: &AESKey) -> RoundKeys {
         //! Expands the key into a set of round keys.
 
-        let mut round_keys = match key {
-            AESKey::AES128(_) => RoundKeys::AES128([[0; 4]; 44]),
-            AESKey::AES192(_) => RoundKeys::AES192([[0; 4]; 52]),
-            AESKey::AES256(_) => RoundKeys::AES256([[0; 4]; 60]),
-        };
+        match key {
+            AESKey::AES128(key_seq) => RoundKeys::AES128(expand_key_128(*key_seq)),
+            AESKey::AES192(key_seq) => RoundKeys::AES192(expand_key_192(*key_seq)),
+            AESKey::AES256(key_seq) => RoundKeys::AES256(expand_key_256(*key_seq)),
+        }
+    }
 
-        let mut position: usize = 0;
+    fn inv_key_expansion(round_keys: &RoundKeys) -> RoundKeys {
+        //! Transforms the round keys for use in the equivalent inverse cipher.
+        //! InvMixColumns is applied to all round keys except the first and the last one.
 
-        match key {
-            AESKey::AES128(key_seq) => {
-                for i in (0..16).step_by(4) {
-                    round_keys[position] = [
-                        key_seq[i],
-                        key_seq[i + 1],
-                        key_seq[i + 2],
-                        key_seq[i + 3]
-                    ];
-                    position += 1;
-                }
-            },
-            AESKey::AES192(key_seq) => {
-                for i in (0..24).step_by(4) {
-                    round_keys[position] = [
-                        key_seq[i],
-                        key_seq[i + 1],
-                        key_seq[i + 2],
-                        key_seq[i + 3]
-                    ];
-                    position += 1;
-                }
-            },
-            AESKey::AES256(key_seq) => {
-                for i in (0..32).step_by(4) {
-                    round_keys[position] = [
-                        key_seq[i],
-                        key_seq[i + 1],
-                        key_seq[i + 2],
-                        key_seq[i + 3]
-                    ];
-                    position += 1;
-                }
-            },
+        let mut inv_round_keys = *round_keys;
+
+        for round in 1..(round_keys.len() / 4 - 1) {
+            let mut state: [u32; 4] = [0; 4];
+            for c in 0..4 {
+                state[c] = u32::from_be_bytes(round_keys[round * 4 + c]);
+            }
+
+            Self::inv_mix_columns(&mut state);
+
+            for c in 0..4 {
+                inv_round_keys[round * 4 + c] = state[c].to_be_bytes();
+            }
         }
 
-        let nk: usize = match key {
-            AESKey::AES128(_) => 4,
-            AESKey::AES192(_) => 6,
-            AESKey::AES256(_) => 8,
-        };
+        inv_round_keys
+    }
+
+    const fn rot_word(word: &mut [u8; 4]) {
+        //! Rotates the word to the left by one byte.
+
+        *word = [word[1], word[2], word[3], word[0]];
+    }
+
+    const fn sub_word(word: &mut [u8; 4]) {
+        //! Substitutes the bytes of the word with the S-Box.
 
-        for i in position..round_keys.len() {
+        let mut i: usize = 0;
+        while i < 4 {
+            word[i] = sub_byte(word[i]);
+            i += 1;
+        }
+    }
+
+    const fn expand_key<const KEY_LEN: usize, const WORDS: usize>(key: [u8; KEY_LEN]) -> [[u8; 4]; WORDS] {
+        //! Expands the key of `KEY_LEN` bytes into `WORDS` words of round keys.
+        //! Loops are written with `while`, so the expansion can be evaluated at compile time.
+
+        let nk: usize = KEY_LEN / 4;
+        let mut round_keys: [[u8; 4]; WORDS] = [[0; 4]; WORDS];
+
+        let mut i: usize = 0;
+        while i < nk {
+            round_keys[i] = [key[i * 4], key[i * 4 + 1], key[i * 4 + 2], key[i * 4 + 3]];
+            i += 1;
+        }
+
+        while i < WORDS {
             let mut temp: [u8; 4] = round_keys[i - 1];
-            if i % nk == 0 {
+            if i.is_multiple_of(nk) {
                 Self::rot_word(&mut temp);
                 Self::sub_word(&mut temp);
                 temp[0] ^= (R_CON[(i / nk) - 1] >> 24) as u8;
             } else if nk == 8 && i % nk == 4 {
                 Self::sub_word(&mut temp);
             }
-            round_keys[position] = [
+            round_keys[i] = [
                 round_keys[i - nk][0] ^ temp[0],
                 round_keys[i - nk][1] ^ temp[1],
                 round_keys[i - nk][2] ^ temp[2],
                 round_keys[i - nk][3] ^ temp[3],
             ];
-            position += 1;
+            i += 1;
         }
 
         round_keys
     }
+}
+
+/// The AES-128 cipher, specialized for the most common key size.
+/// The key schedule is a fixed array and the number of rounds is a constant, so there is no runtime match on the key size.
+/// The ciphertext is the same as of `AESCore` with the same key, which it converts into for use with the modes of operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Aes128 {
+    /// The round keys used in the AES algorithm.
+    round_keys: [[u8; 4]; 44],
+}
+
+/// Public functions for encrypting and decrypting data with AES-128.
+impl Aes128 {
+    pub fn new(key: [u8; 16]) -> Self {
+        //! Creates a new AES-128 instance with the given key.
+
+        Self {
+            round_keys: expand_key_128(key),
+        }
+    }
+
+    pub fn encrypt(&self, block: &Block) -> Block {
+        //! Encrypts the given block of data.
+
+        let mut out_block: [u8; 16] = *block;
+        self.encrypt_in_place(&mut out_block);
+        out_block
+    }
+
+    pub fn encrypt_in_place(&self, block: &mut Block) {
+        //! Encrypts the given block of data in place.
+
+        let mut state: [u32; 4] = AESCore::load_state(block);
+
+        AESCore::add_round_key(&mut state, &self.round_keys[0..4]);
+        for round in 1..10 {
+            AESCore::sub_shift(&mut state);
+            AESCore::mix_columns(&mut state);
+            AESCore::add_round_key(&mut state, &self.round_keys[round * 4..(round + 1) * 4]);
+        }
+        AESCore::sub_shift(&mut state);
+        AESCore::add_round_key(&mut state, &self.round_keys[40..44]);
+
+        AESCore::store_state(&state, block);
+    }
+
+    pub fn decrypt(&self, block: &Block) -> Block {
+        //! Decrypts the given block of data.
+
+        let mut out_block: [u8; 16] = *block;
+        self.decrypt_in_place(&mut out_block);
+        out_block
+    }
+
+    pub fn decrypt_in_place(&self, block: &mut Block) {
+        //! Decrypts the given block of data in place.
+
+        let mut state: [u32; 4] = AESCore::load_state(block);
+
+        AESCore::add_round_key(&mut state, &self.round_keys[40..44]);
+        for round in (1..10).rev() {
+            AESCore::inv_shift_sub(&mut state);
+            AESCore::add_round_key(&mut state, &self.round_keys[round * 4..(round + 1) * 4]);
+            AESCore::inv_mix_columns(&mut state);
+        }
+        AESCore::inv_shift_sub(&mut state);
+        AESCore::add_round_key(&mut state, &self.round_keys[0..4]);
+
+        AESCore::store_state(&state, block);
+    }
+}
+
+impl From<Aes128> for AESCore {
+    fn from(aes: Aes128) -> Self {
+        let round_keys = RoundKeys::AES128(aes.round_keys);
+
+        Self {
+            round_keys,
+            inv_round_keys: Self::inv_key_expansion(&round_keys),
+        }
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub const fn expand_key_128(key: [u8; 16]) -> [[u8; 4]; 44] {
+    //! Expands the AES-128 key into the round keys at compile time.
+    //! Useful for keys known at compile time, which can be expanded into a `const`.
+    //!
+    //! **Example:**
+    //! ```
+    //! use tinyaes::expand_key_128;
+    //!
+    //! const ROUND_KEYS: [[u8; 4]; 44] = expand_key_128([0x00; 16]);
+    //! assert_eq!(ROUND_KEYS[43], [0x6f, 0x8f, 0x18, 0x8e]);
+    //! ```
+
+    AESCore::expand_key(key)
+}
+
+pub const fn expand_key_192(key: [u8; 24]) -> [[u8; 4]; 52] {
+    //! Expands the AES-192 key into the round keys at compile time.
+
+    AESCore::expand_key(key)
+}
+
+pub const fn expand_key_256(key: [u8; 32]) -> [[u8; 4]; 60] {
+    //! Expands the AES-256 key into the round keys at compile time.
+
+    AESCore::expand_key(key)
+}
+
+pub fn verify_sboxes() -> bool {
+    //! Checks the S-Box tables against their definition, to catch transcription errors.
+    //! Every S-Box entry must be the affine transformation of the multiplicative inverse in GF(2^8),
+    //! and the inverse S-Box must undo the S-Box for all 256 values.
+    //! Intended for tests and debug checks, it isn't needed for encryption.
+    //! # Returns
+    //! * `bool` - Whether both tables are correct.
+
+    (0..=255_u8).all(|x| {
+        let substituted: u8 = S_BOX[(x >> 4) as usize][(x & 0b00001111) as usize];
+
+        // the multiplicative inverse is x^254 (zero is mapped to itself)
+        let mut inverse: u8 = 1;
+        for _ in 0..254 {
+            inverse = gf_mul(inverse, x);
+        }
+        let affine: u8 = inverse ^ inverse.rotate_left(1) ^ inverse.rotate_left(2) ^ inverse.rotate_left(3) ^ inverse.rotate_left(4) ^ 0x63;
+
+        substituted == affine && INV_S_BOX[(substituted >> 4) as usize][(substituted & 0b00001111) as usize] == x
+    })
+}
+
+pub fn mct(key: AESKey, plaintext: Block) -> Block {
+    //! Runs the NIST AES Monte Carlo Test in ECB mode (AESAVS, section 6.4.1) as a self-check of the whole cipher.
+    //! In each of the 100 outer iterations, the block is encrypted 1000 times in a chain,
+    //! and then the key is XORed with the last output bits (as many as the key has).
+    //! The result can be compared with the last `CIPHERTEXT` of the CAVP `ECBMCT` files.
+    //! # Arguments
+    //! * `key` - The seed key.
+    //! * `plaintext` - The seed block.
+    //! # Returns
+    //! * `Block` - The last ciphertext of the last outer iteration.
+
+    let mut key: AESKey = key;
+    let mut block: Block = plaintext;
+
+    for _ in 0..100 {
+        let core = AESCore::new(key);
+        let mut previous: Block = block;
+        for _ in 0..1000 {
+            previous = block;
+            core.encrypt_in_place(&mut block);
+        }
+
+        let mut outputs: [u8; 32] = [0; 32];
+        outputs[..16].copy_from_slice(&previous);
+        outputs[16..].copy_from_slice(&block);
+        let mut key_bytes: [u8; 32] = [0; 32];
+        let key_len = key.as_bytes().len();
+        for i in 0..key_len {
+            key_bytes[i] = key.as_bytes()[i] ^ outputs[32 - key_len + i];
+        }
+        key = AESKey::try_from(&key_bytes[..key_len]).expect("the key length is unchanged");
+    }
+
+    block
+}
+
+const fn sub_byte(byte: u8) -> u8 {
+    //! Substitutes the byte with the S-Box of the selected backend (the `sbox-table` or the `sbox-computed` feature).
+    //! The computed S-Box is used if both features are enabled.
+
+    #[cfg(feature = "sbox-computed")]
+    {
+        sub_byte_computed(byte)
+    }
+    #[cfg(not(feature = "sbox-computed"))]
+    {
+        sub_byte_table(byte)
+    }
+}
+
+const fn inv_sub_byte(byte: u8) -> u8 {
+    //! Substitutes the byte with the inverse S-Box of the selected backend (the `sbox-table` or the `sbox-computed` feature).
+    //! The computed inverse S-Box is used if both features are enabled.
+
+    #[cfg(feature = "sbox-computed")]
+    {
+        inv_sub_byte_computed(byte)
+    }
+    #[cfg(not(feature = "sbox-computed"))]
+    {
+        inv_sub_byte_table(byte)
+    }
+}
 
-    fn rot_word(word: &mut [u8; 4]) {
-        //! Rotates the word to the left by one byte.
+#[cfg(any(test, not(feature = "sbox-computed")))]
+const fn sub_byte_table(byte: u8) -> u8 {
+    //! Substitutes the byte with a lookup in the S-Box table.
+    //! Fast, but the memory access depends on the byte, so the timing may leak it through the cache.
+
+    S_BOX[(byte >> 4) as usize][(byte & 0b00001111) as usize]
+}
+
+#[cfg(any(test, not(feature = "sbox-computed")))]
+const fn inv_sub_byte_table(byte: u8) -> u8 {
+    //! Substitutes the byte with a lookup in the inverse S-Box table.
+
+    INV_S_BOX[(byte >> 4) as usize][(byte & 0b00001111) as usize]
+}
+
+#[cfg(any(test, feature = "sbox-computed"))]
+const fn sub_byte_computed(byte: u8) -> u8 {
+    //! Substitutes the byte by computing the S-Box, the affine transformation of the multiplicative inverse in GF(2^8).
+    //! There are no table lookups or branches on the byte, so the time taken doesn't depend on it.
 
-        word.rotate_left(1);
+    let inverse: u8 = gf_inv_ct(byte);
+    inverse ^ inverse.rotate_left(1) ^ inverse.rotate_left(2) ^ inverse.rotate_left(3) ^ inverse.rotate_left(4) ^ 0x63
+}
+
+#[cfg(any(test, feature = "sbox-computed"))]
+const fn inv_sub_byte_computed(byte: u8) -> u8 {
+    //! Substitutes the byte by computing the inverse S-Box, the multiplicative inverse of the inverse affine transformation.
+
+    gf_inv_ct(byte.rotate_left(1) ^ byte.rotate_left(3) ^ byte.rotate_left(6) ^ 0x05)
+}
+
+#[cfg(any(test, feature = "sbox-computed"))]
+const fn gf_inv_ct(x: u8) -> u8 {
+    //! Computes the multiplicative inverse in GF(2^8) in constant time, as x^254 (zero is mapped to itself).
+    //! The exponent is reached with a fixed chain of squarings and multiplications.
+
+    let x3: u8 = gf_mul_ct(gf_mul_ct(x, x), x);
+    let x7: u8 = gf_mul_ct(gf_mul_ct(x3, x3), x);
+    let x15: u8 = gf_mul_ct(gf_mul_ct(x7, x7), x);
+    let x31: u8 = gf_mul_ct(gf_mul_ct(x15, x15), x);
+    let x63: u8 = gf_mul_ct(gf_mul_ct(x31, x31), x);
+    let x127: u8 = gf_mul_ct(gf_mul_ct(x63, x63), x);
+    gf_mul_ct(x127, x127)
+}
+
+#[cfg(any(test, feature = "sbox-computed"))]
+const fn gf_mul_ct(a: u8, b: u8) -> u8 {
+    //! Multiplies two bytes in GF(2^8) with the AES polynomial, always in 8 steps and without branches.
+
+    let mut a: u8 = a;
+    let mut product: u8 = 0;
+    let mut i: u32 = 0;
+    while i < 8 {
+        product ^= a & ((b >> i) & 1).wrapping_neg();
+        a = (a << 1) ^ (((a >> 7) & 1) * 0x1b);
+        i += 1;
     }
+    product
+}
 
-    fn sub_word(word: &mut [u8; 4]) {
-        //! Substitutes the bytes of the word with the S-Box.
+fn gf_mul(a: u8, b: u8) -> u8 {
+    //! Multiplies two bytes in GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1.
 
-        for i in 0..4 {
-            word[i] = S_BOX[(word[i] >> 4) as usize][(word[i] & 0b00001111) as usize];
+    let mut a: u8 = a;
+    let mut b: u8 = b;
+    let mut product: u8 = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            product ^= a;
         }
+        a = (a << 1) ^ (((a >> 7) & 1) * 0x1b);
+        b >>= 1;
     }
+    product
 }
 
 
@@ -470,6 +1384,12 @@ impl AESCore {
 
 // CONSTANTS
 
+/// The size of an AES block in bytes (the same for all key sizes).
+pub const BLOCK_SIZE: usize = 16;
+
+/// An AES block.
+pub type Block = [u8; BLOCK_SIZE];
+
 /// The S-Box used in the AES algorithm.
 pub const S_BOX: [[u8; 16]; 16] = [
     [0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76],
@@ -525,6 +1445,36 @@ pub const R_CON: [u32; 10] = [
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
+
+    /// The expanded AES-128 example key from FIPS-197 (appendix A.1).
+    const ROUND_KEYS_128: [[u8; 4]; 44] = [
+        [0x2b, 0x7e, 0x15, 0x16], [0x28, 0xae, 0xd2, 0xa6], [0xab, 0xf7, 0x15, 0x88], [0x09, 0xcf, 0x4f, 0x3c],
+        [0xa0, 0xfa, 0xfe, 0x17], [0x88, 0x54, 0x2c, 0xb1], [0x23, 0xa3, 0x39, 0x39], [0x2a, 0x6c, 0x76, 0x05],
+        [0xf2, 0xc2, 0x95, 0xf2], [0x7a, 0x96, 0xb9, 0x43], [0x59, 0x35, 0x80, 0x7a], [0x73, 0x59, 0xf6, 0x7f],
+        [0x3d, 0x80, 0x47, 0x7d], [0x47, 0x16, 0xfe, 0x3e], [0x1e, 0x23, 0x7e, 0x44], [0x6d, 0x7a, 0x88, 0x3b],
+        [0xef, 0x44, 0xa5, 0x41], [0xa8, 0x52, 0x5b, 0x7f], [0xb6, 0x71, 0x25, 0x3b], [0xdb, 0x0b, 0xad, 0x00],
+        [0xd4, 0xd1, 0xc6, 0xf8], [0x7c, 0x83, 0x9d, 0x87], [0xca, 0xf2, 0xb8, 0xbc], [0x11, 0xf9, 0x15, 0xbc],
+        [0x6d, 0x88, 0xa3, 0x7a], [0x11, 0x0b, 0x3e, 0xfd], [0xdb, 0xf9, 0x86, 0x41], [0xca, 0x00, 0x93, 0xfd],
+        [0x4e, 0x54, 0xf7, 0x0e], [0x5f, 0x5f, 0xc9, 0xf3], [0x84, 0xa6, 0x4f, 0xb2], [0x4e, 0xa6, 0xdc, 0x4f],
+        [0xea, 0xd2, 0x73, 0x21], [0xb5, 0x8d, 0xba, 0xd2], [0x31, 0x2b, 0xf5, 0x60], [0x7f, 0x8d, 0x29, 0x2f],
+        [0xac, 0x77, 0x66, 0xf3], [0x19, 0xfa, 0xdc, 0x21], [0x28, 0xd1, 0x29, 0x41], [0x57, 0x5c, 0x00, 0x6e],
+        [0xd0, 0x14, 0xf9, 0xa8], [0xc9, 0xee, 0x25, 0x89], [0xe1, 0x3f, 0x0c, 0xc8], [0xb6, 0x63, 0x0c, 0xa6],
+    ];
+
+    fn columns(rows: [[u8; 4]; 4]) -> [u32; 4] {
+        //! Packs the state written as rows into the column words.
+
+        let mut state: [u32; 4] = [0; 4];
+        for c in 0..4 {
+            state[c] = u32::from_be_bytes([rows[0][c], rows[1][c], rows[2][c], rows[3][c]]);
+        }
+        state
+    }
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
 
     #[test]
     fn new() {
@@ -537,7 +1487,7 @@ mod tests {
              0x09, 0xcf, 0x4f, 0x3c],
         ));
 
-        match aes128.key {
+        match aes128.key() {
             AESKey::AES128(_) => (),
             _ => panic!("AES128 not created correctly in new function"),
         }
@@ -551,7 +1501,7 @@ mod tests {
              0x52, 0x2c, 0x6b, 0x7b],
         ));
 
-        match aes192.key {
+        match aes192.key() {
             AESKey::AES192(_) => (),
             _ => panic!("AES192 not created correctly in new function"),
         }
@@ -567,12 +1517,37 @@ mod tests {
              0x09, 0x14, 0xdf, 0xf4],
         ));
 
-        match aes256.key {
+        match aes256.key() {
             AESKey::AES256(_) => (),
             _ => panic!("AES256 not created correctly in new function"),
         }
     }
 
+    #[test]
+    #[allow(clippy::clone_on_copy)]  // testing Clone explicitly
+    fn clone_and_eq() {
+        //! Test that a cloned core produces identical ciphertext and that keys compare by value
+
+        let key = AESKey::AES192([0x5a; 24]);
+        let aes_core = AESCore::new(key);
+        let cloned_aes_core = aes_core.clone();
+        let block: [u8; 16] = *b"cloned AES core!";
+
+        assert_eq!(cloned_aes_core, aes_core);
+        assert_eq!(cloned_aes_core.encrypt(&block), aes_core.encrypt(&block));
+        assert_eq!(cloned_aes_core.decrypt(&block), aes_core.decrypt(&block));
+
+        let mut other_key = [0x5a; 24];
+        other_key[23] = 0x5b;
+        assert_eq!(key.clone(), key);
+        assert_ne!(AESKey::AES192(other_key), key);
+        assert_ne!(AESKey::AES128([0x5a; 16]), AESKey::AES256([0x5a; 32]));
+        assert_ne!(AESCore::new(AESKey::AES192(other_key)), aes_core);
+
+        let keys: std::collections::HashSet<AESKey> = [key, key.clone(), AESKey::AES192(other_key)].into_iter().collect();
+        assert_eq!(keys.len(), 2);
+    }
+
     #[test]
     fn encrypt() {
         //! Test encryption with AES-128, AES-192, and AES-256
@@ -714,6 +1689,202 @@ mod tests {
             0xcc, 0xdd, 0xee, 0xff]);
     }
 
+    #[test]
+    fn decrypt_eqinv() {
+        //! Test that the equivalent inverse cipher matches the inverse cipher
+
+        // simple xorshift generator, good enough for producing test blocks
+        let mut seed: u64 = 0x9e3779b97f4a7c15;
+        let mut next_byte = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 32) as u8
+        };
+
+        for _ in 0..100 {
+            let mut key128: [u8; 16] = [0; 16];
+            let mut key192: [u8; 24] = [0; 24];
+            let mut key256: [u8; 32] = [0; 32];
+            let mut block: [u8; 16] = [0; 16];
+            key128.iter_mut().for_each(|byte| *byte = next_byte());
+            key192.iter_mut().for_each(|byte| *byte = next_byte());
+            key256.iter_mut().for_each(|byte| *byte = next_byte());
+            block.iter_mut().for_each(|byte| *byte = next_byte());
+
+            for aes in [
+                AESCore::new(AESKey::AES128(key128)),
+                AESCore::new(AESKey::AES192(key192)),
+                AESCore::new(AESKey::AES256(key256)),
+            ] {
+                assert_eq!(aes.decrypt_eqinv(&block), aes.decrypt(&block));
+                assert_eq!(aes.decrypt_eqinv(&aes.encrypt(&block)), block);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_keys() {
+        //! Test the all-zero and all-0xFF keys and plaintexts with every key size
+
+        let cases: [(AESKey, [u8; 16], [u8; 16]); 12] = [
+            (AESKey::AES128([0x00; 16]), [0x00; 16], [0x66, 0xe9, 0x4b, 0xd4, 0xef, 0x8a, 0x2c, 0x3b, 0x88, 0x4c, 0xfa, 0x59, 0xca, 0x34, 0x2b, 0x2e]),
+            (AESKey::AES128([0x00; 16]), [0xff; 16], [0x3f, 0x5b, 0x8c, 0xc9, 0xea, 0x85, 0x5a, 0x0a, 0xfa, 0x73, 0x47, 0xd2, 0x3e, 0x8d, 0x66, 0x4e]),
+            (AESKey::AES128([0xff; 16]), [0x00; 16], [0xa1, 0xf6, 0x25, 0x8c, 0x87, 0x7d, 0x5f, 0xcd, 0x89, 0x64, 0x48, 0x45, 0x38, 0xbf, 0xc9, 0x2c]),
+            (AESKey::AES128([0xff; 16]), [0xff; 16], [0xbc, 0xbf, 0x21, 0x7c, 0xb2, 0x80, 0xcf, 0x30, 0xb2, 0x51, 0x70, 0x52, 0x19, 0x3a, 0xb9, 0x79]),
+            (AESKey::AES192([0x00; 24]), [0x00; 16], [0xaa, 0xe0, 0x69, 0x92, 0xac, 0xbf, 0x52, 0xa3, 0xe8, 0xf4, 0xa9, 0x6e, 0xc9, 0x30, 0x0b, 0xd7]),
+            (AESKey::AES192([0x00; 24]), [0xff; 16], [0xb1, 0x3d, 0xb4, 0xda, 0x1f, 0x71, 0x8b, 0xc6, 0x90, 0x47, 0x97, 0xc8, 0x2b, 0xcf, 0x2d, 0x32]),
+            (AESKey::AES192([0xff; 24]), [0x00; 16], [0xdd, 0x8a, 0x49, 0x35, 0x14, 0x23, 0x1c, 0xbf, 0x56, 0xec, 0xce, 0xe4, 0xc4, 0x08, 0x89, 0xfb]),
+            (AESKey::AES192([0xff; 24]), [0xff; 16], [0xbf, 0x70, 0x03, 0x4e, 0x29, 0xff, 0x71, 0x8e, 0xe4, 0x8d, 0xdf, 0x36, 0xbb, 0x81, 0x74, 0xef]),
+            (AESKey::AES256([0x00; 32]), [0x00; 16], [0xdc, 0x95, 0xc0, 0x78, 0xa2, 0x40, 0x89, 0x89, 0xad, 0x48, 0xa2, 0x14, 0x92, 0x84, 0x20, 0x87]),
+            (AESKey::AES256([0x00; 32]), [0xff; 16], [0xac, 0xda, 0xce, 0x80, 0x78, 0xa3, 0x2b, 0x1a, 0x18, 0x2b, 0xfa, 0x49, 0x87, 0xca, 0x13, 0x47]),
+            (AESKey::AES256([0xff; 32]), [0x00; 16], [0x4b, 0xf8, 0x5f, 0x1b, 0x5d, 0x54, 0xad, 0xbc, 0x30, 0x7b, 0x0a, 0x04, 0x83, 0x89, 0xad, 0xcb]),
+            (AESKey::AES256([0xff; 32]), [0xff; 16], [0xd5, 0xf9, 0x3d, 0x6d, 0x33, 0x11, 0xcb, 0x30, 0x9f, 0x23, 0x62, 0x1b, 0x02, 0xfb, 0xd5, 0xe2]),
+        ];
+
+        for (key, plaintext, ciphertext) in cases {
+            let aes_core = AESCore::new(key);
+            assert_eq!(aes_core.encrypt(&plaintext), ciphertext);
+            assert_eq!(aes_core.decrypt(&ciphertext), plaintext);
+            assert_eq!(aes_core.decrypt_eqinv(&ciphertext), plaintext);
+        }
+    }
+
+    #[test]
+    fn in_place() {
+        //! Test that in-place encryption and decryption match the by-value functions
+
+        let keys = [
+            AESKey::AES128([0x0f; 16]),
+            AESKey::AES192([0x1e; 24]),
+            AESKey::AES256([0x2d; 32]),
+        ];
+        let mut block: [u8; 16] = *b"in-place block!!";
+
+        for key in keys {
+            let aes_core = AESCore::new(key);
+            for _ in 0..4 {
+                let encrypted = aes_core.encrypt(&block);
+                let original_block = block;
+
+                aes_core.encrypt_in_place(&mut block);
+                assert_eq!(block, encrypted);
+                assert_eq!(aes_core.decrypt(&block), original_block);
+
+                aes_core.decrypt_in_place(&mut block);
+                assert_eq!(block, original_block);
+
+                block = encrypted;
+            }
+        }
+    }
+
+    #[test]
+    fn key_conversions() {
+        //! Test converting arrays and slices into keys
+
+        let key: AESKey = [0x01; 16].into();
+        assert_eq!(key, AESKey::AES128([0x01; 16]));
+        let key: AESKey = [0x02; 24].into();
+        assert_eq!(key, AESKey::AES192([0x02; 24]));
+        let key: AESKey = [0x03; 32].into();
+        assert_eq!(key, AESKey::AES256([0x03; 32]));
+
+        let bytes: Vec<u8> = (0..40).collect();
+        assert_eq!(AESKey::try_from(&bytes[..16]), Ok(AESKey::AES128(bytes[..16].try_into().unwrap())));
+        assert_eq!(AESKey::try_from(&bytes[..24]), Ok(AESKey::AES192(bytes[..24].try_into().unwrap())));
+        assert_eq!(AESKey::try_from(&bytes[..32]), Ok(AESKey::AES256(bytes[..32].try_into().unwrap())));
+
+        for len in [0, 15, 17, 20, 31, 33, 40] {
+            assert_eq!(AESKey::try_from(&bytes[..len]), Err(KeyError::InvalidLength));
+        }
+        assert_eq!(KeyError::InvalidLength.to_string(), "key must be 16, 24 or 32 bytes long");
+    }
+
+    #[test]
+    fn encrypt_to() {
+        //! Test encrypting into caller-provided buffers
+
+        let aes_core = AESCore::new(AESKey::AES128([0x3c; 16]));
+        let input: Vec<u8> = (0..64).collect();
+
+        let mut out: [u8; 16] = [0; 16];
+        aes_core.encrypt_to(input[..16].try_into().unwrap(), &mut out);
+        assert_eq!(out, aes_core.encrypt(input[..16].try_into().unwrap()));
+
+        let mut output: [u8; 64] = [0; 64];
+        aes_core.encrypt_slice_to(&input, &mut output).unwrap();
+        for i in (0..64).step_by(16) {
+            assert_eq!(output[i..(i + 16)], aes_core.encrypt(input[i..(i + 16)].try_into().unwrap()));
+        }
+
+        aes_core.encrypt_slice_to(&[], &mut []).unwrap();
+        assert_eq!(aes_core.encrypt_slice_to(&input[..63], &mut output[..63]), Err(LenError::NotBlockMultiple));
+        assert_eq!(aes_core.encrypt_slice_to(&input, &mut output[..48]), Err(LenError::LengthMismatch));
+        assert_eq!(aes_core.encrypt_slice_to(&input[..32], &mut output), Err(LenError::LengthMismatch));
+        assert_eq!(LenError::LengthMismatch.to_string(), "input and output must be the same length");
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_ecb() {
+        //! Test that the parallel ECB functions match the serial path on a multi-megabyte buffer
+
+        let aes_core = AESCore::new(AESKey::AES192([0x4e; 24]));
+        let plaintext: Vec<u8> = (0..(4 << 20)).map(|i: u32| (i * 31 % 251) as u8).collect();
+
+        let mut expected: Vec<u8> = vec![0; plaintext.len()];
+        aes_core.encrypt_slice_to(&plaintext, &mut expected).unwrap();
+
+        let mut data = plaintext.clone();
+        aes_core.par_encrypt_ecb(&mut data).unwrap();
+        assert_eq!(data, expected);
+        aes_core.par_decrypt_ecb(&mut data).unwrap();
+        assert_eq!(data, plaintext);
+
+        assert_eq!(aes_core.par_encrypt_ecb(&mut data[..100]), Err(LenError::NotBlockMultiple));
+        assert_eq!(aes_core.par_decrypt_ecb(&mut data[..100]), Err(LenError::NotBlockMultiple));
+    }
+
+    #[test]
+    fn slice_blocks() {
+        //! Test encrypting and decrypting blocks given as slices of any length
+
+        let aes_core = AESCore::new(AESKey::AES256([0x7d; 32]));
+        let input: Vec<u8> = (0..17).collect();
+
+        let ciphertext = aes_core.encrypt_slice(&input[..16]).unwrap();
+        assert_eq!(ciphertext, aes_core.encrypt(input[..16].try_into().unwrap()));
+        assert_eq!(aes_core.decrypt_slice(&ciphertext).unwrap(), input[..16]);
+
+        for len in [0, 15, 17] {
+            assert_eq!(aes_core.encrypt_slice(&input[..len]), Err(LenError::NotOneBlock));
+            assert_eq!(aes_core.decrypt_slice(&input[..len]), Err(LenError::NotOneBlock));
+        }
+        assert_eq!(LenError::NotOneBlock.to_string(), "block must be exactly 16 bytes long");
+    }
+
+    #[test]
+    fn key_size_and_rounds() {
+        //! Test the key size and the number of rounds of every variant
+
+        let aes128 = AESCore::new(AESKey::AES128([0; 16]));
+        assert_eq!(aes128.key_size_bits(), 128);
+        assert_eq!(aes128.num_rounds(), 10);
+
+        let aes192 = AESCore::new(AESKey::AES192([0; 24]));
+        assert_eq!(aes192.key_size_bits(), 192);
+        assert_eq!(aes192.num_rounds(), 12);
+
+        let aes256 = AESCore::new(AESKey::AES256([0; 32]));
+        assert_eq!(aes256.key_size_bits(), 256);
+        assert_eq!(aes256.num_rounds(), 14);
+
+        for aes_core in [aes128, aes192, aes256] {
+            assert_eq!(aes_core.round_keys().len(), 4 * (aes_core.num_rounds() + 1));
+        }
+    }
+
     #[test]
     fn set_key() {
         //! Test changing the key
@@ -727,7 +1898,6 @@ mod tests {
         let mut aes_core = AESCore::new(key);
         let original_aes_core = aes_core;
 
-        assert_eq!(aes_core.key, key);
         assert_eq!(aes_core.key(), key);
         assert_eq!(aes_core.round_keys, AESCore::key_expansion(&key));
         assert_eq!(aes_core, original_aes_core);
@@ -738,7 +1908,6 @@ mod tests {
             0x18, 0x19, 0x1a, 0x1b,
             0x1c, 0x1d, 0x1e, 0x1f]);
         aes_core.set_key(new_key);
-        assert_eq!(aes_core.key, new_key);
         assert_eq!(aes_core.key(), new_key);
         assert_eq!(aes_core.round_keys, AESCore::key_expansion(&new_key));
         assert_ne!(aes_core, original_aes_core);
@@ -751,7 +1920,6 @@ mod tests {
             0x30, 0x31, 0x32, 0x33,
             0x34, 0x35, 0x36, 0x37]);
         aes_core.set_key(new_key2);
-        assert_eq!(aes_core.key, new_key2);
         assert_eq!(aes_core.key(), new_key2);
         assert_eq!(aes_core.round_keys, AESCore::key_expansion(&new_key2));
         assert_ne!(aes_core, original_aes_core);
@@ -766,18 +1934,123 @@ mod tests {
             0x58, 0x59, 0x5a, 0x5b,
             0x5c, 0x5d, 0x5e, 0x5f]);
         aes_core.set_key(new_key3);
-        assert_eq!(aes_core.key, new_key3);
         assert_eq!(aes_core.key(), new_key3);
         assert_eq!(aes_core.round_keys, AESCore::key_expansion(&new_key3));
         assert_ne!(aes_core, original_aes_core);
 
         aes_core.set_key(key);
-        assert_eq!(aes_core.key, key);
         assert_eq!(aes_core.key(), key);
         assert_eq!(aes_core.round_keys, AESCore::key_expansion(&key));
         assert_eq!(aes_core, original_aes_core);
     }
 
+    #[test]
+    fn derived() {
+        //! Test that derived instances are deterministic and independent for distinct salts
+
+        let root = AESKey::AES256([0x2a; 32]);
+        let block: [u8; 16] = [0x5c; 16];
+
+        let encryption = AESCore::derived(&root, b"encryption");
+        let authentication = AESCore::derived(&root, b"authentication");
+
+        assert_eq!(encryption, AESCore::derived(&root, b"encryption"));
+        assert_eq!(encryption.key(), derive_subkey(&root, b"encryption"));
+        assert!(matches!(encryption.key(), AESKey::AES256(_)));
+
+        assert_ne!(encryption.key(), authentication.key());
+        assert_ne!(encryption.key(), root);
+        assert_ne!(encryption.encrypt(&block), authentication.encrypt(&block));
+        assert_ne!(encryption.encrypt(&block), AESCore::new(root).encrypt(&block));
+        assert_eq!(encryption.decrypt(&encryption.encrypt(&block)), block);
+    }
+
+    #[test]
+    #[cfg(feature = "trace")]
+    fn encrypt_trace() {
+        //! Test the traced states against the FIPS-197 appendix B example
+
+        let aes128: AESCore = AESCore::new(AESKey::AES128([
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f, 0x3c,
+        ]));
+        let block: [u8; 16] = [0x32, 0x43, 0xf6, 0xa8, 0x88, 0x5a, 0x30, 0x8d, 0x31, 0x31, 0x98, 0xa2, 0xe0, 0x37, 0x07, 0x34];
+
+        let trace = aes128.encrypt_trace(&block);
+        assert_eq!(trace.len(), 1 + 9 * 4 + 3);
+        assert_eq!(trace.last().unwrap().as_flattened(), aes128.encrypt(&block));
+
+        let expected: [(usize, [[u8; 4]; 4]); 10] = [
+            // the start of round 1
+            (0, [[0x19, 0x3d, 0xe3, 0xbe], [0xa0, 0xf4, 0xe2, 0x2b], [0x9a, 0xc6, 0x8d, 0x2a], [0xe9, 0xf8, 0x48, 0x08]]),
+            // round 1 after SubBytes, ShiftRows, MixColumns and AddRoundKey
+            (1, [[0xd4, 0x27, 0x11, 0xae], [0xe0, 0xbf, 0x98, 0xf1], [0xb8, 0xb4, 0x5d, 0xe5], [0x1e, 0x41, 0x52, 0x30]]),
+            (2, [[0xd4, 0xbf, 0x5d, 0x30], [0xe0, 0xb4, 0x52, 0xae], [0xb8, 0x41, 0x11, 0xf1], [0x1e, 0x27, 0x98, 0xe5]]),
+            (3, [[0x04, 0x66, 0x81, 0xe5], [0xe0, 0xcb, 0x19, 0x9a], [0x48, 0xf8, 0xd3, 0x7a], [0x28, 0x06, 0x26, 0x4c]]),
+            (4, [[0xa4, 0x9c, 0x7f, 0xf2], [0x68, 0x9f, 0x35, 0x2b], [0x6b, 0x5b, 0xea, 0x43], [0x02, 0x6a, 0x50, 0x49]]),
+            // round 9 after MixColumns and AddRoundKey
+            (35, [[0x47, 0x37, 0x94, 0xed], [0x40, 0xd4, 0xe4, 0xa5], [0xa3, 0x70, 0x3a, 0xa6], [0x4c, 0x9f, 0x42, 0xbc]]),
+            (36, [[0xeb, 0x40, 0xf2, 0x1e], [0x59, 0x2e, 0x38, 0x84], [0x8b, 0xa1, 0x13, 0xe7], [0x1b, 0xc3, 0x42, 0xd2]]),
+            // round 10 after SubBytes, ShiftRows and AddRoundKey (the output)
+            (37, [[0xe9, 0x09, 0x89, 0x72], [0xcb, 0x31, 0x07, 0x5f], [0x3d, 0x32, 0x7d, 0x94], [0xaf, 0x2e, 0x2c, 0xb5]]),
+            (38, [[0xe9, 0x31, 0x7d, 0xb5], [0xcb, 0x32, 0x2c, 0x72], [0x3d, 0x2e, 0x89, 0x5f], [0xaf, 0x09, 0x07, 0x94]]),
+            (39, [[0x39, 0x25, 0x84, 0x1d], [0x02, 0xdc, 0x09, 0xfb], [0xdc, 0x11, 0x85, 0x97], [0x19, 0x6a, 0x0b, 0x32]]),
+        ];
+        for (index, state) in expected {
+            assert_eq!(trace[index], state);
+        }
+
+        let aes256: AESCore = AESCore::new(AESKey::AES256([0x60; 32]));
+        let trace = aes256.encrypt_trace(&block);
+        assert_eq!(trace.len(), 1 + 13 * 4 + 3);
+        assert_eq!(trace.last().unwrap().as_flattened(), aes256.encrypt(&block));
+    }
+
+    #[test]
+    fn sboxes() {
+        //! Test that the S-Box tables match their definition
+
+        assert!(verify_sboxes());
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        assert_eq!(gf_mul(0x57, 0x13), 0xfe);
+    }
+
+    #[test]
+    fn state_packing() {
+        //! Test loading the block into the column words and storing it back
+
+        let block: [u8; 16] = [
+            0x00, 0x01, 0x02, 0x03,
+            0x10, 0x11, 0x12, 0x13,
+            0x20, 0x21, 0x22, 0x23,
+            0x30, 0x31, 0x32, 0x33,
+        ];
+        let state: [u32; 4] = AESCore::load_state(&block);
+        assert_eq!(state, [0x00010203, 0x10111213, 0x20212223, 0x30313233]);
+        assert_eq!(state, columns([
+            [0x00, 0x10, 0x20, 0x30],
+            [0x01, 0x11, 0x21, 0x31],
+            [0x02, 0x12, 0x22, 0x32],
+            [0x03, 0x13, 0x23, 0x33]
+        ]));
+
+        let mut out_block: [u8; 16] = [0; 16];
+        AESCore::store_state(&state, &mut out_block);
+        assert_eq!(out_block, block);
+
+        // FIPS-197 appendix B, the state after the first round of the example cipher
+        let mut state: [u32; 4] = AESCore::load_state(&[
+            0x19, 0x3d, 0xe3, 0xbe, 0xa0, 0xf4, 0xe2, 0x2b, 0x9a, 0xc6, 0x8d, 0x2a, 0xe9, 0xf8, 0x48, 0x08,
+        ]);
+        AESCore::sub_bytes(&mut state);
+        AESCore::shift_rows(&mut state);
+        AESCore::mix_columns(&mut state);
+        AESCore::add_round_key(&mut state, &ROUND_KEYS_128[4..8]);
+        AESCore::store_state(&state, &mut out_block);
+        assert_eq!(out_block, [
+            0xa4, 0x9c, 0x7f, 0xf2, 0x68, 0x9f, 0x35, 0x2b, 0x6b, 0x5b, 0xea, 0x43, 0x02, 0x6a, 0x50, 0x49,
+        ]);
+    }
+
     #[test]
     fn add_round_key() {
         //! Test the add round key function
@@ -809,57 +2082,57 @@ mod tests {
                 0x1c, 0x1d, 0x1e, 0x1f],
         ));
 
-        let state_aes128_original: [[u8; 4]; 4] = [
+        let state_aes128_original: [u32; 4] = columns([
             [0x00, 0x44, 0x88, 0xcc],
             [0x11, 0x55, 0x99, 0xdd],
             [0x22, 0x66, 0xaa, 0xee],
             [0x33, 0x77, 0xbb, 0xff]
-        ];
-        let state_aes128_inverted: [[u8; 4]; 4] = [
+        ]);
+        let state_aes128_inverted: [u32; 4] = columns([
             [0x00, 0x40, 0x80, 0xc0],
             [0x10, 0x50, 0x90, 0xd0],
             [0x20, 0x60, 0xa0, 0xe0],
             [0x30, 0x70, 0xb0, 0xf0]
-        ];
-        let mut state_aes128_temp: [[u8; 4]; 4] = state_aes128_original;
+        ]);
+        let mut state_aes128_temp: [u32; 4] = state_aes128_original;
         assert_eq!(state_aes128_original, state_aes128_temp);
         AESCore::add_round_key(&mut state_aes128_temp, &aes128.round_keys[0..4]);
         assert_eq!(state_aes128_temp, state_aes128_inverted);
         AESCore::add_round_key(&mut state_aes128_temp, &aes128.round_keys[0..4]);
         assert_eq!(state_aes128_temp, state_aes128_original);
 
-        let state_aes192_original: [[u8; 4]; 4] = [
+        let state_aes192_original: [u32; 4] = columns([
             [0x00, 0x44, 0x88, 0xcc],
             [0x11, 0x55, 0x99, 0xdd],
             [0x22, 0x66, 0xaa, 0xee],
             [0x33, 0x77, 0xbb, 0xff]
-        ];
-        let state_aes192_inverted: [[u8; 4]; 4] = [
+        ]);
+        let state_aes192_inverted: [u32; 4] = columns([
             [0x00, 0x40, 0x80, 0xc0],
             [0x10, 0x50, 0x90, 0xd0],
             [0x20, 0x60, 0xa0, 0xe0],
             [0x30, 0x70, 0xb0, 0xf0]
-        ];
-        let mut state_aes192_temp: [[u8; 4]; 4] = state_aes192_original;
+        ]);
+        let mut state_aes192_temp: [u32; 4] = state_aes192_original;
         assert_eq!(state_aes192_original, state_aes192_temp);
         AESCore::add_round_key(&mut state_aes192_temp, &aes192.round_keys[0..4]);
         assert_eq!(state_aes192_temp, state_aes192_inverted);
         AESCore::add_round_key(&mut state_aes192_temp, &aes192.round_keys[0..4]);
         assert_eq!(state_aes192_temp, state_aes192_original);
 
-        let state_aes256_original: [[u8; 4]; 4] = [
+        let state_aes256_original: [u32; 4] = columns([
             [0x00, 0x44, 0x88, 0xcc],
             [0x11, 0x55, 0x99, 0xdd],
             [0x22, 0x66, 0xaa, 0xee],
             [0x33, 0x77, 0xbb, 0xff]
-        ];
-        let state_aes256_inverted: [[u8; 4]; 4] = [
+        ]);
+        let state_aes256_inverted: [u32; 4] = columns([
             [0x00, 0x40, 0x80, 0xc0],
             [0x10, 0x50, 0x90, 0xd0],
             [0x20, 0x60, 0xa0, 0xe0],
             [0x30, 0x70, 0xb0, 0xf0]
-        ];
-        let mut state_aes256_temp: [[u8; 4]; 4] = state_aes256_original;
+        ]);
+        let mut state_aes256_temp: [u32; 4] = state_aes256_original;
         assert_eq!(state_aes256_original, state_aes256_temp);
         AESCore::add_round_key(&mut state_aes256_temp, &aes256.round_keys[0..4]);
         assert_eq!(state_aes256_temp, state_aes256_inverted);
@@ -871,20 +2144,20 @@ mod tests {
     fn mix_columns() {
         //! Test the mix columns and inverse mix columns functions
 
-        let original_state: [[u8; 4]; 4] = [
+        let original_state: [u32; 4] = columns([
             [0xdb, 0xf2, 0x01, 0xc6],
             [0x13, 0x0a, 0x01, 0xc6],
             [0x53, 0x22, 0x01, 0xc6],
             [0x45, 0x5c, 0x01, 0xc6]
-        ];
-        let inverted_state: [[u8; 4]; 4] = [
+        ]);
+        let inverted_state: [u32; 4] = columns([
             [0x8e, 0x9f, 0x01, 0xc6],
             [0x4d, 0xdc, 0x01, 0xc6],
             [0xa1, 0x58, 0x01, 0xc6],
             [0xbc, 0x9d, 0x01, 0xc6]
-        ];
+        ]);
 
-        let mut temp_state: [[u8; 4]; 4] = original_state;
+        let mut temp_state: [u32; 4] = original_state;
 
         assert_eq!(original_state, temp_state);
         AESCore::mix_columns(&mut temp_state);
@@ -893,24 +2166,80 @@ mod tests {
         assert_eq!(original_state, temp_state);
     }
 
+    #[test]
+    fn xtime() {
+        //! Test that the branchless xtime matches the branching definition for all 256 bytes, in every byte of the word
+
+        fn branching_xtime(byte: u8) -> u8 {
+            if (byte >> 7) == 1 {
+                (byte << 1) ^ 0x1b
+            } else {
+                byte << 1
+            }
+        }
+
+        for byte in 0..=255_u8 {
+            for shift in [0, 8, 16, 24] {
+                assert_eq!(AESCore::xtime((byte as u32) << shift), (branching_xtime(byte) as u32) << shift);
+            }
+            let word = u32::from_be_bytes([byte, byte ^ 0x80, !byte, byte.wrapping_add(0x41)]);
+            let expected = u32::from_be_bytes([
+                branching_xtime(byte),
+                branching_xtime(byte ^ 0x80),
+                branching_xtime(!byte),
+                branching_xtime(byte.wrapping_add(0x41)),
+            ]);
+            assert_eq!(AESCore::xtime(word), expected);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "simd")]
+    fn simd() {
+        //! Test that the SIMD AddRoundKey and MixColumns match the scalar ones
+
+        let mut rng_state: u32 = 0x12345678;
+        let mut next = || {
+            rng_state ^= rng_state << 13;
+            rng_state ^= rng_state >> 17;
+            rng_state ^= rng_state << 5;
+            rng_state
+        };
+
+        for _ in 0..1000 {
+            let state: [u32; 4] = [next(), next(), next(), next()];
+            let round_key: [[u8; 4]; 4] = [next().to_be_bytes(), next().to_be_bytes(), next().to_be_bytes(), next().to_be_bytes()];
+
+            let (mut simd_state, mut scalar_state) = (state, state);
+            AESCore::add_round_key(&mut simd_state, &round_key);
+            AESCore::add_round_key_scalar(&mut scalar_state, &round_key);
+            assert_eq!(simd_state, scalar_state);
+
+            let (mut simd_state, mut scalar_state) = (state, state);
+            AESCore::mix_columns(&mut simd_state);
+            AESCore::mix_columns_scalar(&mut scalar_state);
+            assert_eq!(simd_state, scalar_state);
+        }
+    }
+
     #[test]
     fn shift_rows() {
         //! Test the shift rows and inverse shift rows functions
 
-        let original_state: [[u8; 4]; 4] = [
+        let original_state: [u32; 4] = columns([
             [0x00, 0x01, 0x02, 0x03],
             [0x10, 0x11, 0x12, 0x13],
             [0x20, 0x21, 0x22, 0x23],
             [0x30, 0x31, 0x32, 0x33]
-        ];
-        let inverted_state: [[u8; 4]; 4] = [
+        ]);
+        let inverted_state: [u32; 4] = columns([
             [0x00, 0x01, 0x02, 0x03],
             [0x11, 0x12, 0x13, 0x10],
             [0x22, 0x23, 0x20, 0x21],
             [0x33, 0x30, 0x31, 0x32]
-        ];
+        ]);
 
-        let mut temp_state: [[u8; 4]; 4] = original_state;
+        let mut temp_state: [u32; 4] = original_state;
 
         assert_eq!(original_state, temp_state);
         AESCore::shift_rows(&mut temp_state);
@@ -923,20 +2252,20 @@ mod tests {
     fn sub_bytes() {
         //! Test the sub bytes and inverse sub bytes functions
 
-        let original_state: [[u8; 4]; 4] = [
+        let original_state: [u32; 4] = columns([
             [0x19, 0xa0, 0x9a, 0xe9],
             [0x3d, 0xf4, 0xc6, 0xf8],
             [0xe3, 0xe2, 0x8d, 0x48],
             [0xbe, 0x2b, 0x2a, 0x08]
-        ];
-        let inverted_state: [[u8; 4]; 4] = [
+        ]);
+        let inverted_state: [u32; 4] = columns([
             [0xd4, 0xe0, 0xb8, 0x1e],
             [0x27, 0xbf, 0xb4, 0x41],
             [0x11, 0x98, 0x5d, 0x52],
             [0xae, 0xf1, 0xe5, 0x30]
-        ];
+        ]);
 
-        let mut temp_state: [[u8; 4]; 4] = original_state;
+        let mut temp_state: [u32; 4] = original_state;
 
         assert_eq!(original_state, temp_state);
         AESCore::sub_bytes(&mut temp_state);
@@ -945,6 +2274,57 @@ mod tests {
         assert_eq!(original_state, temp_state);
     }
 
+    #[test]
+    fn sub_shift() {
+        //! Test that the fused functions match the separate transformations, and the whole cipher on pseudo-random blocks
+
+        let mut state: [u32; 4] = [0x193de3be, 0xa0f4e22b, 0x9ac68d2a, 0xe9f84808];
+        for _ in 0..64 {
+            let mut expected: [u32; 4] = state;
+            AESCore::sub_bytes(&mut expected);
+            AESCore::shift_rows(&mut expected);
+            let mut fused: [u32; 4] = state;
+            AESCore::sub_shift(&mut fused);
+            assert_eq!(fused, expected);
+
+            AESCore::inv_shift_rows(&mut expected);
+            AESCore::inv_sub_bytes(&mut expected);
+            AESCore::inv_shift_sub(&mut fused);
+            assert_eq!(fused, expected);
+            assert_eq!(fused, state);
+
+            AESCore::mix_columns(&mut state);
+            AESCore::sub_bytes(&mut state);
+        }
+
+        for key in [AESKey::AES128([0x2b; 16]), AESKey::AES192([0x8e; 24]), AESKey::AES256([0x60; 32])] {
+            let core = AESCore::new(key);
+            let mut block: [u8; 16] = [0x32; 16];
+            for _ in 0..64 {
+                // the reference cipher with the separate transformations
+                let mut state: [u32; 4] = AESCore::load_state(&block);
+                let rounds = core.round_keys.len() / 4 - 1;
+                AESCore::add_round_key(&mut state, &core.round_keys[0..4]);
+                for round in 1..=rounds {
+                    AESCore::sub_bytes(&mut state);
+                    AESCore::shift_rows(&mut state);
+                    if round != rounds {
+                        AESCore::mix_columns(&mut state);
+                    }
+                    AESCore::add_round_key(&mut state, &core.round_keys[round * 4..(round + 1) * 4]);
+                }
+                let mut expected: [u8; 16] = [0; 16];
+                AESCore::store_state(&state, &mut expected);
+
+                let ciphertext = core.encrypt(&block);
+                assert_eq!(ciphertext, expected);
+                assert_eq!(core.decrypt(&ciphertext), block);
+                assert_eq!(core.decrypt_eqinv(&ciphertext), block);
+                block = ciphertext;
+            }
+        }
+    }
+
     #[test]
     fn key_expansion() {
         //! Test the key expansion function
@@ -982,6 +2362,79 @@ mod tests {
         assert_eq!(aes192.round_keys.len(), 52);
         assert_eq!(aes256.round_keys[aes256.round_keys.len() - 1], [0x70, 0x6c, 0x63, 0x1e]);
         assert_eq!(aes256.round_keys.len(), 60);
+
+        // FIPS-197, appendix A.1 to A.3
+        let expected192: [[u8; 4]; 52] = [
+            [0x8e, 0x73, 0xb0, 0xf7], [0xda, 0x0e, 0x64, 0x52], [0xc8, 0x10, 0xf3, 0x2b], [0x80, 0x90, 0x79, 0xe5],
+            [0x62, 0xf8, 0xea, 0xd2], [0x52, 0x2c, 0x6b, 0x7b], [0xfe, 0x0c, 0x91, 0xf7], [0x24, 0x02, 0xf5, 0xa5],
+            [0xec, 0x12, 0x06, 0x8e], [0x6c, 0x82, 0x7f, 0x6b], [0x0e, 0x7a, 0x95, 0xb9], [0x5c, 0x56, 0xfe, 0xc2],
+            [0x4d, 0xb7, 0xb4, 0xbd], [0x69, 0xb5, 0x41, 0x18], [0x85, 0xa7, 0x47, 0x96], [0xe9, 0x25, 0x38, 0xfd],
+            [0xe7, 0x5f, 0xad, 0x44], [0xbb, 0x09, 0x53, 0x86], [0x48, 0x5a, 0xf0, 0x57], [0x21, 0xef, 0xb1, 0x4f],
+            [0xa4, 0x48, 0xf6, 0xd9], [0x4d, 0x6d, 0xce, 0x24], [0xaa, 0x32, 0x63, 0x60], [0x11, 0x3b, 0x30, 0xe6],
+            [0xa2, 0x5e, 0x7e, 0xd5], [0x83, 0xb1, 0xcf, 0x9a], [0x27, 0xf9, 0x39, 0x43], [0x6a, 0x94, 0xf7, 0x67],
+            [0xc0, 0xa6, 0x94, 0x07], [0xd1, 0x9d, 0xa4, 0xe1], [0xec, 0x17, 0x86, 0xeb], [0x6f, 0xa6, 0x49, 0x71],
+            [0x48, 0x5f, 0x70, 0x32], [0x22, 0xcb, 0x87, 0x55], [0xe2, 0x6d, 0x13, 0x52], [0x33, 0xf0, 0xb7, 0xb3],
+            [0x40, 0xbe, 0xeb, 0x28], [0x2f, 0x18, 0xa2, 0x59], [0x67, 0x47, 0xd2, 0x6b], [0x45, 0x8c, 0x55, 0x3e],
+            [0xa7, 0xe1, 0x46, 0x6c], [0x94, 0x11, 0xf1, 0xdf], [0x82, 0x1f, 0x75, 0x0a], [0xad, 0x07, 0xd7, 0x53],
+            [0xca, 0x40, 0x05, 0x38], [0x8f, 0xcc, 0x50, 0x06], [0x28, 0x2d, 0x16, 0x6a], [0xbc, 0x3c, 0xe7, 0xb5],
+            [0xe9, 0x8b, 0xa0, 0x6f], [0x44, 0x8c, 0x77, 0x3c], [0x8e, 0xcc, 0x72, 0x04], [0x01, 0x00, 0x22, 0x02],
+        ];
+        let expected256: [[u8; 4]; 60] = [
+            [0x60, 0x3d, 0xeb, 0x10], [0x15, 0xca, 0x71, 0xbe], [0x2b, 0x73, 0xae, 0xf0], [0x85, 0x7d, 0x77, 0x81],
+            [0x1f, 0x35, 0x2c, 0x07], [0x3b, 0x61, 0x08, 0xd7], [0x2d, 0x98, 0x10, 0xa3], [0x09, 0x14, 0xdf, 0xf4],
+            [0x9b, 0xa3, 0x54, 0x11], [0x8e, 0x69, 0x25, 0xaf], [0xa5, 0x1a, 0x8b, 0x5f], [0x20, 0x67, 0xfc, 0xde],
+            [0xa8, 0xb0, 0x9c, 0x1a], [0x93, 0xd1, 0x94, 0xcd], [0xbe, 0x49, 0x84, 0x6e], [0xb7, 0x5d, 0x5b, 0x9a],
+            [0xd5, 0x9a, 0xec, 0xb8], [0x5b, 0xf3, 0xc9, 0x17], [0xfe, 0xe9, 0x42, 0x48], [0xde, 0x8e, 0xbe, 0x96],
+            [0xb5, 0xa9, 0x32, 0x8a], [0x26, 0x78, 0xa6, 0x47], [0x98, 0x31, 0x22, 0x29], [0x2f, 0x6c, 0x79, 0xb3],
+            [0x81, 0x2c, 0x81, 0xad], [0xda, 0xdf, 0x48, 0xba], [0x24, 0x36, 0x0a, 0xf2], [0xfa, 0xb8, 0xb4, 0x64],
+            [0x98, 0xc5, 0xbf, 0xc9], [0xbe, 0xbd, 0x19, 0x8e], [0x26, 0x8c, 0x3b, 0xa7], [0x09, 0xe0, 0x42, 0x14],
+            [0x68, 0x00, 0x7b, 0xac], [0xb2, 0xdf, 0x33, 0x16], [0x96, 0xe9, 0x39, 0xe4], [0x6c, 0x51, 0x8d, 0x80],
+            [0xc8, 0x14, 0xe2, 0x04], [0x76, 0xa9, 0xfb, 0x8a], [0x50, 0x25, 0xc0, 0x2d], [0x59, 0xc5, 0x82, 0x39],
+            [0xde, 0x13, 0x69, 0x67], [0x6c, 0xcc, 0x5a, 0x71], [0xfa, 0x25, 0x63, 0x95], [0x96, 0x74, 0xee, 0x15],
+            [0x58, 0x86, 0xca, 0x5d], [0x2e, 0x2f, 0x31, 0xd7], [0x7e, 0x0a, 0xf1, 0xfa], [0x27, 0xcf, 0x73, 0xc3],
+            [0x74, 0x9c, 0x47, 0xab], [0x18, 0x50, 0x1d, 0xda], [0xe2, 0x75, 0x7e, 0x4f], [0x74, 0x01, 0x90, 0x5a],
+            [0xca, 0xfa, 0xaa, 0xe3], [0xe4, 0xd5, 0x9b, 0x34], [0x9a, 0xdf, 0x6a, 0xce], [0xbd, 0x10, 0x19, 0x0d],
+            [0xfe, 0x48, 0x90, 0xd1], [0xe6, 0x18, 0x8d, 0x0b], [0x04, 0x6d, 0xf3, 0x44], [0x70, 0x6c, 0x63, 0x1e],
+        ];
+
+        assert_eq!(aes128.round_keys(), ROUND_KEYS_128);
+        assert_eq!(aes192.round_keys(), expected192);
+        assert_eq!(aes256.round_keys(), expected256);
+    }
+
+    #[test]
+    fn round_keys() {
+        //! Test the key schedule accessor with the AES-128 key expansion example from FIPS-197 (appendix A.1)
+
+        let aes128: AESCore = AESCore::new(AESKey::AES128(
+            [0x2b, 0x7e, 0x15, 0x16,
+             0x28, 0xae, 0xd2, 0xa6,
+             0xab, 0xf7, 0x15, 0x88,
+             0x09, 0xcf, 0x4f, 0x3c],
+        ));
+
+        assert_eq!(aes128.round_keys(), ROUND_KEYS_128);
+    }
+
+    #[test]
+    fn const_key_expansion() {
+        //! Test that the key expansion evaluated at compile time matches the runtime one
+
+        const KEY_128: [u8; 16] = [
+            0x2b, 0x7e, 0x15, 0x16,
+            0x28, 0xae, 0xd2, 0xa6,
+            0xab, 0xf7, 0x15, 0x88,
+            0x09, 0xcf, 0x4f, 0x3c];
+        const CONST_ROUND_KEYS_128: [[u8; 4]; 44] = expand_key_128(KEY_128);
+        const CONST_ROUND_KEYS_192: [[u8; 4]; 52] = expand_key_192([0x5e; 24]);
+        const CONST_ROUND_KEYS_256: [[u8; 4]; 60] = expand_key_256([0xa7; 32]);
+
+        const _: () = assert!(CONST_ROUND_KEYS_128[43][0] == 0xb6 && CONST_ROUND_KEYS_128[43][3] == 0xa6);
+
+        assert_eq!(CONST_ROUND_KEYS_128, ROUND_KEYS_128);
+        assert_eq!(AESCore::key_expansion(&AESKey::AES128(KEY_128)), RoundKeys::AES128(CONST_ROUND_KEYS_128));
+        assert_eq!(AESCore::new(AESKey::AES192([0x5e; 24])).round_keys(), CONST_ROUND_KEYS_192);
+        assert_eq!(AESCore::new(AESKey::AES256([0xa7; 32])).round_keys(), CONST_ROUND_KEYS_256);
     }
 
     #[test]
@@ -1003,4 +2456,288 @@ mod tests {
         AESCore::sub_word(&mut word);
         assert_eq!(word, subbed_word);
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_key() {
+        //! Test serializing and deserializing the key
+
+        let key = AESKey::AES128([
+            0x00, 0x01, 0x02, 0x03,
+            0x04, 0x05, 0x06, 0x07,
+            0x08, 0x09, 0x0a, 0x0b,
+            0x0c, 0x0d, 0x0e, 0x0f]);
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, "{\"AES128\":[0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15]}");
+        assert_eq!(serde_json::from_str::<AESKey>(&json).unwrap(), key);
+
+        for key in [AESKey::AES192([0xc0; 24]), AESKey::AES256([0xff; 32])] {
+            let json = serde_json::to_string(&key).unwrap();
+            assert_eq!(serde_json::from_str::<AESKey>(&json).unwrap(), key);
+        }
+
+        assert!(serde_json::from_str::<AESKey>("{\"AES128\":[0,1,2]}").is_err());
+    }
+
+    #[test]
+    fn block_size() {
+        //! Test that the block type has the block size, and is interchangeable with byte arrays
+
+        assert_eq!(BLOCK_SIZE, 16);
+        assert_eq!(core::mem::size_of::<Block>(), BLOCK_SIZE);
+
+        let aes = AESCore::new(AESKey::AES128([0x00; 16]));
+        let block: [u8; 16] = [0x00; BLOCK_SIZE];
+        let encrypted: Block = aes.encrypt(&block);
+        assert_eq!(aes.decrypt(&encrypted), block);
+    }
+
+    #[test]
+    fn key_equality() {
+        //! Test the constant-time key comparison, with differences in the first and the last byte of each variant
+
+        let keys = [AESKey::AES128([0x3c; 16]), AESKey::AES192([0x3c; 24]), AESKey::AES256([0x3c; 32])];
+
+        for (i, key) in keys.iter().enumerate() {
+            for (j, other) in keys.iter().enumerate() {
+                assert_eq!(key == other, i == j);
+            }
+
+            let len = key.as_bytes().len();
+            for position in [0, len - 1] {
+                let mut bytes = key.as_bytes().to_vec();
+                bytes[position] ^= 0x80;
+                let changed = AESKey::try_from(&bytes[..]).unwrap();
+                assert_ne!(&changed, key);
+                assert_eq!(changed, changed.clone());
+            }
+        }
+    }
+
+    #[test]
+    fn from_schedule() {
+        //! Test that a core built from a shared key schedule encrypts the same as a freshly expanded one
+
+        let keys = [
+            AESKey::AES128([0x2b; 16]),
+            AESKey::AES192([0x2b; 24]),
+            AESKey::AES256([0x2b; 32]),
+        ];
+        let block: [u8; 16] = *b"a shared key! ok";
+
+        for key in keys {
+            let fresh = AESCore::new(key);
+            let schedule: Arc<[[u8; 4]]> = Arc::from(fresh.round_keys());
+
+            let shared = AESCore::from_schedule(&schedule).unwrap();
+            assert_eq!(shared, fresh);
+            assert_eq!(shared.key(), key);
+            assert_eq!(shared.inv_round_keys(), fresh.inv_round_keys());
+            assert_eq!(shared.encrypt(&block), fresh.encrypt(&block));
+            assert_eq!(shared.decrypt(&block), fresh.decrypt(&block));
+            assert_eq!(shared.decrypt_eqinv(&block), fresh.decrypt(&block));
+
+            assert_eq!(AESCore::from_schedule(&schedule[1..]), Err(KeyError::InvalidScheduleLength));
+        }
+
+        assert_eq!(AESCore::from_schedule(&[]), Err(KeyError::InvalidScheduleLength));
+        assert_eq!(AESCore::from_schedule(&[[0; 4]; 48]), Err(KeyError::InvalidScheduleLength));
+    }
+
+    #[test]
+    fn sbox_backends() {
+        //! Test that the table and the computed S-Box backends give the same outputs for all bytes
+
+        for byte in 0..=255_u8 {
+            assert_eq!(sub_byte_computed(byte), sub_byte_table(byte));
+            assert_eq!(inv_sub_byte_computed(byte), inv_sub_byte_table(byte));
+            assert_eq!(sub_byte(byte), sub_byte_table(byte));
+            assert_eq!(inv_sub_byte(byte), inv_sub_byte_table(byte));
+        }
+    }
+
+    #[test]
+    fn encrypt_array() {
+        //! Test encrypting arrays of 1, 2 and 4 blocks, the same as encrypting every block
+
+        let aes = AESCore::new(AESKey::AES256([0x7e; 32]));
+
+        let mut one: [u8; 16] = [0x01; 16];
+        aes.encrypt_array(&mut one);
+        assert_eq!(one, aes.encrypt(&[0x01; 16]));
+
+        let mut two: [u8; 32] = core::array::from_fn(|i| i as u8);
+        let expected = two;
+        aes.encrypt_array(&mut two);
+        for (block, plaintext) in two.chunks(16).zip(expected.chunks(16)) {
+            assert_eq!(block, aes.encrypt(plaintext.try_into().unwrap()));
+        }
+
+        let mut four: [u8; 64] = core::array::from_fn(|i| (i * 3) as u8);
+        let mut expected = four;
+        aes.encrypt_slice_to(&four, &mut expected).unwrap();
+        aes.encrypt_array(&mut four);
+        assert_eq!(four, expected);
+    }
+
+    #[test]
+    fn new_borrowed() {
+        //! Test that the core created from a borrowed key is the same as from an owned key
+
+        for key in [AESKey::AES128([0x0f; 16]), AESKey::AES192([0x1e; 24]), AESKey::AES256([0x2d; 32])] {
+            let borrowed = AESCore::new_borrowed(&key);
+            let owned = AESCore::new(key);
+
+            assert_eq!(borrowed, owned);
+            assert_eq!(borrowed.key(), key);
+            assert_eq!(borrowed.encrypt(b"This is a block!"), owned.encrypt(b"This is a block!"));
+            assert_eq!(borrowed.decrypt(b"This is a block!"), owned.decrypt(b"This is a block!"));
+        }
+    }
+
+    #[test]
+    fn aes128() {
+        //! Test that the specialized AES-128 gives the same results as the generic core
+
+        // FIPS-197, Appendix C.1
+        let aes = Aes128::new([0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f]);
+        let plaintext: [u8; 16] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff];
+        let ciphertext: [u8; 16] = [0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a];
+        assert_eq!(aes.encrypt(&plaintext), ciphertext);
+        assert_eq!(aes.decrypt(&ciphertext), plaintext);
+
+        let key: [u8; 16] = [0x2b; 16];
+        let aes = Aes128::new(key);
+        let core = AESCore::new(AESKey::AES128(key));
+        assert_eq!(AESCore::from(aes), core);
+
+        let mut block: [u8; 16] = [0; 16];
+        for i in 0..64 {
+            block[i % 16] ^= i as u8;
+            let encrypted = aes.encrypt(&block);
+            assert_eq!(encrypted, core.encrypt(&block));
+            assert_eq!(aes.decrypt(&encrypted), block);
+            assert_eq!(aes.decrypt(&block), core.decrypt(&block));
+            block = encrypted;
+        }
+    }
+
+    #[test]
+    fn primitives() {
+        //! Test that the public round transformations assemble the whole cipher, and that the inverses undo them
+
+        let key = AESKey::AES256([0x42; 32]);
+        let core = AESCore::new(key);
+        let round_keys: Vec<[[u8; 4]; 4]> = core.round_keys().chunks(4).map(|words| words.try_into().unwrap()).collect();
+        let block: [u8; 16] = *b"This is a block!";
+
+        let mut state = primitives::State::from(block);
+        primitives::add_round_key(&mut state, &round_keys[0]);
+        for round in 1..core.num_rounds() {
+            primitives::sub_bytes(&mut state);
+            primitives::shift_rows(&mut state);
+            primitives::mix_columns(&mut state);
+            primitives::add_round_key(&mut state, &round_keys[round]);
+        }
+        primitives::sub_bytes(&mut state);
+        primitives::shift_rows(&mut state);
+        primitives::add_round_key(&mut state, &round_keys[core.num_rounds()]);
+        assert_eq!(<[u8; 16]>::from(state), core.encrypt(&block));
+
+        primitives::add_round_key(&mut state, &round_keys[core.num_rounds()]);
+        primitives::inv_shift_rows(&mut state);
+        primitives::inv_sub_bytes(&mut state);
+        for round in (1..core.num_rounds()).rev() {
+            primitives::add_round_key(&mut state, &round_keys[round]);
+            primitives::inv_mix_columns(&mut state);
+            primitives::inv_shift_rows(&mut state);
+            primitives::inv_sub_bytes(&mut state);
+        }
+        primitives::add_round_key(&mut state, &round_keys[0]);
+        assert_eq!(<[u8; 16]>::from(state), block);
+        assert_eq!(state.0[1], *b" is ");
+    }
+
+    #[test]
+    fn monte_carlo() {
+        //! Test the Monte Carlo Test with the last values of the CAVP ECBMCT files (encryption)
+
+        let vectors: [(&str, &str, &str); 3] = [
+            ("139a35422f1d61de3c91787fe0507afd", "b9145a768b7dc489a096b546f43b231f", "fb2649694783b551eacd9d5db6126d47"),
+            ("b9a63e09e1dfc42e93a90d9bad739e5967aef672eedd5da9", "85a1f7a58167b389cddc8a9ff175ee26", "5d1196da8f184975e240949a25104554"),
+            (
+                "f9e8389f5b80712e3886cc1fa2d28a3b8c9cd88a2d4a54c6aa86ce0fef944be0", "b379777f9050e2a818f2940cbbd9aba4",
+                "c5d2cb3d5b7ff0e23e308967ee074825",
+            ),
+        ];
+
+        for (key, plaintext, ciphertext) in vectors {
+            let key = AESKey::try_from(hex(key).as_slice()).unwrap();
+            assert_eq!(mct(key, hex(plaintext).try_into().unwrap()).to_vec(), hex(ciphertext));
+        }
+    }
+
+    #[test]
+    fn encrypt_with() {
+        //! Test implementing CBC with the closures, compared with the NIST SP 800-38A vectors (F.2.1 CBC-AES128.Encrypt)
+
+        use std::cell::Cell;
+
+        let aes = AESCore::new(AESKey::AES128(hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap()));
+        let plaintext = hex(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e5130c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+        );
+        let ciphertext = hex(
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b273bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7"
+        );
+
+        let mut blocks: Vec<Block> = plaintext.chunks(16).map(|block| block.try_into().unwrap()).collect();
+        let previous: Cell<Block> = Cell::new(hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap());
+        let mut indices: Vec<usize> = Vec::new();
+        aes.encrypt_with(
+            &mut blocks,
+            |index, block| {
+                indices.push(index);
+                for (byte, previous) in block.iter_mut().zip(previous.get()) {
+                    *byte ^= previous;
+                }
+            },
+            |_, block| previous.set(*block),
+        );
+
+        assert_eq!(blocks.as_flattened(), ciphertext);
+        assert_eq!(indices, [0, 1, 2, 3]);
+
+        // without any processing it is ECB
+        let mut blocks: Vec<Block> = plaintext.chunks(16).map(|block| block.try_into().unwrap()).collect();
+        aes.encrypt_with(&mut blocks, |_, _| (), |_, _| ());
+        for (block, plaintext) in blocks.iter().zip(plaintext.chunks(16)) {
+            assert_eq!(*block, aes.encrypt(plaintext.try_into().unwrap()));
+        }
+    }
+
+    #[test]
+    fn state_byte_order() {
+        //! Test the block to state mapping against the FIPS-197 example (Appendix B, the input and its state)
+
+        use primitives::State;
+
+        let input: [u8; 16] = hex("3243f6a8885a308d313198a2e0370734").try_into().unwrap();
+        let rows: [u8; 16] = hex("328831e0435a3137f6309807a88da234").try_into().unwrap();
+
+        let state = State::from_bytes_be(input);
+        assert_eq!(state.0[0], [0x32, 0x43, 0xf6, 0xa8]);
+        assert_eq!(state.0[3], [0xe0, 0x37, 0x07, 0x34]);
+        assert_eq!(state, State::from(input));
+        assert_eq!(state.to_bytes_be(), input);
+
+        assert_eq!(State::from_rows(rows), state);
+        assert_eq!(state.to_rows(), rows);
+
+        let mut reversed = input;
+        reversed.reverse();
+        assert_eq!(State::from_bytes_le(reversed), state);
+        assert_eq!(state.to_bytes_le(), reversed);
+        assert_eq!(State::from_bytes_le(u128::from_be_bytes(input).to_le_bytes()), state);
+    }
 }
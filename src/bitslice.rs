@@ -0,0 +1,370 @@
+//! A module containing the bitsliced AES encryption.
+//! The blocks are transposed so that each bit of the state is held in one `u64`, one bit per block,
+//! so 64 blocks are encrypted at once with bitwise operations only.
+//! SubBytes is computed with a Boolean circuit instead of the S-Box table,
+//! so there are no data-dependent memory accesses and the encryption runs in constant time.
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::AESCore;
+
+
+
+
+
+// STRUCTS
+
+/// Public functions for bitsliced encryption.
+impl AESCore {
+    pub fn encrypt_blocks_bitsliced(&self, blocks: &mut [[u8; 16]]) {
+        //! Encrypts the blocks in place with the bitsliced implementation, 64 blocks at a time.
+        //! The result is the same as encrypting each block with `AESCore::encrypt_in_place`.
+        //! # Arguments
+        //! * `blocks` - The blocks to be encrypted, any number of them (a partial batch is padded internally).
+
+        let round_keys = self.round_keys();
+        let rounds: usize = round_keys.len() / 4 - 1;
+
+        for batch in blocks.chunks_mut(64) {
+            let mut state: BitslicedState = transpose_in(batch);
+
+            add_round_key(&mut state, &round_keys[0..4]);
+            for round in 1..rounds {
+                sub_bytes(&mut state);
+                shift_rows(&mut state);
+                mix_columns(&mut state);
+                add_round_key(&mut state, &round_keys[round * 4..(round + 1) * 4]);
+            }
+            sub_bytes(&mut state);
+            shift_rows(&mut state);
+            add_round_key(&mut state, &round_keys[rounds * 4..]);
+
+            transpose_out(&state, batch);
+        }
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+/// The bitsliced state, indexed by the byte of the block and the bit of the byte (0 being the least significant).
+/// Bit `j` of each word belongs to the block `j` of the batch.
+type BitslicedState = [[u64; 8]; 16];
+
+fn transpose_in(blocks: &[[u8; 16]]) -> BitslicedState {
+    //! Transposes up to 64 blocks into the bitsliced state.
+
+    let mut state: BitslicedState = [[0; 8]; 16];
+    for (j, block) in blocks.iter().enumerate() {
+        for i in 0..16 {
+            for bit in 0..8 {
+                state[i][bit] |= (((block[i] >> bit) & 1) as u64) << j;
+            }
+        }
+    }
+    state
+}
+
+fn transpose_out(state: &BitslicedState, blocks: &mut [[u8; 16]]) {
+    //! Transposes the bitsliced state back into the blocks.
+
+    for (j, block) in blocks.iter_mut().enumerate() {
+        for i in 0..16 {
+            let mut byte: u8 = 0;
+            for bit in 0..8 {
+                byte |= (((state[i][bit] >> j) & 1) as u8) << bit;
+            }
+            block[i] = byte;
+        }
+    }
+}
+
+fn add_round_key(state: &mut BitslicedState, round_key: &[[u8; 4]]) {
+    //! Adds the round key to every block, each key bit is expanded into a mask of all zeros or all ones.
+
+    for i in 0..16 {
+        let key_byte: u8 = round_key[i / 4][i % 4];
+        for bit in 0..8 {
+            state[i][bit] ^= 0_u64.wrapping_sub(((key_byte >> bit) & 1) as u64);
+        }
+    }
+}
+
+fn sub_bytes(state: &mut BitslicedState) {
+    //! Substitutes every byte of the state with the S-Box circuit.
+
+    for byte in state.iter_mut() {
+        sub_byte(byte);
+    }
+}
+
+fn shift_rows(state: &mut BitslicedState) {
+    //! Shifts the rows of the state, the bytes are stored column by column.
+    //! Row r of column c is taken from column c + r.
+
+    let old_state: BitslicedState = *state;
+    for c in 0..4 {
+        for r in 0..4 {
+            state[c * 4 + r] = old_state[((c + r) % 4) * 4 + r];
+        }
+    }
+}
+
+fn mix_columns(state: &mut BitslicedState) {
+    //! Mixes the columns of the state.
+    //! Each byte becomes 2 * (a0 + a1) + a1 + a2 + a3, where a1 - a3 are the following bytes of the column (cyclically).
+
+    for c in 0..4 {
+        let column: [[u64; 8]; 4] = [state[c * 4], state[c * 4 + 1], state[c * 4 + 2], state[c * 4 + 3]];
+        for r in 0..4 {
+            let (a0, a1, a2, a3) = (column[r], column[(r + 1) % 4], column[(r + 2) % 4], column[(r + 3) % 4]);
+            let mut sum: [u64; 8] = [0; 8];
+            for bit in 0..8 {
+                sum[bit] = a0[bit] ^ a1[bit];
+            }
+            let doubled: [u64; 8] = xtime(&sum);
+            for bit in 0..8 {
+                state[c * 4 + r][bit] = doubled[bit] ^ a1[bit] ^ a2[bit] ^ a3[bit];
+            }
+        }
+    }
+}
+
+fn xtime(byte: &[u64; 8]) -> [u64; 8] {
+    //! Multiplies the bitsliced byte by 2 in GF(2^8), the carried out bit is reduced with 0x1b.
+
+    [
+        byte[7],
+        byte[0] ^ byte[7],
+        byte[1],
+        byte[2] ^ byte[7],
+        byte[3] ^ byte[7],
+        byte[4],
+        byte[5],
+        byte[6],
+    ]
+}
+
+fn sub_byte(byte: &mut [u64; 8]) {
+    //! Substitutes the bitsliced byte with the S-Box circuit by Boyar and Peralta
+    //! ("A depth-16 circuit for the AES S-box", 113 gates).
+    //! The variables are numbered from the most significant bit, as in the paper.
+
+    let (x0, x1, x2, x3, x4, x5, x6, x7) = (byte[7], byte[6], byte[5], byte[4], byte[3], byte[2], byte[1], byte[0]);
+
+    // the top linear transformation
+    let y14 = x3 ^ x5;
+    let y13 = x0 ^ x6;
+    let y9 = x0 ^ x3;
+    let y8 = x0 ^ x5;
+    let t0 = x1 ^ x2;
+    let y1 = t0 ^ x7;
+    let y4 = y1 ^ x3;
+    let y12 = y13 ^ y14;
+    let y2 = y1 ^ x0;
+    let y5 = y1 ^ x6;
+    let y3 = y5 ^ y8;
+    let t1 = x4 ^ y12;
+    let y15 = t1 ^ x5;
+    let y20 = t1 ^ x1;
+    let y6 = y15 ^ x7;
+    let y10 = y15 ^ t0;
+    let y11 = y20 ^ y9;
+    let y7 = x7 ^ y11;
+    let y17 = y10 ^ y11;
+    let y19 = y10 ^ y8;
+    let y16 = t0 ^ y11;
+    let y21 = y13 ^ y16;
+    let y18 = x0 ^ y16;
+
+    // the non-linear section (the inversion in GF(2^8))
+    let t2 = y12 & y15;
+    let t3 = y3 & y6;
+    let t4 = t3 ^ t2;
+    let t5 = y4 & x7;
+    let t6 = t5 ^ t2;
+    let t7 = y13 & y16;
+    let t8 = y5 & y1;
+    let t9 = t8 ^ t7;
+    let t10 = y2 & y7;
+    let t11 = t10 ^ t7;
+    let t12 = y9 & y11;
+    let t13 = y14 & y17;
+    let t14 = t13 ^ t12;
+    let t15 = y8 & y10;
+    let t16 = t15 ^ t12;
+    let t17 = t4 ^ t14;
+    let t18 = t6 ^ t16;
+    let t19 = t9 ^ t14;
+    let t20 = t11 ^ t16;
+    let t21 = t17 ^ y20;
+    let t22 = t18 ^ y19;
+    let t23 = t19 ^ y21;
+    let t24 = t20 ^ y18;
+    let t25 = t21 ^ t22;
+    let t26 = t21 & t23;
+    let t27 = t24 ^ t26;
+    let t28 = t25 & t27;
+    let t29 = t28 ^ t22;
+    let t30 = t23 ^ t24;
+    let t31 = t22 ^ t26;
+    let t32 = t31 & t30;
+    let t33 = t32 ^ t24;
+    let t34 = t23 ^ t33;
+    let t35 = t27 ^ t33;
+    let t36 = t24 & t35;
+    let t37 = t36 ^ t34;
+    let t38 = t27 ^ t36;
+    let t39 = t29 & t38;
+    let t40 = t25 ^ t39;
+    let t41 = t40 ^ t37;
+    let t42 = t29 ^ t33;
+    let t43 = t29 ^ t40;
+    let t44 = t33 ^ t37;
+    let t45 = t42 ^ t41;
+    let z0 = t44 & y15;
+    let z1 = t37 & y6;
+    let z2 = t33 & x7;
+    let z3 = t43 & y16;
+    let z4 = t40 & y1;
+    let z5 = t29 & y7;
+    let z6 = t42 & y11;
+    let z7 = t45 & y17;
+    let z8 = t41 & y10;
+    let z9 = t44 & y12;
+    let z10 = t37 & y3;
+    let z11 = t33 & y4;
+    let z12 = t43 & y13;
+    let z13 = t40 & y5;
+    let z14 = t29 & y2;
+    let z15 = t42 & y9;
+    let z16 = t45 & y14;
+    let z17 = t41 & y8;
+
+    // the bottom linear transformation
+    let t46 = z15 ^ z16;
+    let t47 = z10 ^ z11;
+    let t48 = z5 ^ z13;
+    let t49 = z9 ^ z10;
+    let t50 = z2 ^ z12;
+    let t51 = z2 ^ z5;
+    let t52 = z7 ^ z8;
+    let t53 = z0 ^ z3;
+    let t54 = z6 ^ z7;
+    let t55 = z16 ^ z17;
+    let t56 = z12 ^ t48;
+    let t57 = t50 ^ t53;
+    let t58 = z4 ^ t46;
+    let t59 = z3 ^ t54;
+    let t60 = t46 ^ t57;
+    let t61 = z14 ^ t57;
+    let t62 = t52 ^ t58;
+    let t63 = t49 ^ t58;
+    let t64 = z4 ^ t59;
+    let t65 = t61 ^ t62;
+    let t66 = z1 ^ t63;
+    let s0 = t59 ^ t63;
+    let s6 = t56 ^ !t62;
+    let s7 = t48 ^ !t60;
+    let t67 = t64 ^ t65;
+    let s3 = t53 ^ t66;
+    let s4 = t51 ^ t66;
+    let s5 = t47 ^ t65;
+    let s1 = t64 ^ !s3;
+    let s2 = t55 ^ !t67;
+
+    *byte = [s7, s6, s5, s4, s3, s2, s1, s0];
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::{
+        AESKey,
+        S_BOX,
+    };
+
+    #[test]
+    fn sbox_circuit() {
+        //! Test the S-Box circuit against the S-Box table for all 256 bytes at once (4 words of 64 bytes)
+
+        for word in 0..4 {
+            let mut byte: [u64; 8] = [0; 8];
+            for j in 0..64 {
+                let value: usize = word * 64 + j;
+                for bit in 0..8 {
+                    byte[bit] |= (((value >> bit) & 1) as u64) << j;
+                }
+            }
+
+            sub_byte(&mut byte);
+
+            for j in 0..64 {
+                let value: usize = word * 64 + j;
+                let mut substituted: u8 = 0;
+                for bit in 0..8 {
+                    substituted |= (((byte[bit] >> j) & 1) as u8) << bit;
+                }
+                assert_eq!(substituted, S_BOX[value >> 4][value & 0b00001111]);
+            }
+        }
+    }
+
+    #[test]
+    fn bitsliced_encryption() {
+        //! Test that the bitsliced encryption matches the table-based one on a large batch, for all key sizes
+
+        // FIPS-197 appendix C.1
+        let core = AESCore::new(AESKey::AES128([
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        ]));
+        let mut blocks: [[u8; 16]; 1] = [[
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+        ]];
+        core.encrypt_blocks_bitsliced(&mut blocks);
+        assert_eq!(blocks[0], [
+            0x69, 0xc4, 0xe0, 0xd8, 0x6a, 0x7b, 0x04, 0x30, 0xd8, 0xcd, 0xb7, 0x80, 0x70, 0xb4, 0xc5, 0x5a,
+        ]);
+
+        for key in [AESKey::AES128([0x2b; 16]), AESKey::AES192([0x8e; 24]), AESKey::AES256([0x60; 32])] {
+            let core = AESCore::new(key);
+
+            // pseudo-random blocks, 1000 is not a multiple of the batch size
+            let mut plaintext: Vec<[u8; 16]> = Vec::with_capacity(1000);
+            let mut block: [u8; 16] = [0x5a; 16];
+            for _ in 0..1000 {
+                block = core.encrypt(&block);
+                plaintext.push(block);
+            }
+
+            let mut blocks = plaintext.clone();
+            core.encrypt_blocks_bitsliced(&mut blocks);
+            for (ciphertext, plaintext) in blocks.iter().zip(&plaintext) {
+                assert_eq!(*ciphertext, core.encrypt(plaintext));
+            }
+        }
+
+        core.encrypt_blocks_bitsliced(&mut []);
+    }
+}
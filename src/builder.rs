@@ -0,0 +1,428 @@
+//! A module containing the builder for a fully configured cipher.
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::{
+    AESCore,
+    AESKey,
+};
+use crate::error::Error;
+use crate::modes::{
+    Cipher,
+    Direction,
+    Mode,
+};
+use crate::padding::{
+    Padding,
+    PaddingError,
+    PaddingTypes,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with builder errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuildError {
+    /// The key wasn't set.
+    MissingKey,
+    /// The mode of operation wasn't set.
+    MissingMode,
+    /// The mode of operation requires an IV, but it wasn't given.
+    MissingIv,
+    /// The mode of operation doesn't use an IV, but it was given.
+    UnexpectedIv,
+    /// The mode of operation requires padding, but it wasn't set (or was set to `PaddingTypes::None`).
+    MissingPadding,
+    /// The mode of operation doesn't use padding, but it was set.
+    UnexpectedPadding,
+    /// The padding can't be removed after decryption without knowing the length of the message (`PaddingTypes::ZeroPadding`).
+    AmbiguousPadding,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingKey => write!(f, "no key was set"),
+            BuildError::MissingMode => write!(f, "no mode of operation was set"),
+            BuildError::MissingIv => write!(f, "the mode of operation requires an IV"),
+            BuildError::UnexpectedIv => write!(f, "the mode of operation doesn't use an IV"),
+            BuildError::MissingPadding => write!(f, "the mode of operation requires padding"),
+            BuildError::UnexpectedPadding => write!(f, "the mode of operation doesn't use padding"),
+            BuildError::AmbiguousPadding => write!(f, "the padding can't be removed unambiguously"),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+
+
+
+
+// STRUCTS
+
+/// The builder for a fully configured cipher.
+/// The IV isn't part of the configuration, because it must be different for every message,
+/// so it's given to each call of `ConfiguredCipher::encrypt` and `ConfiguredCipher::decrypt`.
+///
+/// **Example:** Building an AES-128 CBC cipher with PKCS#7 padding
+/// ```
+/// use tinyaes::{AesBuilder, AESKey, Mode, PaddingTypes};
+///
+/// let cipher = AesBuilder::new()
+///     .key(AESKey::AES128([0x2b; 16]))
+///     .mode(Mode::Cbc)
+///     .padding(PaddingTypes::PKCS7)
+///     .build()
+///     .unwrap();
+///
+/// let iv = [0x00; 16];  // use a random IV for every message
+/// let ciphertext = cipher.encrypt(Some(iv), b"Attack at dawn!").unwrap();
+/// assert_eq!(ciphertext.len(), 16);
+/// assert_eq!(cipher.decrypt(Some(iv), &ciphertext).unwrap(), b"Attack at dawn!");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct AesBuilder {
+    /// The key.
+    key: Option<AESKey>,
+    /// The mode of operation.
+    mode: Option<Mode>,
+    /// The padding type.
+    padding: Option<PaddingTypes>,
+}
+
+/// Public functions for the builder.
+impl AesBuilder {
+    pub fn new() -> Self {
+        //! Creates a new builder with nothing set.
+
+        Self::default()
+    }
+
+    pub fn key(mut self, key: AESKey) -> Self {
+        //! Sets the key.
+
+        self.key = Some(key);
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        //! Sets the mode of operation.
+
+        self.mode = Some(mode);
+        self
+    }
+
+    pub fn padding(mut self, padding_type: PaddingTypes) -> Self {
+        //! Sets the padding type.
+
+        self.padding = Some(padding_type);
+        self
+    }
+
+    pub fn build(self) -> Result<ConfiguredCipher, BuildError> {
+        //! Validates the configuration and builds the cipher.
+        //! * ECB and CBC require padding that can be removed unambiguously (not `PaddingTypes::ZeroPadding`).
+        //! * CTR, CFB and OFB don't use padding.
+        //! # Returns
+        //! * Result<ConfiguredCipher, BuildError> - The configured cipher or an error.
+        //! # Errors
+        //! * BuildError - The first problem found with the configuration.
+
+        let key = self.key.ok_or(BuildError::MissingKey)?;
+        let mode = self.mode.ok_or(BuildError::MissingMode)?;
+        let padding_type = self.padding.unwrap_or(PaddingTypes::None);
+
+        match mode {
+            Mode::Ecb | Mode::Cbc => {
+                if padding_type == PaddingTypes::None {
+                    return Err(BuildError::MissingPadding);
+                }
+                if padding_type == PaddingTypes::ZeroPadding {
+                    return Err(BuildError::AmbiguousPadding);
+                }
+            }
+            Mode::Ctr | Mode::Cfb | Mode::Ofb => {
+                if padding_type != PaddingTypes::None {
+                    return Err(BuildError::UnexpectedPadding);
+                }
+            }
+        }
+
+        Ok(ConfiguredCipher {
+            core: AESCore::new(key),
+            mode,
+            padding: Padding::new(padding_type),
+        })
+    }
+}
+
+/// A cipher with the key, mode of operation and padding configured.
+/// Build it with `AesBuilder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConfiguredCipher {
+    /// The AES core.
+    core: AESCore,
+    /// The mode of operation.
+    mode: Mode,
+    /// The padding.
+    padding: Padding,
+}
+
+/// Public functions for the configured cipher.
+impl ConfiguredCipher {
+    pub fn core(&self) -> &AESCore {
+        //! Returns the AES core.
+
+        &self.core
+    }
+
+    pub fn mode(&self) -> Mode {
+        //! Returns the mode of operation.
+
+        self.mode
+    }
+
+    pub fn padding_type(&self) -> PaddingTypes {
+        //! Returns the padding type.
+
+        self.padding.padding_type()
+    }
+
+    pub fn encrypt(&self, iv: Option<[u8; 16]>, plaintext: &[u8]) -> Result<Vec<u8>, BuildError> {
+        //! Encrypts the message, padding it if the mode requires it.
+        //! # Arguments
+        //! * `iv` - The IV of this message (the initial counter block for CTR mode), which must be None for ECB mode.
+        //!   It must never be reused with the same key, a reused IV reveals the XOR of the plaintexts in the stream modes.
+        //! * `plaintext` - The message to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, BuildError>` - The ciphertext or an error.
+        //! # Errors
+        //! * BuildError::MissingIv - The mode of operation requires an IV, but it wasn't given.
+        //! * BuildError::UnexpectedIv - An IV was given for ECB mode.
+
+        let mut cipher = Cipher::new(self.core, self.mode, Direction::Encrypt, iv)?;
+        let mut data = match self.mode {
+            Mode::Ecb | Mode::Cbc => self.padding.pad_buffer(plaintext).expect("padding is validated by the builder"),
+            Mode::Ctr | Mode::Cfb | Mode::Ofb => plaintext.to_vec(),
+        };
+        cipher.process(&mut data).expect("the padded data is a multiple of 16 bytes long");
+        Ok(data)
+    }
+
+    pub fn decrypt(&self, iv: Option<[u8; 16]>, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        //! Decrypts the message, removing the padding if the mode requires it.
+        //! # Arguments
+        //! * `iv` - The IV the message was encrypted with (the initial counter block for CTR mode), which must be None for ECB mode.
+        //! * `ciphertext` - The message to be decrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, Error>` - The plaintext or an error.
+        //! # Errors
+        //! * Error::Build - The IV is missing, or it was given for ECB mode.
+        //! * Error::Padding - The ciphertext length isn't a non-zero multiple of 16 bytes, or the padding is invalid.
+
+        let mut cipher = Cipher::new(self.core, self.mode, Direction::Decrypt, iv)?;
+        let mut data = ciphertext.to_vec();
+        match self.mode {
+            Mode::Ecb | Mode::Cbc => {
+                if data.is_empty() || cipher.process(&mut data).is_err() {
+                    return Err(PaddingError::InvalidPaddedSize.into());
+                }
+
+                let len = self.padding.de_pad_buffer(&data)?.len();
+                data.truncate(len);
+            }
+            Mode::Ctr | Mode::Cfb | Mode::Ofb => cipher.process(&mut data).expect("the stream modes accept data of any length"),
+        }
+        Ok(data)
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modes::{
+        CbcEncryptor,
+        CfbEncryptor,
+        OfbCipher,
+    };
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16,
+        0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88,
+        0x09, 0xcf, 0x4f, 0x3c,
+    ];
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03,
+        0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b,
+        0x0c, 0x0d, 0x0e, 0x0f,
+    ];
+
+    #[test]
+    fn build_ecb() {
+        //! Test building and using an ECB cipher
+
+        let cipher = AesBuilder::new()
+            .key(AESKey::AES128(KEY))
+            .mode(Mode::Ecb)
+            .padding(PaddingTypes::PKCS7)
+            .build()
+            .unwrap();
+
+        assert_eq!(cipher.mode(), Mode::Ecb);
+        assert_eq!(cipher.padding_type(), PaddingTypes::PKCS7);
+
+        let plaintext: [u8; 16] = [0x42; 16];
+        let ciphertext = cipher.encrypt(None, &plaintext).unwrap();
+        assert_eq!(ciphertext.len(), 32);
+        assert_eq!(ciphertext[..16], cipher.core().encrypt(&plaintext));
+        assert_eq!(ciphertext[16..], cipher.core().encrypt(&[0x10; 16]));
+        assert_eq!(cipher.decrypt(None, &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn build_cbc() {
+        //! Test building and using a CBC cipher
+
+        let cipher = AesBuilder::new()
+            .key(AESKey::AES128(KEY))
+            .mode(Mode::Cbc)
+            .padding(PaddingTypes::ISO78164)
+            .build()
+            .unwrap();
+
+        assert_eq!(cipher.mode(), Mode::Cbc);
+
+        let plaintext = b"Cipher Block Chaining with ISO 7816-4 padding";
+        let ciphertext = cipher.encrypt(Some(IV), plaintext).unwrap();
+        assert_eq!(ciphertext.len(), 48);
+
+        let mut encryptor = CbcEncryptor::new(AESCore::new(AESKey::AES128(KEY)), IV);
+        assert_eq!(ciphertext[..16], encryptor.encrypt_block(plaintext[..16].try_into().unwrap()));
+        assert_eq!(ciphertext[16..32], encryptor.encrypt_block(plaintext[16..32].try_into().unwrap()));
+
+        assert_eq!(cipher.decrypt(Some(IV), &ciphertext).unwrap(), plaintext);
+        assert_eq!(cipher.decrypt(Some(IV), &ciphertext[..47]), Err(Error::Padding(PaddingError::InvalidPaddedSize)));
+        assert_eq!(cipher.decrypt(Some(IV), &[]), Err(Error::Padding(PaddingError::InvalidPaddedSize)));
+
+        // every message is encrypted with its own IV
+        let other_iv = [0xa5; 16];
+        let other_ciphertext = cipher.encrypt(Some(other_iv), plaintext).unwrap();
+        assert_ne!(other_ciphertext, ciphertext);
+        assert_eq!(cipher.decrypt(Some(other_iv), &other_ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn build_ctr() {
+        //! Test building and using a CTR cipher
+
+        let counter: [u8; 16] = [
+            0xf0, 0xf1, 0xf2, 0xf3,
+            0xf4, 0xf5, 0xf6, 0xf7,
+            0xf8, 0xf9, 0xfa, 0xfb,
+            0xfc, 0xfd, 0xfe, 0xff,
+        ];
+        let cipher = AesBuilder::new()
+            .key(AESKey::AES128(KEY))
+            .mode(Mode::Ctr)
+            .build()
+            .unwrap();
+        assert_eq!(cipher, AesBuilder::new().key(AESKey::AES128(KEY)).mode(Mode::Ctr).padding(PaddingTypes::None).build().unwrap());
+
+        // NIST SP 800-38A, F.5.1 CTR-AES128.Encrypt, first block and a half
+        let plaintext: [u8; 24] = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+            0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c,
+        ];
+        let ciphertext: [u8; 24] = [
+            0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6, 0xce,
+            0x98, 0x06, 0xf6, 0x6b, 0x79, 0x70, 0xfd, 0xff,
+        ];
+        assert_eq!(cipher.encrypt(Some(counter), &plaintext).unwrap(), ciphertext);
+        assert_eq!(cipher.decrypt(Some(counter), &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn build_errors() {
+        //! Test that every invalid configuration is rejected
+
+        let key = AESKey::AES256([0x11; 32]);
+
+        assert_eq!(AesBuilder::new().mode(Mode::Ecb).padding(PaddingTypes::PKCS7).build(), Err(BuildError::MissingKey));
+        assert_eq!(AesBuilder::new().key(key).padding(PaddingTypes::PKCS7).build(), Err(BuildError::MissingMode));
+
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Ecb).build(), Err(BuildError::MissingPadding));
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Ecb).padding(PaddingTypes::None).build(), Err(BuildError::MissingPadding));
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Ecb).padding(PaddingTypes::ZeroPadding).build(), Err(BuildError::AmbiguousPadding));
+
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Cbc).build(), Err(BuildError::MissingPadding));
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Cbc).padding(PaddingTypes::None).build(), Err(BuildError::MissingPadding));
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Cbc).padding(PaddingTypes::ZeroPadding).build(), Err(BuildError::AmbiguousPadding));
+
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Ctr).padding(PaddingTypes::X923).build(), Err(BuildError::UnexpectedPadding));
+        assert_eq!(AesBuilder::new().key(key).mode(Mode::Ofb).padding(PaddingTypes::PKCS7).build(), Err(BuildError::UnexpectedPadding));
+
+        // the IV is checked on every call
+        let ecb = AesBuilder::new().key(key).mode(Mode::Ecb).padding(PaddingTypes::PKCS7).build().unwrap();
+        assert_eq!(ecb.encrypt(Some(IV), b"message"), Err(BuildError::UnexpectedIv));
+        assert_eq!(ecb.decrypt(Some(IV), &[0; 16]), Err(Error::Build(BuildError::UnexpectedIv)));
+        for mode in [Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            let padding_type = if mode == Mode::Cbc { PaddingTypes::PKCS7 } else { PaddingTypes::None };
+            let cipher = AesBuilder::new().key(key).mode(mode).padding(padding_type).build().unwrap();
+            assert_eq!(cipher.encrypt(None, b"message"), Err(BuildError::MissingIv));
+            assert_eq!(cipher.decrypt(None, &[0; 16]), Err(Error::Build(BuildError::MissingIv)));
+        }
+    }
+
+    #[test]
+    fn build_feedback_modes() {
+        //! Test building and using CFB and OFB ciphers
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let plaintext = b"feedback modes need no padding";
+
+        let cipher = AesBuilder::new().key(AESKey::AES128(KEY)).mode(Mode::Cfb).build().unwrap();
+        let ciphertext = cipher.encrypt(Some(IV), plaintext).unwrap();
+        let mut expected = plaintext.to_vec();
+        CfbEncryptor::new(core, IV).encrypt(&mut expected);
+        assert_eq!(ciphertext, expected);
+        assert_eq!(cipher.decrypt(Some(IV), &ciphertext).unwrap(), plaintext);
+
+        let cipher = AesBuilder::new().key(AESKey::AES128(KEY)).mode(Mode::Ofb).build().unwrap();
+        let ciphertext = cipher.encrypt(Some(IV), plaintext).unwrap();
+        let mut expected = plaintext.to_vec();
+        OfbCipher::new(core, IV).apply_keystream(&mut expected);
+        assert_eq!(ciphertext, expected);
+        assert_eq!(cipher.decrypt(Some(IV), &ciphertext).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn error_display() {
+        //! Test the error messages
+
+        assert_eq!(BuildError::MissingKey.to_string(), "no key was set");
+        assert_eq!(BuildError::MissingMode.to_string(), "no mode of operation was set");
+        assert_eq!(BuildError::MissingIv.to_string(), "the mode of operation requires an IV");
+        assert_eq!(BuildError::UnexpectedIv.to_string(), "the mode of operation doesn't use an IV");
+        assert_eq!(BuildError::MissingPadding.to_string(), "the mode of operation requires padding");
+        assert_eq!(BuildError::UnexpectedPadding.to_string(), "the mode of operation doesn't use padding");
+        assert_eq!(BuildError::AmbiguousPadding.to_string(), "the padding can't be removed unambiguously");
+    }
+}
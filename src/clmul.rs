@@ -0,0 +1,95 @@
+//! A module containing the GF(2^128) multiplication with carry-less multiplication (PCLMULQDQ on x86-64).
+//! The 256-bit product is computed with four 64-bit carry-less multiplications,
+//! shifted by one bit because of the bit-reflected representation used in GCM,
+//! and reduced modulo x^128 + x^7 + x^2 + x + 1 with shifts (Intel's carry-less multiplication white paper).
+//! PCLMULQDQ isn't a part of the x86-64 baseline, so the support is detected at runtime.
+
+
+
+
+
+// IMPORTS
+
+use core::arch::x86_64::{
+    __m128i,
+    _mm_clmulepi64_si128,
+    _mm_cvtsi128_si64,
+    _mm_or_si128,
+    _mm_set_epi64x,
+    _mm_slli_epi32,
+    _mm_slli_si128,
+    _mm_srli_epi32,
+    _mm_srli_si128,
+    _mm_unpackhi_epi64,
+    _mm_xor_si128,
+};
+
+
+
+
+
+// FUNCTIONS
+
+pub(crate) fn is_supported() -> bool {
+    //! Returns whether the processor supports the carry-less multiplication.
+
+    std::arch::is_x86_feature_detected!("pclmulqdq")
+}
+
+#[target_feature(enable = "pclmulqdq")]
+pub(crate) fn gf128_mul(x: u128, y: u128) -> u128 {
+    //! Multiplies two elements of GF(2^128) as defined for GCM (bit-reflected, big-endian blocks).
+    //! The result is the same as the software multiplication.
+    //! Must only be called if the processor supports the carry-less multiplication (see `is_supported`).
+
+    let a: __m128i = to_register(x);
+    let b: __m128i = to_register(y);
+
+    // the 256-bit carry-less product, high:low
+    let low: __m128i = _mm_clmulepi64_si128::<0x00>(a, b);
+    let middle: __m128i = _mm_xor_si128(_mm_clmulepi64_si128::<0x10>(a, b), _mm_clmulepi64_si128::<0x01>(a, b));
+    let high: __m128i = _mm_clmulepi64_si128::<0x11>(a, b);
+    let low: __m128i = _mm_xor_si128(low, _mm_slli_si128::<8>(middle));
+    let high: __m128i = _mm_xor_si128(high, _mm_srli_si128::<8>(middle));
+
+    // shift the product left by one bit, as the operands are bit-reflected
+    let low_carry: __m128i = _mm_srli_epi32::<31>(low);
+    let high_carry: __m128i = _mm_srli_epi32::<31>(high);
+    let low: __m128i = _mm_or_si128(_mm_slli_epi32::<1>(low), _mm_slli_si128::<4>(low_carry));
+    let high: __m128i = _mm_or_si128(
+        _mm_or_si128(_mm_slli_epi32::<1>(high), _mm_slli_si128::<4>(high_carry)),
+        _mm_srli_si128::<12>(low_carry),
+    );
+
+    // the first phase of the reduction
+    let folded: __m128i = _mm_xor_si128(
+        _mm_xor_si128(_mm_slli_epi32::<31>(low), _mm_slli_epi32::<30>(low)),
+        _mm_slli_epi32::<25>(low),
+    );
+    let folded_high: __m128i = _mm_srli_si128::<4>(folded);
+    let low: __m128i = _mm_xor_si128(low, _mm_slli_si128::<12>(folded));
+
+    // the second phase of the reduction
+    let reduced: __m128i = _mm_xor_si128(
+        _mm_xor_si128(_mm_srli_epi32::<1>(low), _mm_srli_epi32::<2>(low)),
+        _mm_xor_si128(_mm_srli_epi32::<7>(low), folded_high),
+    );
+
+    from_register(_mm_xor_si128(high, _mm_xor_si128(low, reduced)))
+}
+
+#[target_feature(enable = "sse2")]
+fn to_register(value: u128) -> __m128i {
+    //! Loads the value into a register, the least significant half in the low lane.
+
+    _mm_set_epi64x((value >> 64) as u64 as i64, value as u64 as i64)
+}
+
+#[target_feature(enable = "sse2")]
+fn from_register(register: __m128i) -> u128 {
+    //! Stores the register into a value, the low lane being the least significant half.
+
+    let low = _mm_cvtsi128_si64(register) as u64 as u128;
+    let high = _mm_cvtsi128_si64(_mm_unpackhi_epi64(register, register)) as u64 as u128;
+    (high << 64) | low
+}
@@ -0,0 +1,339 @@
+//! A module containing the CTR_DRBG deterministic random bit generator (NIST SP 800-90A).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::{
+    AESCore,
+    AESKey,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with DRBG errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrbgError {
+    /// The personalization string or the additional input is longer than the seed length (48 bytes).
+    InvalidLength,
+    /// More than 65536 bytes were requested in a single call.
+    RequestTooLarge,
+    /// The reseed interval was reached, the generator must be reseeded.
+    ReseedRequired,
+}
+
+impl fmt::Display for DrbgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrbgError::InvalidLength => write!(f, "input must not be longer than 48 bytes"),
+            DrbgError::RequestTooLarge => write!(f, "at most 65536 bytes can be requested at once"),
+            DrbgError::ReseedRequired => write!(f, "generator must be reseeded"),
+        }
+    }
+}
+
+impl std::error::Error for DrbgError {}
+
+
+
+
+
+// STRUCTS
+
+/// The CTR_DRBG with AES-256, without the derivation function (NIST SP 800-90A, section 10.2.1).
+///
+/// The entropy input must be full entropy, as it is used directly as the seed material.
+/// The generator doesn't gather entropy by itself, the caller is responsible for providing it.
+///
+/// The generator isn't `Clone` or `Copy`, a copy would produce the same output as the original.
+/// Its `Debug` output doesn't include the key or the counter value.
+pub struct CtrDrbg {
+    /// The AES core keyed with the current key.
+    core: AESCore,
+    /// The current counter value.
+    v: u128,
+    /// The number of generate requests since the last (re)seeding.
+    reseed_counter: u64,
+}
+
+impl fmt::Debug for CtrDrbg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CtrDrbg")
+            .field("reseed_counter", &self.reseed_counter)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Public functions for generating random bits.
+impl CtrDrbg {
+    pub fn instantiate(entropy: &[u8; SEED_LEN], personalization: &[u8]) -> Result<Self, DrbgError> {
+        //! Instantiates the generator.
+        //! The variant without the derivation function doesn't use a nonce.
+        //! # Arguments
+        //! * `entropy` - The full entropy input.
+        //! * `personalization` - The optional personalization string, at most 48 bytes long.
+        //! # Errors
+        //! * DrbgError::InvalidLength - The personalization string is too long.
+
+        let mut drbg = Self {
+            core: AESCore::new(AESKey::AES256([0; 32])),
+            v: 0,
+            reseed_counter: 1,
+        };
+        drbg.update(&seed_material(entropy, personalization)?);
+
+        Ok(drbg)
+    }
+
+    pub fn reseed(&mut self, entropy: &[u8; SEED_LEN], additional_input: &[u8]) -> Result<(), DrbgError> {
+        //! Reseeds the generator with fresh entropy.
+        //! # Arguments
+        //! * `entropy` - The full entropy input.
+        //! * `additional_input` - The optional additional input, at most 48 bytes long.
+        //! # Errors
+        //! * DrbgError::InvalidLength - The additional input is too long.
+
+        self.update(&seed_material(entropy, additional_input)?);
+        self.reseed_counter = 1;
+
+        Ok(())
+    }
+
+    pub fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        //! Fills the output with random bytes.
+        //! The key and the counter are updated after every call, so the previous outputs can't be recovered
+        //! from the state.
+        //! # Arguments
+        //! * `out` - The output buffer, at most 65536 bytes long.
+        //! * `additional_input` - The optional additional input, at most 48 bytes long.
+        //! # Errors
+        //! * DrbgError::InvalidLength - The additional input is too long.
+        //! * DrbgError::RequestTooLarge - The output buffer is too long.
+        //! * DrbgError::ReseedRequired - The reseed interval was reached.
+
+        if out.len() > MAX_REQUEST_LEN {
+            return Err(DrbgError::RequestTooLarge);
+        }
+        if self.reseed_counter > RESEED_INTERVAL {
+            return Err(DrbgError::ReseedRequired);
+        }
+
+        let has_additional_input = !additional_input.is_empty();
+        let additional_input = padded(additional_input)?;
+        if has_additional_input {
+            self.update(&additional_input);
+        }
+
+        for chunk in out.chunks_mut(16) {
+            self.v = self.v.wrapping_add(1);
+            let block = self.core.encrypt(&self.v.to_be_bytes());
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+
+        self.update(&additional_input);
+        self.reseed_counter += 1;
+
+        Ok(())
+    }
+}
+
+/// Internal functions for generating random bits.
+impl CtrDrbg {
+    fn update(&mut self, provided_data: &[u8; SEED_LEN]) {
+        //! Updates the internal state with the provided data (CTR_DRBG_Update).
+
+        let mut temp: [u8; SEED_LEN] = [0; SEED_LEN];
+        for chunk in temp.chunks_exact_mut(16) {
+            self.v = self.v.wrapping_add(1);
+            chunk.copy_from_slice(&self.core.encrypt(&self.v.to_be_bytes()));
+        }
+
+        for i in 0..SEED_LEN {
+            temp[i] ^= provided_data[i];
+        }
+
+        self.core.set_key(AESKey::AES256(temp[..32].try_into().unwrap()));
+        self.v = u128::from_be_bytes(temp[32..].try_into().unwrap());
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+fn padded(input: &[u8]) -> Result<[u8; SEED_LEN], DrbgError> {
+    //! Pads the input with zeros to the seed length.
+
+    if input.len() > SEED_LEN {
+        return Err(DrbgError::InvalidLength);
+    }
+
+    let mut output: [u8; SEED_LEN] = [0; SEED_LEN];
+    output[..input.len()].copy_from_slice(input);
+    Ok(output)
+}
+
+fn seed_material(entropy: &[u8; SEED_LEN], input: &[u8]) -> Result<[u8; SEED_LEN], DrbgError> {
+    //! Combines the entropy with the padded personalization string or additional input.
+
+    let mut seed_material = padded(input)?;
+    for i in 0..SEED_LEN {
+        seed_material[i] ^= entropy[i];
+    }
+    Ok(seed_material)
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The seed length of CTR_DRBG with AES-256 (the key length plus the block length), in bytes.
+pub const SEED_LEN: usize = 48;
+
+/// The maximum number of bytes per generate request (2^19 bits).
+const MAX_REQUEST_LEN: usize = 1 << 16;
+
+/// The maximum number of generate requests between reseeds.
+const RESEED_INTERVAL: u64 = 1 << 48;
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn cavp() {
+        //! Test with the CAVP CTR_DRBG known answer test (AES-256, no derivation function, no reseed, count 0)
+
+        let entropy: [u8; SEED_LEN] = hex(
+            "df5d73faa468649edda33b5cca79b0b05600419ccb7a879ddfec9db32ee494e5531b51de16a30f769262474c73bec010"
+        ).try_into().unwrap();
+
+        let mut drbg = CtrDrbg::instantiate(&entropy, &[]).unwrap();
+        let mut returned_bits: [u8; 64] = [0; 64];
+        drbg.generate(&mut returned_bits, &[]).unwrap();
+        drbg.generate(&mut returned_bits, &[]).unwrap();
+
+        assert_eq!(returned_bits.to_vec(), hex(
+            "d1c07cd95af8a7f11012c84ce48bb8cb87189e99d40fccb1771c619bdf82ab22\
+             80b1dc2f2581f39164f7ac0c510494b3a43c41b7db17514c87b107ae793e01c5"
+        ));
+    }
+
+    #[test]
+    fn cavp_reseed() {
+        //! Test with the CAVP CTR_DRBG known answer test with a reseed
+        //! (AES-256, no derivation function, no prediction resistance, count 0):
+        //! instantiate, reseed, two generate calls, only the second output is compared
+
+        let entropy: [u8; SEED_LEN] = hex(
+            "e4bc23c5089a19d86f4119cb3fa08c0a4991e0a1def17e101e4c14d9c323460a7c2fb58e0b086c6c57b55f56cae25bad"
+        ).try_into().unwrap();
+        let reseed_entropy: [u8; SEED_LEN] = hex(
+            "fd85a836bba85019881e8c6bad23c9061adc75477659acaea8e4a01dfe07a1832dad1c136f59d70f8653a5dc118663d6"
+        ).try_into().unwrap();
+
+        let mut drbg = CtrDrbg::instantiate(&entropy, &[]).unwrap();
+        drbg.reseed(&reseed_entropy, &[]).unwrap();
+        let mut returned_bits: [u8; 64] = [0; 64];
+        drbg.generate(&mut returned_bits, &[]).unwrap();
+        drbg.generate(&mut returned_bits, &[]).unwrap();
+
+        assert_eq!(returned_bits.to_vec(), hex(
+            "b2cb8905c05e5950ca31895096be29ea3d5a3b82b269495554eb80fe07de43e1\
+             93b9e7c3ece73b80e062b1c1f68202fbb1c52a040ea2478864295282234aaada"
+        ));
+    }
+
+    #[test]
+    fn personalization_and_additional_input() {
+        //! Test that the personalization string and the additional input are XORed into the seed material
+        //! (SP 800-90A, section 10.2.1), and that a shorter input is padded with zeros
+
+        let entropy: [u8; SEED_LEN] = [0x5a; SEED_LEN];
+        let input: [u8; 20] = [0xc3; 20];
+        let mut combined: [u8; SEED_LEN] = entropy;
+        for i in 0..input.len() {
+            combined[i] ^= input[i];
+        }
+
+        let output = |drbg: &mut CtrDrbg, additional_input: &[u8]| {
+            let mut out: [u8; 64] = [0; 64];
+            drbg.generate(&mut out, additional_input).unwrap();
+            out
+        };
+
+        let mut personalized = CtrDrbg::instantiate(&entropy, &input).unwrap();
+        let mut reference = CtrDrbg::instantiate(&combined, &[]).unwrap();
+        assert_eq!(output(&mut personalized, &[]), output(&mut reference, &[]));
+
+        personalized.reseed(&entropy, &input).unwrap();
+        reference.reseed(&combined, &[]).unwrap();
+        assert_eq!(output(&mut personalized, &[]), output(&mut reference, &[]));
+
+        // a non-empty additional input updates the state before and after the output
+        let mut with_input = CtrDrbg::instantiate(&entropy, &[]).unwrap();
+        let mut without_input = CtrDrbg::instantiate(&entropy, &[]).unwrap();
+        assert_ne!(output(&mut with_input, &input), output(&mut without_input, &[]));
+        assert_ne!(output(&mut with_input, &[]), output(&mut without_input, &[]));
+    }
+
+    #[test]
+    fn drbg_errors() {
+        //! Test the input length limits and the reseed interval
+
+        assert_eq!(CtrDrbg::instantiate(&[0; SEED_LEN], &[0; SEED_LEN + 1]).err(), Some(DrbgError::InvalidLength));
+
+        let mut drbg = CtrDrbg::instantiate(&[0; SEED_LEN], &[0; SEED_LEN]).unwrap();
+        assert_eq!(drbg.reseed(&[0; SEED_LEN], &[0; SEED_LEN + 1]), Err(DrbgError::InvalidLength));
+        assert_eq!(drbg.generate(&mut [0; 16], &[0; SEED_LEN + 1]), Err(DrbgError::InvalidLength));
+        assert_eq!(drbg.generate(&mut vec![0; MAX_REQUEST_LEN + 1], &[]), Err(DrbgError::RequestTooLarge));
+        drbg.generate(&mut vec![0; MAX_REQUEST_LEN], &[]).unwrap();
+
+        drbg.reseed_counter = RESEED_INTERVAL;
+        drbg.generate(&mut [0; 16], &[]).unwrap();
+        assert_eq!(drbg.generate(&mut [0; 16], &[]), Err(DrbgError::ReseedRequired));
+        drbg.reseed(&[1; SEED_LEN], &[]).unwrap();
+        drbg.generate(&mut [0; 16], &[]).unwrap();
+
+        assert_eq!(DrbgError::ReseedRequired.to_string(), "generator must be reseeded");
+    }
+
+    #[test]
+    fn debug_redacted() {
+        //! Test that the debug output doesn't reveal the key or the counter value
+
+        let mut drbg = CtrDrbg::instantiate(&[0x5a; SEED_LEN], &[]).unwrap();
+        drbg.generate(&mut [0; 16], &[]).unwrap();
+
+        assert_eq!(format!("{drbg:?}"), "CtrDrbg { reseed_counter: 2, .. }");
+    }
+}
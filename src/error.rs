@@ -0,0 +1,191 @@
+//! A module containing the crate-level error type.
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::{
+    KeyError,
+    LenError,
+};
+use crate::builder::BuildError;
+use crate::drbg::DrbgError;
+use crate::gcm::AeadError;
+use crate::keywrap::KwError;
+use crate::mac::MacStateError;
+use crate::modes::{
+    BatchError,
+    FrameError,
+    ModeError,
+};
+use crate::padding::PaddingError;
+
+
+
+
+
+// ENUMS
+
+/// The enum unifying the errors of the crate.
+/// Every error converts into it with `From`, so the errors of different parts of the crate
+/// can be propagated with `?` from a single function and handled at the top level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Error {
+    /// An invalid key.
+    Key(KeyError),
+    /// An invalid data length.
+    Len(LenError),
+    /// A padding error.
+    Padding(PaddingError),
+    /// An authenticated encryption error.
+    Aead(AeadError),
+    /// A mode of operation error.
+    Mode(ModeError),
+    /// An invalid cipher configuration.
+    Build(BuildError),
+    /// A key wrap error.
+    Keywrap(KwError),
+    /// A DRBG error.
+    Drbg(DrbgError),
+    /// A framing error.
+    Frame(FrameError),
+    /// A batch processing error.
+    Batch(BatchError),
+    /// An invalid MAC state.
+    MacState(MacStateError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Key(error) => write!(f, "{error}"),
+            Error::Len(error) => write!(f, "{error}"),
+            Error::Padding(error) => write!(f, "{error}"),
+            Error::Aead(error) => write!(f, "{error}"),
+            Error::Mode(error) => write!(f, "{error}"),
+            Error::Build(error) => write!(f, "{error}"),
+            Error::Keywrap(error) => write!(f, "{error}"),
+            Error::Drbg(error) => write!(f, "{error}"),
+            Error::Frame(error) => write!(f, "{error}"),
+            Error::Batch(error) => write!(f, "{error}"),
+            Error::MacState(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<KeyError> for Error {
+    fn from(error: KeyError) -> Self {
+        Error::Key(error)
+    }
+}
+impl From<LenError> for Error {
+    fn from(error: LenError) -> Self {
+        Error::Len(error)
+    }
+}
+impl From<PaddingError> for Error {
+    fn from(error: PaddingError) -> Self {
+        Error::Padding(error)
+    }
+}
+impl From<AeadError> for Error {
+    fn from(error: AeadError) -> Self {
+        Error::Aead(error)
+    }
+}
+impl From<ModeError> for Error {
+    fn from(error: ModeError) -> Self {
+        Error::Mode(error)
+    }
+}
+impl From<BuildError> for Error {
+    fn from(error: BuildError) -> Self {
+        Error::Build(error)
+    }
+}
+impl From<KwError> for Error {
+    fn from(error: KwError) -> Self {
+        Error::Keywrap(error)
+    }
+}
+impl From<DrbgError> for Error {
+    fn from(error: DrbgError) -> Self {
+        Error::Drbg(error)
+    }
+}
+impl From<FrameError> for Error {
+    fn from(error: FrameError) -> Self {
+        Error::Frame(error)
+    }
+}
+impl From<BatchError> for Error {
+    fn from(error: BatchError) -> Self {
+        Error::Batch(error)
+    }
+}
+impl From<MacStateError> for Error {
+    fn from(error: MacStateError) -> Self {
+        Error::MacState(error)
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::{
+        AESCore,
+        AESKey,
+    };
+    use crate::modes::cbc_decrypt_in_place;
+    use crate::padding::{
+        Padding,
+        PaddingTypes,
+    };
+
+    #[test]
+    fn conversions() {
+        //! Test the conversions into the crate-level error, which keep the message of the original error
+
+        assert_eq!(Error::from(KeyError::InvalidLength), Error::Key(KeyError::InvalidLength));
+        assert_eq!(Error::from(LenError::NotBlockMultiple), Error::Len(LenError::NotBlockMultiple));
+        assert_eq!(Error::from(PaddingError::InvalidPadding), Error::Padding(PaddingError::InvalidPadding));
+        assert_eq!(Error::from(AeadError::AuthenticationFailed), Error::Aead(AeadError::AuthenticationFailed));
+        assert_eq!(Error::from(ModeError::ReusedIv), Error::Mode(ModeError::ReusedIv));
+        assert_eq!(Error::from(BuildError::MissingKey), Error::Build(BuildError::MissingKey));
+        assert_eq!(Error::from(KwError::IntegrityCheckFailed), Error::Keywrap(KwError::IntegrityCheckFailed));
+        assert_eq!(Error::from(DrbgError::ReseedRequired), Error::Drbg(DrbgError::ReseedRequired));
+        assert_eq!(Error::from(FrameError::TooShort), Error::Frame(FrameError::TooShort));
+        assert_eq!(Error::from(BatchError::EmptyPool), Error::Batch(BatchError::EmptyPool));
+        assert_eq!(Error::from(MacStateError::InvalidState), Error::MacState(MacStateError::InvalidState));
+
+        assert_eq!(Error::from(PaddingError::InvalidPadding).to_string(), PaddingError::InvalidPadding.to_string());
+        assert_eq!(Error::from(KeyError::InvalidLength).to_string(), KeyError::InvalidLength.to_string());
+    }
+
+    #[test]
+    fn propagation() {
+        //! Test propagating the errors of different parts of the crate with `?`
+
+        fn decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+            let core = AESCore::new(AESKey::try_from(key)?);
+            let mut data = ciphertext.to_vec();
+            cbc_decrypt_in_place(&core, [0; 16], &mut data)?;
+            Ok(Padding::new(PaddingTypes::PKCS7).de_pad_buffer(&data)?.to_vec())
+        }
+
+        assert_eq!(decrypt(&[0; 15], &[0; 16]), Err(Error::Key(KeyError::InvalidLength)));
+        assert_eq!(decrypt(&[0; 16], &[0; 20]), Err(Error::Len(LenError::NotBlockMultiple)));
+        assert!(matches!(decrypt(&[0; 16], &[0; 16]), Err(Error::Padding(_))));
+    }
+}
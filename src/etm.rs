@@ -0,0 +1,211 @@
+//! A module containing the generic Encrypt-then-MAC authenticated encryption (a mode of operation with CMAC).
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::AESCore;
+use crate::gcm::AeadError;
+use crate::mac::Cmac;
+use crate::modes::{
+    Cipher,
+    Direction,
+    Mode,
+};
+use crate::padding::{
+    Padding,
+    PaddingTypes,
+};
+use crate::util::ct_eq;
+
+
+
+
+
+// STRUCTS
+
+/// The Encrypt-then-MAC authenticated encryption.
+/// The plaintext is encrypted with a mode of operation (padded with PKCS#7 in CBC mode),
+/// and the CMAC is computed over the associated data, the IV and the ciphertext.
+/// The encryption and the MAC must use separate keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EtmCipher {
+    /// The AES core used for encryption.
+    encryption_core: AESCore,
+    /// The AES core used for the CMAC.
+    mac_core: AESCore,
+    /// The mode of operation used for encryption.
+    mode: Mode,
+}
+
+/// Public functions for authenticated encryption with Encrypt-then-MAC.
+impl EtmCipher {
+    pub fn new(encryption_core: AESCore, mac_core: AESCore, mode: Mode) -> Self {
+        //! Creates a new Encrypt-then-MAC instance.
+        //! # Arguments
+        //! * `encryption_core` - The AES core used to encrypt the data.
+        //! * `mac_core` - The AES core used to authenticate the data, with a key independent of the encryption key.
+        //! * `mode` - The mode of operation (CBC, CTR, CFB or OFB).
+        //! # Panics
+        //! * If the mode of operation is ECB, which doesn't use an IV.
+
+        assert_ne!(mode, Mode::Ecb, "Encrypt-then-MAC requires a mode of operation with an IV");
+
+        Self {
+            encryption_core,
+            mac_core,
+            mode,
+        }
+    }
+
+    pub fn seal(&self, iv: [u8; 16], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+        //! Encrypts the plaintext, and authenticates the associated data, the IV and the ciphertext.
+        //! # Arguments
+        //! * `iv` - The IV (the initial counter block in CTR mode). Must be unpredictable in CBC and CFB modes, and never reused.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Vec<u8>` - The IV, the ciphertext and the tag.
+
+        let mut ciphertext = if self.mode == Mode::Cbc {
+            Padding::new(PaddingTypes::PKCS7).pad_buffer(plaintext).expect("PKCS#7 padding can't fail")
+        } else {
+            plaintext.to_vec()
+        };
+        self.cipher(Direction::Encrypt, iv)
+            .process(&mut ciphertext)
+            .expect("the padded data is a multiple of 16 bytes long");
+
+        let mut output = Vec::with_capacity(16 + ciphertext.len() + 16);
+        output.extend_from_slice(&iv);
+        output.extend_from_slice(&ciphertext);
+        output.extend_from_slice(&self.tag(aad, &output));
+        output
+    }
+
+    pub fn open(&self, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies the tag in constant time, and then decrypts the ciphertext.
+        //! # Arguments
+        //! * `aad` - The associated data used when sealing.
+        //! * `sealed` - The IV, the ciphertext and the tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidLength - The input is shorter than the IV and the tag, or the CBC ciphertext isn't a multiple of 16 bytes long.
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        if sealed.len() < 32 {
+            return Err(AeadError::InvalidLength);
+        }
+        let (authenticated, tag) = sealed.split_at(sealed.len() - 16);
+
+        if !ct_eq(&self.tag(aad, authenticated), tag) {
+            return Err(AeadError::AuthenticationFailed);
+        }
+
+        let (iv, ciphertext) = authenticated.split_at(16);
+        let mut plaintext = ciphertext.to_vec();
+        self.cipher(Direction::Decrypt, iv.try_into().unwrap())
+            .process(&mut plaintext)
+            .map_err(|_| AeadError::InvalidLength)?;
+
+        if self.mode == Mode::Cbc {
+            // the padding is authenticated, so it is only invalid if the sender holding the MAC key produced it
+            let len = Padding::new(PaddingTypes::PKCS7)
+                .de_pad_buffer(&plaintext)
+                .map_err(|_| AeadError::InvalidLength)?
+                .len();
+            plaintext.truncate(len);
+        }
+
+        Ok(plaintext)
+    }
+}
+
+/// Internal functions for authenticated encryption with Encrypt-then-MAC.
+impl EtmCipher {
+    fn cipher(&self, direction: Direction, iv: [u8; 16]) -> Cipher {
+        //! Creates the cipher of the mode of operation.
+
+        Cipher::new(self.encryption_core, self.mode, direction, Some(iv)).expect("the mode of operation uses an IV")
+    }
+
+    fn tag(&self, aad: &[u8], authenticated: &[u8]) -> [u8; 16] {
+        //! Computes the CMAC over the length of the associated data, the associated data, the IV and the ciphertext.
+        //! The length prefix keeps the boundary between the associated data and the IV unambiguous.
+
+        let mut cmac = Cmac::new(self.mac_core);
+        cmac.update(&(aad.len() as u64).to_be_bytes());
+        cmac.update(aad);
+        cmac.update(authenticated);
+        cmac.finalize()
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn etm(mode: Mode) -> EtmCipher {
+        EtmCipher::new(AESCore::new(AESKey::AES128([0x11; 16])), AESCore::new(AESKey::AES128([0x22; 16])), mode)
+    }
+
+    #[test]
+    fn seal_open() {
+        //! Test that opening restores the plaintext, with the IV in front and the CMAC at the end
+
+        let iv: [u8; 16] = [0x33; 16];
+        for mode in [Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            let etm = etm(mode);
+            for len in [0, 1, 15, 16, 17, 40] {
+                let plaintext: Vec<u8> = (0..len).collect();
+                let sealed = etm.seal(iv, b"header", &plaintext);
+
+                let ciphertext_len = if mode == Mode::Cbc { (len as usize / 16 + 1) * 16 } else { len as usize };
+                assert_eq!(sealed.len(), 16 + ciphertext_len + 16);
+                assert_eq!(sealed[..16], iv);
+                assert_eq!(etm.open(b"header", &sealed).unwrap(), plaintext);
+            }
+        }
+
+        // the ciphertext is the output of the mode of operation
+        let sealed = etm(Mode::Ctr).seal(iv, b"", b"plaintext");
+        let mut ciphertext = b"plaintext".to_vec();
+        Cipher::new(AESCore::new(AESKey::AES128([0x11; 16])), Mode::Ctr, Direction::Encrypt, Some(iv)).unwrap().process(&mut ciphertext).unwrap();
+        assert_eq!(sealed[16..25], ciphertext);
+    }
+
+    #[test]
+    fn tamper_detection() {
+        //! Test that flipping any bit of the IV, the ciphertext or the tag, or changing the associated data fails opening
+
+        for mode in [Mode::Cbc, Mode::Ctr] {
+            let etm = etm(mode);
+            let sealed = etm.seal([0x33; 16], b"header", b"a message longer than a block");
+
+            for position in 0..sealed.len() {
+                let mut tampered = sealed.clone();
+                tampered[position] ^= 0x01;
+                assert_eq!(etm.open(b"header", &tampered), Err(AeadError::AuthenticationFailed));
+            }
+
+            assert_eq!(etm.open(b"header!", &sealed), Err(AeadError::AuthenticationFailed));
+            assert_eq!(etm.open(b"", &sealed), Err(AeadError::AuthenticationFailed));
+            assert_eq!(etm.open(b"header", &sealed[..31]), Err(AeadError::InvalidLength));
+            assert_eq!(etm.open(b"header", &sealed[..(sealed.len() - 1)]), Err(AeadError::AuthenticationFailed));
+
+            // a different MAC key doesn't verify
+            let other = EtmCipher::new(AESCore::new(AESKey::AES128([0x11; 16])), AESCore::new(AESKey::AES128([0x23; 16])), mode);
+            assert_eq!(other.open(b"header", &sealed), Err(AeadError::AuthenticationFailed));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! A module containing the C interface to the AES block cipher.
+//! The functions operate on raw pointers, validate the key length and return a status code,
+//! so the crate can be used from C (or any language with a C FFI).
+//! To get a shared library, build with `cargo rustc --release --features ffi --crate-type cdylib`.
+//!
+//! The matching C declarations:
+//! ```c
+//! #include <stddef.h>
+//! #include <stdint.h>
+//!
+//! #define TINYAES_OK 0
+//! #define TINYAES_NULL_POINTER -1
+//! #define TINYAES_INVALID_KEY_LENGTH -2
+//!
+//! /* Encrypts the 16-byte block at `in` into the 16 bytes at `out` (which may be the same). */
+//! int32_t tinyaes_encrypt_block(const uint8_t *key, size_t key_len, const uint8_t *in, uint8_t *out);
+//!
+//! /* Decrypts the 16-byte block at `in` into the 16 bytes at `out` (which may be the same). */
+//! int32_t tinyaes_decrypt_block(const uint8_t *key, size_t key_len, const uint8_t *in, uint8_t *out);
+//! ```
+
+
+
+
+
+// IMPORTS
+
+use std::slice;
+
+use crate::aes_core::{
+    AESCore,
+    AESKey,
+    Block,
+};
+
+
+
+
+
+// FUNCTIONS
+
+#[no_mangle]
+pub unsafe extern "C" fn tinyaes_encrypt_block(key_ptr: *const u8, key_len: usize, in_ptr: *const u8, out_ptr: *mut u8) -> i32 {
+    //! Encrypts a single block with the given key, for callers through the C ABI.
+    //! # Arguments
+    //! * `key_ptr` - The pointer to the key.
+    //! * `key_len` - The length of the key in bytes (16, 24 or 32).
+    //! * `in_ptr` - The pointer to the block to be encrypted.
+    //! * `out_ptr` - The pointer to where the encrypted block is written.
+    //! # Returns
+    //! * `i32` - `TINYAES_OK`, `TINYAES_NULL_POINTER` or `TINYAES_INVALID_KEY_LENGTH`.
+    //! # Safety
+    //! * `key_ptr` must be valid for reading `key_len` bytes.
+    //! * `in_ptr` must be valid for reading 16 bytes, and `out_ptr` must be valid for writing 16 bytes (they may be the same).
+
+    // SAFETY: the caller guarantees the pointers are valid for the given lengths
+    unsafe { process_block(key_ptr, key_len, in_ptr, out_ptr, |core, block| core.encrypt(block)) }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn tinyaes_decrypt_block(key_ptr: *const u8, key_len: usize, in_ptr: *const u8, out_ptr: *mut u8) -> i32 {
+    //! Decrypts a single block with the given key, for callers through the C ABI.
+    //! # Arguments
+    //! * `key_ptr` - The pointer to the key.
+    //! * `key_len` - The length of the key in bytes (16, 24 or 32).
+    //! * `in_ptr` - The pointer to the block to be decrypted.
+    //! * `out_ptr` - The pointer to where the decrypted block is written.
+    //! # Returns
+    //! * `i32` - `TINYAES_OK`, `TINYAES_NULL_POINTER` or `TINYAES_INVALID_KEY_LENGTH`.
+    //! # Safety
+    //! * `key_ptr` must be valid for reading `key_len` bytes.
+    //! * `in_ptr` must be valid for reading 16 bytes, and `out_ptr` must be valid for writing 16 bytes (they may be the same).
+
+    // SAFETY: the caller guarantees the pointers are valid for the given lengths
+    unsafe { process_block(key_ptr, key_len, in_ptr, out_ptr, |core, block| core.decrypt(block)) }
+}
+
+unsafe fn process_block(
+    key_ptr: *const u8,
+    key_len: usize,
+    in_ptr: *const u8,
+    out_ptr: *mut u8,
+    operation: impl Fn(&AESCore, &Block) -> Block,
+) -> i32 {
+    //! Validates the arguments, and applies the operation to the input block.
+    //! The input is copied before the output is written, so the pointers may alias.
+
+    if key_ptr.is_null() || in_ptr.is_null() || out_ptr.is_null() {
+        return TINYAES_NULL_POINTER;
+    }
+
+    // SAFETY: the pointer isn't null, and the caller guarantees it is valid for reading key_len bytes
+    let key = unsafe { slice::from_raw_parts(key_ptr, key_len) };
+    let key = match AESKey::try_from(key) {
+        Ok(key) => key,
+        Err(_) => return TINYAES_INVALID_KEY_LENGTH,
+    };
+
+    let mut block: Block = [0; 16];
+    // SAFETY: the pointer isn't null, and the caller guarantees it is valid for reading 16 bytes
+    block.copy_from_slice(unsafe { slice::from_raw_parts(in_ptr, 16) });
+
+    let result = operation(&AESCore::new(key), &block);
+    // SAFETY: the pointer isn't null, and the caller guarantees it is valid for writing 16 bytes
+    unsafe { slice::from_raw_parts_mut(out_ptr, 16) }.copy_from_slice(&result);
+
+    TINYAES_OK
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The status code of a successful call.
+pub const TINYAES_OK: i32 = 0;
+
+/// The status code of a call with a null pointer.
+pub const TINYAES_NULL_POINTER: i32 = -1;
+
+/// The status code of a call with a key which isn't 16, 24 or 32 bytes long.
+pub const TINYAES_INVALID_KEY_LENGTH: i32 = -2;
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ptr;
+
+    const KEY: [u8; 32] = *b"This is a 256-bit key as bytes!!";
+
+    #[test]
+    fn extern_functions() {
+        //! Test calling the extern functions through their Rust signatures
+
+        let plaintext: [u8; 16] = *b"This is a block!";
+        let mut ciphertext: [u8; 16] = [0; 16];
+        let mut decrypted: [u8; 16] = [0; 16];
+
+        // SAFETY: the pointers are valid for the given lengths
+        unsafe {
+            assert_eq!(tinyaes_encrypt_block(KEY.as_ptr(), KEY.len(), plaintext.as_ptr(), ciphertext.as_mut_ptr()), TINYAES_OK);
+            assert_eq!(tinyaes_decrypt_block(KEY.as_ptr(), KEY.len(), ciphertext.as_ptr(), decrypted.as_mut_ptr()), TINYAES_OK);
+        }
+        assert_eq!(ciphertext, AESCore::new(AESKey::AES256(KEY)).encrypt(&plaintext));
+        assert_eq!(decrypted, plaintext);
+
+        // in place
+        let mut block = plaintext;
+        // SAFETY: the pointers are valid for the given lengths, and may alias
+        unsafe {
+            assert_eq!(tinyaes_encrypt_block(KEY.as_ptr(), 16, block.as_ptr(), block.as_mut_ptr()), TINYAES_OK);
+        }
+        assert_eq!(block, AESCore::new(AESKey::AES128(KEY[..16].try_into().unwrap())).encrypt(&plaintext));
+    }
+
+    #[test]
+    fn extern_errors() {
+        //! Test that null pointers and invalid key lengths are rejected without writing the output
+
+        let block: [u8; 16] = [0x42; 16];
+        let mut out: [u8; 16] = [0; 16];
+
+        // SAFETY: the non-null pointers are valid for the given lengths
+        unsafe {
+            assert_eq!(tinyaes_encrypt_block(ptr::null(), 16, block.as_ptr(), out.as_mut_ptr()), TINYAES_NULL_POINTER);
+            assert_eq!(tinyaes_encrypt_block(KEY.as_ptr(), 16, ptr::null(), out.as_mut_ptr()), TINYAES_NULL_POINTER);
+            assert_eq!(tinyaes_decrypt_block(KEY.as_ptr(), 16, block.as_ptr(), ptr::null_mut()), TINYAES_NULL_POINTER);
+            assert_eq!(tinyaes_encrypt_block(KEY.as_ptr(), 20, block.as_ptr(), out.as_mut_ptr()), TINYAES_INVALID_KEY_LENGTH);
+            assert_eq!(tinyaes_decrypt_block(KEY.as_ptr(), 0, block.as_ptr(), out.as_mut_ptr()), TINYAES_INVALID_KEY_LENGTH);
+        }
+        assert_eq!(out, [0; 16]);
+    }
+}
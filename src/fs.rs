@@ -0,0 +1,233 @@
+//! A module containing functions for encrypting and decrypting whole files.
+//!
+//! The encrypted file starts with the 16-byte IV (or the initial counter block for CTR mode),
+//! followed by the ciphertext. The files are processed in chunks, so they are never loaded into memory whole.
+
+
+
+
+
+// IMPORTS
+
+use std::fs::File;
+use std::io::{
+    self,
+    BufWriter,
+    ErrorKind,
+    Read,
+    Write,
+};
+use std::path::Path;
+use crate::aes_core::AESCore;
+use crate::builder::BuildError;
+use crate::modes::{
+    CbcDecryptor,
+    CbcEncryptor,
+    Cipher,
+    Direction,
+    Mode,
+};
+use crate::padding::{
+    Padding,
+    PaddingError,
+    PaddingTypes,
+};
+use crate::util::blocks;
+
+#[cfg(feature = "rand")]
+use crate::modes::random_iv;
+#[cfg(feature = "rand")]
+use rand_core::CryptoRng;
+
+
+
+
+
+// FUNCTIONS
+
+pub fn encrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(core: AESCore, mode: Mode, padding_type: PaddingTypes, iv: [u8; 16], in_path: P, out_path: Q) -> io::Result<()> {
+    //! Encrypts the file, writing the IV followed by the ciphertext to the output file.
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the data.
+    //! * `mode` - The mode of operation, either `Mode::Cbc` or one of the stream modes (`Mode::Ctr`, `Mode::Cfb`, `Mode::Ofb`).
+    //! * `padding_type` - The padding type, required for CBC mode and must be `PaddingTypes::None` for the stream modes.
+    //! * `iv` - The IV (or the initial counter block for CTR mode). Must never be reused with the same key.
+    //! * `in_path` - The path of the file to be encrypted.
+    //! * `out_path` - The path of the encrypted file, created or truncated.
+    //! # Errors
+    //! * io::Error - Reading or writing failed.
+    //! * io::Error with `ErrorKind::InvalidInput` - The configuration is invalid, wraps a `BuildError`.
+
+    check_config(mode, padding_type)?;
+
+    let mut input = File::open(in_path)?;
+    let mut output = BufWriter::new(File::create(out_path)?);
+    output.write_all(&iv)?;
+
+    match mode {
+        Mode::Cbc => {
+            let padding = Padding::new(padding_type);
+            let mut encryptor = CbcEncryptor::new(core, iv);
+            process_chunks(&mut input, |chunk, is_last| {
+                let full_blocks_len = chunk.len() - chunk.len() % 16;
+                for block in blocks(&chunk[..full_blocks_len]).0 {
+                    output.write_all(&encryptor.encrypt_block(block))?;
+                }
+
+                if is_last {
+                    let last_block = padding.pad(&chunk[full_blocks_len..]).map_err(invalid_data)?;
+                    output.write_all(&encryptor.encrypt_block(&last_block))?;
+                }
+                Ok(())
+            })?;
+        }
+        _ => process_stream(Cipher::new(core, mode, Direction::Encrypt, Some(iv)).map_err(invalid_input)?, &mut input, &mut output)?,
+    }
+
+    output.flush()
+}
+
+#[cfg(feature = "rand")]
+pub fn encrypt_file_random_iv<P: AsRef<Path>, Q: AsRef<Path>, R: CryptoRng + ?Sized>(core: AESCore, mode: Mode, padding_type: PaddingTypes, in_path: P, out_path: Q, rng: &mut R) -> io::Result<[u8; 16]> {
+    //! Encrypts the file with a fresh random IV, written followed by the ciphertext to the output file.
+    //! The output is the same as `encrypt_file` with the generated IV, and is decrypted with `decrypt_file`.
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the data.
+    //! * `mode` - The mode of operation, either `Mode::Cbc` or one of the stream modes (`Mode::Ctr`, `Mode::Cfb`, `Mode::Ofb`).
+    //! * `padding_type` - The padding type, required for CBC mode and must be `PaddingTypes::None` for the stream modes.
+    //! * `in_path` - The path of the file to be encrypted.
+    //! * `out_path` - The path of the encrypted file, created or truncated.
+    //! * `rng` - The cryptographically secure random number generator for the IV.
+    //! # Returns
+    //! * `io::Result<[u8; 16]>` - The generated IV (also stored at the start of the file), or an error.
+    //! # Errors
+    //! * io::Error - Reading or writing failed.
+    //! * io::Error with `ErrorKind::InvalidInput` - The configuration is invalid, wraps a `BuildError`.
+
+    check_config(mode, padding_type)?;
+
+    let iv: [u8; 16] = random_iv(rng);
+    encrypt_file(core, mode, padding_type, iv, in_path, out_path)?;
+    Ok(iv)
+}
+
+pub fn decrypt_file<P: AsRef<Path>, Q: AsRef<Path>>(core: AESCore, mode: Mode, padding_type: PaddingTypes, in_path: P, out_path: Q) -> io::Result<()> {
+    //! Decrypts the file encrypted with `encrypt_file`, reading the IV from its start.
+    //! The output file may contain partially decrypted data in case of an error.
+    //! # Arguments
+    //! * `core` - The AES core used to decrypt the data.
+    //! * `mode` - The mode of operation used for encryption.
+    //! * `padding_type` - The padding type used for encryption.
+    //! * `in_path` - The path of the encrypted file.
+    //! * `out_path` - The path of the decrypted file, created or truncated.
+    //! # Errors
+    //! * io::Error - Reading or writing failed.
+    //! * io::Error with `ErrorKind::InvalidInput` - The configuration is invalid, wraps a `BuildError`.
+    //! * io::Error with `ErrorKind::InvalidData` - The encrypted file is malformed, wraps a `PaddingError`.
+
+    check_config(mode, padding_type)?;
+
+    let mut input = File::open(in_path)?;
+    let mut iv: [u8; 16] = [0; 16];
+    input.read_exact(&mut iv)?;
+    let mut output = BufWriter::new(File::create(out_path)?);
+
+    match mode {
+        Mode::Cbc => {
+            let padding = Padding::new(padding_type);
+            let mut decryptor = CbcDecryptor::new(core, iv);
+            // the last block is held back until the end of the file is reached, as it contains the padding
+            let mut last_block: Option<[u8; 16]> = None;
+            process_chunks(&mut input, |chunk, _| {
+                if !chunk.len().is_multiple_of(16) {
+                    return Err(invalid_data(PaddingError::InvalidPaddedSize));
+                }
+
+                for block in blocks(chunk).0 {
+                    if let Some(block) = last_block {
+                        output.write_all(&block)?;
+                    }
+                    last_block = Some(decryptor.decrypt_block(block));
+                }
+                Ok(())
+            })?;
+
+            let last_block = last_block.ok_or_else(|| invalid_data(PaddingError::InvalidPaddedSize))?;
+            output.write_all(padding.de_pad(&last_block).map_err(invalid_data)?)?;
+        }
+        _ => process_stream(Cipher::new(core, mode, Direction::Decrypt, Some(iv)).map_err(invalid_input)?, &mut input, &mut output)?,
+    }
+
+    output.flush()
+}
+
+fn check_config(mode: Mode, padding_type: PaddingTypes) -> io::Result<()> {
+    //! Checks whether the mode of operation and the padding can be used for files.
+    //! The encrypted files always start with an IV, so ECB mode isn't supported.
+
+    let error = match mode {
+        Mode::Ecb => Some(BuildError::UnexpectedIv),
+        Mode::Cbc if padding_type == PaddingTypes::None => Some(BuildError::MissingPadding),
+        Mode::Cbc if padding_type == PaddingTypes::ZeroPadding => Some(BuildError::AmbiguousPadding),
+        Mode::Ctr | Mode::Cfb | Mode::Ofb if padding_type != PaddingTypes::None => Some(BuildError::UnexpectedPadding),
+        _ => None,
+    };
+
+    match error {
+        Some(error) => Err(invalid_input(error)),
+        None => Ok(()),
+    }
+}
+
+fn process_chunks<R: Read, F: FnMut(&mut [u8], bool) -> io::Result<()>>(input: &mut R, mut process: F) -> io::Result<()> {
+    //! Reads the input in chunks of a fixed-size buffer and passes each one to the closure,
+    //! along with whether it is the last one (shorter than the buffer, possibly empty).
+
+    let mut buffer: [u8; 4096] = [0; 4096];
+    loop {
+        let read = read_full(input, &mut buffer)?;
+        let is_last = read < buffer.len();
+        process(&mut buffer[..read], is_last)?;
+
+        if is_last {
+            return Ok(());
+        }
+    }
+}
+
+fn process_stream<R: Read, W: Write>(mut cipher: Cipher, input: &mut R, output: &mut W) -> io::Result<()> {
+    //! Encrypts or decrypts the whole input in one of the stream modes, writing the result to the output.
+
+    process_chunks(input, |chunk, _| {
+        cipher.process(chunk).map_err(invalid_data)?;
+        output.write_all(chunk)
+    })
+}
+
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    //! Reads until the buffer is full or the end of the input is reached.
+    //! Returns the number of bytes read, which is less than the buffer length only at the end of the input.
+
+    let mut read: usize = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(read)
+}
+
+fn invalid_input(error: BuildError) -> io::Error {
+    //! Wraps the configuration error as an invalid input I/O error.
+
+    io::Error::new(ErrorKind::InvalidInput, error)
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(error: E) -> io::Error {
+    //! Wraps the error as an invalid data I/O error.
+
+    io::Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,1683 @@
+//! A module containing the Galois/Counter Mode (GCM) authenticated encryption.
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use std::io::{
+    self,
+    ErrorKind,
+    Read,
+    Write,
+};
+use crate::aead::verify_or_wipe;
+use crate::aes_core::AESCore;
+use crate::util::{
+    blocks,
+    ct_eq,
+};
+
+#[cfg(all(feature = "clmul", target_arch = "x86_64"))]
+use crate::clmul;
+#[cfg(feature = "nonce-guard")]
+use crate::error::Error;
+#[cfg(feature = "nonce-guard")]
+use crate::modes::IvTracker;
+#[cfg(feature = "zeroize")]
+use crate::util::{
+    zeroize,
+    Zeroize,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with authenticated encryption errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AeadError {
+    /// The authentication tag doesn't match, the message was tampered with or the key/nonce/AAD are wrong.
+    AuthenticationFailed,
+    /// The input is too short to contain all the required parts.
+    InvalidLength,
+    /// The nonce isn't the length required by the mode.
+    InvalidNonceLength,
+    /// The plaintext (or ciphertext) or the associated data exceeds the length limit of the mode.
+    InputTooLong,
+    /// The key size isn't defined for the mode.
+    UnsupportedKeySize,
+    /// The tag length isn't allowed by the mode.
+    InvalidTagLength,
+}
+
+impl fmt::Display for AeadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AeadError::AuthenticationFailed => write!(f, "authentication failed"),
+            AeadError::InvalidLength => write!(f, "input is too short"),
+            AeadError::InvalidNonceLength => write!(f, "invalid nonce length"),
+            AeadError::InputTooLong => write!(f, "input is too long"),
+            AeadError::UnsupportedKeySize => write!(f, "key size isn't supported by the mode"),
+            AeadError::InvalidTagLength => write!(f, "invalid tag length"),
+        }
+    }
+}
+
+impl std::error::Error for AeadError {}
+
+
+
+
+
+// STRUCTS
+
+/// The GCM authenticated encryption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gcm {
+    /// The AES core used to encrypt the counter blocks.
+    core: AESCore,
+    /// The hash subkey (the encrypted zero block).
+    h: u128,
+    /// The length of the truncated tag in bytes (16 for the full tag).
+    tag_len: usize,
+}
+
+/// Public functions for authenticated encryption in GCM.
+impl Gcm {
+    pub fn new(core: AESCore) -> Self {
+        //! Creates a new GCM instance.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the data.
+
+        Self::with_tag_len(core, 16).expect("the full tag length is allowed")
+    }
+
+    pub fn with_tag_len(core: AESCore, tag_len: usize) -> Result<Self, AeadError> {
+        //! Creates a new GCM instance with a truncated tag, used by `Gcm::encrypt_truncated` and `Gcm::decrypt_truncated`.
+        //! The functions with a `[u8; 16]` tag always use the full tag.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the data.
+        //! * `tag_len` - The length of the tag in bytes, 12 to 16 (96, 104, 112, 120 or 128 bits, SP 800-38D).
+        //! # Returns
+        //! * `Result<Gcm, AeadError>` - The GCM instance or an error.
+        //! # Errors
+        //! * AeadError::InvalidTagLength - The tag length is not between 12 and 16 bytes.
+
+        if !(12..=16).contains(&tag_len) {
+            return Err(AeadError::InvalidTagLength);
+        }
+
+        Ok(Self {
+            core,
+            h: u128::from_be_bytes(core.encrypt(&[0; 16])),
+            tag_len,
+        })
+    }
+
+    pub fn tag_len(&self) -> usize {
+        //! Returns the length of the truncated tag in bytes.
+
+        self.tag_len
+    }
+
+    pub fn encrypt_truncated(&self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts and authenticates the plaintext, appending the tag truncated to the tag length.
+        //! # Arguments
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The ciphertext followed by the truncated tag, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+
+        let message = self.seal(nonce, aad, plaintext)?;
+
+        let mut output = message.ciphertext;
+        output.extend_from_slice(&message.tag[..self.tag_len]);
+        Ok(output)
+    }
+
+    pub fn decrypt_truncated(&self, nonce: [u8; 12], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies and decrypts the output of `Gcm::encrypt_truncated`.
+        //! Only the truncated tag is compared, in constant time, before decrypting.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `ciphertext` - The ciphertext followed by the truncated tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidLength - The ciphertext is shorter than the tag.
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        if ciphertext.len() < self.tag_len {
+            return Err(AeadError::InvalidLength);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_len);
+        check_gcm_lengths(aad.len() as u64, ciphertext.len() as u64)?;
+
+        let j0 = self.j0(&nonce);
+        if !ct_eq(&self.tag(&j0, aad, ciphertext)[..self.tag_len], tag) {
+            return Err(AeadError::AuthenticationFailed);
+        }
+
+        let mut plaintext = ciphertext.to_vec();
+        self.gctr(&j0, &mut plaintext);
+        Ok(plaintext)
+    }
+
+    pub fn encrypt(&self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts and authenticates the plaintext, appending the tag.
+        //! The lengths are checked first, so an input beyond the limits of GCM is rejected instead of reusing the keystream.
+        //! # Arguments
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The ciphertext followed by the tag, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+
+        let message = self.seal(nonce, aad, plaintext)?;
+
+        let mut output = message.ciphertext;
+        output.extend_from_slice(&message.tag);
+        Ok(output)
+    }
+
+    pub fn seal(&self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<SealedMessage, AeadError> {
+        //! Encrypts and authenticates the plaintext, and authenticates the associated data.
+        //! # Arguments
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<SealedMessage, AeadError>` - The nonce, the ciphertext and the authentication tag, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+
+        check_gcm_lengths(aad.len() as u64, plaintext.len() as u64)?;
+        let j0 = self.j0(&nonce);
+
+        let mut ciphertext = plaintext.to_vec();
+        self.gctr(&j0, &mut ciphertext);
+        let tag = self.tag(&j0, aad, &ciphertext);
+
+        Ok(SealedMessage {
+            nonce,
+            ciphertext,
+            tag,
+        })
+    }
+
+    pub fn open(&self, message: &SealedMessage, aad: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies and decrypts the sealed message.
+        //! The tag is verified before decrypting, so no unauthenticated plaintext is ever produced.
+        //! # Arguments
+        //! * `message` - The sealed message.
+        //! * `aad` - The associated data used when sealing the message.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        check_gcm_lengths(aad.len() as u64, message.ciphertext.len() as u64)?;
+        let j0 = self.j0(&message.nonce);
+
+        if !ct_eq(&self.tag(&j0, aad, &message.ciphertext), &message.tag) {
+            return Err(AeadError::AuthenticationFailed);
+        }
+
+        let mut plaintext = message.ciphertext.clone();
+        self.gctr(&j0, &mut plaintext);
+
+        Ok(plaintext)
+    }
+
+    pub fn encrypt_detached(&self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, [u8; 16]), AeadError> {
+        //! Encrypts and authenticates the plaintext, returning the tag separately from the ciphertext.
+        //! # Arguments
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<(Vec<u8>, [u8; 16]), AeadError>` - The ciphertext and the authentication tag, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+
+        let message = self.seal(nonce, aad, plaintext)?;
+        Ok((message.ciphertext, message.tag))
+    }
+
+    pub fn decrypt_detached(&self, nonce: [u8; 12], aad: &[u8], ciphertext: &[u8], tag: &[u8; 16]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies and decrypts the output of `Gcm::encrypt_detached`, the same as `Gcm::decrypt`.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `ciphertext` - The ciphertext.
+        //! * `tag` - The authentication tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        self.decrypt(nonce, aad, ciphertext, tag)
+    }
+
+    pub fn decrypt(&self, nonce: [u8; 12], aad: &[u8], ciphertext: &[u8], tag: &[u8; 16]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies and decrypts the ciphertext with a detached tag.
+        //! The plaintext is only produced after the tag is verified, so unverified plaintext is never exposed.
+        //! # Arguments
+        //! * `nonce` - The nonce used when sealing the message.
+        //! * `aad` - The associated data used when sealing the message.
+        //! * `ciphertext` - The ciphertext.
+        //! * `tag` - The authentication tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt_in_place_detached(nonce, aad, &mut plaintext, tag)?;
+        Ok(plaintext)
+    }
+
+    pub fn decrypt_in_place_detached(&self, nonce: [u8; 12], aad: &[u8], buffer: &mut [u8], tag: &[u8; 16]) -> Result<(), AeadError> {
+        //! Verifies and decrypts the ciphertext in place with a detached tag.
+        //! The tag is verified in constant time before decrypting. If it doesn't match,
+        //! the buffer is wiped (filled with zeros), so its contents can't be used by mistake.
+        //! # Arguments
+        //! * `nonce` - The nonce used when sealing the message.
+        //! * `aad` - The associated data used when sealing the message.
+        //! * `buffer` - The ciphertext, replaced with the plaintext (or zeros on failure).
+        //! * `tag` - The authentication tag.
+        //! # Errors
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match, the buffer was wiped.
+
+        check_gcm_lengths(aad.len() as u64, buffer.len() as u64)?;
+        self.open_in_place(&self.j0(&nonce), aad, buffer, tag)
+    }
+
+    pub fn encrypt_with_nonce(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<(Vec<u8>, [u8; 16]), AeadError> {
+        //! Encrypts and authenticates the plaintext with a nonce of any length, returning the tag separately.
+        //! A nonce other than 96-bit is hashed with GHASH into the pre-counter block (NIST SP 800-38D, section 7.1),
+        //! prefer the 96-bit nonces of `Gcm::seal` unless a protocol requires another length.
+        //! # Arguments
+        //! * `nonce` - The nonce, at least 1 byte long. Must never be reused with the same key.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<(Vec<u8>, [u8; 16]), AeadError>` - The ciphertext and the authentication tag, or an error.
+        //! # Errors
+        //! * AeadError::InvalidNonceLength - The nonce is empty.
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+
+        if nonce.is_empty() {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        check_gcm_lengths(aad.len() as u64, plaintext.len() as u64)?;
+        let j0 = self.j0(nonce);
+
+        let mut ciphertext = plaintext.to_vec();
+        self.gctr(&j0, &mut ciphertext);
+        let tag = self.tag(&j0, aad, &ciphertext);
+
+        Ok((ciphertext, tag))
+    }
+
+    pub fn decrypt_with_nonce(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8], tag: &[u8; 16]) -> Result<Vec<u8>, AeadError> {
+        //! Verifies and decrypts the output of `Gcm::encrypt_with_nonce`.
+        //! The tag is verified in constant time before decrypting, so unverified plaintext is never exposed.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `ciphertext` - The ciphertext.
+        //! * `tag` - The authentication tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidNonceLength - The nonce is empty.
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        if nonce.is_empty() {
+            return Err(AeadError::InvalidNonceLength);
+        }
+        check_gcm_lengths(aad.len() as u64, ciphertext.len() as u64)?;
+
+        let mut plaintext = ciphertext.to_vec();
+        self.open_in_place(&self.j0(nonce), aad, &mut plaintext, tag)?;
+        Ok(plaintext)
+    }
+
+    pub fn verify_reader<R: Read>(&self, nonce: [u8; 12], aad: &[u8], mut ciphertext: R, tag: &[u8; 16]) -> std::io::Result<bool> {
+        //! Verifies the tag of a stored ciphertext without decrypting it.
+        //! The ciphertext is read in a single pass using a fixed-size buffer,
+        //! which makes this suitable for periodically checking stored data for corruption or tampering.
+        //! # Arguments
+        //! * `nonce` - The nonce used when sealing the message.
+        //! * `aad` - The associated data used when sealing the message.
+        //! * `ciphertext` - The reader of the ciphertext, read until the end.
+        //! * `tag` - The stored authentication tag.
+        //! # Returns
+        //! * `std::io::Result<bool>` - Whether the tag matches, or an error from the reader.
+
+        let mut ghash = Ghash::new(self.h);
+        ghash.update_padded(aad);
+
+        let mut buffer: [u8; 4096] = [0; 4096];
+        let mut buffered: usize = 0;
+        let mut ciphertext_len: u64 = 0;
+
+        loop {
+            let read = match ciphertext.read(&mut buffer[buffered..]) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            buffered += read;
+            ciphertext_len += read as u64;
+
+            let full_blocks_len = buffered - buffered % 16;
+            for block in blocks(&buffer[..full_blocks_len]).0 {
+                ghash.update_block(block);
+            }
+            buffer.copy_within(full_blocks_len..buffered, 0);
+            buffered -= full_blocks_len;
+        }
+        ghash.update_padded(&buffer[..buffered]);
+
+        let j0 = self.j0(&nonce);
+        Ok(ct_eq(&self.finish_tag(ghash, &j0, aad.len() as u64, ciphertext_len), tag))
+    }
+}
+
+/// Clearing the hash subkey of GCM.
+#[cfg(feature = "zeroize")]
+impl Zeroize for Gcm {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.h);
+    }
+}
+
+/// Internal functions for authenticated encryption in GCM.
+impl Gcm {
+    fn j0(&self, nonce: &[u8]) -> [u8; 16] {
+        //! Derives the pre-counter block from the nonce.
+
+        if nonce.len() == 12 {
+            let mut j0: [u8; 16] = [0; 16];
+            j0[..12].copy_from_slice(nonce);
+            j0[15] = 1;
+            j0
+        } else {
+            let mut ghash = Ghash::new(self.h);
+            ghash.update_padded(nonce);
+            ghash.update_block(&((nonce.len() as u128) * 8).to_be_bytes());
+            ghash.finalize()
+        }
+    }
+
+    fn open_in_place(&self, j0: &[u8; 16], aad: &[u8], buffer: &mut [u8], tag: &[u8; 16]) -> Result<(), AeadError> {
+        //! Verifies the tag and decrypts the ciphertext in place, wiping the buffer if the tag doesn't match.
+
+        verify_or_wipe(&self.tag(j0, aad, buffer), tag, buffer)?;
+        self.gctr(j0, buffer);
+        Ok(())
+    }
+
+    fn gctr(&self, j0: &[u8; 16], data: &mut [u8]) {
+        //! Encrypts or decrypts the data with the counter blocks following the pre-counter block.
+
+        let mut counter = *j0;
+        for chunk in data.chunks_mut(16) {
+            inc32(&mut counter);
+            let keystream = self.core.encrypt(&counter);
+            for i in 0..chunk.len() {
+                chunk[i] ^= keystream[i];
+            }
+        }
+    }
+
+    fn tag(&self, j0: &[u8; 16], aad: &[u8], ciphertext: &[u8]) -> [u8; 16] {
+        //! Computes the authentication tag.
+
+        let mut ghash = Ghash::new(self.h);
+        ghash.update_padded(aad);
+        ghash.update_padded(ciphertext);
+
+        self.finish_tag(ghash, j0, aad.len() as u64, ciphertext.len() as u64)
+    }
+
+    fn finish_tag(&self, mut ghash: Ghash, j0: &[u8; 16], aad_len: u64, ciphertext_len: u64) -> [u8; 16] {
+        //! Absorbs the lengths block and computes the authentication tag from the hash value.
+
+        ghash.update_block(&(((aad_len as u128) * 8) << 64 | ((ciphertext_len as u128) * 8)).to_be_bytes());
+
+        let mut tag = ghash.finalize();
+        let encrypted_j0 = self.core.encrypt(j0);
+        for i in 0..16 {
+            tag[i] ^= encrypted_j0[i];
+        }
+        tag
+    }
+}
+
+/// A GCM session which rejects reused nonces.
+///
+/// Reusing a nonce with the same key reveals the XOR of the plaintexts and allows forging tags.
+/// The session remembers a bounded number of the most recently used nonces (see `IvTracker`),
+/// so it is meant to catch the misuse during testing, not to replace a sound nonce scheme.
+#[cfg(feature = "nonce-guard")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcmSession {
+    /// The GCM instance used to seal the messages.
+    gcm: Gcm,
+    /// The record of the used nonces, as their pre-counter blocks.
+    tracker: IvTracker,
+}
+
+/// Public functions for the tracked GCM session.
+#[cfg(feature = "nonce-guard")]
+impl GcmSession {
+    pub fn new(core: AESCore, capacity: usize) -> Self {
+        //! Creates a new GCM session.
+        //! # Arguments
+        //! * `core` - The AES core used to authenticate and encrypt the data.
+        //! * `capacity` - The maximum number of nonces remembered.
+
+        Self {
+            gcm: Gcm::new(core),
+            tracker: IvTracker::new(capacity),
+        }
+    }
+
+    pub fn gcm(&self) -> &Gcm {
+        //! Returns the GCM instance, e.g. for opening the messages.
+
+        &self.gcm
+    }
+
+    pub fn tracker(&self) -> &IvTracker {
+        //! Returns the record of the used nonces.
+
+        &self.tracker
+    }
+
+    pub fn seal(&mut self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<SealedMessage, Error> {
+        //! Encrypts and authenticates the plaintext (see `Gcm::seal`).
+        //! The nonce is only registered if the lengths are within the limits.
+        //! # Errors
+        //! * Error::Aead(AeadError::InputTooLong) - The plaintext or the associated data is too long (see `check_gcm_lengths`).
+        //! * Error::Mode(ModeError::ReusedIv) - The nonce was already used within this session.
+
+        check_gcm_lengths(aad.len() as u64, plaintext.len() as u64)?;
+        self.tracker.register(self.gcm.j0(&nonce))?;
+
+        Ok(self.gcm.seal(nonce, aad, plaintext)?)
+    }
+}
+
+/// A message sealed with GCM.
+/// None of the parts are secret, but the length of the ciphertext reveals the length of the plaintext.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SealedMessage {
+    /// The 96-bit nonce.
+    pub nonce: [u8; 12],
+    /// The ciphertext, the same length as the plaintext.
+    pub ciphertext: Vec<u8>,
+    /// The authentication tag.
+    pub tag: [u8; 16],
+}
+
+/// Public functions for serializing the sealed message.
+impl SealedMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        //! Serializes the sealed message as nonce || ciphertext || tag.
+
+        let mut bytes = Vec::with_capacity(12 + self.ciphertext.len() + 16);
+        bytes.extend_from_slice(&self.nonce);
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes.extend_from_slice(&self.tag);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, AeadError> {
+        //! Deserializes the sealed message from nonce || ciphertext || tag.
+        //! # Errors
+        //! * AeadError::InvalidLength - The input is shorter than the nonce and the tag together.
+
+        if bytes.len() < 12 + 16 {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let (nonce, rest) = bytes.split_at(12);
+        let (ciphertext, tag) = rest.split_at(rest.len() - 16);
+
+        Ok(Self {
+            nonce: nonce.try_into().unwrap(),
+            ciphertext: ciphertext.to_vec(),
+            tag: tag.try_into().unwrap(),
+        })
+    }
+}
+
+/// The GMAC message authentication code (GCM authenticating only the associated data).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gmac {
+    /// The GCM instance used to compute the tag.
+    gcm: Gcm,
+    /// The pre-counter block derived from the nonce.
+    j0: [u8; 16],
+    /// The GHASH state over the data.
+    ghash: StreamingGhash,
+    /// The total length of the data.
+    data_len: u64,
+}
+
+/// Public functions for computing the GMAC.
+impl Gmac {
+    pub fn new(core: AESCore, nonce: [u8; 12]) -> Self {
+        //! Creates a new GMAC instance.
+        //! # Arguments
+        //! * `core` - The AES core used to authenticate the data.
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+
+        let gcm = Gcm::new(core);
+
+        Self {
+            gcm,
+            j0: gcm.j0(&nonce),
+            ghash: StreamingGhash::new(gcm.h),
+            data_len: 0,
+        }
+    }
+
+    pub fn update(&mut self, aad: &[u8]) -> Result<(), AeadError> {
+        //! Feeds the data into the GMAC computation.
+        //! # Errors
+        //! * AeadError::InputTooLong - The total length of the data would exceed `GCM_MAX_AAD_LEN`, the data isn't fed.
+
+        let data_len = self.data_len.saturating_add(aad.len() as u64);
+        check_gcm_lengths(data_len, 0)?;
+
+        self.data_len = data_len;
+        self.ghash.update(aad);
+        Ok(())
+    }
+
+    pub fn finalize(mut self) -> [u8; 16] {
+        //! Finishes the computation and returns the authentication tag.
+        //! The tag is the same as the GCM tag of an empty plaintext with the data as the associated data.
+
+        self.ghash.pad();
+        self.gcm.finish_tag(self.ghash.ghash, &self.j0, self.data_len, 0)
+    }
+}
+
+/// Clearing the hash subkey and the GHASH state of the GMAC.
+#[cfg(feature = "zeroize")]
+impl Zeroize for Gmac {
+    fn zeroize(&mut self) {
+        self.gcm.zeroize();
+        zeroize(&mut self.j0);
+        self.ghash.zeroize();
+        zeroize(&mut self.data_len);
+    }
+}
+
+/// The streaming GCM encryptor.
+/// The associated data and the plaintext can be fed in parts of any length,
+/// the associated data must be fed before the plaintext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GcmEncryptor {
+    /// The GCM instance used to encrypt the data and compute the tag.
+    gcm: Gcm,
+    /// The pre-counter block derived from the nonce.
+    j0: [u8; 16],
+    /// The last used counter block.
+    counter: [u8; 16],
+    /// The current keystream block.
+    keystream: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+    /// The GHASH state over the associated data and the ciphertext.
+    ghash: StreamingGhash,
+    /// The total length of the associated data.
+    aad_len: u64,
+    /// The total length of the ciphertext.
+    ciphertext_len: u64,
+    /// Whether the encryption started (no more associated data can be fed).
+    encrypting: bool,
+}
+
+/// Public functions for encrypting data in GCM in parts.
+impl GcmEncryptor {
+    pub fn new(core: AESCore, nonce: [u8; 12]) -> Self {
+        //! Creates a new streaming GCM encryptor.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the data.
+        //! * `nonce` - The 96-bit nonce. Must never be reused with the same key.
+
+        let gcm = Gcm::new(core);
+        let j0 = gcm.j0(&nonce);
+
+        Self {
+            gcm,
+            j0,
+            counter: j0,
+            keystream: [0; 16],
+            position: 16,
+            ghash: StreamingGhash::new(gcm.h),
+            aad_len: 0,
+            ciphertext_len: 0,
+            encrypting: false,
+        }
+    }
+
+    pub fn update_aad(&mut self, aad: &[u8]) -> Result<(), AeadError> {
+        //! Feeds the next part of the associated data, authenticated but not encrypted.
+        //! # Errors
+        //! * AeadError::InputTooLong - The total length of the associated data would exceed `GCM_MAX_AAD_LEN`, the part isn't fed.
+        //! # Panics
+        //! * The encryption already started with `GcmEncryptor::encrypt_update`.
+
+        assert!(!self.encrypting, "associated data must be fed before the plaintext");
+
+        let aad_len = self.aad_len.saturating_add(aad.len() as u64);
+        check_gcm_lengths(aad_len, self.ciphertext_len)?;
+
+        self.aad_len = aad_len;
+        self.ghash.update(aad);
+        Ok(())
+    }
+
+    pub fn encrypt_update(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts the next part of the plaintext.
+        //! The running total is checked before encrypting, so the 32-bit counter never wraps around and reuses the keystream.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The ciphertext, the same length as the plaintext, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The total length of the plaintext would exceed `GCM_MAX_PLAINTEXT_LEN`, the part isn't encrypted.
+
+        let ciphertext_len = self.ciphertext_len.saturating_add(plaintext.len() as u64);
+        check_gcm_lengths(self.aad_len, ciphertext_len)?;
+
+        if !self.encrypting {
+            // the associated data is padded to a whole block before the ciphertext
+            self.ghash.pad();
+            self.encrypting = true;
+        }
+
+        let mut ciphertext = plaintext.to_vec();
+        for byte in ciphertext.iter_mut() {
+            if self.position == 16 {
+                inc32(&mut self.counter);
+                self.keystream = self.gcm.core.encrypt(&self.counter);
+                self.position = 0;
+            }
+            *byte ^= self.keystream[self.position];
+            self.position += 1;
+        }
+
+        self.ciphertext_len = ciphertext_len;
+        self.ghash.update(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    pub fn encrypt_readers<A: Read, P: Read, W: Write>(mut self, mut aad: A, mut plaintext: P, mut output: W) -> io::Result<[u8; 16]> {
+        //! Encrypts the data from separate readers of the associated data and the plaintext, and finishes the encryption.
+        //! The whole associated data is read first, then the plaintext is encrypted in chunks using a fixed-size buffer,
+        //! so neither of them is loaded into memory whole.
+        //! # Arguments
+        //! * `aad` - The reader of the associated data, read until the end.
+        //! * `plaintext` - The reader of the plaintext, read until the end.
+        //! * `output` - The writer the ciphertext is written to.
+        //! # Returns
+        //! * `io::Result<[u8; 16]>` - The authentication tag, or an error.
+        //! # Errors
+        //! * io::Error - Reading or writing failed.
+        //! * io::Error with `ErrorKind::InvalidInput` - The associated data or the plaintext is too long, wraps an `AeadError`.
+        //! # Panics
+        //! * The encryption already started with `GcmEncryptor::encrypt_update`.
+
+        let mut buffer: [u8; 4096] = [0; 4096];
+
+        loop {
+            let read = read_retrying(&mut aad, &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            self.update_aad(&buffer[..read]).map_err(|error| io::Error::new(ErrorKind::InvalidInput, error))?;
+        }
+
+        loop {
+            let read = read_retrying(&mut plaintext, &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            let ciphertext = self.encrypt_update(&buffer[..read]).map_err(|error| io::Error::new(ErrorKind::InvalidInput, error))?;
+            output.write_all(&ciphertext)?;
+        }
+
+        Ok(self.finish())
+    }
+
+    pub fn finish(mut self) -> [u8; 16] {
+        //! Finishes the encryption and returns the authentication tag.
+
+        self.ghash.pad();
+        self.gcm.finish_tag(self.ghash.ghash, &self.j0, self.aad_len, self.ciphertext_len)
+    }
+}
+
+/// Clearing the hash subkey, the counter blocks, the keystream block and the GHASH state of the encryptor.
+#[cfg(feature = "zeroize")]
+impl Zeroize for GcmEncryptor {
+    fn zeroize(&mut self) {
+        self.gcm.zeroize();
+        zeroize(&mut self.j0);
+        zeroize(&mut self.counter);
+        zeroize(&mut self.keystream);
+        self.position = 16;
+        self.ghash.zeroize();
+        zeroize(&mut self.aad_len);
+        zeroize(&mut self.ciphertext_len);
+        zeroize(&mut self.encrypting);
+    }
+}
+
+/// The GHASH universal hash function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Ghash {
+    /// The hash subkey.
+    h: u128,
+    /// The current hash value.
+    y: u128,
+}
+
+/// The GHASH function over data fed in parts of any length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct StreamingGhash {
+    /// The GHASH state over the whole blocks.
+    ghash: Ghash,
+    /// The buffered partial block.
+    buffer: [u8; 16],
+    /// The number of bytes in the buffer.
+    buffered: usize,
+}
+
+/// Functions for computing GHASH in parts.
+impl StreamingGhash {
+    fn new(h: u128) -> Self {
+        //! Creates a new streaming GHASH instance with the given hash subkey.
+
+        Self {
+            ghash: Ghash::new(h),
+            buffer: [0; 16],
+            buffered: 0,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        //! Absorbs the data, buffering the trailing partial block.
+
+        let mut data = data;
+        if self.buffered > 0 {
+            let taken = data.len().min(16 - self.buffered);
+            self.buffer[self.buffered..(self.buffered + taken)].copy_from_slice(&data[..taken]);
+            self.buffered += taken;
+            data = &data[taken..];
+
+            if self.buffered < 16 {
+                return;
+            }
+            self.ghash.update_block(&self.buffer);
+            self.buffered = 0;
+        }
+
+        let (whole_blocks, remainder) = blocks(data);
+        for block in whole_blocks {
+            self.ghash.update_block(block);
+        }
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+    }
+
+    fn pad(&mut self) {
+        //! Absorbs the buffered partial block padded with zeros, so the next data starts a new block.
+
+        self.ghash.update_padded(&self.buffer[..self.buffered]);
+        self.buffered = 0;
+    }
+
+    #[cfg(feature = "zeroize")]
+    fn zeroize(&mut self) {
+        //! Overwrites the hash subkey, the hash value and the buffered data with zeros.
+
+        zeroize(&mut self.ghash.h);
+        zeroize(&mut self.ghash.y);
+        zeroize(&mut self.buffer);
+        self.buffered = 0;
+    }
+}
+
+/// Functions for computing GHASH.
+impl Ghash {
+    fn new(h: u128) -> Self {
+        //! Creates a new GHASH instance with the given hash subkey.
+
+        Self {
+            h,
+            y: 0,
+        }
+    }
+
+    fn update_block(&mut self, block: &[u8; 16]) {
+        //! Absorbs a single block.
+
+        self.y = gf128_mul(self.y ^ u128::from_be_bytes(*block), self.h);
+    }
+
+    fn update_padded(&mut self, data: &[u8]) {
+        //! Absorbs the data, padding the last partial block with zeros.
+
+        for chunk in data.chunks(16) {
+            let mut block: [u8; 16] = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.update_block(&block);
+        }
+    }
+
+    fn finalize(&self) -> [u8; 16] {
+        //! Returns the hash value.
+
+        self.y.to_be_bytes()
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub(crate) fn gf128_mul(x: u128, y: u128) -> u128 {
+    //! Multiplies two elements of GF(2^128) as defined for GCM (bit-reflected, big-endian blocks).
+    //! With the `clmul` feature on x86-64, the carry-less multiplication is used if the processor supports it.
+
+    #[cfg(all(feature = "clmul", target_arch = "x86_64"))]
+    if clmul::is_supported() {
+        // SAFETY: the processor supports the carry-less multiplication
+        return unsafe { clmul::gf128_mul(x, y) };
+    }
+
+    gf128_mul_soft(x, y)
+}
+
+fn gf128_mul_soft(x: u128, y: u128) -> u128 {
+    //! Multiplies two elements of GF(2^128) bit by bit, the reference implementation.
+    //! The multiplication doesn't branch on the values.
+
+    let mut z: u128 = 0;
+    let mut v: u128 = y;
+
+    for i in 0..128 {
+        z ^= v & ((x >> (127 - i)) & 1).wrapping_neg();
+        v = (v >> 1) ^ (R & (v & 1).wrapping_neg());
+    }
+
+    z
+}
+
+pub fn check_gcm_lengths(aad_len: u64, text_len: u64) -> Result<(), AeadError> {
+    //! Checks the lengths of the associated data and of the plaintext (or ciphertext) against the limits of GCM
+    //! (NIST SP 800-38D, section 5.2.1.1). The 32-bit counter allows at most 2^32 - 2 blocks of keystream,
+    //! and the lengths block of GHASH encodes the bit lengths in 64 bits.
+    //! # Arguments
+    //! * `aad_len` - The length of the associated data in bytes.
+    //! * `text_len` - The length of the plaintext or the ciphertext in bytes.
+    //! # Errors
+    //! * AeadError::InputTooLong - The associated data is longer than `GCM_MAX_AAD_LEN`,
+    //!   or the plaintext is longer than `GCM_MAX_PLAINTEXT_LEN`.
+
+    if aad_len > GCM_MAX_AAD_LEN || text_len > GCM_MAX_PLAINTEXT_LEN {
+        return Err(AeadError::InputTooLong);
+    }
+
+    Ok(())
+}
+
+fn inc32(counter: &mut [u8; 16]) {
+    //! Increments the last 32 bits of the counter block, wrapping around.
+
+    let value = u32::from_be_bytes(counter[12..16].try_into().unwrap()).wrapping_add(1);
+    counter[12..16].copy_from_slice(&value.to_be_bytes());
+}
+
+fn read_retrying<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    //! Reads into the buffer, retrying if the read was interrupted.
+    //! Returns the number of bytes read, 0 only at the end of the input.
+
+    loop {
+        match reader.read(buffer) {
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The maximum length of the plaintext in GCM, 2^39 - 256 bits (about 64 GiB).
+pub const GCM_MAX_PLAINTEXT_LEN: u64 = (1 << 36) - 32;
+
+/// The maximum length of the associated data in GCM, 2^64 - 1 bits rounded down to whole bytes.
+pub const GCM_MAX_AAD_LEN: u64 = (1 << 61) - 1;
+
+/// The reduction polynomial of GF(2^128) used in GCM.
+pub(crate) const R: u128 = 0xe1 << 120;
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::aes_core::AESKey;
+    #[cfg(feature = "nonce-guard")]
+    use crate::modes::ModeError;
+    #[cfg(feature = "zeroize")]
+    use crate::util::state_after_drop;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn seal() {
+        //! Test sealing with the test cases from the GCM specification
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128([0; 16])));
+
+        let sealed1 = gcm.seal([0; 12], &[], &[]).unwrap();
+        assert!(sealed1.ciphertext.is_empty());
+        assert_eq!(sealed1.tag.to_vec(), hex("58e2fccefa7e3061367f1d57a4e7455a"));
+
+        let sealed2 = gcm.seal([0; 12], &[], &[0; 16]).unwrap();
+        assert_eq!(sealed2.ciphertext, hex("0388dace60b6a392f328c2b971b2fe78"));
+        assert_eq!(sealed2.tag.to_vec(), hex("ab6e47d42cec13bdf53a67b21257bddf"));
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap())));
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255"
+        );
+
+        let sealed3 = gcm.seal(nonce, &[], &plaintext).unwrap();
+        assert_eq!(sealed3.nonce, nonce);
+        assert_eq!(sealed3.ciphertext, hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985"
+        ));
+        assert_eq!(sealed3.tag.to_vec(), hex("4d5c2af327cd64a62cf35abd2ba6fab4"));
+
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let sealed4 = gcm.seal(nonce, &aad, &plaintext[..60]).unwrap();
+        assert_eq!(sealed4.ciphertext, hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091"
+        ));
+        assert_eq!(sealed4.tag.to_vec(), hex("5bc94fbc3221a5db94fae95ae7121a47"));
+    }
+
+    #[test]
+    fn truncated_tags() {
+        //! Test 96-bit and 128-bit tags with the test cases 2 and 4 from the GCM specification, and the rejected tag lengths
+
+        let gcm128 = Gcm::new(AESCore::new(AESKey::AES128([0; 16])));
+        let gcm96 = Gcm::with_tag_len(AESCore::new(AESKey::AES128([0; 16])), 12).unwrap();
+        assert_eq!((gcm128.tag_len(), gcm96.tag_len()), (16, 12));
+
+        let output = hex("0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf");
+        assert_eq!(gcm128.encrypt_truncated([0; 12], &[], &[0; 16]).unwrap(), output);
+        assert_eq!(gcm96.encrypt_truncated([0; 12], &[], &[0; 16]).unwrap(), output[..28]);
+        assert_eq!(gcm128.decrypt_truncated([0; 12], &[], &output).unwrap(), [0; 16]);
+        assert_eq!(gcm96.decrypt_truncated([0; 12], &[], &output[..28]).unwrap(), [0; 16]);
+
+        let core = AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()));
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+        );
+        let output = hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+             5bc94fbc3221a5db94fae95a"
+        );
+
+        for tag_len in 12..=16 {
+            let gcm = Gcm::with_tag_len(core, tag_len).unwrap();
+            let truncated = gcm.encrypt_truncated(nonce, &aad, &plaintext).unwrap();
+            assert_eq!(truncated.len(), 60 + tag_len);
+            assert_eq!(truncated[..72], output);
+            assert_eq!(gcm.decrypt_truncated(nonce, &aad, &truncated).unwrap(), plaintext);
+
+            let mut tampered = truncated.clone();
+            tampered[59 + tag_len] ^= 0x01;
+            assert_eq!(gcm.decrypt_truncated(nonce, &aad, &tampered), Err(AeadError::AuthenticationFailed));
+            assert_eq!(gcm.decrypt_truncated(nonce, &aad, &truncated[..(tag_len - 1)]), Err(AeadError::InvalidLength));
+        }
+
+        // a longer tag than expected doesn't verify
+        let gcm96 = Gcm::with_tag_len(core, 12).unwrap();
+        let full = Gcm::new(core).encrypt_truncated(nonce, &aad, &plaintext).unwrap();
+        assert_eq!(gcm96.decrypt_truncated(nonce, &aad, &full), Err(AeadError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn truncated_tag_length() {
+        //! Test that tag lengths outside 96 to 128 bits are rejected
+
+        let core = AESCore::new(AESKey::AES128([0; 16]));
+        for tag_len in [0, 4, 8, 11, 17, 32] {
+            assert_eq!(Gcm::with_tag_len(core, tag_len), Err(AeadError::InvalidTagLength));
+        }
+    }
+
+    #[test]
+    fn spec_vectors() {
+        //! Test all three key sizes with the test cases 4 - 6, 10 - 12 and 16 - 18 from the GCM specification
+        //! (96-bit, 64-bit and 480-bit nonces), and a 1024-bit nonce
+
+        let plaintext = hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+             1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+        );
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+
+        // key, nonce, ciphertext, tag
+        let vectors: [(AESKey, &str, &str, &str); 12] = [
+            (
+                AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "cafebabefacedbaddecaf888",
+                "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+                 21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091",
+                "5bc94fbc3221a5db94fae95ae7121a47",
+            ),
+            (
+                AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "cafebabefacedbad",
+                "61353b4c2806934a777ff51fa22a4755699b2a714fcdc6f83766e5f97b6c7423\
+                 73806900e49f24b22b097544d4896b424989b5e1ebac0f07c23f4598",
+                "3612d2e79e3b0785561be14aaca2fccb",
+            ),
+            (
+                AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+                 c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+                "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+                 01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5",
+                "619cc5aefffe0bfa462af43c1699d050",
+            ),
+            (
+                AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+                 202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+                 404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+                 606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f",
+                "dc03137fe838695d3760010ca55ca973991ae00f8d57f293386e5938730c9f52\
+                 ebe6f2db4c9d69c4839807cbbb032eed33a1d62bf155f363c670ab73",
+                "6d36761779fbfbeeeb0a16fa5ceda15f",
+            ),
+            (
+                AESKey::AES192(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c").try_into().unwrap()),
+                "cafebabefacedbaddecaf888",
+                "3980ca0b3c00e841eb06fac4872a2757859e1ceaa6efd984628593b40ca1e19c\
+                 7d773d00c144c525ac619d18c84a3f4718e2448b2fe324d9ccda2710",
+                "2519498e80f1478f37ba55bd6d27618c",
+            ),
+            (
+                AESKey::AES192(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c").try_into().unwrap()),
+                "cafebabefacedbad",
+                "0f10f599ae14a154ed24b36e25324db8c566632ef2bbb34f8347280fc4507057\
+                 fddc29df9a471f75c66541d4d4dad1c9e93a19a58e8b473fa0f062f7",
+                "65dcc57fcf623a24094fcca40d3533f8",
+            ),
+            (
+                AESKey::AES192(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c").try_into().unwrap()),
+                "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+                 c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+                "d27e88681ce3243c4830165a8fdcf9ff1de9a1d8e6b447ef6ef7b79828666e45\
+                 81e79012af34ddd9e2f037589b292db3e67c036745fa22e7e9b7373b",
+                "dcf566ff291c25bbb8568fc3d376a6d9",
+            ),
+            (
+                AESKey::AES192(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c").try_into().unwrap()),
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+                 202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+                 404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+                 606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f",
+                "4028fa3287cf5349233c7ab2262cada2846648b9f0a9953876ff53df07b77755\
+                 4686198aa5f3bf200d0af18a6cda0861e546d722e332b9834a533343",
+                "1cd5fe62698fa00f2ec4e7c577cbbf07",
+            ),
+            (
+                AESKey::AES256(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "cafebabefacedbaddecaf888",
+                "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+                 8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662",
+                "76fc6ece0f4e1768cddf8853bb2d551b",
+            ),
+            (
+                AESKey::AES256(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "cafebabefacedbad",
+                "c3762df1ca787d32ae47c13bf19844cbaf1ae14d0b976afac52ff7d79bba9de0\
+                 feb582d33934a4f0954cc2363bc73f7862ac430e64abe499f47c9b1f",
+                "3a337dbf46a792c45e454913fe2ea8f2",
+            ),
+            (
+                AESKey::AES256(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+                 c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+                "5a8def2f0c9e53f1f75d7853659e2a20eeb2b22aafde6419a058ab4f6f746bf4\
+                 0fc0c3b780f244452da3ebf1c5d82cdea2418997200ef82e44ae7e3f",
+                "a44a8266ee1c8eb0c8b5d4cf5ae9f19a",
+            ),
+            (
+                AESKey::AES256(hex("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308").try_into().unwrap()),
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f\
+                 202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f\
+                 404142434445464748494a4b4c4d4e4f505152535455565758595a5b5c5d5e5f\
+                 606162636465666768696a6b6c6d6e6f707172737475767778797a7b7c7d7e7f",
+                "a335bc530eeb97f2df9586c2ac67a9015ce403fe80ae9dcc48d6f1ede619a65c\
+                 644c3b4debdaf113f48e9bd80f869d72d274f8d3e73cb52fa906b540",
+                "c5e3e62111a6b1aca194afcfc2fb8e36",
+            ),
+        ];
+
+        for (key, nonce, ciphertext, tag) in vectors {
+            let gcm = Gcm::new(AESCore::new(key));
+            let (nonce, ciphertext, tag) = (hex(nonce), hex(ciphertext), hex(tag));
+
+            let (encrypted, encrypted_tag) = gcm.encrypt_with_nonce(&nonce, &aad, &plaintext).unwrap();
+            assert_eq!(encrypted, ciphertext);
+            assert_eq!(encrypted_tag.to_vec(), tag);
+            assert_eq!(gcm.decrypt_with_nonce(&nonce, &aad, &ciphertext, &encrypted_tag).unwrap(), plaintext);
+
+            if let Ok(nonce) = <[u8; 12]>::try_from(nonce.as_slice()) {
+                let sealed = gcm.seal(nonce, &aad, &plaintext).unwrap();
+                assert_eq!(sealed.ciphertext, ciphertext);
+                assert_eq!(sealed.tag.to_vec(), tag);
+                assert_eq!(gcm.decrypt(nonce, &aad, &ciphertext, &sealed.tag).unwrap(), plaintext);
+            }
+        }
+    }
+
+    #[test]
+    fn open() {
+        //! Test opening sealed messages, including tampered ones
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap())));
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = b"The quick brown fox jumps over the lazy dog.";
+
+        let sealed = gcm.seal(nonce, &aad, plaintext).unwrap();
+        assert_eq!(gcm.open(&sealed, &aad).unwrap(), plaintext);
+
+        assert_eq!(gcm.open(&sealed, &aad[1..]), Err(AeadError::AuthenticationFailed));
+
+        let mut tampered = sealed.clone();
+        tampered.ciphertext[7] ^= 0x01;
+        assert_eq!(gcm.open(&tampered, &aad), Err(AeadError::AuthenticationFailed));
+
+        let mut tampered = sealed.clone();
+        tampered.tag[15] ^= 0x80;
+        assert_eq!(gcm.open(&tampered, &aad), Err(AeadError::AuthenticationFailed));
+
+        let mut tampered = sealed;
+        tampered.nonce[0] ^= 0x01;
+        assert_eq!(gcm.open(&tampered, &aad), Err(AeadError::AuthenticationFailed));
+
+        // a nonce of another length, which must not be empty
+        let (ciphertext, tag) = gcm.encrypt_with_nonce(&[0x5c; 7], &aad, plaintext).unwrap();
+        assert_eq!(gcm.decrypt_with_nonce(&[0x5c; 7], &aad, &ciphertext, &tag).unwrap(), plaintext);
+        assert_eq!(gcm.decrypt_with_nonce(&[0x5c; 8], &aad, &ciphertext, &tag), Err(AeadError::AuthenticationFailed));
+        assert_eq!(gcm.encrypt_with_nonce(&[], &aad, plaintext), Err(AeadError::InvalidNonceLength));
+        assert_eq!(gcm.decrypt_with_nonce(&[], &aad, &ciphertext, &tag), Err(AeadError::InvalidNonceLength));
+    }
+
+    #[test]
+    fn decrypt_detached() {
+        //! Test decrypting with a detached tag, and that the buffer is cleared on a tag mismatch
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap())));
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let plaintext = b"The quick brown fox jumps over the lazy dog.";
+        let sealed = gcm.seal(nonce, &aad, plaintext).unwrap();
+
+        assert_eq!(gcm.decrypt(nonce, &aad, &sealed.ciphertext, &sealed.tag).unwrap(), plaintext);
+
+        let mut buffer = sealed.ciphertext.clone();
+        gcm.decrypt_in_place_detached(nonce, &aad, &mut buffer, &sealed.tag).unwrap();
+        assert_eq!(buffer, plaintext);
+
+        let mut tag = sealed.tag;
+        tag[0] ^= 0x01;
+        assert_eq!(gcm.decrypt(nonce, &aad, &sealed.ciphertext, &tag), Err(AeadError::AuthenticationFailed));
+
+        let mut buffer = sealed.ciphertext.clone();
+        assert_eq!(gcm.decrypt_in_place_detached(nonce, &aad, &mut buffer, &tag), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, vec![0; plaintext.len()]);
+
+        let mut buffer = sealed.ciphertext.clone();
+        buffer[3] ^= 0x10;
+        assert_eq!(gcm.decrypt_in_place_detached(nonce, &aad[1..], &mut buffer, &sealed.tag), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, vec![0; plaintext.len()]);
+    }
+
+    #[test]
+    fn detached_and_combined() {
+        //! Test that the detached and the combined forms are consistent
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES256([0x6e; 32])));
+        let nonce: [u8; 12] = [0x24; 12];
+        let plaintext = b"ciphertext and tag sent separately";
+
+        let (ciphertext, tag) = gcm.encrypt_detached(nonce, b"aad", plaintext).unwrap();
+        let sealed = gcm.seal(nonce, b"aad", plaintext).unwrap();
+        assert_eq!(ciphertext, sealed.ciphertext);
+        assert_eq!(tag, sealed.tag);
+        assert_eq!(gcm.decrypt_detached(nonce, b"aad", &ciphertext, &tag).unwrap(), plaintext);
+        assert_eq!(gcm.open(&sealed, b"aad").unwrap(), plaintext);
+
+        let combined = sealed.to_bytes();
+        let (ciphertext, tag) = combined[12..].split_at(combined.len() - 12 - 16);
+        assert_eq!(gcm.decrypt_detached(nonce, b"aad", ciphertext, tag.try_into().unwrap()).unwrap(), plaintext);
+        assert_eq!(gcm.decrypt_detached(nonce, b"aad", &ciphertext[1..], tag.try_into().unwrap()), Err(AeadError::AuthenticationFailed));
+    }
+
+    #[test]
+    fn streaming_encryption() {
+        //! Test that encrypting in parts of various lengths matches sealing at once
+
+        let core = AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()));
+        let gcm = Gcm::new(core);
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+        let aad: Vec<u8> = (0..100).map(|i| (i * 3) as u8).collect();
+        let plaintext: Vec<u8> = (0..203).map(|i| (i * 7) as u8).collect();
+
+        for (aad_len, plaintext_len) in [(0, 0), (20, 60), (100, 203), (17, 64)] {
+            let sealed = gcm.seal(nonce, &aad[..aad_len], &plaintext[..plaintext_len]).unwrap();
+
+            for chunk_len in [1, 5, 16, 33, 256] {
+                let mut encryptor = GcmEncryptor::new(core, nonce);
+                for chunk in aad[..aad_len].chunks(chunk_len) {
+                    encryptor.update_aad(chunk).unwrap();
+                }
+                let mut ciphertext = Vec::new();
+                for chunk in plaintext[..plaintext_len].chunks(chunk_len) {
+                    ciphertext.extend(encryptor.encrypt_update(chunk).unwrap());
+                }
+                assert_eq!(ciphertext, sealed.ciphertext);
+                assert_eq!(encryptor.finish(), sealed.tag);
+            }
+        }
+
+        // the GCM specification test case 4
+        let mut encryptor = GcmEncryptor::new(core, nonce);
+        encryptor.update_aad(&hex("feedfacedeadbeef")).unwrap();
+        encryptor.update_aad(&hex("feedfacedeadbeefabaddad2")).unwrap();
+        let mut ciphertext = encryptor.encrypt_update(&hex("d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72")).unwrap();
+        ciphertext.extend(encryptor.encrypt_update(&hex("1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39")).unwrap());
+        assert_eq!(ciphertext, hex(
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091"
+        ));
+        assert_eq!(encryptor.finish().to_vec(), hex("5bc94fbc3221a5db94fae95ae7121a47"));
+    }
+
+    #[test]
+    fn streaming_readers() {
+        //! Test that encrypting from separate readers of the associated data and the plaintext matches the detached encryption
+
+        let core = AESCore::new(AESKey::AES256([0x6d; 32]));
+        let gcm = Gcm::new(core);
+        let nonce: [u8; 12] = [0x3a; 12];
+        let aad: Vec<u8> = (0..5000).map(|i| (i * 11) as u8).collect();
+        let plaintext: Vec<u8> = (0..10_000).map(|i| (i * 7) as u8).collect();
+
+        for (aad_len, plaintext_len) in [(0, 0), (0, 100), (17, 0), (100, 4096), (5000, 10_000)] {
+            let (expected_ciphertext, expected_tag) = gcm.encrypt_detached(nonce, &aad[..aad_len], &plaintext[..plaintext_len]).unwrap();
+
+            let mut ciphertext = Vec::new();
+            let tag = GcmEncryptor::new(core, nonce)
+                .encrypt_readers(Cursor::new(&aad[..aad_len]), Cursor::new(&plaintext[..plaintext_len]), &mut ciphertext)
+                .unwrap();
+            assert_eq!(ciphertext, expected_ciphertext);
+            assert_eq!(tag, expected_tag);
+        }
+
+        let mut encryptor = GcmEncryptor::new(core, nonce);
+        encryptor.aad_len = GCM_MAX_AAD_LEN;
+        let error = encryptor.encrypt_readers(Cursor::new(b"aad"), Cursor::new(b"plaintext"), Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert_eq!(error.into_inner().unwrap().downcast_ref::<AeadError>(), Some(&AeadError::InputTooLong));
+    }
+
+    #[test]
+    #[should_panic(expected = "associated data must be fed before the plaintext")]
+    fn streaming_aad_after_plaintext() {
+        //! Test that feeding associated data after the plaintext panics
+
+        let mut encryptor = GcmEncryptor::new(AESCore::new(AESKey::AES128([0; 16])), [0; 12]);
+        encryptor.encrypt_update(b"plaintext").unwrap();
+        encryptor.update_aad(b"aad").unwrap();
+    }
+
+    #[test]
+    fn sealed_message_bytes() {
+        //! Test serializing and deserializing the sealed message
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES256([0x42; 32])));
+        let sealed = gcm.seal([0x24; 12], b"header", b"payload").unwrap();
+
+        let bytes = sealed.to_bytes();
+        assert_eq!(bytes.len(), 12 + 7 + 16);
+        assert_eq!(&bytes[..12], &sealed.nonce);
+        assert_eq!(&bytes[12..19], &sealed.ciphertext[..]);
+        assert_eq!(&bytes[19..], &sealed.tag);
+
+        let deserialized = SealedMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(deserialized, sealed);
+        assert_eq!(gcm.open(&deserialized, b"header").unwrap(), b"payload");
+
+        let empty = gcm.seal([0; 12], &[], &[]).unwrap();
+        assert_eq!(SealedMessage::from_bytes(&empty.to_bytes()).unwrap(), empty);
+
+        assert_eq!(SealedMessage::from_bytes(&bytes[..27]), Err(AeadError::InvalidLength));
+    }
+
+    /// A reader returning the data in small chunks, to exercise the buffering.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk_len: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.chunk_len.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn verify_reader() {
+        //! Test verifying the tag of a large ciphertext read from a stream
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES192([0x5a; 24])));
+        let nonce: [u8; 12] = [0x01; 12];
+        let aad = b"blob metadata";
+        let plaintext: Vec<u8> = (0..(1 << 20) + 5).map(|i| (i % 251) as u8).collect();
+
+        let sealed = gcm.seal(nonce, aad, &plaintext).unwrap();
+
+        let cursor = std::io::Cursor::new(&sealed.ciphertext);
+        assert!(gcm.verify_reader(nonce, aad, cursor, &sealed.tag).unwrap());
+
+        let reader = ChunkedReader { data: &sealed.ciphertext, chunk_len: 7 };
+        assert!(gcm.verify_reader(nonce, aad, reader, &sealed.tag).unwrap());
+
+        let mut corrupted = sealed.ciphertext.clone();
+        corrupted[123_456] ^= 0x04;
+        let cursor = std::io::Cursor::new(&corrupted);
+        assert!(!gcm.verify_reader(nonce, aad, cursor, &sealed.tag).unwrap());
+
+        let cursor = std::io::Cursor::new(&sealed.ciphertext);
+        assert!(!gcm.verify_reader(nonce, b"other metadata", cursor, &sealed.tag).unwrap());
+
+        let empty = gcm.seal(nonce, aad, &[]).unwrap();
+        assert!(gcm.verify_reader(nonce, aad, std::io::empty(), &empty.tag).unwrap());
+    }
+
+    #[test]
+    fn gmac() {
+        //! Test GMAC against GCM with an empty plaintext
+
+        let gmac = Gmac::new(AESCore::new(AESKey::AES128([0; 16])), [0; 12]);
+        assert_eq!(gmac.finalize().to_vec(), hex("58e2fccefa7e3061367f1d57a4e7455a"));
+
+        let core = AESCore::new(AESKey::AES128(hex("feffe9928665731c6d6a8f9467308308").try_into().unwrap()));
+        let nonce: [u8; 12] = hex("cafebabefacedbaddecaf888").try_into().unwrap();
+
+        let mut gmac = Gmac::new(core, nonce);
+        gmac.update(&hex("feedfacedeadbeeffeedfacedeadbeefabaddad2")).unwrap();
+        assert_eq!(gmac.finalize().to_vec(), hex("346434fd51d5cd0c5887ec63e39b907a"));
+
+        let aad: Vec<u8> = (0..100).collect();
+        let expected = hex("d57ff5caf5b6fe991a56c69bf9fbc3b7");
+        assert_eq!(Gcm::new(core).seal(nonce, &aad, &[]).unwrap().tag.to_vec(), expected);
+        for chunk_size in [1, 7, 16, 33, 100] {
+            let mut gmac = Gmac::new(core, nonce);
+            for chunk in aad.chunks(chunk_size) {
+                gmac.update(chunk).unwrap();
+            }
+            gmac.update(&[]).unwrap();
+            assert_eq!(gmac.finalize().to_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn gf128_multiplication() {
+        //! Test the GF(2^128) multiplication
+
+        // the multiplicative identity is the block with only the leftmost bit set
+        let one: u128 = 1 << 127;
+        let value: u128 = 0x66e94bd4ef8a2c3b884cfa59ca342b2e;
+        assert_eq!(gf128_mul(value, one), value);
+        assert_eq!(gf128_mul(one, value), value);
+        assert_eq!(gf128_mul(value, 0), 0);
+        assert_eq!(gf128_mul(0x0388dace60b6a392f328c2b971b2fe78, 0x66e94bd4ef8a2c3b884cfa59ca342b2e), 0x5e2ec746917062882c85b0685353deb7);
+    }
+
+    #[test]
+    fn error_display() {
+        //! Test the error messages
+
+        assert_eq!(AeadError::AuthenticationFailed.to_string(), "authentication failed");
+        assert_eq!(AeadError::InvalidLength.to_string(), "input is too short");
+        assert_eq!(AeadError::InvalidNonceLength.to_string(), "invalid nonce length");
+        assert_eq!(AeadError::InputTooLong.to_string(), "input is too long");
+        assert_eq!(AeadError::UnsupportedKeySize.to_string(), "key size isn't supported by the mode");
+        assert_eq!(AeadError::InvalidTagLength.to_string(), "invalid tag length");
+    }
+
+    #[test]
+    #[cfg(feature = "nonce-guard")]
+    fn session_rejects_reused_nonce() {
+        //! Test that the tracked session rejects a reused nonce and accepts distinct ones
+
+        let core = AESCore::new(AESKey::AES128([0x11; 16]));
+        let mut session = GcmSession::new(core, 16);
+
+        let sealed = session.seal([0; 12], b"aad", b"plaintext").unwrap();
+        assert_eq!(sealed, Gcm::new(core).seal([0; 12], b"aad", b"plaintext").unwrap());
+        assert_eq!(session.gcm().open(&sealed, b"aad").unwrap(), b"plaintext");
+
+        assert_eq!(session.seal([0; 12], b"other aad", b"other plaintext"), Err(Error::Mode(ModeError::ReusedIv)));
+        for i in 1..8 {
+            assert!(session.seal([i; 12], b"aad", b"plaintext").is_ok());
+        }
+        assert_eq!(session.seal([7; 12], b"aad", b"plaintext"), Err(Error::Mode(ModeError::ReusedIv)));
+        assert_eq!(session.tracker().len(), 8);
+    }
+
+    #[test]
+    #[cfg(all(feature = "clmul", target_arch = "x86_64"))]
+    fn clmul() {
+        //! Test that the carry-less multiplication matches the software multiplication
+
+        if !clmul::is_supported() {
+            return;
+        }
+
+        let mut state: u128 = 0x0123456789abcdeffedcba9876543210;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for (x, y) in [(0, 0), (1, 1 << 127), (u128::MAX, u128::MAX), (1 << 127, R)] {
+            assert_eq!(unsafe { clmul::gf128_mul(x, y) }, gf128_mul_soft(x, y));
+        }
+        for _ in 0..10000 {
+            let (x, y) = (next(), next());
+            assert_eq!(unsafe { clmul::gf128_mul(x, y) }, gf128_mul_soft(x, y));
+        }
+    }
+
+    #[test]
+    fn length_limits() {
+        //! Test the length limits at their exact thresholds, without allocating the data
+
+        assert_eq!(check_gcm_lengths(0, 0), Ok(()));
+        assert_eq!(check_gcm_lengths(GCM_MAX_AAD_LEN, GCM_MAX_PLAINTEXT_LEN), Ok(()));
+        assert_eq!(check_gcm_lengths(GCM_MAX_AAD_LEN + 1, 0), Err(AeadError::InputTooLong));
+        assert_eq!(check_gcm_lengths(0, GCM_MAX_PLAINTEXT_LEN + 1), Err(AeadError::InputTooLong));
+        assert_eq!(check_gcm_lengths(u64::MAX, u64::MAX), Err(AeadError::InputTooLong));
+
+        // the last counter block, 2^32 - 1 (the first one is used for the tag), is the last block of the longest plaintext
+        assert_eq!(GCM_MAX_PLAINTEXT_LEN.div_ceil(16), (1 << 32) - 2);
+        assert_eq!(GCM_MAX_PLAINTEXT_LEN * 8, (1 << 39) - 256);
+        assert!(GCM_MAX_AAD_LEN.checked_mul(8).is_some());
+        assert!((GCM_MAX_AAD_LEN + 1).checked_mul(8).is_none());
+
+        let gcm = Gcm::new(AESCore::new(AESKey::AES128([0; 16])));
+        let message = gcm.seal([0; 12], b"aad", b"plaintext").unwrap();
+        assert_eq!(gcm.encrypt([0; 12], b"aad", b"plaintext").unwrap(), [message.ciphertext, message.tag.to_vec()].concat());
+    }
+
+    #[test]
+    fn streaming_length_limits() {
+        //! Test that the streaming APIs check the running totals, starting just below the limits
+
+        let core = AESCore::new(AESKey::AES128([0; 16]));
+
+        // the plaintext can reach the limit exactly, but not a byte more
+        let mut encryptor = GcmEncryptor::new(core, [0; 12]);
+        encryptor.ciphertext_len = GCM_MAX_PLAINTEXT_LEN - 16;
+        assert_eq!(encryptor.encrypt_update(&[0; 17]), Err(AeadError::InputTooLong));
+        let unchanged = encryptor;
+        assert_eq!(encryptor.encrypt_update(&[0; 16]).unwrap().len(), 16);
+        assert_eq!(encryptor.encrypt_update(&[0; 1]), Err(AeadError::InputTooLong));
+        assert_eq!(unchanged.ciphertext_len, GCM_MAX_PLAINTEXT_LEN - 16);
+        assert_eq!(encryptor.ciphertext_len, GCM_MAX_PLAINTEXT_LEN);
+
+        let mut encryptor = GcmEncryptor::new(core, [0; 12]);
+        encryptor.aad_len = GCM_MAX_AAD_LEN - 3;
+        assert_eq!(encryptor.update_aad(&[0; 4]), Err(AeadError::InputTooLong));
+        assert_eq!(encryptor.update_aad(&[0; 3]), Ok(()));
+        assert_eq!(encryptor.update_aad(&[0; 1]), Err(AeadError::InputTooLong));
+        assert_eq!(encryptor.aad_len, GCM_MAX_AAD_LEN);
+
+        let mut gmac = Gmac::new(core, [0; 12]);
+        gmac.data_len = GCM_MAX_AAD_LEN;
+        assert_eq!(gmac.update(&[]), Ok(()));
+        assert_eq!(gmac.update(&[0; 1]), Err(AeadError::InputTooLong));
+        assert_eq!(gmac.data_len, GCM_MAX_AAD_LEN);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        //! Test that clearing GCM overwrites the hash subkey
+
+        let core = AESCore::new(AESKey::AES128([0x2b; 16]));
+        let mut gcm = Gcm::new(core);
+        assert_ne!(gcm.h, 0);
+        gcm.zeroize();
+        assert_eq!(gcm.h, 0);
+        assert_eq!(gcm.core, core);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize_streaming() {
+        //! Test that clearing the streaming encryptor and GMAC overwrites the counter, the keystream and the GHASH state,
+        //! and resets the lengths, both when called directly and when the `Zeroizing` wrapper is dropped
+
+        let core = AESCore::new(AESKey::AES128([0x2b; 16]));
+        let mut encryptor = GcmEncryptor::new(core, [0x11; 12]);
+        encryptor.update_aad(b"header").unwrap();
+        encryptor.encrypt_update(b"a partial block of plaintext").unwrap();
+        assert_ne!(encryptor.keystream, [0; 16]);
+        assert_ne!(encryptor.ghash.buffered, 0);
+
+        encryptor.zeroize();
+        assert_eq!(encryptor.gcm.h, 0);
+        assert_eq!(encryptor.gcm.core, core);
+        assert_eq!(encryptor.j0, [0; 16]);
+        assert_eq!(encryptor.counter, [0; 16]);
+        assert_eq!(encryptor.keystream, [0; 16]);
+        assert_eq!(encryptor.position, 16);
+        assert_eq!((encryptor.ghash.ghash.h, encryptor.ghash.ghash.y), (0, 0));
+        assert_eq!((encryptor.ghash.buffer, encryptor.ghash.buffered), ([0; 16], 0));
+        assert_eq!((encryptor.aad_len, encryptor.ciphertext_len, encryptor.encrypting), (0, 0, false));
+        encryptor.update_aad(b"associated data can be fed again").unwrap();
+
+        let mut gmac = Gmac::new(core, [0x11; 12]);
+        gmac.update(b"some data").unwrap();
+        gmac.zeroize();
+        assert_eq!(gmac.gcm.h, 0);
+        assert_eq!(gmac.j0, [0; 16]);
+        assert_eq!((gmac.ghash.ghash.h, gmac.ghash.ghash.y), (0, 0));
+        assert_eq!((gmac.ghash.buffer, gmac.ghash.buffered), ([0; 16], 0));
+        assert_eq!(gmac.data_len, 0);
+
+        // and by dropping the wrapper
+        let mut encryptor = GcmEncryptor::new(core, [0x11; 12]);
+        encryptor.update_aad(b"header").unwrap();
+        encryptor.encrypt_update(b"plaintext").unwrap();
+        let dropped = state_after_drop(encryptor);
+        assert_eq!((dropped.gcm.h, dropped.j0, dropped.counter, dropped.keystream), (0, [0; 16], [0; 16], [0; 16]));
+        assert_eq!((dropped.ghash.ghash.y, dropped.ghash.buffered), (0, 0));
+        assert_eq!((dropped.aad_len, dropped.ciphertext_len, dropped.encrypting), (0, 0, false));
+    }
+}
@@ -0,0 +1,412 @@
+//! A module containing the AES-GCM-SIV nonce misuse-resistant authenticated encryption (RFC 8452).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aead::verify_or_wipe;
+use crate::aes_core::{
+    AESCore,
+    AESKey,
+};
+use crate::gcm::{
+    gf128_mul,
+    AeadError,
+    R,
+};
+
+
+
+
+
+// STRUCTS
+
+/// The AES-GCM-SIV authenticated encryption.
+/// The message-authentication and message-encryption keys are derived from the key-generating key for each nonce,
+/// and the tag (computed with POLYVAL over the associated data and the plaintext) is the initial counter block.
+/// Reusing a nonce only reveals whether the same message was encrypted with the same associated data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GcmSiv {
+    /// The AES core keyed with the key-generating key.
+    core: AESCore,
+}
+
+/// Public functions for authenticated encryption with AES-GCM-SIV.
+impl GcmSiv {
+    pub fn new(core: AESCore) -> Result<Self, AeadError> {
+        //! Creates a new AES-GCM-SIV instance.
+        //! # Arguments
+        //! * `core` - The AES core keyed with the key-generating key (AES-128 or AES-256).
+        //! # Returns
+        //! * `Result<GcmSiv, AeadError>` - The AES-GCM-SIV instance or an error.
+        //! # Errors
+        //! * AeadError::UnsupportedKeySize - The core uses AES-192, which isn't defined for AES-GCM-SIV.
+
+        if core.key_size_bits() == 192 {
+            return Err(AeadError::UnsupportedKeySize);
+        }
+
+        Ok(Self {
+            core,
+        })
+    }
+
+    pub fn encrypt(&self, nonce: [u8; 12], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts and authenticates the plaintext, and authenticates the associated data.
+        //! # Arguments
+        //! * `nonce` - The 96-bit nonce. Should be unique for each message, but reusing it doesn't break the confidentiality of other messages.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The ciphertext followed by the tag, or an error.
+        //! # Errors
+        //! * AeadError::InputTooLong - The plaintext or the associated data is too long (see `check_gcm_siv_lengths`).
+
+        check_gcm_siv_lengths(aad.len() as u64, plaintext.len() as u64)?;
+        let (auth_key, encryption_core) = self.derive_keys(&nonce);
+        let tag = tag(&auth_key, &encryption_core, &nonce, aad, plaintext);
+
+        let mut output = Vec::with_capacity(plaintext.len() + 16);
+        output.extend_from_slice(plaintext);
+        ctr(&encryption_core, &tag, &mut output);
+        output.extend_from_slice(&tag);
+        Ok(output)
+    }
+
+    pub fn decrypt(&self, nonce: [u8; 12], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Decrypts the ciphertext and verifies the tag in constant time.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `ciphertext` - The ciphertext followed by the tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidLength - The ciphertext is shorter than the tag.
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_siv_lengths`).
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        if ciphertext.len() < 16 {
+            return Err(AeadError::InvalidLength);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - 16);
+
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt_in_place_detached(nonce, aad, &mut plaintext, tag.try_into().unwrap())?;
+        Ok(plaintext)
+    }
+
+    pub fn decrypt_in_place_detached(&self, nonce: [u8; 12], aad: &[u8], buffer: &mut [u8], received_tag: &[u8; 16]) -> Result<(), AeadError> {
+        //! Decrypts the ciphertext in place and verifies the detached tag in constant time.
+        //! The tag is computed over the plaintext, so the buffer is decrypted before the verification.
+        //! If the tag doesn't match, the buffer is wiped (filled with zeros), so no unverified plaintext is left in it.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `buffer` - The ciphertext, replaced with the plaintext (or zeros on failure).
+        //! * `received_tag` - The authentication tag.
+        //! # Errors
+        //! * AeadError::InputTooLong - The ciphertext or the associated data is too long (see `check_gcm_siv_lengths`),
+        //!   the buffer is unchanged.
+        //! * AeadError::AuthenticationFailed - The tag doesn't match, the buffer was wiped.
+
+        check_gcm_siv_lengths(aad.len() as u64, buffer.len() as u64)?;
+        let (auth_key, encryption_core) = self.derive_keys(&nonce);
+        ctr(&encryption_core, received_tag, buffer);
+
+        verify_or_wipe(&tag(&auth_key, &encryption_core, &nonce, aad, buffer), received_tag, buffer)
+    }
+}
+
+/// Internal functions for authenticated encryption with AES-GCM-SIV.
+impl GcmSiv {
+    fn derive_keys(&self, nonce: &[u8; 12]) -> ([u8; 16], AESCore) {
+        //! Derives the message-authentication key and the message-encryption key for the nonce.
+        //! Each 8-byte half of the keys is the first half of the encrypted block (little-endian counter || nonce).
+
+        let mut halves = (0..(self.core.key_size_bits() / 64 + 2) as u32).map(|i| {
+            let mut block: [u8; 16] = [0; 16];
+            block[..4].copy_from_slice(&i.to_le_bytes());
+            block[4..].copy_from_slice(nonce);
+            self.core.encrypt(&block)
+        });
+
+        let mut auth_key: [u8; 16] = [0; 16];
+        for chunk in auth_key.chunks_exact_mut(8) {
+            chunk.copy_from_slice(&halves.next().unwrap()[..8]);
+        }
+
+        let encryption_key: Vec<u8> = halves.flat_map(|block| block[..8].to_vec()).collect();
+        let encryption_key = AESKey::try_from(&encryption_key[..]).expect("the derived key is 16 or 32 bytes long");
+
+        (auth_key, AESCore::new(encryption_key))
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn check_gcm_siv_lengths(aad_len: u64, text_len: u64) -> Result<(), AeadError> {
+    //! Checks the lengths of the associated data and of the plaintext (or ciphertext without the tag)
+    //! against the limits of AES-GCM-SIV (RFC 8452, section 6).
+    //! A longer plaintext would wrap the 32-bit counter around and reuse the keystream.
+    //! # Arguments
+    //! * `aad_len` - The length of the associated data in bytes.
+    //! * `text_len` - The length of the plaintext or the ciphertext in bytes.
+    //! # Errors
+    //! * AeadError::InputTooLong - The associated data is longer than `GCM_SIV_MAX_AAD_LEN`,
+    //!   or the plaintext is longer than `GCM_SIV_MAX_PLAINTEXT_LEN`.
+
+    if aad_len > GCM_SIV_MAX_AAD_LEN || text_len > GCM_SIV_MAX_PLAINTEXT_LEN {
+        return Err(AeadError::InputTooLong);
+    }
+
+    Ok(())
+}
+
+fn tag(auth_key: &[u8; 16], encryption_core: &AESCore, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> [u8; 16] {
+    //! Computes the tag, the encrypted POLYVAL of the associated data, the plaintext and their lengths, XORed with the nonce.
+
+    let mut length_block: [u8; 16] = [0; 16];
+    length_block[..8].copy_from_slice(&((aad.len() as u64) * 8).to_le_bytes());
+    length_block[8..].copy_from_slice(&((plaintext.len() as u64) * 8).to_le_bytes());
+
+    let mut s = polyval(auth_key, &[aad, plaintext, &length_block]);
+    for i in 0..12 {
+        s[i] ^= nonce[i];
+    }
+    s[15] &= 0x7f;
+
+    encryption_core.encrypt(&s)
+}
+
+fn ctr(encryption_core: &AESCore, tag: &[u8; 16], data: &mut [u8]) {
+    //! Encrypts or decrypts the data in CTR mode, starting from the tag with the most significant bit set.
+    //! The first 32 bits of the counter block are incremented as a little-endian integer, wrapping around.
+
+    let mut counter: [u8; 16] = *tag;
+    counter[15] |= 0x80;
+
+    for chunk in data.chunks_mut(16) {
+        let keystream = encryption_core.encrypt(&counter);
+        for i in 0..chunk.len() {
+            chunk[i] ^= keystream[i];
+        }
+
+        let value = u32::from_le_bytes(counter[..4].try_into().unwrap()).wrapping_add(1);
+        counter[..4].copy_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn polyval(h: &[u8; 16], parts: &[&[u8]]) -> [u8; 16] {
+    //! Computes POLYVAL of the parts, each padded with zeros to a whole number of blocks.
+    //! POLYVAL is computed with the GHASH multiplication, as the byte-reversed GHASH with the key multiplied by x:
+    //! POLYVAL(H, X) = ByteReverse(GHASH(mulX_GHASH(ByteReverse(H)), ByteReverse(X))).
+
+    let h = u128::from_le_bytes(*h);
+    let h = (h >> 1) ^ (R & (h & 1).wrapping_neg());
+
+    let mut s: u128 = 0;
+    for part in parts {
+        for chunk in part.chunks(16) {
+            let mut block: [u8; 16] = [0; 16];
+            block[..chunk.len()].copy_from_slice(chunk);
+            s = gf128_mul(s ^ u128::from_le_bytes(block), h);
+        }
+    }
+
+    s.to_le_bytes()
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The maximum length of the plaintext in AES-GCM-SIV, 2^36 bytes.
+pub const GCM_SIV_MAX_PLAINTEXT_LEN: u64 = 1 << 36;
+
+/// The maximum length of the associated data in AES-GCM-SIV, 2^36 bytes.
+pub const GCM_SIV_MAX_AAD_LEN: u64 = 1 << 36;
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    fn gcm_siv(key: &str) -> GcmSiv {
+        GcmSiv::new(AESCore::new(AESKey::try_from(&hex(key)[..]).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn polyval_rfc8452() {
+        //! Test POLYVAL with the RFC 8452 example (Appendix A)
+
+        let h: [u8; 16] = hex("25629347589242761d31f826ba4b757b").try_into().unwrap();
+        let x = hex("4f4f95668c83dfb6401762bb2d01a262d1a24ddd2721d006bbe45f20d3c9f362");
+
+        assert_eq!(polyval(&h, &[&x]).to_vec(), hex("f7a3b47b846119fae5b7866cf5e5b77e"));
+        assert_eq!(polyval(&h, &[&x[..16], &x[16..]]).to_vec(), hex("f7a3b47b846119fae5b7866cf5e5b77e"));
+    }
+
+    #[test]
+    fn rfc8452() {
+        //! Test with the RFC 8452 test vectors (Appendix C), including the counter wraparound
+
+        let vectors: [(&str, &str, &str, &str, &str); 9] = [
+            (
+                "01000000000000000000000000000000",
+                "030000000000000000000000",
+                "",
+                "0100000000000000",
+                "b5d839330ac7b786578782fff6013b815b287c22493a364c",
+            ),
+            (
+                "01000000000000000000000000000000",
+                "030000000000000000000000",
+                "",
+                "01000000000000000000000000000000",
+                "743f7c8077ab25f8624e2e948579cf77303aaf90f6fe21199c6068577437a0c4",
+            ),
+            (
+                "01000000000000000000000000000000",
+                "030000000000000000000000",
+                "01",
+                "020000000000000000000000",
+                "296c7889fd99f41917f4462008299c5102745aaa3a0c469fad9e075a",
+            ),
+            (
+                "01000000000000000000000000000000",
+                "030000000000000000000000",
+                "010000000000000000000000",
+                "0200000000000000000000000000000003000000000000000000000000000000",
+                "605cb439c366cef0525ee9032cdb81b0366ac7435bfd6dfe4892a59fbca85d6a2421355f1398a00afdb9396b73604ed2",
+            ),
+            (
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "030000000000000000000000",
+                "",
+                "",
+                "07f5f4169bbf55a8400cd47ea6fd400f",
+            ),
+            (
+                "0100000000000000000000000000000000000000000000000000000000000000",
+                "030000000000000000000000",
+                "01",
+                "02000000000000000000000000000000030000000000000000000000000000000400000000000000",
+                "bdf21f4913e5f08f4ae4afaf2ab98d4c2bfdb33e78df11fe84e4572a412ca639508f8f535cea6342c7c492f145d7a8bb3c5973eaae7a3dc8",
+            ),
+            (
+                "e66021d5eb8e4f4066d4adb9c33560e4",
+                "f46e44bb3da0015c94f70887",
+                "",
+                "",
+                "a4194b79071b01a87d65f706e3949578",
+            ),
+            (
+                "00000000000000000000000000000000",
+                "000000000000000000000000",
+                "",
+                "000000000000000000000000000000004db923dc793ee6497c76dcc03a98e108",
+                "bb7a5e3d4a7ca9ee707ae34b060583e2484abe63b8d48b208d0e34c1d8e38946bbdd1e3b79feb6f284f8d5ec4a656bf6",
+            ),
+            (
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "000000000000000000000000",
+                "",
+                "000000000000000000000000000000004db923dc793ee6497c76dcc03a98e108",
+                "f3f80f2cf0cb2dd9c5984fcda908456cc537703b5ba70324a6793a7bf218d3eaffffffff000000000000000000000000",
+            ),
+        ];
+
+        for (key, nonce, aad, plaintext, output) in vectors {
+            let gcm_siv = gcm_siv(key);
+            let nonce: [u8; 12] = hex(nonce).try_into().unwrap();
+            let (aad, plaintext, output) = (hex(aad), hex(plaintext), hex(output));
+
+            assert_eq!(gcm_siv.encrypt(nonce, &aad, &plaintext).unwrap(), output);
+            assert_eq!(gcm_siv.decrypt(nonce, &aad, &output).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn gcm_siv_errors() {
+        //! Test that tampered inputs are rejected
+
+        let gcm_siv = gcm_siv("ee8e1ed9ff2540ae8f2ba9f50bc2f27c");
+        let nonce: [u8; 12] = [0x75; 12];
+        let output = gcm_siv.encrypt(nonce, b"header", b"a message longer than a block").unwrap();
+
+        assert_eq!(gcm_siv.decrypt(nonce, b"header", &output[..15]), Err(AeadError::InvalidLength));
+        assert_eq!(gcm_siv.decrypt(nonce, b"header!", &output), Err(AeadError::AuthenticationFailed));
+        assert_eq!(gcm_siv.decrypt([0x76; 12], b"header", &output), Err(AeadError::AuthenticationFailed));
+
+        for position in [0, 20, output.len() - 1] {
+            let mut tampered = output.clone();
+            tampered[position] ^= 0x01;
+            assert_eq!(gcm_siv.decrypt(nonce, b"header", &tampered), Err(AeadError::AuthenticationFailed));
+        }
+    }
+
+    #[test]
+    fn tampered_in_place() {
+        //! Test that a tampered ciphertext decrypted in place returns an error and leaves the buffer zeroed
+
+        let gcm_siv = gcm_siv("ee8e1ed9ff2540ae8f2ba9f50bc2f27c");
+        let nonce: [u8; 12] = [0x75; 12];
+        let output = gcm_siv.encrypt(nonce, b"header", b"a message longer than a block").unwrap();
+        let (ciphertext, tag) = output.split_at(output.len() - 16);
+        let tag: [u8; 16] = tag.try_into().unwrap();
+
+        let mut buffer = ciphertext.to_vec();
+        gcm_siv.decrypt_in_place_detached(nonce, b"header", &mut buffer, &tag).unwrap();
+        assert_eq!(buffer, b"a message longer than a block");
+
+        let mut buffer = ciphertext.to_vec();
+        buffer[3] ^= 0x01;
+        assert_eq!(gcm_siv.decrypt_in_place_detached(nonce, b"header", &mut buffer, &tag), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 29]);
+    }
+
+    #[test]
+    fn gcm_siv_aes192() {
+        //! Test that AES-192 is rejected
+
+        assert_eq!(GcmSiv::new(AESCore::new(AESKey::AES192([0; 24]))), Err(AeadError::UnsupportedKeySize));
+    }
+
+    #[test]
+    fn gcm_siv_lengths() {
+        //! Test the length limits of the plaintext and the associated data
+
+        assert_eq!(check_gcm_siv_lengths(GCM_SIV_MAX_AAD_LEN, GCM_SIV_MAX_PLAINTEXT_LEN), Ok(()));
+        assert_eq!(check_gcm_siv_lengths(GCM_SIV_MAX_AAD_LEN + 1, 0), Err(AeadError::InputTooLong));
+        assert_eq!(check_gcm_siv_lengths(0, GCM_SIV_MAX_PLAINTEXT_LEN + 1), Err(AeadError::InputTooLong));
+
+        // the longest plaintext takes exactly 2^32 counter blocks, so the counter never repeats
+        assert_eq!(GCM_SIV_MAX_PLAINTEXT_LEN / 16, 1 << 32);
+    }
+}
@@ -0,0 +1,200 @@
+//! A module containing the key derivation functions built on AES.
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::{
+    AESCore,
+    AESKey,
+};
+use crate::mac::Cmac;
+
+#[cfg(feature = "pbkdf2")]
+use crate::aes_core::{
+    KeyError,
+    KeyVariant,
+};
+#[cfg(feature = "pbkdf2")]
+use hmac::{
+    Hmac,
+    Mac,
+};
+#[cfg(feature = "pbkdf2")]
+use sha2::Sha256;
+
+
+
+
+
+// STRUCTS
+
+/// Public functions for deriving keys from passwords.
+#[cfg(feature = "pbkdf2")]
+impl AESKey {
+    pub fn from_password(password: &[u8], salt: &[u8], iterations: u32, variant: KeyVariant) -> Result<AESKey, KeyError> {
+        //! Derives a key from the password with PBKDF2-HMAC-SHA256 (RFC 8018).
+        //! # Arguments
+        //! * `password` - The password.
+        //! * `salt` - The salt, should be random and at least 16 bytes long.
+        //! * `iterations` - The number of iterations, should be as high as acceptable (at least 600000).
+        //! * `variant` - The size of the derived key.
+        //! # Returns
+        //! * AESKey - The derived key.
+        //! # Errors
+        //! * KeyError::ZeroIterations - The number of iterations is 0.
+
+        if iterations == 0 {
+            return Err(KeyError::ZeroIterations);
+        }
+
+        let mut output: [u8; 32] = [0; 32];
+        pbkdf2_hmac_sha256(password, salt, iterations, &mut output);
+
+        Ok(match variant {
+            KeyVariant::AES128 => AESKey::AES128(output[..16].try_into().unwrap()),
+            KeyVariant::AES192 => AESKey::AES192(output[..24].try_into().unwrap()),
+            KeyVariant::AES256 => AESKey::AES256(output),
+        })
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn derive_subkey(root: &AESKey, salt: &[u8]) -> AESKey {
+    //! Derives a key of the same variant as the root key.
+    //! Uses the KDF in counter mode with CMAC as the PRF (NIST SP 800-108),
+    //! with the salt as the label and an empty context.
+    //! Each PRF block is computed over `[i]_32 || salt || 0x00 || [L]_32`,
+    //! where `i` is the block counter starting at 1 and `L` is the derived key length in bits.
+    //! # Arguments
+    //! * `root` - The root key.
+    //! * `salt` - The domain separation salt, distinct salts yield independent keys.
+    //! # Returns
+    //! * AESKey - The derived key.
+
+    let core = AESCore::new(*root);
+    let key_len: usize = match root {
+        AESKey::AES128(_) => 16,
+        AESKey::AES192(_) => 24,
+        AESKey::AES256(_) => 32,
+    };
+
+    let mut output: [u8; 32] = [0; 32];
+    for (i, chunk) in output[..key_len].chunks_mut(16).enumerate() {
+        let mut prf = Cmac::new(core);
+        prf.update(&(i as u32 + 1).to_be_bytes());
+        prf.update(salt);
+        prf.update(&[0x00]);
+        prf.update(&(key_len as u32 * 8).to_be_bytes());
+        chunk.copy_from_slice(&prf.finalize()[..chunk.len()]);
+    }
+
+    match root {
+        AESKey::AES128(_) => AESKey::AES128(output[..16].try_into().unwrap()),
+        AESKey::AES192(_) => AESKey::AES192(output[..24].try_into().unwrap()),
+        AESKey::AES256(_) => AESKey::AES256(output),
+    }
+}
+
+#[cfg(feature = "pbkdf2")]
+fn pbkdf2_hmac_sha256(password: &[u8], salt: &[u8], iterations: u32, output: &mut [u8; 32]) {
+    //! Computes the first block of PBKDF2 with HMAC-SHA256 as the PRF.
+    //! A single block (32 bytes) covers all AES key sizes, the shorter keys are its prefixes.
+
+    let prf = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts keys of any length");
+
+    let mut mac = prf.clone();
+    mac.update(salt);
+    mac.update(&1_u32.to_be_bytes());
+    let mut u: [u8; 32] = mac.finalize().into_bytes().into();
+    *output = u;
+
+    for _ in 1..iterations {
+        let mut mac = prf.clone();
+        mac.update(&u);
+        u = mac.finalize().into_bytes().into();
+        for (output_byte, u_byte) in output.iter_mut().zip(u) {
+            *output_byte ^= u_byte;
+        }
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPECTED_128: [u8; 16] = [
+        0x15, 0xaa, 0xc3, 0xd8, 0x9d, 0x3d, 0x61, 0x74, 0x35, 0x74, 0xe7, 0xa0, 0xcd, 0x7c, 0xaa, 0x5e,
+    ];
+    const EXPECTED_192: [u8; 24] = [
+        0x1f, 0xda, 0xbb, 0x42, 0xb2, 0x57, 0x59, 0x20, 0x47, 0x99, 0x1f, 0xe2, 0x22, 0x62, 0xe3, 0x0f,
+        0x33, 0xec, 0xb0, 0x3b, 0xe1, 0x4b, 0x7d, 0xa1,
+    ];
+    const EXPECTED_256: [u8; 32] = [
+        0xfe, 0xc7, 0x75, 0xe8, 0xc4, 0xdf, 0x53, 0x63, 0xd9, 0xa4, 0xf3, 0x7c, 0xc2, 0x65, 0xde, 0x2e,
+        0xbe, 0xfb, 0x80, 0x84, 0x00, 0x6e, 0x21, 0xe4, 0xa3, 0x0e, 0xe9, 0x18, 0x83, 0x45, 0x20, 0xb5,
+    ];
+
+    #[test]
+    #[cfg(feature = "pbkdf2")]
+    fn from_password() {
+        //! Test deriving keys from passwords with the published PBKDF2-HMAC-SHA256 vectors (RFC 7914, section 11)
+
+        let key = AESKey::from_password(b"passwd", b"salt", 1, KeyVariant::AES256).unwrap();
+        assert_eq!(key, AESKey::AES256([
+            0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f, 0xec, 0x16, 0x91, 0xc2, 0x25, 0x44, 0xb6, 0x05,
+            0xf9, 0x41, 0x85, 0x21, 0x6d, 0xde, 0x04, 0x65, 0xe6, 0x8b, 0x9d, 0x57, 0xc2, 0x0d, 0xac, 0xbc,
+        ]));
+
+        let key = AESKey::from_password(b"password", b"salt", 4096, KeyVariant::AES256).unwrap();
+        assert_eq!(key, AESKey::AES256([
+            0xc5, 0xe4, 0x78, 0xd5, 0x92, 0x88, 0xc8, 0x41, 0xaa, 0x53, 0x0d, 0xb6, 0x84, 0x5c, 0x4c, 0x8d,
+            0x96, 0x28, 0x93, 0xa0, 0x01, 0xce, 0x4e, 0x11, 0xa4, 0x96, 0x38, 0x73, 0xaa, 0x98, 0x13, 0x4a,
+        ]));
+
+        let key = AESKey::from_password(b"password", b"salt", 2, KeyVariant::AES192).unwrap();
+        assert_eq!(key, AESKey::AES192([
+            0xae, 0x4d, 0x0c, 0x95, 0xaf, 0x6b, 0x46, 0xd3, 0x2d, 0x0a, 0xdf, 0xf9, 0x28, 0xf0, 0x6d, 0xd0,
+            0x2a, 0x30, 0x3f, 0x8e, 0xf3, 0xc2, 0x51, 0xdf,
+        ]));
+
+        let key = AESKey::from_password(b"passwordPASSWORDpassword", b"saltSALTsaltSALTsaltSALTsaltSALTsalt", 4096, KeyVariant::AES128).unwrap();
+        assert_eq!(key, AESKey::AES128([
+            0x34, 0x8c, 0x89, 0xdb, 0xcb, 0xd3, 0x2b, 0x2f, 0x32, 0xd8, 0x14, 0xb8, 0x11, 0x6e, 0x84, 0xcf,
+        ]));
+
+        assert_eq!(AESKey::from_password(b"password", b"salt", 0, KeyVariant::AES256), Err(KeyError::ZeroIterations));
+        assert_eq!(KeyError::ZeroIterations.to_string(), "number of iterations must be at least 1");
+    }
+
+    #[test]
+    fn derive() {
+        //! Test the derived keys of every variant
+
+        assert_eq!(
+            derive_subkey(&AESKey::AES128([0x00; 16]), b"encryption"),
+            AESKey::AES128(EXPECTED_128),
+        );
+        assert_eq!(
+            derive_subkey(&AESKey::AES192([0x01; 24]), b"encryption"),
+            AESKey::AES192(EXPECTED_192),
+        );
+        assert_eq!(
+            derive_subkey(&AESKey::AES256([0x02; 32]), b""),
+            AESKey::AES256(EXPECTED_256),
+        );
+    }
+}
@@ -0,0 +1,341 @@
+//! A module containing the AES key wrap algorithms, without (RFC 3394) and with padding (RFC 5649).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::AESCore;
+use crate::util::ct_eq;
+
+
+
+
+
+// ENUMS
+
+/// The enum with key wrap errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KwError {
+    /// The key data isn't a multiple of 8 bytes, or is shorter than 16 bytes (24 bytes when wrapped).
+    /// With padding, the key data is empty or longer than 2^32 - 1 bytes, or the wrapped key isn't a multiple of 8 bytes
+    /// or is shorter than 16 bytes.
+    InvalidLength,
+    /// The integrity check failed, the wrapped key is corrupted or the wrong key-encryption key was used.
+    IntegrityCheckFailed,
+}
+
+impl fmt::Display for KwError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KwError::InvalidLength => write!(f, "invalid length of the key data or the wrapped key"),
+            KwError::IntegrityCheckFailed => write!(f, "integrity check failed"),
+        }
+    }
+}
+
+impl std::error::Error for KwError {}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn wrap(kek: &AESCore, key_data: &[u8]) -> Result<Vec<u8>, KwError> {
+    //! Wraps the key data with the key-encryption key.
+    //! # Arguments
+    //! * `kek` - The AES core keyed with the key-encryption key.
+    //! * `key_data` - The key data to be wrapped, a multiple of 8 bytes and at least 16 bytes long.
+    //! # Returns
+    //! * `Vec<u8>` - The wrapped key, 8 bytes longer than the key data.
+    //! # Errors
+    //! * KwError::InvalidLength - The key data has an invalid length.
+
+    if key_data.len() < 16 || !key_data.len().is_multiple_of(8) {
+        return Err(KwError::InvalidLength);
+    }
+
+    Ok(wrap_with_iv(kek, DEFAULT_IV, key_data))
+}
+
+pub fn unwrap(kek: &AESCore, wrapped: &[u8]) -> Result<Vec<u8>, KwError> {
+    //! Unwraps the key wrapped with `wrap`, verifying its integrity.
+    //! # Arguments
+    //! * `kek` - The AES core keyed with the key-encryption key.
+    //! * `wrapped` - The wrapped key, a multiple of 8 bytes and at least 24 bytes long.
+    //! # Returns
+    //! * `Vec<u8>` - The key data.
+    //! # Errors
+    //! * KwError::InvalidLength - The wrapped key has an invalid length.
+    //! * KwError::IntegrityCheckFailed - The integrity check failed.
+
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(KwError::InvalidLength);
+    }
+
+    let (iv, key_data) = unwrap_to_iv(kek, wrapped);
+    if !ct_eq(&iv, &DEFAULT_IV) {
+        return Err(KwError::IntegrityCheckFailed);
+    }
+
+    Ok(key_data)
+}
+
+pub fn wrap_with_padding(kek: &AESCore, key_data: &[u8]) -> Result<Vec<u8>, KwError> {
+    //! Wraps the key data of any length with the key-encryption key, padding it to a multiple of 8 bytes.
+    //! The alternative initial value contains the length of the key data.
+    //! # Arguments
+    //! * `kek` - The AES core keyed with the key-encryption key.
+    //! * `key_data` - The key data to be wrapped, at least 1 byte long.
+    //! # Returns
+    //! * `Vec<u8>` - The wrapped key, a multiple of 8 bytes and at least 16 bytes long.
+    //! # Errors
+    //! * KwError::InvalidLength - The key data has an invalid length.
+
+    if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+        return Err(KwError::InvalidLength);
+    }
+
+    let mut iv: [u8; 8] = [0; 8];
+    iv[..4].copy_from_slice(&AIV_MARKER);
+    iv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+
+    let mut padded = key_data.to_vec();
+    padded.resize(key_data.len().next_multiple_of(8), 0);
+
+    if padded.len() == 8 {
+        // a single block is encrypted directly with the initial value
+        let mut block: [u8; 16] = [0; 16];
+        block[..8].copy_from_slice(&iv);
+        block[8..].copy_from_slice(&padded);
+        kek.encrypt_in_place(&mut block);
+        Ok(block.to_vec())
+    } else {
+        Ok(wrap_with_iv(kek, iv, &padded))
+    }
+}
+
+pub fn unwrap_with_padding(kek: &AESCore, wrapped: &[u8]) -> Result<Vec<u8>, KwError> {
+    //! Unwraps the key wrapped with `wrap_with_padding`, verifying its integrity and removing the padding.
+    //! # Arguments
+    //! * `kek` - The AES core keyed with the key-encryption key.
+    //! * `wrapped` - The wrapped key, a multiple of 8 bytes and at least 16 bytes long.
+    //! # Returns
+    //! * `Vec<u8>` - The key data.
+    //! # Errors
+    //! * KwError::InvalidLength - The wrapped key has an invalid length.
+    //! * KwError::IntegrityCheckFailed - The integrity check failed (wrong marker, length or padding).
+
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(KwError::InvalidLength);
+    }
+
+    let (iv, mut padded) = if wrapped.len() == 16 {
+        let mut block: [u8; 16] = wrapped.try_into().unwrap();
+        kek.decrypt_in_place(&mut block);
+        (block[..8].try_into().unwrap(), block[8..].to_vec())
+    } else {
+        unwrap_to_iv(kek, wrapped)
+    };
+
+    // the length must fall within the last block, and the padding bytes must be zero
+    let key_data_len = u32::from_be_bytes(iv[4..].try_into().unwrap()) as usize;
+    let valid_len = key_data_len <= padded.len() && key_data_len + 8 > padded.len();
+    let padding_len = if valid_len { padded.len() - key_data_len } else { 0 };
+    let zero_padding = padded[(padded.len() - padding_len)..].iter().fold(0, |acc, &byte| acc | byte) == 0;
+
+    if !(ct_eq(&iv[..4], &AIV_MARKER) & valid_len & zero_padding) {
+        return Err(KwError::IntegrityCheckFailed);
+    }
+
+    padded.truncate(key_data_len);
+    Ok(padded)
+}
+
+fn wrap_with_iv(kek: &AESCore, iv: [u8; 8], key_data: &[u8]) -> Vec<u8> {
+    //! Wraps the key data (a multiple of 8 bytes) with the given initial value (RFC 3394, section 2.2.1).
+
+    let n = key_data.len() / 8;
+    let mut a = iv;
+    let mut r = key_data.to_vec();
+
+    let mut block: [u8; 16] = [0; 16];
+    for j in 0..6 {
+        for i in 0..n {
+            block[..8].copy_from_slice(&a);
+            block[8..].copy_from_slice(&r[(i * 8)..(i * 8 + 8)]);
+            kek.encrypt_in_place(&mut block);
+
+            let t = (n * j + i + 1) as u64;
+            a = (u64::from_be_bytes(block[..8].try_into().unwrap()) ^ t).to_be_bytes();
+            r[(i * 8)..(i * 8 + 8)].copy_from_slice(&block[8..]);
+        }
+    }
+
+    let mut wrapped = Vec::with_capacity(key_data.len() + 8);
+    wrapped.extend_from_slice(&a);
+    wrapped.extend_from_slice(&r);
+    wrapped
+}
+
+fn unwrap_to_iv(kek: &AESCore, wrapped: &[u8]) -> ([u8; 8], Vec<u8>) {
+    //! Unwraps the wrapped key (a multiple of 8 bytes) and returns the recovered initial value
+    //! along with the key data (RFC 3394, section 2.2.2).
+    //! The initial value must be checked by the caller.
+
+    let n = wrapped.len() / 8 - 1;
+    let mut a: [u8; 8] = wrapped[..8].try_into().unwrap();
+    let mut r = wrapped[8..].to_vec();
+
+    let mut block: [u8; 16] = [0; 16];
+    for j in (0..6).rev() {
+        for i in (0..n).rev() {
+            let t = (n * j + i + 1) as u64;
+            block[..8].copy_from_slice(&(u64::from_be_bytes(a) ^ t).to_be_bytes());
+            block[8..].copy_from_slice(&r[(i * 8)..(i * 8 + 8)]);
+            kek.decrypt_in_place(&mut block);
+
+            a = block[..8].try_into().unwrap();
+            r[(i * 8)..(i * 8 + 8)].copy_from_slice(&block[8..]);
+        }
+    }
+
+    (a, r)
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The default initial value used as the integrity check (RFC 3394, section 2.2.3.1).
+const DEFAULT_IV: [u8; 8] = [0xA6; 8];
+
+/// The constant first half of the alternative initial value used with padding (RFC 5649, section 3).
+const AIV_MARKER: [u8; 4] = [0xA6, 0x59, 0x59, 0xA6];
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn rfc3394() {
+        //! Test with the RFC 3394 test vectors (sections 4.1 - 4.6)
+
+        let vectors = [
+            ("000102030405060708090A0B0C0D0E0F", "00112233445566778899AABBCCDDEEFF",
+             "1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5"),
+            ("000102030405060708090A0B0C0D0E0F1011121314151617", "00112233445566778899AABBCCDDEEFF",
+             "96778B25AE6CA435F92B5B97C050AED2468AB8A17AD84E5D"),
+            ("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F", "00112233445566778899AABBCCDDEEFF",
+             "64E8C3F9CE0F5BA263E9777905818A2A93C8191E7D6E8AE7"),
+            ("000102030405060708090A0B0C0D0E0F1011121314151617", "00112233445566778899AABBCCDDEEFF0001020304050607",
+             "031D33264E15D33268F24EC260743EDCE1C6C7DDEE725A936BA814915C6762D2"),
+            ("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F", "00112233445566778899AABBCCDDEEFF0001020304050607",
+             "A8F9BC1612C68B3FF6E6F4FBE30E71E4769C8B80A32CB8958CD5D17D6B254DA1"),
+            ("000102030405060708090A0B0C0D0E0F101112131415161718191A1B1C1D1E1F", "00112233445566778899AABBCCDDEEFF000102030405060708090A0B0C0D0E0F",
+             "28C9F404C4B810F4CBCCB35CFB87F8263F5786E2D80ED326CBC7F0E71A99F43BFB988B9B7A02DD21"),
+        ];
+
+        for (kek, key_data, wrapped) in vectors {
+            let kek = AESCore::new(AESKey::try_from(hex(kek).as_slice()).unwrap());
+            assert_eq!(wrap(&kek, &hex(key_data)).unwrap(), hex(wrapped));
+            assert_eq!(unwrap(&kek, &hex(wrapped)).unwrap(), hex(key_data));
+        }
+    }
+
+    #[test]
+    fn rfc5649() {
+        //! Test with the RFC 5649 examples (section 6), including the single block case
+
+        let kek = AESCore::new(AESKey::try_from(hex("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").as_slice()).unwrap());
+
+        let vectors = [
+            ("c37b7e6492584340bed12207808941155068f738", "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a"),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+            ("466f725061736931", "21bcdaade5a1ed4c488d954efc68480b"),
+        ];
+
+        for (key_data, wrapped) in vectors {
+            assert_eq!(wrap_with_padding(&kek, &hex(key_data)).unwrap(), hex(wrapped));
+            assert_eq!(unwrap_with_padding(&kek, &hex(wrapped)).unwrap(), hex(key_data));
+        }
+    }
+
+    #[test]
+    fn keywrap_with_padding_errors() {
+        //! Test the length checks and the validation of the marker, the length and the padding
+
+        let kek = AESCore::new(AESKey::AES128([0; 16]));
+        assert_eq!(wrap_with_padding(&kek, &[]), Err(KwError::InvalidLength));
+        assert_eq!(unwrap_with_padding(&kek, &[0; 8]), Err(KwError::InvalidLength));
+        assert_eq!(unwrap_with_padding(&kek, &[0; 20]), Err(KwError::InvalidLength));
+
+        // a key wrapped without padding has a different marker
+        assert_eq!(unwrap_with_padding(&kek, &wrap(&kek, &[0x42; 16]).unwrap()), Err(KwError::IntegrityCheckFailed));
+
+        // forged initial values with a wrong length, and with a non-zero padding byte
+        for (length, last_byte) in [(17_u32, 0x00), (8, 0x00), (0, 0x00), (12, 0x01)] {
+            let mut iv: [u8; 8] = [0; 8];
+            iv[..4].copy_from_slice(&AIV_MARKER);
+            iv[4..].copy_from_slice(&length.to_be_bytes());
+            let mut key_data = [0x42; 16];
+            key_data[15] = last_byte;
+            let wrapped = wrap_with_iv(&kek, iv, &key_data);
+            assert_eq!(unwrap_with_padding(&kek, &wrapped), Err(KwError::IntegrityCheckFailed));
+        }
+        let mut iv: [u8; 8] = [0; 8];
+        iv[..4].copy_from_slice(&AIV_MARKER);
+        iv[4..].copy_from_slice(&12_u32.to_be_bytes());
+        let mut key_data = [0x42; 16];
+        key_data[12..].fill(0);
+        assert_eq!(unwrap_with_padding(&kek, &wrap_with_iv(&kek, iv, &key_data)).unwrap(), key_data[..12].to_vec());
+
+        let mut wrapped = wrap_with_padding(&kek, &[0x42; 5]).unwrap();
+        wrapped[3] ^= 0x01;
+        assert_eq!(unwrap_with_padding(&kek, &wrapped), Err(KwError::IntegrityCheckFailed));
+    }
+
+    #[test]
+    fn keywrap_errors() {
+        //! Test the length checks and the integrity check
+
+        let kek = AESCore::new(AESKey::AES128([0; 16]));
+        assert_eq!(wrap(&kek, &[0; 8]), Err(KwError::InvalidLength));
+        assert_eq!(wrap(&kek, &[0; 20]), Err(KwError::InvalidLength));
+        assert_eq!(unwrap(&kek, &[0; 16]), Err(KwError::InvalidLength));
+        assert_eq!(unwrap(&kek, &[0; 25]), Err(KwError::InvalidLength));
+
+        let mut wrapped = wrap(&kek, &[0x42; 16]).unwrap();
+        wrapped[12] ^= 0x01;
+        assert_eq!(unwrap(&kek, &wrapped), Err(KwError::IntegrityCheckFailed));
+
+        let other_kek = AESCore::new(AESKey::AES128([1; 16]));
+        assert_eq!(unwrap(&other_kek, &wrap(&kek, &[0x42; 16]).unwrap()), Err(KwError::IntegrityCheckFailed));
+
+        assert_eq!(KwError::IntegrityCheckFailed.to_string(), "integrity check failed");
+    }
+}
@@ -22,13 +22,98 @@
 //! let decrypted: [u8; 16] = aes256.decrypt(&ciphertext);
 //! assert_eq!(decrypted, plaintext);
 //! ```
+//!
+//! **S-Box backends:** the `sbox-table` feature (default) substitutes bytes with table lookups,
+//! and the `sbox-computed` feature computes the S-Box in constant time (slower, but without data-dependent memory accesses).
+//! If both are enabled, the computed S-Box takes precedence, so `sbox-computed` can be added without disabling the default features.
+
 
+#[cfg(not(any(feature = "sbox-table", feature = "sbox-computed")))]
+compile_error!("an S-Box backend must be selected, enable either the `sbox-table` or the `sbox-computed` feature");
 
+pub mod aead;
 pub mod aes_core;
+#[cfg(feature = "bitslice")]
+pub mod bitslice;
+pub mod builder;
+#[cfg(all(feature = "clmul", target_arch = "x86_64"))]
+mod clmul;
+pub mod drbg;
+pub mod error;
+pub mod etm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod fs;
+pub mod gcm;
+pub mod gcm_siv;
+pub mod kdf;
+pub mod keywrap;
+pub mod mac;
+pub mod modes;
+pub mod ocb;
 pub mod padding;
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod simd;
+pub mod siv;
+pub mod tweak;
+pub mod util;
+
+#[doc(inline)]
+pub use aead::*;
 
 #[doc(inline)]
 pub use aes_core::*;
 
+#[doc(inline)]
+pub use builder::*;
+
+#[doc(inline)]
+pub use drbg::*;
+
+#[doc(inline)]
+pub use error::*;
+
+#[doc(inline)]
+pub use etm::*;
+
+#[cfg(feature = "ffi")]
+#[doc(inline)]
+pub use ffi::*;
+
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use fs::*;
+
+#[doc(inline)]
+pub use gcm::*;
+
+#[doc(inline)]
+pub use gcm_siv::*;
+
+#[doc(inline)]
+pub use kdf::*;
+
+#[doc(inline)]
+pub use keywrap::*;
+
+#[doc(inline)]
+pub use mac::*;
+
+#[doc(inline)]
+pub use modes::*;
+
+#[doc(inline)]
+pub use ocb::*;
+
 #[doc(inline)]
 pub use padding::*;
+
+#[doc(inline)]
+pub use siv::*;
+
+#[doc(inline)]
+pub use tweak::*;
+
+#[doc(inline)]
+pub use util::*;
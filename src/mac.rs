@@ -0,0 +1,429 @@
+//! A module containing the message authentication codes built on AES.
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::{
+    AESCore,
+    LenError,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with MAC state errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MacStateError {
+    /// The exported state is malformed (the wrong length, or more than 16 buffered bytes).
+    InvalidState,
+}
+
+impl fmt::Display for MacStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacStateError::InvalidState => write!(f, "invalid mac state"),
+        }
+    }
+}
+
+impl std::error::Error for MacStateError {}
+
+
+
+
+
+// STRUCTS
+
+/// The CMAC message authentication code (NIST SP 800-38B, RFC 4493).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cmac {
+    /// The AES core used to encrypt the blocks.
+    core: AESCore,
+    /// The subkey applied to a complete final block.
+    k1: [u8; 16],
+    /// The subkey applied to a padded final block.
+    k2: [u8; 16],
+    /// The chaining value.
+    state: [u8; 16],
+    /// The buffered input not yet processed (the final block is held back until finalization).
+    buffer: [u8; 16],
+    /// The number of bytes in the buffer.
+    buffer_len: usize,
+}
+
+/// Public functions for computing the CMAC.
+impl Cmac {
+    pub fn new(core: AESCore) -> Self {
+        //! Creates a new CMAC instance.
+        //! # Arguments
+        //! * `core` - The AES core used to authenticate the data.
+
+        let k1 = double(&core.encrypt(&[0; 16]));
+        let k2 = double(&k1);
+
+        Self {
+            core,
+            k1,
+            k2,
+            state: [0; 16],
+            buffer: [0; 16],
+            buffer_len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        //! Feeds the data into the CMAC computation.
+
+        for &byte in data {
+            if self.buffer_len == 16 {
+                for i in 0..16 {
+                    self.state[i] ^= self.buffer[i];
+                }
+                self.state = self.core.encrypt(&self.state);
+                self.buffer_len = 0;
+            }
+            self.buffer[self.buffer_len] = byte;
+            self.buffer_len += 1;
+        }
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the computation for a new message, keeping the expanded key and the subkeys.
+
+        self.state = [0; 16];
+        self.buffer = [0; 16];
+        self.buffer_len = 0;
+    }
+
+    pub fn export_state(&self) -> CmacState {
+        //! Exports the running state of the computation, so it can be persisted and resumed later.
+        //! The state doesn't contain the key or the subkeys, only the chaining value and the buffered input.
+        //! # Returns
+        //! * CmacState - The chaining value and the buffered partial block.
+
+        CmacState {
+            chaining: self.state,
+            buffer: self.buffer,
+            buffer_len: self.buffer_len,
+        }
+    }
+
+    pub fn import_state(core: AESCore, state: CmacState) -> Result<Self, MacStateError> {
+        //! Resumes a computation from an exported state.
+        //! # Arguments
+        //! * `core` - The AES core used when the state was exported (with the same key).
+        //! * `state` - The exported state.
+        //! # Returns
+        //! * `Result<Cmac, MacStateError>` - The resumed CMAC instance or an error.
+        //! # Errors
+        //! * MacStateError::InvalidState - The state has more than 16 buffered bytes.
+
+        if state.buffer_len > 16 {
+            return Err(MacStateError::InvalidState);
+        }
+
+        let mut cmac = Self::new(core);
+        cmac.state = state.chaining;
+        cmac.buffer = state.buffer;
+        cmac.buffer_len = state.buffer_len;
+        Ok(cmac)
+    }
+
+    pub fn finalize(self) -> [u8; 16] {
+        //! Finishes the computation and returns the authentication tag.
+
+        let mut last_block: [u8; 16] = [0; 16];
+        last_block[..self.buffer_len].copy_from_slice(&self.buffer[..self.buffer_len]);
+        let subkey = if self.buffer_len == 16 {
+            &self.k1
+        } else {
+            last_block[self.buffer_len] = 0x80;
+            &self.k2
+        };
+
+        let mut input = self.state;
+        for i in 0..16 {
+            input[i] ^= last_block[i] ^ subkey[i];
+        }
+        self.core.encrypt(&input)
+    }
+}
+
+/// The exported running state of a CMAC computation (see `Cmac::export_state`).
+/// It holds the chaining value and the buffered input, but not the key,
+/// so the key must be supplied again when the computation is resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CmacState {
+    /// The chaining value.
+    chaining: [u8; 16],
+    /// The buffered input not yet processed.
+    buffer: [u8; 16],
+    /// The number of bytes in the buffer.
+    buffer_len: usize,
+}
+
+/// Public functions for persisting the CMAC state.
+impl CmacState {
+    pub fn to_bytes(&self) -> [u8; 33] {
+        //! Serializes the state as chaining value || buffered byte count || buffer.
+
+        let mut bytes: [u8; 33] = [0; 33];
+        bytes[..16].copy_from_slice(&self.chaining);
+        bytes[16] = self.buffer_len as u8;
+        bytes[17..].copy_from_slice(&self.buffer);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MacStateError> {
+        //! Deserializes the state from chaining value || buffered byte count || buffer.
+        //! # Errors
+        //! * MacStateError::InvalidState - The input isn't 33 bytes long, or has more than 16 buffered bytes.
+
+        if bytes.len() != 33 || bytes[16] > 16 {
+            return Err(MacStateError::InvalidState);
+        }
+
+        Ok(Self {
+            chaining: bytes[..16].try_into().unwrap(),
+            buffer: bytes[17..].try_into().unwrap(),
+            buffer_len: bytes[16] as usize,
+        })
+    }
+}
+
+/// The raw CBC-MAC message authentication code (a zero IV, the last CBC ciphertext block is the tag).
+///
+/// CBC-MAC is only secure for messages of a single fixed length (agreed upon in advance).
+/// If messages of different lengths are authenticated with the same key, tags can be forged, so use `Cmac` instead
+/// unless a legacy protocol requires raw CBC-MAC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CbcMac {
+    /// The AES core used to encrypt the blocks.
+    core: AESCore,
+    /// The chaining value.
+    state: [u8; 16],
+    /// The buffered input not yet processed.
+    buffer: [u8; 16],
+    /// The number of bytes in the buffer.
+    buffer_len: usize,
+}
+
+/// Public functions for computing the CBC-MAC.
+impl CbcMac {
+    pub fn new(core: AESCore) -> Self {
+        //! Creates a new CBC-MAC instance.
+        //! # Arguments
+        //! * `core` - The AES core used to authenticate the data.
+
+        Self {
+            core,
+            state: [0; 16],
+            buffer: [0; 16],
+            buffer_len: 0,
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        //! Feeds the data into the CBC-MAC computation.
+
+        for &byte in data {
+            self.buffer[self.buffer_len] = byte;
+            self.buffer_len += 1;
+            if self.buffer_len == 16 {
+                for i in 0..16 {
+                    self.state[i] ^= self.buffer[i];
+                }
+                self.state = self.core.encrypt(&self.state);
+                self.buffer_len = 0;
+            }
+        }
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the computation for a new message, keeping the expanded key.
+
+        *self = Self::new(self.core);
+    }
+
+    pub fn finalize(self) -> Result<[u8; 16], LenError> {
+        //! Finishes the computation and returns the authentication tag.
+        //! The message isn't padded, so it must be a multiple of 16 bytes long.
+        //! # Returns
+        //! * `Result<[u8; 16], LenError>` - The authentication tag or an error.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The message isn't a multiple of 16 bytes long.
+
+        if self.buffer_len != 0 {
+            return Err(LenError::NotBlockMultiple);
+        }
+
+        Ok(self.state)
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub(crate) fn double(block: &[u8; 16]) -> [u8; 16] {
+    //! Multiplies the block by x in GF(2^128) (the subkey generation of SP 800-38B).
+
+    let value = u128::from_be_bytes(*block);
+    let carry = (value >> 127) as u8;
+    ((value << 1) ^ (carry.wrapping_neg() as u128 & 0x87)).to_be_bytes()
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16,
+        0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88,
+        0x09, 0xcf, 0x4f, 0x3c,
+    ];
+    const MESSAGE: [u8; 64] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a,
+        0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51,
+        0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef,
+        0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10,
+    ];
+
+    #[test]
+    fn subkeys() {
+        //! Test the subkey generation with the RFC 4493 example
+
+        let cmac = Cmac::new(AESCore::new(AESKey::AES128(KEY)));
+        assert_eq!(cmac.k1, [0xfb, 0xee, 0xd6, 0x18, 0x35, 0x71, 0x33, 0x66, 0x7c, 0x85, 0xe0, 0x8f, 0x72, 0x36, 0xa8, 0xde]);
+        assert_eq!(cmac.k2, [0xf7, 0xdd, 0xac, 0x30, 0x6a, 0xe2, 0x66, 0xcc, 0xf9, 0x0b, 0xc1, 0x1e, 0xe4, 0x6d, 0x51, 0x3b]);
+    }
+
+    #[test]
+    fn cmac() {
+        //! Test CMAC with the RFC 4493 examples
+
+        let expected: [(usize, [u8; 16]); 4] = [
+            (0, [0xbb, 0x1d, 0x69, 0x29, 0xe9, 0x59, 0x37, 0x28, 0x7f, 0xa3, 0x7d, 0x12, 0x9b, 0x75, 0x67, 0x46]),
+            (16, [0x07, 0x0a, 0x16, 0xb4, 0x6b, 0x4d, 0x41, 0x44, 0xf7, 0x9b, 0xdd, 0x9d, 0xd0, 0x4a, 0x28, 0x7c]),
+            (40, [0xdf, 0xa6, 0x67, 0x47, 0xde, 0x9a, 0xe6, 0x30, 0x30, 0xca, 0x32, 0x61, 0x14, 0x97, 0xc8, 0x27]),
+            (64, [0x51, 0xf0, 0xbe, 0xbf, 0x7e, 0x3b, 0x9d, 0x92, 0xfc, 0x49, 0x74, 0x17, 0x79, 0x36, 0x3c, 0xfe]),
+        ];
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        for (len, tag) in expected {
+            let mut cmac = Cmac::new(core);
+            cmac.update(&MESSAGE[..len]);
+            assert_eq!(cmac.finalize(), tag);
+
+            // the result mustn't depend on how the data is split
+            let mut cmac = Cmac::new(core);
+            for chunk in MESSAGE[..len].chunks(7) {
+                cmac.update(chunk);
+            }
+            cmac.update(&[]);
+            assert_eq!(cmac.finalize(), tag);
+        }
+    }
+
+    #[test]
+    fn cbc_mac() {
+        //! Test that CBC-MAC produces the last block of the CBC encryption with a zero IV
+
+        let expected: [(usize, [u8; 16]); 4] = [
+            (0, [0; 16]),
+            (16, [0x3a, 0xd7, 0x7b, 0xb4, 0x0d, 0x7a, 0x36, 0x60, 0xa8, 0x9e, 0xca, 0xf3, 0x24, 0x66, 0xef, 0x97]),
+            (32, [0xb1, 0x48, 0xc1, 0x7f, 0x30, 0x9e, 0xe6, 0x92, 0x28, 0x7a, 0xe5, 0x7c, 0xf1, 0x2a, 0xdd, 0x49]),
+            (64, [0xa7, 0x35, 0x6e, 0x12, 0x07, 0xbb, 0x40, 0x66, 0x39, 0xe5, 0xe5, 0xce, 0xb9, 0xa9, 0xed, 0x93]),
+        ];
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        for (len, tag) in expected {
+            let mut cbc_mac = CbcMac::new(core);
+            for chunk in MESSAGE[..len].chunks(7) {
+                cbc_mac.update(chunk);
+            }
+            assert_eq!(cbc_mac.finalize(), Ok(tag));
+        }
+
+        let mut cbc_mac = CbcMac::new(core);
+        cbc_mac.update(&MESSAGE[..40]);
+        assert_eq!(cbc_mac.finalize(), Err(LenError::NotBlockMultiple));
+    }
+
+    #[test]
+    fn reset() {
+        //! Test that a reset MAC matches a fresh one
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+
+        let mut cmac = Cmac::new(core);
+        cmac.update(&MESSAGE[..40]);
+        cmac.reset();
+        assert_eq!(cmac, Cmac::new(core));
+        cmac.update(&MESSAGE);
+        assert_eq!(cmac.finalize(), [0x51, 0xf0, 0xbe, 0xbf, 0x7e, 0x3b, 0x9d, 0x92, 0xfc, 0x49, 0x74, 0x17, 0x79, 0x36, 0x3c, 0xfe]);
+
+        let mut cbc_mac = CbcMac::new(core);
+        cbc_mac.update(&MESSAGE[..40]);
+        cbc_mac.reset();
+        assert_eq!(cbc_mac, CbcMac::new(core));
+        cbc_mac.update(&MESSAGE);
+        assert_eq!(cbc_mac.finalize(), Ok([0xa7, 0x35, 0x6e, 0x12, 0x07, 0xbb, 0x40, 0x66, 0x39, 0xe5, 0xe5, 0xce, 0xb9, 0xa9, 0xed, 0x93]));
+    }
+
+    #[test]
+    fn resumed_cmac() {
+        //! Test that a CMAC exported and imported at any point gives the same tag as a single pass
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let tag = [0x51, 0xf0, 0xbe, 0xbf, 0x7e, 0x3b, 0x9d, 0x92, 0xfc, 0x49, 0x74, 0x17, 0x79, 0x36, 0x3c, 0xfe];
+
+        for split in 0..=MESSAGE.len() {
+            let mut cmac = Cmac::new(core);
+            cmac.update(&MESSAGE[..split]);
+            let state = cmac.export_state();
+
+            let mut resumed = Cmac::import_state(core, state).unwrap();
+            assert_eq!(resumed, cmac);
+            resumed.update(&MESSAGE[split..]);
+            assert_eq!(resumed.finalize(), tag);
+
+            let bytes = state.to_bytes();
+            assert_eq!(CmacState::from_bytes(&bytes), Ok(state));
+            let mut resumed = Cmac::import_state(core, CmacState::from_bytes(&bytes).unwrap()).unwrap();
+            resumed.update(&MESSAGE[split..]);
+            assert_eq!(resumed.finalize(), tag);
+        }
+
+        let mut bytes = Cmac::new(core).export_state().to_bytes();
+        assert_eq!(CmacState::from_bytes(&bytes[..32]), Err(MacStateError::InvalidState));
+        bytes[16] = 17;
+        assert_eq!(CmacState::from_bytes(&bytes), Err(MacStateError::InvalidState));
+    }
+}
@@ -0,0 +1,262 @@
+//! A module containing the block cipher modes of operation.
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use crate::aes_core::AESCore;
+use crate::padding::{
+    Padding,
+    PaddingError,
+};
+use crate::util::blocks_mut;
+
+#[cfg(feature = "rand")]
+use rand_core::CryptoRng;
+
+
+
+
+
+// MODULES
+
+pub mod cbc;
+pub mod cfb;
+pub mod cipher;
+pub mod ctr;
+pub mod cts;
+pub mod ofb;
+
+#[doc(inline)]
+pub use cbc::*;
+
+#[doc(inline)]
+pub use cfb::*;
+
+#[doc(inline)]
+pub use cipher::*;
+
+#[doc(inline)]
+pub use ctr::*;
+
+#[doc(inline)]
+pub use cts::*;
+
+#[doc(inline)]
+pub use ofb::*;
+
+
+
+
+
+// ENUMS
+
+/// The enum with modes of operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Mode {
+    /// Electronic Codebook mode.
+    /// Every block is encrypted independently, so identical plaintext blocks produce identical ciphertext blocks.
+    Ecb,
+    /// Cipher Block Chaining mode.
+    Cbc,
+    /// Counter mode.
+    Ctr,
+    /// Cipher Feedback mode, with 128-bit segments.
+    Cfb,
+    /// Output Feedback mode.
+    Ofb,
+}
+
+/// The enum with directions of a cipher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// The data is encrypted.
+    Encrypt,
+    /// The data is decrypted.
+    Decrypt,
+}
+
+/// The enum with mode of operation errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeError {
+    /// The IV was already used within the tracked session.
+    ReusedIv,
+    /// The counter would wrap around, the key (or nonce) must be changed before processing more data.
+    CounterExhausted,
+}
+
+impl fmt::Display for ModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModeError::ReusedIv => write!(f, "IV was already used within this session"),
+            ModeError::CounterExhausted => write!(f, "counter is exhausted"),
+        }
+    }
+}
+
+impl std::error::Error for ModeError {}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn ecb_encrypt_padded(core: &AESCore, padding: &Padding, plaintext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    //! Pads the plaintext and encrypts it in ECB mode.
+    //! **ECB mode isn't secure for most data**, identical plaintext blocks produce identical ciphertext blocks,
+    //! so the patterns in the data remain visible. Use it only for interoperability and testing.
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the blocks.
+    //! * `padding` - The padding appended to the plaintext, see `Padding::pad_buffer`.
+    //! * `plaintext` - The data to be encrypted, of any length.
+    //! # Returns
+    //! * `Result<Vec<u8>, PaddingError>` - The ciphertext or an error.
+    //! # Errors
+    //! * PaddingError::NonePadding - Trying to pad with `PaddingTypes::None`.
+
+    let mut data = padding.pad_buffer(plaintext)?;
+    for block in blocks_mut(&mut data).0 {
+        core.encrypt_in_place(block);
+    }
+    Ok(data)
+}
+
+pub fn ecb_decrypt_padded(core: &AESCore, padding: &Padding, ciphertext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    //! Decrypts the ciphertext in ECB mode and removes the padding.
+    //! **ECB mode isn't secure for most data**, see `ecb_encrypt_padded`.
+    //! # Arguments
+    //! * `core` - The AES core used to decrypt the blocks.
+    //! * `padding` - The padding used for encryption.
+    //! * `ciphertext` - The data to be decrypted, a non-zero multiple of 16 bytes long.
+    //! # Returns
+    //! * `Result<Vec<u8>, PaddingError>` - The plaintext or an error.
+    //! # Errors
+    //! * PaddingError::InvalidPadding - The padding is invalid (e.g. the key is wrong).
+    //! * PaddingError::InvalidPaddedSize - The ciphertext isn't a non-zero multiple of 16 bytes long.
+    //! * PaddingError::NonePadding - Trying to de-pad with `PaddingTypes::None`.
+    //! * PaddingError::AmbiguousPadding - Trying to de-pad with `PaddingTypes::ZeroPadding`.
+
+    if ciphertext.is_empty() || !ciphertext.len().is_multiple_of(16) {
+        return Err(PaddingError::InvalidPaddedSize);
+    }
+
+    let mut data = ciphertext.to_vec();
+    for block in blocks_mut(&mut data).0 {
+        core.decrypt_in_place(block);
+    }
+    let plaintext_len = padding.de_pad_buffer(&data)?.len();
+    data.truncate(plaintext_len);
+    Ok(data)
+}
+
+#[cfg(feature = "rand")]
+pub fn random_iv<R: CryptoRng + ?Sized>(rng: &mut R) -> [u8; 16] {
+    //! Generates a random IV (or initial counter block) with the given cryptographically secure RNG.
+    //! # Arguments
+    //! * `rng` - The random number generator.
+    //! # Returns
+    //! * `[u8; 16]` - The random IV.
+
+    let mut iv: [u8; 16] = [0; 16];
+    rng.fill_bytes(&mut iv);
+    iv
+}
+
+#[cfg(feature = "rand")]
+pub fn random_iv_os() -> [u8; 16] {
+    //! Generates a random IV (or initial counter block) with the operating system's RNG.
+    //! # Returns
+    //! * `[u8; 16]` - The random IV.
+    //! # Panics
+    //! * If the operating system's RNG fails, which shouldn't happen on any supported platform.
+
+    let mut iv: [u8; 16] = [0; 16];
+    getrandom::fill(&mut iv).expect("the operating system's RNG failed");
+    iv
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+    use crate::padding::PaddingTypes;
+
+    #[test]
+    fn error_display() {
+        //! Test the error messages
+
+        assert_eq!(ModeError::ReusedIv.to_string(), "IV was already used within this session");
+        assert_eq!(ModeError::CounterExhausted.to_string(), "counter is exhausted");
+    }
+
+    #[test]
+    fn ecb_padded() {
+        //! Test the padded ECB round trip for several lengths, and the errors
+
+        let core = AESCore::new(AESKey::AES128([0x2b; 16]));
+        let padding = Padding::new(PaddingTypes::PKCS7);
+        let plaintext: Vec<u8> = (0..100).collect();
+
+        for len in [0, 1, 15, 16, 17, 31, 32, 100] {
+            let ciphertext = ecb_encrypt_padded(&core, &padding, &plaintext[..len]).unwrap();
+            assert_eq!(ciphertext.len(), (len / 16 + 1) * 16);
+            assert_eq!(ciphertext[..16], core.encrypt(&padding.pad_buffer(&plaintext[..len]).unwrap()[..16].try_into().unwrap()));
+            assert_eq!(ecb_decrypt_padded(&core, &padding, &ciphertext).unwrap(), plaintext[..len]);
+        }
+
+        let iso_padding = Padding::new(PaddingTypes::ISO78164);
+        let ciphertext = ecb_encrypt_padded(&core, &iso_padding, &plaintext[..40]).unwrap();
+        assert_eq!(ecb_decrypt_padded(&core, &iso_padding, &ciphertext).unwrap(), plaintext[..40]);
+
+        let ciphertext = ecb_encrypt_padded(&core, &padding, &plaintext[..20]).unwrap();
+        let wrong_core = AESCore::new(AESKey::AES128([0x2c; 16]));
+        assert_eq!(ecb_decrypt_padded(&wrong_core, &padding, &ciphertext), Err(PaddingError::InvalidPadding));
+        assert_eq!(ecb_decrypt_padded(&core, &padding, &ciphertext[..20]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(ecb_decrypt_padded(&core, &padding, &[]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(ecb_encrypt_padded(&core, &Padding::new(PaddingTypes::None), &plaintext), Err(PaddingError::NonePadding));
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn random_ivs() {
+        //! Test that two generated IVs are different
+
+        assert_ne!(random_iv_os(), random_iv_os());
+
+        let mut rng = rand_core::UnwrapErr(getrandom::SysRng);
+        assert_ne!(random_iv(&mut rng), random_iv(&mut rng));
+
+        // the returned IV is the one the cipher was created with
+        let core = crate::aes_core::AESCore::new(crate::aes_core::AESKey::AES128([0; 16]));
+        let (encryptor, iv) = CbcEncryptor::new_random_iv(core, &mut rng);
+        assert_eq!(encryptor, CbcEncryptor::new(core, iv));
+        let (encryptor, iv) = CfbEncryptor::new_random_iv(core, &mut rng);
+        assert_eq!(encryptor, CfbEncryptor::new(core, iv));
+        let (cipher, iv) = OfbCipher::new_random_iv(core, &mut rng);
+        assert_eq!(cipher, OfbCipher::new(core, iv));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_mode() {
+        //! Test serializing and deserializing the mode of operation
+
+        for mode in [Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            let json = serde_json::to_string(&mode).unwrap();
+            assert_eq!(serde_json::from_str::<Mode>(&json).unwrap(), mode);
+        }
+        assert_eq!(serde_json::to_string(&Mode::Cbc).unwrap(), "\"Cbc\"");
+    }
+}
@@ -0,0 +1,733 @@
+//! A module containing the Cipher Block Chaining (CBC) mode of operation.
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use std::collections::{
+    HashSet,
+    VecDeque,
+};
+use std::fmt;
+use crate::aes_core::{
+    AESCore,
+    LenError,
+};
+use crate::modes::ModeError;
+use crate::padding::{
+    Padding,
+    PaddingError,
+};
+
+#[cfg(feature = "rand")]
+use crate::modes::random_iv;
+#[cfg(feature = "rand")]
+use rand_core::CryptoRng;
+#[cfg(feature = "zeroize")]
+use crate::util::{
+    zeroize,
+    Zeroize,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with framing errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameError {
+    /// The framed data is shorter than the IV header.
+    TooShort,
+    /// The ciphertext after the IV header isn't a multiple of 16 bytes long.
+    NotBlockMultiple,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooShort => write!(f, "framed data is shorter than the IV"),
+            FrameError::NotBlockMultiple => write!(f, "ciphertext must be a multiple of 16 bytes long"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+
+
+
+
+// STRUCTS
+
+/// The CBC mode encryptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CbcEncryptor {
+    /// The AES core used to encrypt the blocks.
+    core: AESCore,
+    /// The initialization vector of the message.
+    iv: [u8; 16],
+    /// The previous ciphertext block (the IV before the first block is encrypted).
+    previous_block: [u8; 16],
+}
+
+/// Public functions for encrypting data in CBC mode.
+impl CbcEncryptor {
+    pub fn new(core: AESCore, iv: [u8; 16]) -> Self {
+        //! Creates a new CBC encryptor.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the blocks.
+        //! * `iv` - The initialization vector. Should be unpredictable and never reused with the same key.
+
+        Self {
+            core,
+            iv,
+            previous_block: iv,
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn new_random_iv<R: CryptoRng + ?Sized>(core: AESCore, rng: &mut R) -> (Self, [u8; 16]) {
+        //! Creates a new CBC encryptor with a random IV.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the blocks.
+        //! * `rng` - The cryptographically secure random number generator.
+        //! # Returns
+        //! * `(CbcEncryptor, [u8; 16])` - The encryptor and the IV, which must be sent along with the ciphertext.
+
+        let iv: [u8; 16] = random_iv(rng);
+        (Self::new(core, iv), iv)
+    }
+
+    pub fn with_iv(&self, iv: [u8; 16]) -> Self {
+        //! Creates a new encryptor for another message with the same key.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the new message.
+
+        Self::new(self.core, iv)
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the encryptor back to the initial IV, keeping the expanded key.
+        //! The same message can then be encrypted again. Encrypting a different message after the reset
+        //! reuses the IV, so use `CbcEncryptor::with_iv` for new messages instead.
+
+        *self = Self::new(self.core, self.iv);
+    }
+
+    pub fn encrypt_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        //! Encrypts the next block of the message.
+
+        for i in 0..16 {
+            self.previous_block[i] ^= block[i];
+        }
+
+        self.core.encrypt_in_place(&mut self.previous_block);
+        self.previous_block
+    }
+
+    pub fn encrypt_blocks(&mut self, blocks: &mut [[u8; 16]]) {
+        //! Encrypts the next blocks of the message in place.
+
+        for block in blocks {
+            for i in 0..16 {
+                block[i] ^= self.previous_block[i];
+            }
+            self.core.encrypt_in_place(block);
+            self.previous_block = *block;
+        }
+    }
+}
+
+/// Clearing the IV and the chaining block of the encryptor.
+#[cfg(feature = "zeroize")]
+impl Zeroize for CbcEncryptor {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.iv);
+        zeroize(&mut self.previous_block);
+    }
+}
+
+/// The CBC mode decryptor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CbcDecryptor {
+    /// The AES core used to decrypt the blocks.
+    core: AESCore,
+    /// The initialization vector of the message.
+    iv: [u8; 16],
+    /// The previous ciphertext block (the IV before the first block is decrypted).
+    previous_block: [u8; 16],
+}
+
+/// Public functions for decrypting data in CBC mode.
+impl CbcDecryptor {
+    pub fn new(core: AESCore, iv: [u8; 16]) -> Self {
+        //! Creates a new CBC decryptor.
+        //! # Arguments
+        //! * `core` - The AES core used to decrypt the blocks.
+        //! * `iv` - The initialization vector used for encryption.
+
+        Self {
+            core,
+            iv,
+            previous_block: iv,
+        }
+    }
+
+    pub fn with_iv(&self, iv: [u8; 16]) -> Self {
+        //! Creates a new decryptor for another message with the same key.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the new message.
+
+        Self::new(self.core, iv)
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the decryptor back to the initial IV, keeping the expanded key.
+
+        *self = Self::new(self.core, self.iv);
+    }
+
+    pub fn decrypt_block(&mut self, block: &[u8; 16]) -> [u8; 16] {
+        //! Decrypts the next block of the message.
+
+        let mut output: [u8; 16] = self.core.decrypt(block);
+        for i in 0..16 {
+            output[i] ^= self.previous_block[i];
+        }
+
+        self.previous_block = *block;
+        output
+    }
+
+    pub fn decrypt_blocks(&mut self, blocks: &mut [[u8; 16]]) {
+        //! Decrypts the next blocks of the message in place.
+
+        for block in blocks {
+            let ciphertext_block: [u8; 16] = *block;
+            self.core.decrypt_in_place(block);
+            for i in 0..16 {
+                block[i] ^= self.previous_block[i];
+            }
+            self.previous_block = ciphertext_block;
+        }
+    }
+}
+
+/// Clearing the IV and the chaining block of the decryptor.
+#[cfg(feature = "zeroize")]
+impl Zeroize for CbcDecryptor {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.iv);
+        zeroize(&mut self.previous_block);
+    }
+}
+
+/// A bounded record of the IVs used within a session.
+/// Once the capacity is reached, the oldest IV is forgotten to make room for the new one,
+/// so reuse is only detected among the most recently used IVs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IvTracker {
+    /// The maximum number of IVs remembered.
+    capacity: usize,
+    /// The remembered IVs, for fast lookup.
+    used_ivs: HashSet<[u8; 16]>,
+    /// The remembered IVs, in the order they were used.
+    order: VecDeque<[u8; 16]>,
+}
+
+/// Public functions for tracking the used IVs.
+impl IvTracker {
+    pub fn new(capacity: usize) -> Self {
+        //! Creates a new IV tracker.
+        //! # Arguments
+        //! * `capacity` - The maximum number of IVs remembered.
+
+        Self {
+            capacity,
+            used_ivs: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        //! Returns the maximum number of IVs remembered.
+
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        //! Returns the number of IVs currently remembered.
+
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        //! Returns whether no IVs are remembered.
+
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, iv: &[u8; 16]) -> bool {
+        //! Returns whether the IV is remembered as used.
+
+        self.used_ivs.contains(iv)
+    }
+
+    pub fn register(&mut self, iv: [u8; 16]) -> Result<(), ModeError> {
+        //! Records the IV as used.
+        //! # Errors
+        //! * ModeError::ReusedIv - The IV is remembered as already used.
+
+        if self.used_ivs.contains(&iv) {
+            return Err(ModeError::ReusedIv);
+        }
+
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        if self.order.len() == self.capacity {
+            if let Some(oldest_iv) = self.order.pop_front() {
+                self.used_ivs.remove(&oldest_iv);
+            }
+        }
+
+        self.used_ivs.insert(iv);
+        self.order.push_back(iv);
+
+        Ok(())
+    }
+}
+
+/// A CBC encryption session which rejects reused IVs.
+///
+/// Reusing an IV in CBC mode isn't as catastrophic as reusing a nonce in CTR based modes,
+/// but it still leaks whether two messages start with the same blocks.
+/// The session remembers a bounded number of the most recently used IVs (see `IvTracker`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CbcSession {
+    /// The AES core used to encrypt the blocks.
+    core: AESCore,
+    /// The record of the used IVs.
+    tracker: IvTracker,
+}
+
+/// Public functions for the tracked CBC session.
+impl CbcSession {
+    pub fn new(core: AESCore, capacity: usize) -> Self {
+        //! Creates a new CBC session.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the blocks.
+        //! * `capacity` - The maximum number of IVs remembered.
+
+        Self {
+            core,
+            tracker: IvTracker::new(capacity),
+        }
+    }
+
+    pub fn tracker(&self) -> &IvTracker {
+        //! Returns the record of the used IVs.
+
+        &self.tracker
+    }
+
+    pub fn encryptor(&mut self, iv: [u8; 16]) -> Result<CbcEncryptor, ModeError> {
+        //! Creates a CBC encryptor for a new message.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the message.
+        //! # Errors
+        //! * ModeError::ReusedIv - The IV was already used within this session.
+
+        self.tracker.register(iv)?;
+
+        Ok(CbcEncryptor::new(self.core, iv))
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn cbc_decrypt_in_place(core: &AESCore, iv: [u8; 16], data: &mut [u8]) -> Result<(), LenError> {
+    //! Decrypts the whole CBC ciphertext in place, without allocating a separate output.
+    //! Each ciphertext block is saved before it is overwritten, as it is XORed into the next decrypted block.
+    //! # Arguments
+    //! * `core` - The AES core used to decrypt the blocks.
+    //! * `iv` - The initialization vector used for encryption.
+    //! * `data` - The ciphertext, replaced by the plaintext (the padding isn't removed).
+    //! # Errors
+    //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long, it is left unchanged.
+
+    if !data.len().is_multiple_of(16) {
+        return Err(LenError::NotBlockMultiple);
+    }
+
+    CbcDecryptor::new(*core, iv).decrypt_blocks(data.as_chunks_mut::<16>().0);
+    Ok(())
+}
+
+#[cfg(feature = "rand")]
+pub fn cbc_seal<R: CryptoRng + ?Sized>(core: &AESCore, padding: &Padding, rng: &mut R, plaintext: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    //! Pads the plaintext and encrypts it in CBC mode with a fresh random IV, which is prepended to the ciphertext.
+    //! The IV is generated for every call, so it is never reused by mistake. Decrypt with `cbc_open`.
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the blocks.
+    //! * `padding` - The padding appended to the plaintext, see `Padding::pad_buffer`.
+    //! * `rng` - The cryptographically secure random number generator for the IV.
+    //! * `plaintext` - The data to be encrypted, of any length.
+    //! # Returns
+    //! * `Result<Vec<u8>, PaddingError>` - The IV followed by the ciphertext (see `frame`), or an error.
+    //! # Errors
+    //! * PaddingError::NonePadding - Trying to pad with `PaddingTypes::None`.
+
+    let mut ciphertext = padding.pad_buffer(plaintext)?;
+    let (mut encryptor, iv) = CbcEncryptor::new_random_iv(*core, rng);
+    encryptor.encrypt_blocks(ciphertext.as_chunks_mut::<16>().0);
+
+    Ok(frame(&iv, &ciphertext))
+}
+
+pub fn cbc_open(core: &AESCore, padding: &Padding, data: &[u8]) -> Result<Vec<u8>, PaddingError> {
+    //! Splits off the IV prepended by `cbc_seal`, decrypts the ciphertext in CBC mode and removes the padding.
+    //! # Arguments
+    //! * `core` - The AES core used to decrypt the blocks.
+    //! * `padding` - The padding used for encryption.
+    //! * `data` - The IV followed by the ciphertext.
+    //! # Returns
+    //! * `Result<Vec<u8>, PaddingError>` - The plaintext or an error.
+    //! # Errors
+    //! * PaddingError::InvalidPadding - The padding is invalid (e.g. the key is wrong).
+    //! * PaddingError::InvalidPaddedSize - The data isn't the IV followed by a non-zero multiple of 16 bytes.
+    //! * PaddingError::NonePadding - Trying to de-pad with `PaddingTypes::None`.
+    //! * PaddingError::AmbiguousPadding - Trying to de-pad with `PaddingTypes::ZeroPadding`.
+
+    let (iv, ciphertext) = unframe(data).map_err(|_| PaddingError::InvalidPaddedSize)?;
+    if ciphertext.is_empty() {
+        return Err(PaddingError::InvalidPaddedSize);
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    cbc_decrypt_in_place(core, iv, &mut plaintext).expect("the ciphertext is a multiple of 16 bytes long");
+    let len = padding.de_pad_buffer(&plaintext)?.len();
+    plaintext.truncate(len);
+
+    Ok(plaintext)
+}
+
+pub fn frame(iv: &[u8; 16], ciphertext: &[u8]) -> Vec<u8> {
+    //! Frames the CBC ciphertext for storage or transmission, as the 16-byte IV followed by the ciphertext.
+    //! # Arguments
+    //! * `iv` - The IV used to encrypt the message.
+    //! * `ciphertext` - The ciphertext.
+    //! # Returns
+    //! * `Vec<u8>` - The framed data, iv || ciphertext.
+
+    let mut framed = Vec::with_capacity(16 + ciphertext.len());
+    framed.extend_from_slice(iv);
+    framed.extend_from_slice(ciphertext);
+    framed
+}
+
+pub fn unframe(data: &[u8]) -> Result<([u8; 16], &[u8]), FrameError> {
+    //! Parses the data framed with `frame`.
+    //! # Arguments
+    //! * `data` - The framed data, iv || ciphertext.
+    //! # Returns
+    //! * `Result<([u8; 16], &[u8]), FrameError>` - The IV and the ciphertext, or an error.
+    //! # Errors
+    //! * FrameError::TooShort - The data is shorter than 16 bytes.
+    //! * FrameError::NotBlockMultiple - The ciphertext isn't a multiple of 16 bytes long.
+
+    if data.len() < 16 {
+        return Err(FrameError::TooShort);
+    }
+
+    let (iv, ciphertext) = data.split_at(16);
+    if !ciphertext.len().is_multiple_of(16) {
+        return Err(FrameError::NotBlockMultiple);
+    }
+
+    Ok((iv.try_into().unwrap(), ciphertext))
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+    use crate::padding::PaddingTypes;
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16,
+        0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88,
+        0x09, 0xcf, 0x4f, 0x3c,
+    ];
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03,
+        0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b,
+        0x0c, 0x0d, 0x0e, 0x0f,
+    ];
+    const PLAINTEXT: [[u8; 16]; 4] = [
+        [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a],
+        [0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf, 0x8e, 0x51],
+        [0x30, 0xc8, 0x1c, 0x46, 0xa3, 0x5c, 0xe4, 0x11, 0xe5, 0xfb, 0xc1, 0x19, 0x1a, 0x0a, 0x52, 0xef],
+        [0xf6, 0x9f, 0x24, 0x45, 0xdf, 0x4f, 0x9b, 0x17, 0xad, 0x2b, 0x41, 0x7b, 0xe6, 0x6c, 0x37, 0x10],
+    ];
+    const CIPHERTEXT: [[u8; 16]; 4] = [
+        [0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19, 0x7d],
+        [0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76, 0x78, 0xb2],
+        [0x73, 0xbe, 0xd6, 0xb8, 0xe3, 0xc1, 0x74, 0x3b, 0x71, 0x16, 0xe6, 0x9e, 0x22, 0x22, 0x95, 0x16],
+        [0x3f, 0xf1, 0xca, 0xa1, 0x68, 0x1f, 0xac, 0x09, 0x12, 0x0e, 0xca, 0x30, 0x75, 0x86, 0xe1, 0xa7],
+    ];
+
+    #[test]
+    fn encrypt() {
+        //! Test CBC encryption with the NIST SP 800-38A example vectors
+
+        let mut encryptor = CbcEncryptor::new(AESCore::new(AESKey::AES128(KEY)), IV);
+        for i in 0..4 {
+            assert_eq!(encryptor.encrypt_block(&PLAINTEXT[i]), CIPHERTEXT[i]);
+        }
+
+        let mut blocks = PLAINTEXT;
+        CbcEncryptor::new(AESCore::new(AESKey::AES128(KEY)), IV).encrypt_blocks(&mut blocks);
+        assert_eq!(blocks, CIPHERTEXT);
+    }
+
+    #[test]
+    fn decrypt() {
+        //! Test CBC decryption with the NIST SP 800-38A example vectors
+
+        let mut decryptor = CbcDecryptor::new(AESCore::new(AESKey::AES128(KEY)), IV);
+        for i in 0..4 {
+            assert_eq!(decryptor.decrypt_block(&CIPHERTEXT[i]), PLAINTEXT[i]);
+        }
+
+        let mut blocks = CIPHERTEXT;
+        CbcDecryptor::new(AESCore::new(AESKey::AES128(KEY)), IV).decrypt_blocks(&mut blocks);
+        assert_eq!(blocks, PLAINTEXT);
+    }
+
+    #[test]
+    fn reuse_core_with_new_iv() {
+        //! Test that encrypting two messages with a reset IV matches two fresh ciphers
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let other_iv: [u8; 16] = [0xa5; 16];
+
+        let mut encryptor = CbcEncryptor::new(core, IV);
+        let mut first = PLAINTEXT;
+        encryptor.encrypt_blocks(&mut first);
+        let mut second = PLAINTEXT;
+        encryptor.with_iv(other_iv).encrypt_blocks(&mut second);
+
+        assert_eq!(first, CIPHERTEXT);
+        let mut expected = PLAINTEXT;
+        CbcEncryptor::new(core, other_iv).encrypt_blocks(&mut expected);
+        assert_eq!(second, expected);
+
+        let mut decryptor = CbcDecryptor::new(core, IV);
+        decryptor.decrypt_blocks(&mut first);
+        decryptor.with_iv(other_iv).decrypt_blocks(&mut second);
+        assert_eq!(first, PLAINTEXT);
+        assert_eq!(second, PLAINTEXT);
+    }
+
+    #[test]
+    fn reset() {
+        //! Test that a reset cipher matches a fresh one
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+
+        let mut encryptor = CbcEncryptor::new(core, IV);
+        let mut data = PLAINTEXT;
+        encryptor.encrypt_blocks(&mut data[..2]);
+        encryptor.reset();
+        assert_eq!(encryptor, CbcEncryptor::new(core, IV));
+        let mut data = PLAINTEXT;
+        encryptor.encrypt_blocks(&mut data);
+        assert_eq!(data, CIPHERTEXT);
+
+        let mut decryptor = CbcDecryptor::new(core, IV);
+        decryptor.decrypt_blocks(&mut data);
+        decryptor.reset();
+        let mut data = CIPHERTEXT;
+        decryptor.decrypt_blocks(&mut data);
+        assert_eq!(data, PLAINTEXT);
+    }
+
+    #[test]
+    fn session_rejects_reused_iv() {
+        //! Test that the tracked session rejects a reused IV and accepts distinct ones
+
+        let mut session = CbcSession::new(AESCore::new(AESKey::AES128(KEY)), 16);
+
+        let mut encryptor = session.encryptor(IV).unwrap();
+        assert_eq!(encryptor.encrypt_block(&PLAINTEXT[0]), CIPHERTEXT[0]);
+
+        assert_eq!(session.encryptor(IV), Err(ModeError::ReusedIv));
+
+        let mut other_iv = IV;
+        for i in 0..8 {
+            other_iv[15] = 0x10 + i;
+            assert!(session.encryptor(other_iv).is_ok());
+        }
+        assert_eq!(session.encryptor(other_iv), Err(ModeError::ReusedIv));
+        assert_eq!(session.tracker().len(), 9);
+    }
+
+    #[test]
+    fn tracker_is_bounded() {
+        //! Test that the tracker forgets the oldest IV once the capacity is reached
+
+        let mut tracker = IvTracker::new(2);
+        assert!(tracker.is_empty());
+
+        tracker.register([0; 16]).unwrap();
+        tracker.register([1; 16]).unwrap();
+        assert_eq!(tracker.register([1; 16]), Err(ModeError::ReusedIv));
+
+        tracker.register([2; 16]).unwrap();
+        assert_eq!(tracker.len(), 2);
+        assert_eq!(tracker.capacity(), 2);
+        assert!(!tracker.contains(&[0; 16]));
+        assert!(tracker.contains(&[1; 16]));
+        assert!(tracker.contains(&[2; 16]));
+
+        // the oldest IV was forgotten, so its reuse isn't detected anymore
+        tracker.register([0; 16]).unwrap();
+    }
+
+    #[test]
+    fn framing() {
+        //! Test framing the ciphertext with its IV, and the rejection of malformed frames
+
+        let ciphertext: &[u8] = CIPHERTEXT.as_flattened();
+        let framed = frame(&IV, ciphertext);
+        assert_eq!(framed.len(), 16 + ciphertext.len());
+        assert_eq!(framed[..16], IV);
+        assert_eq!(unframe(&framed), Ok((IV, ciphertext)));
+        assert_eq!(unframe(&frame(&IV, &[])), Ok((IV, &[][..])));
+
+        assert_eq!(unframe(&[]), Err(FrameError::TooShort));
+        assert_eq!(unframe(&framed[..15]), Err(FrameError::TooShort));
+        assert_eq!(unframe(&framed[..20]), Err(FrameError::NotBlockMultiple));
+    }
+
+    #[test]
+    fn decrypt_in_place() {
+        //! Test that decrypting in place matches decrypting block by block into a separate output
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut data: Vec<u8> = CIPHERTEXT.as_flattened().to_vec();
+        cbc_decrypt_in_place(&core, IV, &mut data).unwrap();
+        assert_eq!(data, PLAINTEXT.as_flattened());
+
+        let mut ciphertext: Vec<u8> = (0..=255).collect();
+        let mut decryptor = CbcDecryptor::new(core, IV);
+        let expected: Vec<u8> = ciphertext.as_chunks::<16>().0.iter().flat_map(|block| decryptor.decrypt_block(block)).collect();
+        cbc_decrypt_in_place(&core, IV, &mut ciphertext).unwrap();
+        assert_eq!(ciphertext, expected);
+
+        let mut empty: [u8; 0] = [];
+        assert_eq!(cbc_decrypt_in_place(&core, IV, &mut empty), Ok(()));
+        let mut partial: [u8; 20] = [0x11; 20];
+        assert_eq!(cbc_decrypt_in_place(&core, IV, &mut partial), Err(LenError::NotBlockMultiple));
+        assert_eq!(partial, [0x11; 20]);
+    }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn seal_open() {
+        //! Test that opening restores the plaintext, and that sealing the same plaintext twice gives different outputs
+
+        let core = AESCore::new(AESKey::AES128([0x4d; 16]));
+        let padding = Padding::new(PaddingTypes::PKCS7);
+        let mut rng = rand_core::UnwrapErr(getrandom::SysRng);
+        let plaintext: Vec<u8> = (0..50).collect();
+
+        for len in [0, 1, 15, 16, 17, 50] {
+            let sealed = cbc_seal(&core, &padding, &mut rng, &plaintext[..len]).unwrap();
+            assert_eq!(sealed.len(), 16 + (len / 16 + 1) * 16);
+            assert_eq!(cbc_open(&core, &padding, &sealed).unwrap(), plaintext[..len]);
+
+            // the ciphertext is CBC with the prepended IV
+            let (iv, ciphertext) = unframe(&sealed).unwrap();
+            let mut expected = padding.pad_buffer(&plaintext[..len]).unwrap();
+            CbcEncryptor::new(core, iv).encrypt_blocks(expected.as_chunks_mut::<16>().0);
+            assert_eq!(ciphertext, expected);
+        }
+
+        let first = cbc_seal(&core, &padding, &mut rng, &plaintext).unwrap();
+        let second = cbc_seal(&core, &padding, &mut rng, &plaintext).unwrap();
+        assert_ne!(first[..16], second[..16]);
+        assert_ne!(first[16..], second[16..]);
+
+        assert_eq!(cbc_seal(&core, &Padding::new(PaddingTypes::None), &mut rng, &plaintext), Err(PaddingError::NonePadding));
+    }
+
+    #[test]
+    fn open_errors() {
+        //! Test opening data without a whole ciphertext block after the IV
+
+        let core = AESCore::new(AESKey::AES128([0x4d; 16]));
+        let padding = Padding::new(PaddingTypes::PKCS7);
+
+        assert_eq!(cbc_open(&core, &padding, &[0; 15]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(cbc_open(&core, &padding, &[0; 16]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(cbc_open(&core, &padding, &[0; 40]), Err(PaddingError::InvalidPaddedSize));
+
+        let mut block: [u8; 16] = [16; 16];
+        core.encrypt_in_place(&mut block);
+        let mut data = vec![0; 16];
+        data.extend_from_slice(&block);
+        assert_eq!(cbc_open(&core, &padding, &data).unwrap(), Vec::<u8>::new());
+        data[31] ^= 1;
+        assert_eq!(cbc_open(&core, &padding, &data), Err(PaddingError::InvalidPadding));
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        //! Test that clearing the encryptor and the decryptor overwrites the IV and the chaining block, and resets them
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut encryptor = CbcEncryptor::new(core, IV);
+        encryptor.encrypt_block(&PLAINTEXT[0]);
+        encryptor.zeroize();
+        assert_eq!(encryptor.iv, [0; 16]);
+        assert_eq!(encryptor.previous_block, [0; 16]);
+        assert_eq!(encryptor, CbcEncryptor::new(core, [0; 16]));
+
+        let mut decryptor = CbcDecryptor::new(core, IV);
+        decryptor.decrypt_block(&CIPHERTEXT[0]);
+        decryptor.zeroize();
+        assert_eq!(decryptor.iv, [0; 16]);
+        assert_eq!(decryptor.previous_block, [0; 16]);
+        assert_eq!(decryptor, CbcDecryptor::new(core, [0; 16]));
+    }
+}
@@ -0,0 +1,262 @@
+//! A module containing the Cipher Feedback (CFB) mode of operation.
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::AESCore;
+
+#[cfg(feature = "rand")]
+use crate::modes::random_iv;
+#[cfg(feature = "rand")]
+use rand_core::CryptoRng;
+
+#[cfg(feature = "zeroize")]
+use crate::util::{
+    zeroize,
+    Zeroize,
+};
+
+
+
+
+
+// STRUCTS
+
+/// The CFB mode encryptor, with 128-bit segments (CFB128).
+/// The data doesn't have to be a multiple of the block size, so it can be encrypted in parts of any length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CfbEncryptor {
+    /// The AES core used to encrypt the feedback blocks.
+    core: AESCore,
+    /// The initialization vector of the message.
+    iv: [u8; 16],
+    /// The feedback block, filled with the ciphertext of the current block as it is produced.
+    feedback: [u8; 16],
+    /// The current keystream block.
+    block: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+}
+
+/// Public functions for encrypting data in CFB mode.
+impl CfbEncryptor {
+    pub fn new(core: AESCore, iv: [u8; 16]) -> Self {
+        //! Creates a new CFB encryptor.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the feedback blocks.
+        //! * `iv` - The initialization vector. Should be unpredictable and never reused with the same key.
+
+        Self {
+            core,
+            iv,
+            feedback: iv,
+            block: [0; 16],
+            position: 16,
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn new_random_iv<R: CryptoRng + ?Sized>(core: AESCore, rng: &mut R) -> (Self, [u8; 16]) {
+        //! Creates a new CFB encryptor with a random IV.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the feedback blocks.
+        //! * `rng` - The cryptographically secure random number generator.
+        //! # Returns
+        //! * `(CfbEncryptor, [u8; 16])` - The encryptor and the IV, which must be sent along with the ciphertext.
+
+        let iv: [u8; 16] = random_iv(rng);
+        (Self::new(core, iv), iv)
+    }
+
+    pub fn with_iv(&self, iv: [u8; 16]) -> Self {
+        //! Creates a new encryptor for another message with the same key.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the new message.
+
+        Self::new(self.core, iv)
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the encryptor back to the initial IV, keeping the expanded key.
+        //! The same message can then be encrypted again. Encrypting a different message after the reset
+        //! reuses the IV, so use `CfbEncryptor::with_iv` for new messages instead.
+
+        *self = Self::new(self.core, self.iv);
+    }
+
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        //! Encrypts the next part of the message in place.
+
+        for byte in data {
+            if self.position == 16 {
+                self.block = self.core.encrypt(&self.feedback);
+                self.position = 0;
+            }
+            *byte ^= self.block[self.position];
+            self.feedback[self.position] = *byte;
+            self.position += 1;
+        }
+    }
+}
+
+/// Clearing the IV, the feedback and the keystream block of the encryptor.
+#[cfg(feature = "zeroize")]
+impl Zeroize for CfbEncryptor {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.iv);
+        zeroize(&mut self.feedback);
+        zeroize(&mut self.block);
+        self.position = 16;
+    }
+}
+
+/// The CFB mode decryptor, with 128-bit segments (CFB128).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CfbDecryptor {
+    /// The AES core used to encrypt the feedback blocks.
+    core: AESCore,
+    /// The initialization vector of the message.
+    iv: [u8; 16],
+    /// The feedback block, filled with the ciphertext of the current block as it is consumed.
+    feedback: [u8; 16],
+    /// The current keystream block.
+    block: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+}
+
+/// Public functions for decrypting data in CFB mode.
+impl CfbDecryptor {
+    pub fn new(core: AESCore, iv: [u8; 16]) -> Self {
+        //! Creates a new CFB decryptor.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the feedback blocks.
+        //! * `iv` - The initialization vector used for encryption.
+
+        Self {
+            core,
+            iv,
+            feedback: iv,
+            block: [0; 16],
+            position: 16,
+        }
+    }
+
+    pub fn with_iv(&self, iv: [u8; 16]) -> Self {
+        //! Creates a new decryptor for another message with the same key.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the new message.
+
+        Self::new(self.core, iv)
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the decryptor back to the initial IV, keeping the expanded key.
+
+        *self = Self::new(self.core, self.iv);
+    }
+
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        //! Decrypts the next part of the message in place.
+
+        for byte in data {
+            if self.position == 16 {
+                self.block = self.core.encrypt(&self.feedback);
+                self.position = 0;
+            }
+            self.feedback[self.position] = *byte;
+            *byte ^= self.block[self.position];
+            self.position += 1;
+        }
+    }
+}
+
+/// Clearing the IV, the feedback and the keystream block of the decryptor.
+#[cfg(feature = "zeroize")]
+impl Zeroize for CfbDecryptor {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.iv);
+        zeroize(&mut self.feedback);
+        zeroize(&mut self.block);
+        self.position = 16;
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn cfb128() {
+        //! Test with the NIST SP 800-38A F.3.13 CFB128-AES128 vectors, processed in uneven parts
+
+        let core = AESCore::new(AESKey::AES128(hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap()));
+        let iv: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let plaintext = hex(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+        );
+        let ciphertext = hex(
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6"
+        );
+
+        let mut data = plaintext.clone();
+        let mut encryptor = CfbEncryptor::new(core, iv);
+        let (first, rest) = data.split_at_mut(5);
+        encryptor.encrypt(first);
+        let (second, third) = rest.split_at_mut(30);
+        encryptor.encrypt(second);
+        encryptor.encrypt(third);
+        assert_eq!(data, ciphertext);
+
+        let mut decryptor = CfbDecryptor::new(core, iv);
+        let (first, rest) = data.split_at_mut(17);
+        decryptor.decrypt(first);
+        decryptor.decrypt(rest);
+        assert_eq!(data, plaintext);
+
+        // a reset encryptor starts the message again
+        encryptor.reset();
+        encryptor.encrypt(&mut data);
+        assert_eq!(data, ciphertext);
+        decryptor.reset();
+        decryptor.decrypt(&mut data);
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        //! Test that clearing the encryptor and the decryptor overwrites the IV, the feedback and the keystream, and resets them
+
+        let core = AESCore::new(AESKey::AES128([0x2b; 16]));
+        let mut encryptor = CfbEncryptor::new(core, [0x01; 16]);
+        encryptor.encrypt(&mut [0; 20]);
+        encryptor.zeroize();
+        assert_eq!((encryptor.iv, encryptor.feedback, encryptor.block, encryptor.position), ([0; 16], [0; 16], [0; 16], 16));
+        assert_eq!(encryptor, CfbEncryptor::new(core, [0; 16]));
+
+        let mut decryptor = CfbDecryptor::new(core, [0x01; 16]);
+        decryptor.decrypt(&mut [0; 20]);
+        decryptor.zeroize();
+        assert_eq!((decryptor.iv, decryptor.feedback, decryptor.block, decryptor.position), ([0; 16], [0; 16], [0; 16], 16));
+        assert_eq!(decryptor, CfbDecryptor::new(core, [0; 16]));
+    }
+}
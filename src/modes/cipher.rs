@@ -0,0 +1,492 @@
+//! A module containing a cipher dispatching to the mode of operation selected at runtime.
+
+
+
+
+
+// IMPORTS
+
+use std::fmt;
+use std::sync::mpsc;
+use tinypool::ThreadPool;
+use crate::aes_core::{
+    AESCore,
+    LenError,
+};
+use crate::builder::BuildError;
+use crate::modes::{
+    CbcDecryptor,
+    CbcEncryptor,
+    CfbDecryptor,
+    CfbEncryptor,
+    CtrCipher,
+    Direction,
+    Mode,
+    OfbCipher,
+};
+use crate::util::blocks_mut;
+
+
+
+
+
+// ENUMS
+
+/// The state of the selected mode of operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ModeState {
+    Ecb,
+    CbcEncrypt(CbcEncryptor),
+    CbcDecrypt(CbcDecryptor),
+    Ctr(CtrCipher),
+    CfbEncrypt(CfbEncryptor),
+    CfbDecrypt(CfbDecryptor),
+    Ofb(OfbCipher),
+}
+
+/// The enum with batch processing errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BatchError {
+    /// The number of IVs doesn't match the number of messages (there must be none for ECB mode).
+    IvCountMismatch,
+    /// A message isn't a multiple of 16 bytes long in ECB or CBC mode.
+    NotBlockMultiple,
+    /// The thread pool has no threads to process the messages.
+    EmptyPool,
+    /// The counter would wrap around before the whole data is processed (in a non-wrapping CTR cipher).
+    CounterExhausted,
+    /// A job didn't return its data (e.g. it panicked), the data wasn't changed.
+    JobFailed,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::IvCountMismatch => write!(f, "number of IVs doesn't match the number of messages"),
+            BatchError::NotBlockMultiple => write!(f, "message must be a multiple of 16 bytes long"),
+            BatchError::EmptyPool => write!(f, "thread pool has no threads"),
+            BatchError::CounterExhausted => write!(f, "counter is exhausted"),
+            BatchError::JobFailed => write!(f, "job failed to return its data"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+
+
+
+
+// STRUCTS
+
+/// A cipher with the mode of operation and the direction selected at runtime.
+/// It holds the state of the mode (the chaining value or the counter), so consecutive calls
+/// to `Cipher::process` (or `Cipher::update`) continue the same message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cipher {
+    /// The AES core used by the mode of operation.
+    core: AESCore,
+    /// The mode of operation.
+    mode: Mode,
+    /// Whether the data is encrypted or decrypted.
+    direction: Direction,
+    /// The state of the mode of operation.
+    state: ModeState,
+    /// The incomplete block buffered by `Cipher::update` in ECB and CBC modes.
+    buffer: [u8; 16],
+    /// The number of bytes in the buffer.
+    buffered: usize,
+}
+
+/// Public functions for the cipher.
+impl Cipher {
+    pub fn new(core: AESCore, mode: Mode, direction: Direction, iv: Option<[u8; 16]>) -> Result<Self, BuildError> {
+        //! Creates a new cipher.
+        //! # Arguments
+        //! * `core` - The AES core used by the mode of operation.
+        //! * `mode` - The mode of operation.
+        //! * `direction` - Whether the data is encrypted or decrypted.
+        //! * `iv` - The IV (the initial counter block for CTR mode), which must be None for ECB mode.
+        //! # Errors
+        //! * BuildError::MissingIv - The mode of operation requires an IV, but it wasn't given.
+        //! * BuildError::UnexpectedIv - An IV was given for ECB mode.
+
+        let state = match (mode, iv) {
+            (Mode::Ecb, None) => ModeState::Ecb,
+            (Mode::Ecb, Some(_)) => return Err(BuildError::UnexpectedIv),
+            (_, None) => return Err(BuildError::MissingIv),
+            (Mode::Cbc, Some(iv)) => match direction {
+                Direction::Encrypt => ModeState::CbcEncrypt(CbcEncryptor::new(core, iv)),
+                Direction::Decrypt => ModeState::CbcDecrypt(CbcDecryptor::new(core, iv)),
+            },
+            (Mode::Ctr, Some(iv)) => ModeState::Ctr(CtrCipher::new(core, iv)),
+            (Mode::Cfb, Some(iv)) => match direction {
+                Direction::Encrypt => ModeState::CfbEncrypt(CfbEncryptor::new(core, iv)),
+                Direction::Decrypt => ModeState::CfbDecrypt(CfbDecryptor::new(core, iv)),
+            },
+            (Mode::Ofb, Some(iv)) => ModeState::Ofb(OfbCipher::new(core, iv)),
+        };
+
+        Ok(Self {
+            core,
+            mode,
+            direction,
+            state,
+            buffer: [0; 16],
+            buffered: 0,
+        })
+    }
+
+    pub fn mode(&self) -> Mode {
+        //! Returns the mode of operation.
+
+        self.mode
+    }
+
+    pub fn direction(&self) -> Direction {
+        //! Returns whether the data is encrypted or decrypted.
+
+        self.direction
+    }
+
+    pub fn process(&mut self, data: &mut [u8]) -> Result<(), LenError> {
+        //! Encrypts or decrypts the next part of the message in place.
+        //! ECB and CBC modes process whole blocks, so the data must be a multiple of 16 bytes long.
+        //! The stream modes (CTR, CFB and OFB) accept data of any length.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long in ECB or CBC mode.
+        //! * LenError::IncompleteBlock - `Cipher::update` left an incomplete block in the buffer,
+        //!   which would be skipped, the data is left unchanged.
+
+        if self.buffered > 0 {
+            return Err(LenError::IncompleteBlock);
+        }
+        if matches!(self.mode, Mode::Ecb | Mode::Cbc) && !data.len().is_multiple_of(16) {
+            return Err(LenError::NotBlockMultiple);
+        }
+
+        match &mut self.state {
+            ModeState::Ecb => {
+                for block in blocks_mut(data).0 {
+                    match self.direction {
+                        Direction::Encrypt => self.core.encrypt_in_place(block),
+                        Direction::Decrypt => self.core.decrypt_in_place(block),
+                    }
+                }
+            }
+            ModeState::CbcEncrypt(encryptor) => {
+                let (blocks, _) = data.as_chunks_mut::<16>();
+                encryptor.encrypt_blocks(blocks);
+            }
+            ModeState::CbcDecrypt(decryptor) => {
+                let (blocks, _) = data.as_chunks_mut::<16>();
+                decryptor.decrypt_blocks(blocks);
+            }
+            ModeState::Ctr(cipher) => cipher.apply_keystream(data).expect("the full 128-bit counter is never exhausted"),
+            ModeState::CfbEncrypt(encryptor) => encryptor.encrypt(data),
+            ModeState::CfbDecrypt(decryptor) => decryptor.decrypt(data),
+            ModeState::Ofb(cipher) => cipher.apply_keystream(data),
+        }
+
+        Ok(())
+    }
+
+    pub fn update(&mut self, input: &[u8], output: &mut [u8]) -> usize {
+        //! Encrypts or decrypts the next part of the message from the input into the output.
+        //! Meant for data arriving in chunks of any length (e.g. from a network socket).
+        //! The stream modes (CTR, CFB and OFB) process the whole input.
+        //! ECB and CBC modes buffer the incomplete block internally and process it once the next input completes it,
+        //! so the output may be shorter than the input. Call `Cipher::finish` at the end of the message
+        //! to check that no bytes were left in the buffer. `Cipher::process` is rejected while the buffer isn't empty.
+        //! # Arguments
+        //! * `input` - The next part of the message.
+        //! * `output` - The buffer for the processed data.
+        //! # Returns
+        //! * `usize` - The number of bytes written to the start of the output.
+        //! # Panics
+        //! * If the output is shorter than the produced data (at most `input.len() + 15` bytes in ECB and CBC modes,
+        //!   exactly `input.len()` bytes in the stream modes).
+
+        if !matches!(self.mode, Mode::Ecb | Mode::Cbc) {
+            let output = &mut output[..input.len()];
+            output.copy_from_slice(input);
+            self.process(output).expect("the stream modes accept data of any length");
+            return input.len();
+        }
+
+        let mut input = input;
+        let mut written: usize = 0;
+
+        if self.buffered > 0 {
+            let taken = input.len().min(16 - self.buffered);
+            self.buffer[self.buffered..(self.buffered + taken)].copy_from_slice(&input[..taken]);
+            self.buffered += taken;
+            input = &input[taken..];
+
+            if self.buffered < 16 {
+                return 0;
+            }
+            output[..16].copy_from_slice(&self.buffer);
+            self.buffered = 0;
+            self.process(&mut output[..16]).expect("the buffer holds a whole block");
+            written = 16;
+        }
+
+        let whole_blocks_len = input.len() - input.len() % 16;
+        output[written..(written + whole_blocks_len)].copy_from_slice(&input[..whole_blocks_len]);
+        self.process(&mut output[written..(written + whole_blocks_len)]).expect("only whole blocks are processed");
+        written += whole_blocks_len;
+
+        let remainder = &input[whole_blocks_len..];
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+
+        written
+    }
+
+    pub fn finish(self) -> Result<(), LenError> {
+        //! Ends the message, checking that `Cipher::update` didn't leave an incomplete block in the buffer.
+        //! Always succeeds in the stream modes (CTR, CFB and OFB), which don't buffer any data.
+        //! # Errors
+        //! * LenError::IncompleteBlock - The message isn't a multiple of 16 bytes long in ECB or CBC mode,
+        //!   the buffered bytes weren't processed.
+
+        if self.buffered > 0 {
+            return Err(LenError::IncompleteBlock);
+        }
+
+        Ok(())
+    }
+}
+
+/// Functions for processing independent messages concurrently.
+impl AESCore {
+    pub fn encrypt_batch(&self, messages: &mut [Vec<u8>], ivs: &[[u8; 16]], mode: Mode, pool: &ThreadPool) -> Result<(), BatchError> {
+        //! Encrypts the independent messages in place, each one as a separate job in the thread pool.
+        //! Each message is encrypted serially with its own IV (the chaining within a message is kept),
+        //! so the result is the same as encrypting the messages one after another.
+        //! Useful for encrypting many small records at once.
+        //! # Arguments
+        //! * `messages` - The messages to be encrypted.
+        //! * `ivs` - The IV of each message (the initial counter block for CTR mode), must be empty for ECB mode.
+        //! * `mode` - The mode of operation.
+        //! * `pool` - The thread pool running the jobs, it may be shared with other work.
+        //! # Returns
+        //! * `Result<(), BatchError>` - Ok if the messages were encrypted, or an error.
+        //! # Errors
+        //! The messages are checked before any of them is encrypted, so they are left unchanged in case of an error.
+        //! * BatchError::IvCountMismatch - There isn't exactly one IV per message (or there are IVs for ECB mode).
+        //! * BatchError::NotBlockMultiple - A message isn't a multiple of 16 bytes long in ECB or CBC mode.
+        //! * BatchError::EmptyPool - The thread pool has no threads.
+        //! * BatchError::JobFailed - A job didn't return its message (e.g. it panicked).
+
+        let expected_ivs = if mode == Mode::Ecb { 0 } else { messages.len() };
+        if ivs.len() != expected_ivs {
+            return Err(BatchError::IvCountMismatch);
+        }
+        if matches!(mode, Mode::Ecb | Mode::Cbc) && messages.iter().any(|message| !message.len().is_multiple_of(16)) {
+            return Err(BatchError::NotBlockMultiple);
+        }
+        if pool.size() == 0 {
+            return Err(BatchError::EmptyPool);
+        }
+
+        // the jobs must own their data, so each message is copied into its job and sent back when encrypted
+        let (sender, receiver) = mpsc::channel::<(usize, Vec<u8>)>();
+        for (index, message) in messages.iter().enumerate() {
+            let mut cipher = Cipher::new(*self, mode, Direction::Encrypt, ivs.get(index).copied()).expect("the IVs were checked");
+            let mut data = message.clone();
+            let sender = sender.clone();
+            pool.execute(move || {
+                cipher.process(&mut data).expect("the lengths were checked");
+                sender.send((index, data)).expect("the receiver lives until all messages are returned");
+            }).expect("the pool isn't empty");
+        }
+        drop(sender);
+
+        write_messages(messages, receiver)
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+fn write_messages(messages: &mut [Vec<u8>], results: impl IntoIterator<Item = (usize, Vec<u8>)>) -> Result<(), BatchError> {
+    //! Writes the encrypted messages back, but only once all of them are returned,
+    //! so a failed job can't leave a message unencrypted or emptied.
+    //! # Errors
+    //! * BatchError::JobFailed - Fewer messages than `messages.len()` were returned, the messages are unchanged.
+
+    let results: Vec<(usize, Vec<u8>)> = results.into_iter().collect();
+    if results.len() != messages.len() {
+        return Err(BatchError::JobFailed);
+    }
+
+    for (index, data) in results {
+        messages[index] = data;
+    }
+    Ok(())
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    const IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03,
+        0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b,
+        0x0c, 0x0d, 0x0e, 0x0f,
+    ];
+
+    #[test]
+    fn dispatch() {
+        //! Test that the cipher matches the dedicated mode types in every mode, in both directions
+
+        let core = AESCore::new(AESKey::AES192([0x5a; 24]));
+        let plaintext: Vec<u8> = (0..64).collect();
+
+        for mode in [Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            let mut expected = plaintext.clone();
+            match mode {
+                Mode::Ecb => core.encrypt_slice_to(&plaintext, &mut expected).unwrap(),
+                Mode::Cbc => CbcEncryptor::new(core, IV).encrypt_blocks(expected.as_chunks_mut::<16>().0),
+                Mode::Ctr => CtrCipher::new(core, IV).apply_keystream(&mut expected).unwrap(),
+                Mode::Cfb => CfbEncryptor::new(core, IV).encrypt(&mut expected),
+                Mode::Ofb => OfbCipher::new(core, IV).apply_keystream(&mut expected),
+            }
+
+            let iv = if mode == Mode::Ecb { None } else { Some(IV) };
+            let mut encryptor = Cipher::new(core, mode, Direction::Encrypt, iv).unwrap();
+            assert_eq!(encryptor.mode(), mode);
+            assert_eq!(encryptor.direction(), Direction::Encrypt);
+
+            // two calls continue the same message
+            let mut data = plaintext.clone();
+            let (first, second) = data.split_at_mut(32);
+            encryptor.process(first).unwrap();
+            encryptor.process(second).unwrap();
+            assert_eq!(data, expected);
+
+            let mut decryptor = Cipher::new(core, mode, Direction::Decrypt, iv).unwrap();
+            decryptor.process(&mut data).unwrap();
+            assert_eq!(data, plaintext);
+        }
+    }
+
+    #[test]
+    fn update() {
+        //! Test that feeding the message in chunks split at odd boundaries matches processing it at once
+
+        let core = AESCore::new(AESKey::AES256([0x7e; 32]));
+        let plaintext: Vec<u8> = (0..160).collect();
+
+        for mode in [Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            let iv = if mode == Mode::Ecb { None } else { Some(IV) };
+            let mut expected = plaintext.clone();
+            Cipher::new(core, mode, Direction::Encrypt, iv).unwrap().process(&mut expected).unwrap();
+
+            for chunk_lens in [[1, 2, 3, 5, 7, 11, 13, 17, 19, 23, 59], [0, 15, 1, 16, 33, 3, 13, 29, 0, 30, 20]] {
+                let mut cipher = Cipher::new(core, mode, Direction::Encrypt, iv).unwrap();
+                let mut output: Vec<u8> = Vec::new();
+                let mut offset: usize = 0;
+
+                for len in chunk_lens {
+                    let mut chunk_output: [u8; 80] = [0; 80];
+                    let written = cipher.update(&plaintext[offset..(offset + len)], &mut chunk_output);
+                    assert!(written <= len + 15);
+                    output.extend_from_slice(&chunk_output[..written]);
+                    offset += len;
+                }
+
+                assert_eq!(offset, plaintext.len());
+                assert_eq!(output, expected);
+                assert_eq!(cipher.finish(), Ok(()));
+            }
+        }
+
+        // an incomplete block stays buffered
+        let mut cipher = Cipher::new(core, Mode::Cbc, Direction::Decrypt, Some(IV)).unwrap();
+        assert_eq!(cipher.update(&[0; 20], &mut [0; 35]), 16);
+        assert_eq!(cipher.finish(), Err(LenError::IncompleteBlock));
+        assert_eq!(cipher.process(&mut [0; 16]), Err(LenError::IncompleteBlock));
+        assert_eq!(cipher.update(&[0; 11], &mut [0; 26]), 0);
+        assert_eq!(cipher.update(&[0; 1], &mut [0; 16]), 16);
+        assert_eq!(cipher.finish(), Ok(()));
+        cipher.process(&mut [0; 16]).unwrap();
+
+        let mut cipher = Cipher::new(core, Mode::Ecb, Direction::Encrypt, None).unwrap();
+        assert_eq!(cipher.update(&[0; 7], &mut [0; 22]), 0);
+        assert_eq!(cipher.finish(), Err(LenError::IncompleteBlock));
+        let mut data: [u8; 16] = [0x42; 16];
+        assert_eq!(cipher.process(&mut data), Err(LenError::IncompleteBlock));
+        assert_eq!(data, [0x42; 16]);
+        assert_eq!(LenError::IncompleteBlock.to_string(), "an incomplete block is still buffered");
+    }
+
+    #[test]
+    fn encrypt_batch() {
+        //! Test that the messages encrypted concurrently match the messages encrypted serially
+
+        let core = AESCore::new(AESKey::AES128([0x3c; 16]));
+        let pool = ThreadPool::new(4).unwrap();
+
+        for mode in [Mode::Ecb, Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            // the stream modes also get messages with partial blocks
+            let extra_len = if matches!(mode, Mode::Ecb | Mode::Cbc) { 0 } else { 7 };
+            let mut messages: Vec<Vec<u8>> = (0..20).map(|i| vec![i as u8; (i % 5) * 16 + extra_len]).collect();
+            let ivs: Vec<[u8; 16]> = if mode == Mode::Ecb { Vec::new() } else { (0..20).map(|i| [i as u8 ^ 0xa5; 16]).collect() };
+
+            let mut expected = messages.clone();
+            for (index, message) in expected.iter_mut().enumerate() {
+                Cipher::new(core, mode, Direction::Encrypt, ivs.get(index).copied()).unwrap().process(message).unwrap();
+            }
+
+            core.encrypt_batch(&mut messages, &ivs, mode, &pool).unwrap();
+            assert_eq!(messages, expected);
+        }
+
+        let mut messages: Vec<Vec<u8>> = vec![vec![0; 16], vec![0; 20]];
+        assert_eq!(core.encrypt_batch(&mut messages, &[IV], Mode::Ctr, &pool), Err(BatchError::IvCountMismatch));
+        assert_eq!(core.encrypt_batch(&mut messages, &[IV, IV], Mode::Ecb, &pool), Err(BatchError::IvCountMismatch));
+        assert_eq!(core.encrypt_batch(&mut messages, &[IV, IV], Mode::Cbc, &pool), Err(BatchError::NotBlockMultiple));
+        let mut empty_pool = ThreadPool::new(1).unwrap();
+        empty_pool.join();
+        assert_eq!(core.encrypt_batch(&mut messages, &[IV, IV], Mode::Ctr, &empty_pool), Err(BatchError::EmptyPool));
+        assert_eq!(messages, [vec![0; 16], vec![0; 20]]);
+
+        // a job which panics never returns its message, so nothing is written
+        let results = vec![(1, vec![1; 20])];
+        assert_eq!(write_messages(&mut messages, results), Err(BatchError::JobFailed));
+        assert_eq!(messages, [vec![0; 16], vec![0; 20]]);
+        write_messages(&mut messages, vec![(1, vec![1; 20]), (0, vec![2; 16])]).unwrap();
+        assert_eq!(messages, [vec![2; 16], vec![1; 20]]);
+    }
+
+    #[test]
+    fn dispatch_errors() {
+        //! Test the IV checks and the length check of the block modes
+
+        let core = AESCore::new(AESKey::AES128([0; 16]));
+        assert_eq!(Cipher::new(core, Mode::Ecb, Direction::Encrypt, Some(IV)), Err(BuildError::UnexpectedIv));
+        for mode in [Mode::Cbc, Mode::Ctr, Mode::Cfb, Mode::Ofb] {
+            assert_eq!(Cipher::new(core, mode, Direction::Decrypt, None), Err(BuildError::MissingIv));
+        }
+
+        let mut cipher = Cipher::new(core, Mode::Cbc, Direction::Encrypt, Some(IV)).unwrap();
+        assert_eq!(cipher.process(&mut [0; 20]), Err(LenError::NotBlockMultiple));
+
+        let mut cipher = Cipher::new(core, Mode::Cfb, Direction::Encrypt, Some(IV)).unwrap();
+        assert_eq!(cipher.process(&mut [0; 20]), Ok(()));
+    }
+}
@@ -0,0 +1,974 @@
+//! A module containing the Counter (CTR) mode of operation.
+
+
+
+
+
+// IMPORTS
+
+use std::sync::mpsc;
+use tinypool::ThreadPool;
+use crate::aes_core::AESCore;
+use crate::modes::{
+    BatchError,
+    ModeError,
+};
+
+#[cfg(feature = "nonce-guard")]
+use crate::modes::IvTracker;
+#[cfg(feature = "zeroize")]
+use crate::util::{
+    zeroize,
+    Zeroize,
+};
+
+
+
+
+
+// ENUMS
+
+/// The enum with the parts of the counter block which are incremented.
+/// The rest of the block (e.g. the nonce) stays unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CounterMode {
+    /// The whole block is a 128-bit big-endian counter.
+    Full128,
+    /// The last 8 bytes are a 64-bit big-endian counter.
+    Low64,
+    /// The last 4 bytes are a 32-bit big-endian counter (e.g. GCM, RFC 3686).
+    Low32,
+}
+
+
+
+
+
+// STRUCTS
+
+/// An iterator over the CTR mode keystream.
+/// The keystream is produced by encrypting the counter block,
+/// which is incremented as a 128-bit big-endian integer after each block (wrapping around).
+/// The iterator is infinite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeystreamIter {
+    /// The AES core used to encrypt the counter blocks.
+    core: AESCore,
+    /// The next counter block to be encrypted.
+    counter: [u8; 16],
+    /// The current keystream block.
+    block: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+}
+
+/// Public functions for the keystream iterator.
+impl KeystreamIter {
+    pub fn new(core: AESCore, counter: [u8; 16]) -> Self {
+        //! Creates a new keystream iterator.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the counter blocks.
+        //! * `counter` - The initial counter block.
+
+        Self {
+            core,
+            counter,
+            block: [0; 16],
+            position: 16,
+        }
+    }
+}
+
+impl Iterator for KeystreamIter {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position == 16 {
+            self.block = self.core.encrypt(&self.counter);
+            increment_counter(&mut self.counter);
+            self.position = 0;
+        }
+
+        let byte = self.block[self.position];
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// The CTR mode cipher.
+/// Encryption and decryption are the same operation, XORing the data with the keystream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CtrCipher {
+    /// The AES core used to encrypt the counter blocks.
+    core: AESCore,
+    /// The initial counter block of the message.
+    initial_counter: [u8; 16],
+    /// The next counter block to be encrypted.
+    counter: [u8; 16],
+    /// The part of the counter block which is incremented.
+    counter_mode: CounterMode,
+    /// Whether the counter wraps around, or refuses to produce more blocks once it is used up.
+    wrapping: bool,
+    /// Whether the counter was used up (only if it doesn't wrap around).
+    exhausted: bool,
+    /// The current keystream block.
+    block: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+}
+
+/// Public functions for encrypting and decrypting data in CTR mode.
+impl CtrCipher {
+    pub fn new(core: AESCore, counter: [u8; 16]) -> Self {
+        //! Creates a new CTR cipher with a raw 128-bit counter block.
+        //! The counter block is incremented as a 128-bit big-endian integer, wrapping around.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the counter blocks.
+        //! * `counter` - The initial counter block. Must never be reused with the same key.
+
+        Self::with_counter_mode(core, counter, CounterMode::Full128)
+    }
+
+    pub fn with_counter_mode(core: AESCore, counter: [u8; 16], counter_mode: CounterMode) -> Self {
+        //! Creates a new CTR cipher which increments only the selected part of the counter block.
+        //! The counter wraps around within its width, leaving the rest of the block unchanged,
+        //! so the keystream repeats after 2^32 (or 2^64, 2^128) blocks.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the counter blocks.
+        //! * `counter` - The initial counter block. Must never be reused with the same key.
+        //! * `counter_mode` - The part of the counter block which is incremented.
+
+        Self {
+            core,
+            initial_counter: counter,
+            counter,
+            counter_mode,
+            wrapping: true,
+            exhausted: false,
+            block: [0; 16],
+            position: 16,
+        }
+    }
+
+    pub fn new_nonce(core: AESCore, nonce: [u8; 4], iv: [u8; 8]) -> Self {
+        //! Creates a new CTR cipher with the counter block layout from RFC 3686.
+        //! The initial counter block is `nonce || iv || 0x00000001`
+        //! and only the last 32 bits are incremented.
+        //! The counter doesn't wrap around, so at most 2^32 - 1 blocks can be processed
+        //! (see `CtrCipher::apply_keystream`).
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the counter blocks.
+        //! * `nonce` - The nonce, fixed for the lifetime of the key.
+        //! * `iv` - The per-message IV. Must never be reused with the same key and nonce.
+
+        let mut counter: [u8; 16] = [0; 16];
+        counter[..4].copy_from_slice(&nonce);
+        counter[4..12].copy_from_slice(&iv);
+        counter[12..].copy_from_slice(&1_u32.to_be_bytes());
+
+        Self {
+            core,
+            initial_counter: counter,
+            counter,
+            counter_mode: CounterMode::Low32,
+            wrapping: false,
+            exhausted: false,
+            block: [0; 16],
+            position: 16,
+        }
+    }
+
+    pub fn with_counter(&self, counter: [u8; 16]) -> Self {
+        //! Creates a new cipher for another message with the same key and counter mode.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `counter` - The initial counter block of the new message. Must never be reused with the same key.
+
+        Self {
+            initial_counter: counter,
+            counter,
+            exhausted: false,
+            block: [0; 16],
+            position: 16,
+            ..*self
+        }
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the cipher back to the initial counter block, keeping the expanded key and the counter mode.
+        //! The same message can then be processed again. Encrypting a different message after the reset
+        //! reuses the keystream, so use `CtrCipher::with_counter` for new messages instead.
+
+        *self = self.with_counter(self.initial_counter);
+    }
+
+    pub fn with_wrapping(&self, wrapping: bool) -> Self {
+        //! Creates a copy of the cipher which wraps around, or refuses to generate the keystream once the counter is used up.
+        //! A non-wrapping cipher returns `ModeError::CounterExhausted` instead of reusing the keystream
+        //! when the incremented part of the counter block would wrap around (see `CtrCipher::apply_keystream`).
+        //! # Arguments
+        //! * `wrapping` - Whether the counter wraps around (the default, except for `CtrCipher::new_nonce`).
+
+        Self {
+            wrapping,
+            ..*self
+        }
+    }
+
+    pub fn wrapping(&self) -> bool {
+        //! Returns whether the counter wraps around, or refuses to generate more keystream once it is used up.
+
+        self.wrapping
+    }
+
+    pub fn counter_mode(&self) -> CounterMode {
+        //! Returns the part of the counter block which is incremented.
+
+        self.counter_mode
+    }
+
+    pub fn counter(&self) -> [u8; 16] {
+        //! Returns the counter block which will be encrypted for the next keystream block.
+        //! If a keystream block is partially used, its remaining bytes are used first.
+
+        self.counter
+    }
+
+    pub fn set_counter(&mut self, counter: [u8; 16]) {
+        //! Sets the counter block which will be encrypted for the next keystream block.
+        //! The remaining bytes of a partially used keystream block are discarded.
+        //! # Arguments
+        //! * `counter` - The counter block, e.g. previously returned by `CtrCipher::counter`.
+
+        self.counter = counter;
+        self.exhausted = false;
+        self.position = 16;
+    }
+
+    pub fn seek(&mut self, byte_offset: u64) {
+        //! Moves the cipher to the given position within the message, relative to the initial counter block.
+        //! The next processed byte is then the byte at that position, which allows random access into the data.
+        //! If the position is beyond the end of a non-wrapping counter (see `CtrCipher::new_nonce`),
+        //! processing more data returns `ModeError::CounterExhausted`.
+        //! # Arguments
+        //! * `byte_offset` - The position of the byte in the message.
+
+        let mut counter: [u8; 16] = self.initial_counter;
+        let wrapped = add_counter_part(&mut counter, self.counter_mode, (byte_offset / 16) as u128);
+
+        self.counter = counter;
+        self.exhausted = wrapped && !self.wrapping;
+        self.position = 16;
+
+        let block_offset = (byte_offset % 16) as usize;
+        if block_offset != 0 && !self.exhausted {
+            self.next_block();
+            self.position = block_offset;
+        }
+    }
+
+    pub fn apply_keystream(&mut self, data: &mut [u8]) -> Result<(), ModeError> {
+        //! Encrypts or decrypts the next part of the message in place.
+        //! If the data can't be processed whole, it is left unchanged.
+        //! # Errors
+        //! * ModeError::CounterExhausted - The counter (created with `CtrCipher::new_nonce`, or made non-wrapping
+        //!   with `CtrCipher::with_wrapping`) would wrap around before the whole data is processed.
+
+        if let Some(remaining_blocks) = self.remaining_blocks() {
+            let buffered = 16 - self.position;
+            let required_blocks = data.len().saturating_sub(buffered).div_ceil(16) as u128;
+            if required_blocks > remaining_blocks {
+                return Err(ModeError::CounterExhausted);
+            }
+        }
+
+        for byte in data {
+            if self.position == 16 {
+                self.next_block();
+            }
+            *byte ^= self.block[self.position];
+            self.position += 1;
+        }
+
+        Ok(())
+    }
+}
+
+/// Clearing the counter blocks and the keystream block of the cipher.
+#[cfg(feature = "zeroize")]
+impl Zeroize for CtrCipher {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.initial_counter);
+        zeroize(&mut self.counter);
+        zeroize(&mut self.block);
+        self.position = 16;
+        self.exhausted = false;
+    }
+}
+
+/// Functions for processing the data concurrently in CTR mode.
+impl CtrCipher {
+    pub fn apply_keystream_par(&self, data: &mut [u8], pool: &ThreadPool) -> Result<(), BatchError> {
+        //! Encrypts or decrypts the whole message in place, starting at the initial counter block.
+        //! The data is split into a chunk per thread, and the initial counter block of each chunk
+        //! is computed from its block offset, so the chunks are processed independently.
+        //! The result is the same as `CtrCipher::apply_keystream` on a reset cipher, and this cipher isn't advanced.
+        //! # Arguments
+        //! * `data` - The message to be encrypted or decrypted.
+        //! * `pool` - The thread pool running the jobs, it may be shared with other work.
+        //! # Returns
+        //! * `Result<(), BatchError>` - Ok if the data was processed, or an error.
+        //! # Errors
+        //! The data is left unchanged in case of an error.
+        //! * BatchError::EmptyPool - The thread pool has no threads.
+        //! * BatchError::CounterExhausted - The counter (created with `CtrCipher::new_nonce`, or made non-wrapping
+        //!   with `CtrCipher::with_wrapping`) would wrap around before the whole data is processed.
+        //! * BatchError::JobFailed - A job didn't return its chunk (e.g. it panicked).
+
+        if pool.size() == 0 {
+            return Err(BatchError::EmptyPool);
+        }
+        let total_blocks = data.len().div_ceil(16);
+        if let Some(remaining_blocks) = self.with_counter(self.initial_counter).remaining_blocks() {
+            if total_blocks as u128 > remaining_blocks {
+                return Err(BatchError::CounterExhausted);
+            }
+        }
+
+        // the jobs must own their data, so each chunk is copied into its job and sent back when processed
+        let chunk_len = total_blocks.div_ceil(pool.size()).max(1) * 16;
+        let (sender, receiver) = mpsc::channel::<(usize, Vec<u8>)>();
+        let chunks_count = data.len().div_ceil(chunk_len);
+        for (index, chunk) in data.chunks(chunk_len).enumerate() {
+            let mut counter: [u8; 16] = self.initial_counter;
+            add_counter_part(&mut counter, self.counter_mode, (index * chunk_len / 16) as u128);
+            let mut cipher = self.with_counter(counter);
+            let mut chunk = chunk.to_vec();
+            let sender = sender.clone();
+            pool.execute(move || {
+                cipher.apply_keystream(&mut chunk).expect("the remaining blocks were checked");
+                sender.send((index, chunk)).expect("the receiver lives until all chunks are returned");
+            }).expect("the pool isn't empty");
+        }
+        drop(sender);
+
+        write_chunks(data, chunk_len, chunks_count, receiver)
+    }
+}
+
+/// Internal functions for the CTR mode cipher.
+impl CtrCipher {
+    fn remaining_blocks(&self) -> Option<u128> {
+        //! Returns the number of keystream blocks which can still be generated, or None if unlimited.
+
+        if self.wrapping {
+            return None;
+        }
+        if self.exhausted {
+            return Some(0);
+        }
+
+        Some(match self.counter_mode {
+            CounterMode::Full128 => (u128::MAX - u128::from_be_bytes(self.counter)).saturating_add(1),
+            CounterMode::Low64 => (1 << 64) - u64::from_be_bytes(self.counter[8..].try_into().unwrap()) as u128,
+            CounterMode::Low32 => (1 << 32) - u32::from_be_bytes(self.counter[12..].try_into().unwrap()) as u128,
+        })
+    }
+
+    fn next_block(&mut self) {
+        //! Generates the next keystream block and increments the counter.
+
+        self.block = self.core.encrypt(&self.counter);
+        self.position = 0;
+
+        if increment_counter_part(&mut self.counter, self.counter_mode) && !self.wrapping {
+            self.exhausted = true;
+        }
+    }
+}
+
+/// A CTR session which rejects reused initial counter blocks.
+///
+/// Reusing a counter block with the same key reuses the keystream, which reveals the XOR of the plaintexts.
+/// The session remembers a bounded number of the most recently used counter blocks (see `IvTracker`),
+/// so it is meant to catch the misuse during testing, not to replace a sound nonce scheme.
+/// Only the initial counter blocks are compared, overlapping counter ranges of different messages aren't detected.
+#[cfg(feature = "nonce-guard")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtrSession {
+    /// The AES core used to encrypt the counter blocks.
+    core: AESCore,
+    /// The record of the used initial counter blocks.
+    tracker: IvTracker,
+}
+
+/// Public functions for the tracked CTR session.
+#[cfg(feature = "nonce-guard")]
+impl CtrSession {
+    pub fn new(core: AESCore, capacity: usize) -> Self {
+        //! Creates a new CTR session.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the counter blocks.
+        //! * `capacity` - The maximum number of counter blocks remembered.
+
+        Self {
+            core,
+            tracker: IvTracker::new(capacity),
+        }
+    }
+
+    pub fn tracker(&self) -> &IvTracker {
+        //! Returns the record of the used initial counter blocks.
+
+        &self.tracker
+    }
+
+    pub fn cipher(&mut self, counter: [u8; 16]) -> Result<CtrCipher, ModeError> {
+        //! Creates a CTR cipher for a new message (see `CtrCipher::new`).
+        //! # Arguments
+        //! * `counter` - The initial counter block of the message.
+        //! # Errors
+        //! * ModeError::ReusedIv - The counter block was already used within this session.
+
+        self.tracker.register(counter)?;
+
+        Ok(CtrCipher::new(self.core, counter))
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn ctr_encrypt_iter<I>(core: &AESCore, counter: [u8; 16], input: I) -> impl Iterator<Item = u8>
+where
+    I: IntoIterator<Item = u8>,
+{
+    //! Encrypts (or decrypts) the bytes of an iterator in CTR mode, lazily.
+    //! The keystream is produced one block at a time as the bytes are consumed,
+    //! with the counter block incremented as a 128-bit big-endian integer (the same as `CtrCipher::new`).
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the counter blocks.
+    //! * `counter` - The initial counter block. Must never be reused with the same key.
+    //! * `input` - The bytes to be encrypted or decrypted.
+    //! # Returns
+    //! * `impl Iterator<Item = u8>` - The encrypted or decrypted bytes, as many as the input has.
+
+    input
+        .into_iter()
+        .zip(KeystreamIter::new(*core, counter))
+        .map(|(byte, key)| byte ^ key)
+}
+
+fn increment_counter(counter: &mut [u8; 16]) {
+    //! Increments the counter block as a 128-bit big-endian integer, wrapping around.
+
+    *counter = u128::from_be_bytes(*counter).wrapping_add(1).to_be_bytes();
+}
+
+fn increment_counter_part(counter: &mut [u8; 16], counter_mode: CounterMode) -> bool {
+    //! Increments the selected part of the counter block as a big-endian integer, wrapping around within its width.
+    //! Returns whether the counter wrapped around.
+
+    match counter_mode {
+        CounterMode::Full128 => {
+            increment_counter(counter);
+            *counter == [0; 16]
+        }
+        CounterMode::Low64 => {
+            let low = u64::from_be_bytes(counter[8..].try_into().unwrap()).wrapping_add(1);
+            counter[8..].copy_from_slice(&low.to_be_bytes());
+            low == 0
+        }
+        CounterMode::Low32 => {
+            let low = u32::from_be_bytes(counter[12..].try_into().unwrap()).wrapping_add(1);
+            counter[12..].copy_from_slice(&low.to_be_bytes());
+            low == 0
+        }
+    }
+}
+
+fn write_chunks(data: &mut [u8], chunk_len: usize, chunks_count: usize, chunks: impl IntoIterator<Item = (usize, Vec<u8>)>) -> Result<(), BatchError> {
+    //! Writes the processed chunks back into the data, but only once all of them are returned,
+    //! so a failed job can't leave a part of the data unprocessed.
+    //! # Errors
+    //! * BatchError::JobFailed - Fewer chunks than `chunks_count` were returned, the data is unchanged.
+
+    let chunks: Vec<(usize, Vec<u8>)> = chunks.into_iter().collect();
+    if chunks.len() != chunks_count {
+        return Err(BatchError::JobFailed);
+    }
+
+    for (index, chunk) in chunks {
+        data[(index * chunk_len)..][..chunk.len()].copy_from_slice(&chunk);
+    }
+    Ok(())
+}
+
+fn add_counter_part(counter: &mut [u8; 16], counter_mode: CounterMode, blocks: u128) -> bool {
+    //! Adds the number of blocks to the selected part of the counter block, wrapping around within its width.
+    //! Returns whether the counter wrapped around.
+
+    match counter_mode {
+        CounterMode::Full128 => {
+            let (sum, wrapped) = u128::from_be_bytes(*counter).overflowing_add(blocks);
+            *counter = sum.to_be_bytes();
+            wrapped
+        }
+        CounterMode::Low64 => {
+            let sum = u64::from_be_bytes(counter[8..].try_into().unwrap()) as u128 + blocks;
+            counter[8..].copy_from_slice(&(sum as u64).to_be_bytes());
+            sum >> 64 != 0
+        }
+        CounterMode::Low32 => {
+            let sum = u32::from_be_bytes(counter[12..].try_into().unwrap()) as u128 + blocks;
+            counter[12..].copy_from_slice(&(sum as u32).to_be_bytes());
+            sum >> 32 != 0
+        }
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    const KEY: [u8; 16] = [
+        0x2b, 0x7e, 0x15, 0x16,
+        0x28, 0xae, 0xd2, 0xa6,
+        0xab, 0xf7, 0x15, 0x88,
+        0x09, 0xcf, 0x4f, 0x3c,
+    ];
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn keystream() {
+        //! Test that the keystream consists of the encrypted counter blocks
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let counter: [u8; 16] = [
+            0xf0, 0xf1, 0xf2, 0xf3,
+            0xf4, 0xf5, 0xf6, 0xf7,
+            0xf8, 0xf9, 0xfa, 0xfb,
+            0xfc, 0xfd, 0xfe, 0xff,
+        ];
+
+        let keystream: Vec<u8> = KeystreamIter::new(core, counter).take(40).collect();
+
+        let mut next_counter = counter;
+        next_counter[15] = 0x00;
+        next_counter[14] = 0xff;
+        let mut last_counter = next_counter;
+        last_counter[15] = 0x01;
+
+        assert_eq!(keystream[..16], core.encrypt(&counter));
+        assert_eq!(keystream[16..32], core.encrypt(&next_counter));
+        assert_eq!(keystream[32..], core.encrypt(&last_counter)[..8]);
+
+        // NIST SP 800-38A, F.5.1 CTR-AES128.Encrypt, first block
+        let plaintext: [u8; 16] = [0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17, 0x2a];
+        let ciphertext: [u8; 16] = [0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d, 0xb6, 0xce];
+        let encrypted: Vec<u8> = plaintext.iter().zip(KeystreamIter::new(core, counter)).map(|(p, k)| p ^ k).collect();
+        assert_eq!(encrypted, ciphertext);
+    }
+
+    #[test]
+    fn keystream_wraparound() {
+        //! Test that the counter wraps around as a 128-bit big-endian integer
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+
+        let keystream: Vec<u8> = KeystreamIter::new(core, [0xff; 16]).take(48).collect();
+        assert_eq!(keystream[..16], core.encrypt(&[0xff; 16]));
+        assert_eq!(keystream[16..32], core.encrypt(&[0x00; 16]));
+        let mut one: [u8; 16] = [0; 16];
+        one[15] = 1;
+        assert_eq!(keystream[32..], core.encrypt(&one));
+
+        let mut counter: [u8; 16] = [0; 16];
+        counter[8..].fill(0xff);
+        increment_counter(&mut counter);
+        let mut expected: [u8; 16] = [0; 16];
+        expected[7] = 1;
+        assert_eq!(counter, expected);
+    }
+
+    #[test]
+    fn rfc3686() {
+        //! Test the nonce/IV/counter layout with the RFC 3686 test vectors
+
+        let vectors: [(&str, &str, &str, &str, &str); 4] = [
+            (
+                "ae6852f8121067cc4bf7a5765577f39e", "00000030", "0000000000000000",
+                "53696e676c6520626c6f636b206d7367",
+                "e4095d4fb7a7b3792d6175a3261311b8",
+            ),
+            (
+                "7e24067817fae0d743d6ce1f32539163", "006cb6db", "c0543b59da48d90b",
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "5104a106168a72d9790d41ee8edad388eb2e1efc46da57c8fce630df9141be28",
+            ),
+            (
+                "7691be035e5020a8ac6e618529f9a0dc", "00e0017b", "27777f3f4a1786f0",
+                "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20212223",
+                "c1cf48a89f2ffdd9cf4652e9efdb72d74540a42bde6d7836d59a5ceaaef3105325b2072f",
+            ),
+            (
+                "776beff2851db06f4c8a0542c8696f6c6a81af1eec96b4d37fc1d689e6c1c104", "00000060", "db5672c97aa8f0b2",
+                "53696e676c6520626c6f636b206d7367",
+                "145ad01dbf824ec7560863dc71e3e0c0",
+            ),
+        ];
+
+        for (key, nonce, iv, plaintext, ciphertext) in vectors {
+            let key = hex(key);
+            let core = AESCore::new(match key.len() {
+                16 => AESKey::AES128(key.try_into().unwrap()),
+                _ => AESKey::AES256(key.try_into().unwrap()),
+            });
+            let nonce: [u8; 4] = hex(nonce).try_into().unwrap();
+            let iv: [u8; 8] = hex(iv).try_into().unwrap();
+
+            let mut data = hex(plaintext);
+            CtrCipher::new_nonce(core, nonce, iv).apply_keystream(&mut data).unwrap();
+            assert_eq!(data, hex(ciphertext));
+
+            // processing the data in parts gives the same result
+            let mut cipher = CtrCipher::new_nonce(core, nonce, iv);
+            for chunk in data.chunks_mut(5) {
+                cipher.apply_keystream(chunk).unwrap();
+            }
+            assert_eq!(data, hex(plaintext));
+        }
+    }
+
+    #[test]
+    fn counter_exhausted() {
+        //! Test that the 32-bit counter refuses to wrap around
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut cipher = CtrCipher::new_nonce(core, [0xaa; 4], [0xbb; 8]);
+        cipher.counter[12..].copy_from_slice(&0xffff_fffe_u32.to_be_bytes());
+
+        // only 2 blocks are left
+        let mut data: [u8; 33] = [0; 33];
+        assert_eq!(cipher.apply_keystream(&mut data), Err(ModeError::CounterExhausted));
+        assert_eq!(data, [0; 33]);
+
+        let mut data: [u8; 20] = [0; 20];
+        cipher.apply_keystream(&mut data).unwrap();
+        let mut last_counter: [u8; 16] = [0xaa, 0xaa, 0xaa, 0xaa, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xbb, 0xff, 0xff, 0xff, 0xff];
+        assert_eq!(data[16..], core.encrypt(&last_counter)[..4]);
+
+        let mut data: [u8; 12] = [0; 12];
+        cipher.apply_keystream(&mut data).unwrap();
+        assert_eq!(data, core.encrypt(&last_counter)[4..]);
+
+        let mut data: [u8; 1] = [0; 1];
+        assert_eq!(cipher.apply_keystream(&mut data), Err(ModeError::CounterExhausted));
+        cipher.apply_keystream(&mut []).unwrap();
+
+        // the 128-bit counter wraps around instead
+        last_counter = [0xff; 16];
+        let mut cipher = CtrCipher::new(core, last_counter);
+        let mut data: [u8; 32] = [0; 32];
+        cipher.apply_keystream(&mut data).unwrap();
+        assert_eq!(data[..16], core.encrypt(&last_counter));
+        assert_eq!(data[16..], core.encrypt(&[0; 16]));
+    }
+
+    #[test]
+    fn reuse_core_with_new_counter() {
+        //! Test that encrypting two messages with a reset counter matches two fresh ciphers
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let plaintext: [u8; 40] = [0x3c; 40];
+
+        let mut cipher = CtrCipher::new_nonce(core, [0x01; 4], [0x02; 8]);
+        let mut first = plaintext;
+        cipher.apply_keystream(&mut first[..21]).unwrap();
+
+        let mut counter: [u8; 16] = [0x01; 16];
+        counter[12..].copy_from_slice(&1_u32.to_be_bytes());
+        let mut second = plaintext;
+        let mut next_cipher = cipher.with_counter(counter);
+        assert_eq!(next_cipher.counter_mode(), CounterMode::Low32);
+        next_cipher.apply_keystream(&mut second).unwrap();
+
+        let mut expected = plaintext;
+        CtrCipher::new_nonce(core, [0x01; 4], [0x01; 8]).apply_keystream(&mut expected).unwrap();
+        assert_eq!(second, expected);
+
+        // a reset cipher matches a fresh one
+        next_cipher.reset();
+        assert_eq!(next_cipher, CtrCipher::new_nonce(core, [0x01; 4], [0x01; 8]));
+        next_cipher.apply_keystream(&mut second).unwrap();
+        assert_eq!(second, plaintext);
+    }
+
+    #[test]
+    #[cfg(feature = "nonce-guard")]
+    fn session_rejects_reused_counter() {
+        //! Test that the tracked session rejects a reused counter block and accepts distinct ones
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut session = CtrSession::new(core, 16);
+
+        let mut data: [u8; 20] = [0x42; 20];
+        session.cipher([0; 16]).unwrap().apply_keystream(&mut data).unwrap();
+        let mut expected: [u8; 20] = [0x42; 20];
+        CtrCipher::new(core, [0; 16]).apply_keystream(&mut expected).unwrap();
+        assert_eq!(data, expected);
+
+        assert_eq!(session.cipher([0; 16]), Err(ModeError::ReusedIv));
+        for i in 1..8 {
+            assert!(session.cipher([i; 16]).is_ok());
+        }
+        assert_eq!(session.cipher([7; 16]), Err(ModeError::ReusedIv));
+        assert_eq!(session.tracker().len(), 8);
+    }
+
+    #[test]
+    fn seek() {
+        //! Test that seeking to arbitrary positions matches sequential processing
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let plaintext: Vec<u8> = (0..=255).collect();
+
+        for cipher in [
+            CtrCipher::new(core, [0xff; 16]),
+            CtrCipher::with_counter_mode(core, [0xfe; 16], CounterMode::Low64),
+            CtrCipher::new_nonce(core, [1; 4], [2; 8]),
+        ] {
+            let mut ciphertext = plaintext.clone();
+            let mut sequential = cipher;
+            sequential.apply_keystream(&mut ciphertext).unwrap();
+
+            for offset in [0, 1, 15, 16, 17, 31, 32, 100, 255] {
+                let mut seeking = cipher;
+                seeking.apply_keystream(&mut [0; 40]).unwrap();
+                seeking.seek(offset as u64);
+
+                let mut data = ciphertext[offset..].to_vec();
+                seeking.apply_keystream(&mut data).unwrap();
+                assert_eq!(data, plaintext[offset..]);
+            }
+
+            // the counter block can be saved and restored at a block boundary
+            let mut first = cipher;
+            let mut data = ciphertext.clone();
+            first.apply_keystream(&mut data[..64]).unwrap();
+            let mut second = cipher;
+            second.set_counter(first.counter());
+            second.apply_keystream(&mut data[64..]).unwrap();
+            assert_eq!(data, plaintext);
+        }
+
+        // seeking past the end of a non-wrapping counter
+        let mut cipher = CtrCipher::new_nonce(core, [1; 4], [2; 8]);
+        cipher.seek(((1 << 32) - 2) * 16 + 5);
+        assert_eq!(cipher.apply_keystream(&mut [0; 11]), Ok(()));
+        assert_eq!(cipher.apply_keystream(&mut [0; 1]), Err(ModeError::CounterExhausted));
+        cipher.seek((1 << 32) * 16);
+        assert_eq!(cipher.apply_keystream(&mut [0; 1]), Err(ModeError::CounterExhausted));
+        cipher.seek(0);
+        assert_eq!(cipher.apply_keystream(&mut [0; 1]), Ok(()));
+    }
+
+    #[test]
+    fn counter_modes() {
+        //! Test that each counter mode increments and wraps around only its part of the counter block
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut before_wrap: [u8; 16] = [0x5c; 16];
+
+        for (counter_mode, low_len) in [(CounterMode::Full128, 16), (CounterMode::Low64, 8), (CounterMode::Low32, 4)] {
+            before_wrap[(16 - low_len)..].fill(0xff);
+            let mut after_wrap = before_wrap;
+            after_wrap[(16 - low_len)..].fill(0x00);
+            let mut after_one = after_wrap;
+            after_one[15] = 0x01;
+
+            // the byte just above the counter isn't carried into
+            let mut counter = before_wrap;
+            assert!(increment_counter_part(&mut counter, counter_mode));
+            assert_eq!(counter, after_wrap);
+            assert!(!increment_counter_part(&mut counter, counter_mode));
+            assert_eq!(counter, after_one);
+
+            let mut counter = before_wrap;
+            counter[15] = 0xfe;
+            assert!(!increment_counter_part(&mut counter, counter_mode));
+            assert_eq!(counter, before_wrap);
+
+            let mut cipher = CtrCipher::with_counter_mode(core, before_wrap, counter_mode);
+            assert_eq!(cipher.counter_mode(), counter_mode);
+            let mut data: [u8; 40] = [0; 40];
+            cipher.apply_keystream(&mut data).unwrap();
+            assert_eq!(data[..16], core.encrypt(&before_wrap));
+            assert_eq!(data[16..32], core.encrypt(&after_wrap));
+            assert_eq!(data[32..], core.encrypt(&after_one)[..8]);
+        }
+
+        assert_eq!(CtrCipher::new(core, [0; 16]).counter_mode(), CounterMode::Full128);
+        assert_eq!(CtrCipher::new_nonce(core, [0; 4], [0; 8]).counter_mode(), CounterMode::Low32);
+    }
+
+    #[test]
+    fn encrypt_iter() {
+        //! Test that encrypting an iterator gives the same result as the slice-based CTR
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let counter: [u8; 16] = [0xf0; 16];
+
+        for len in [0, 1, 15, 16, 17, 64, 100] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut expected = plaintext.clone();
+            CtrCipher::new(core, counter).apply_keystream(&mut expected).unwrap();
+
+            let encrypted: Vec<u8> = ctr_encrypt_iter(&core, counter, plaintext.iter().copied()).collect();
+            assert_eq!(encrypted, expected);
+
+            let decrypted: Vec<u8> = ctr_encrypt_iter(&core, counter, encrypted).collect();
+            assert_eq!(decrypted, plaintext);
+        }
+
+        // composes with other combinators, and only the consumed bytes are processed
+        let encrypted: Vec<u8> = ctr_encrypt_iter(&core, counter, (0..).map(|i: u32| i as u8)).skip(10).take(30).collect();
+        let mut expected: Vec<u8> = (0..40).collect();
+        CtrCipher::new(core, counter).apply_keystream(&mut expected).unwrap();
+        assert_eq!(encrypted, expected[10..]);
+    }
+
+    #[test]
+    fn non_wrapping() {
+        //! Test that a non-wrapping counter returns an error at the exact block where it would wrap around
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+
+        for (counter_mode, low_len) in [(CounterMode::Full128, 16), (CounterMode::Low64, 8), (CounterMode::Low32, 4)] {
+            // 3 blocks are left before the counter wraps around
+            let mut counter: [u8; 16] = [0x5c; 16];
+            counter[(16 - low_len)..].fill(0xff);
+            counter[15] = 0xfd;
+
+            let wrapping = CtrCipher::with_counter_mode(core, counter, counter_mode);
+            assert!(wrapping.wrapping());
+            let mut cipher = wrapping.with_wrapping(false);
+            assert!(!cipher.wrapping());
+
+            let mut data: [u8; 49] = [0; 49];
+            assert_eq!(cipher.apply_keystream(&mut data), Err(ModeError::CounterExhausted));
+            assert_eq!(data, [0; 49]);
+
+            let mut expected: [u8; 48] = [0; 48];
+            let mut sequential = wrapping;
+            sequential.apply_keystream(&mut expected).unwrap();
+            let mut data: [u8; 48] = [0; 48];
+            cipher.apply_keystream(&mut data[..40]).unwrap();
+            cipher.apply_keystream(&mut data[40..]).unwrap();
+            assert_eq!(data, expected);
+            assert_eq!(cipher.apply_keystream(&mut [0; 1]), Err(ModeError::CounterExhausted));
+
+            // the wrapping cipher continues with the wrapped counter
+            let mut wrapping = wrapping;
+            wrapping.apply_keystream(&mut [0; 49]).unwrap();
+        }
+
+        // the cipher created with new_nonce can be made wrapping
+        let cipher = CtrCipher::new_nonce(core, [1; 4], [2; 8]);
+        assert!(!cipher.wrapping());
+        assert!(cipher.with_wrapping(true).wrapping());
+    }
+
+    #[test]
+    fn apply_keystream_par() {
+        //! Test that the keystream applied concurrently matches the serial CTR mode
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+
+        for threads in [1, 3, 4] {
+            let pool = ThreadPool::new(threads).unwrap();
+
+            for cipher in [
+                CtrCipher::new(core, [0xff; 16]),
+                CtrCipher::with_counter_mode(core, [0xfe; 16], CounterMode::Low64),
+                CtrCipher::new_nonce(core, [1; 4], [2; 8]),
+            ] {
+                // sizes which are and aren't evenly divisible among the threads
+                for len in [0, 1, 16, 17, 48, 100, 4096, 100_003] {
+                    let plaintext: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+                    let mut expected = plaintext.clone();
+                    let mut serial = cipher;
+                    serial.apply_keystream(&mut expected).unwrap();
+
+                    // the position of the cipher doesn't matter
+                    let mut parallel = cipher;
+                    parallel.apply_keystream(&mut [0; 21]).unwrap();
+                    let mut data = plaintext.clone();
+                    parallel.apply_keystream_par(&mut data, &pool).unwrap();
+                    assert_eq!(data, expected);
+                }
+            }
+        }
+
+        let pool = ThreadPool::new(2).unwrap();
+        // only 1 block is left
+        let cipher = CtrCipher::new(core, [0xff; 16]).with_wrapping(false);
+        let mut data: [u8; 17] = [0; 17];
+        assert_eq!(cipher.apply_keystream_par(&mut data, &pool), Err(BatchError::CounterExhausted));
+        assert_eq!(data, [0; 17]);
+        cipher.apply_keystream_par(&mut data[..16], &pool).unwrap();
+
+        let mut empty_pool = ThreadPool::new(1).unwrap();
+        empty_pool.join();
+        assert_eq!(cipher.apply_keystream_par(&mut data, &empty_pool), Err(BatchError::EmptyPool));
+
+        // a job which panics never returns its chunk, so nothing is written
+        let mut data: [u8; 48] = [0; 48];
+        let chunks = vec![(0, vec![1; 16]), (2, vec![3; 16])];
+        assert_eq!(write_chunks(&mut data, 16, 3, chunks.clone()), Err(BatchError::JobFailed));
+        assert_eq!(data, [0; 48]);
+        write_chunks(&mut data, 16, 2, chunks).unwrap();
+        assert_eq!(data[32..], [3; 16]);
+        assert_eq!(BatchError::JobFailed.to_string(), "job failed to return its data");
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        //! Test that clearing the cipher overwrites the counter blocks and the keystream, and resets the cipher
+
+        let core = AESCore::new(AESKey::AES128(KEY));
+        let mut cipher = CtrCipher::new(core, [0xf0; 16]);
+        cipher.apply_keystream(&mut [0; 20]).unwrap();
+
+        cipher.zeroize();
+        assert_eq!(cipher.initial_counter, [0; 16]);
+        assert_eq!(cipher.counter, [0; 16]);
+        assert_eq!(cipher.block, [0; 16]);
+        assert_eq!(cipher, CtrCipher::new(core, [0; 16]));
+
+        // an exhausted non-wrapping cipher is reset too, keeping its counter mode and wrapping
+        let mut cipher = CtrCipher::new_nonce(core, [0xf0; 4], [0x0f; 8]).with_counter([0xff; 16]);
+        cipher.apply_keystream(&mut [0; 16]).unwrap();
+        assert_eq!(cipher.apply_keystream(&mut [0; 1]), Err(ModeError::CounterExhausted));
+        cipher.zeroize();
+        assert_eq!(cipher, CtrCipher::new_nonce(core, [0; 4], [0; 8]).with_counter([0; 16]));
+    }
+}
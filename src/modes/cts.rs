@@ -0,0 +1,220 @@
+//! A module containing the CBC mode with ciphertext stealing (CBC-CS3, as used in Kerberos, RFC 3962).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::{
+    AESCore,
+    LenError,
+};
+use crate::modes::{
+    CbcDecryptor,
+    CbcEncryptor,
+};
+
+
+
+
+
+// STRUCTS
+
+/// The CBC mode with ciphertext stealing, variant CS3 (NIST SP 800-38A Addendum).
+/// The ciphertext is as long as the plaintext, so no padding is needed for data which isn't a multiple of 16 bytes.
+/// The last two ciphertext blocks are always swapped (also for data which is a multiple of 16 bytes),
+/// and the last block is truncated to the length of the last plaintext block.
+/// A single block is encrypted the same as in CBC mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CbcCts {
+    /// The AES core used to encrypt the blocks.
+    core: AESCore,
+}
+
+/// Public functions for encrypting and decrypting data in CBC-CS3 mode.
+impl CbcCts {
+    pub fn new(core: AESCore) -> Self {
+        //! Creates a new CBC-CS3 cipher.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the blocks.
+
+        Self {
+            core,
+        }
+    }
+
+    pub fn encrypt(&self, iv: [u8; 16], plaintext: &[u8]) -> Result<Vec<u8>, LenError> {
+        //! Encrypts the plaintext.
+        //! # Arguments
+        //! * `iv` - The initialization vector. Should be unpredictable and never reused with the same key.
+        //! * `plaintext` - The data to be encrypted, at least 16 bytes long.
+        //! # Returns
+        //! * `Result<Vec<u8>, LenError>` - The ciphertext (as long as the plaintext) or an error.
+        //! # Errors
+        //! * LenError::TooShort - The plaintext is shorter than 16 bytes.
+
+        if plaintext.len() < 16 {
+            return Err(LenError::TooShort);
+        }
+
+        // CBC over the plaintext, the last partial block padded with zeros
+        let last_len = last_block_len(plaintext.len());
+        let mut ciphertext = plaintext.to_vec();
+        ciphertext.resize(plaintext.len() + 16 - last_len, 0);
+        let (blocks, _) = ciphertext.as_chunks_mut::<16>();
+        CbcEncryptor::new(self.core, iv).encrypt_blocks(blocks);
+
+        // swap the last two blocks, and truncate the last one
+        if blocks.len() > 1 {
+            let count = blocks.len();
+            blocks.swap(count - 2, count - 1);
+        }
+        ciphertext.truncate(plaintext.len());
+
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&self, iv: [u8; 16], ciphertext: &[u8]) -> Result<Vec<u8>, LenError> {
+        //! Decrypts the ciphertext.
+        //! # Arguments
+        //! * `iv` - The initialization vector used for encryption.
+        //! * `ciphertext` - The data to be decrypted, at least 16 bytes long.
+        //! # Returns
+        //! * `Result<Vec<u8>, LenError>` - The plaintext (as long as the ciphertext) or an error.
+        //! # Errors
+        //! * LenError::TooShort - The ciphertext is shorter than 16 bytes.
+
+        if ciphertext.len() < 16 {
+            return Err(LenError::TooShort);
+        }
+
+        let mut plaintext = ciphertext.to_vec();
+        if plaintext.len() == 16 {
+            let (blocks, _) = plaintext.as_chunks_mut::<16>();
+            CbcDecryptor::new(self.core, iv).decrypt_blocks(blocks);
+            return Ok(plaintext);
+        }
+
+        // all blocks before the last two are plain CBC
+        let last_len = last_block_len(plaintext.len());
+        let head_len = plaintext.len() - 16 - last_len;
+        let previous_block: [u8; 16] = if head_len == 0 {
+            iv
+        } else {
+            plaintext[(head_len - 16)..head_len].try_into().unwrap()
+        };
+        let (head, tail) = plaintext.split_at_mut(head_len);
+        CbcDecryptor::new(self.core, iv).decrypt_blocks(head.as_chunks_mut::<16>().0);
+
+        // the full block is the encrypted (zero padded) last plaintext block,
+        // whose decryption also holds the stolen end of the second to last ciphertext block
+        let (full_block, last_block) = tail.split_at_mut(16);
+        let decrypted = self.core.decrypt(&(*full_block).try_into().unwrap());
+
+        let mut second_to_last: [u8; 16] = decrypted;
+        second_to_last[..last_len].copy_from_slice(last_block);
+        for i in 0..last_len {
+            last_block[i] ^= decrypted[i];
+        }
+
+        let mut second_to_last_plaintext = self.core.decrypt(&second_to_last);
+        for i in 0..16 {
+            second_to_last_plaintext[i] ^= previous_block[i];
+        }
+        full_block.copy_from_slice(&second_to_last_plaintext);
+
+        Ok(plaintext)
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+fn last_block_len(len: usize) -> usize {
+    //! Returns the length of the last (possibly partial) block, from 1 to 16 bytes.
+
+    (len - 1) % 16 + 1
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn rfc3962() {
+        //! Test with the RFC 3962 test vectors (AES-128 with ciphertext stealing, Appendix B)
+
+        let cts = CbcCts::new(AESCore::new(AESKey::AES128(*b"chicken teriyaki")));
+        let plaintext = hex(
+            "4920776f756c64206c696b65207468652047656e6572616c20476175277320436869636b656e2c20706c656173652c\
+             20616e6420776f6e746f6e20736f75702e"
+        );
+
+        let vectors: [(usize, &str); 6] = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (31, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5"),
+            (32, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584"),
+            (47, "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e39312523a78662d5be7fcbcc98ebf5"),
+            (48, "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd839312523a78662d5be7fcbcc98ebf5a8"),
+            (
+                64,
+                "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+                 4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+            ),
+        ];
+
+        for (len, ciphertext) in vectors {
+            let ciphertext = hex(ciphertext);
+            assert_eq!(cts.encrypt([0; 16], &plaintext[..len]).unwrap(), ciphertext);
+            assert_eq!(cts.decrypt([0; 16], &ciphertext).unwrap(), plaintext[..len]);
+        }
+    }
+
+    #[test]
+    fn cts_lengths() {
+        //! Test the round trip for all lengths, a single block being the same as CBC, and the too short data
+
+        let core = AESCore::new(AESKey::AES256([0x5c; 32]));
+        let cts = CbcCts::new(core);
+        let iv: [u8; 16] = [0xa3; 16];
+        let plaintext: Vec<u8> = (0..100).collect();
+
+        for len in 16..=plaintext.len() {
+            let ciphertext = cts.encrypt(iv, &plaintext[..len]).unwrap();
+            assert_eq!(ciphertext.len(), len);
+            assert_eq!(cts.decrypt(iv, &ciphertext).unwrap(), plaintext[..len]);
+        }
+
+        let mut block: [u8; 16] = plaintext[..16].try_into().unwrap();
+        for i in 0..16 {
+            block[i] ^= iv[i];
+        }
+        assert_eq!(cts.encrypt(iv, &plaintext[..16]).unwrap(), core.encrypt(&block));
+
+        assert_eq!(cts.encrypt(iv, &plaintext[..15]), Err(LenError::TooShort));
+        assert_eq!(cts.decrypt(iv, &[]), Err(LenError::TooShort));
+    }
+}
@@ -0,0 +1,166 @@
+//! A module containing the Output Feedback (OFB) mode of operation.
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::AESCore;
+
+#[cfg(feature = "rand")]
+use crate::modes::random_iv;
+#[cfg(feature = "rand")]
+use rand_core::CryptoRng;
+
+#[cfg(feature = "zeroize")]
+use crate::util::{
+    zeroize,
+    Zeroize,
+};
+
+
+
+
+
+// STRUCTS
+
+/// The OFB mode cipher.
+/// Encryption and decryption are the same operation, XORing the data with the keystream,
+/// which is produced by repeatedly encrypting the IV.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OfbCipher {
+    /// The AES core used to produce the keystream.
+    core: AESCore,
+    /// The initialization vector of the message.
+    iv: [u8; 16],
+    /// The current keystream block (the IV before the first block is produced).
+    block: [u8; 16],
+    /// The position of the next byte in the current keystream block.
+    position: usize,
+}
+
+/// Public functions for encrypting and decrypting data in OFB mode.
+impl OfbCipher {
+    pub fn new(core: AESCore, iv: [u8; 16]) -> Self {
+        //! Creates a new OFB cipher.
+        //! # Arguments
+        //! * `core` - The AES core used to produce the keystream.
+        //! * `iv` - The initialization vector. Must never be reused with the same key.
+
+        Self {
+            core,
+            iv,
+            block: iv,
+            position: 16,
+        }
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn new_random_iv<R: CryptoRng + ?Sized>(core: AESCore, rng: &mut R) -> (Self, [u8; 16]) {
+        //! Creates a new OFB cipher with a random IV.
+        //! # Arguments
+        //! * `core` - The AES core used to produce the keystream.
+        //! * `rng` - The cryptographically secure random number generator.
+        //! # Returns
+        //! * `(OfbCipher, [u8; 16])` - The cipher and the IV, which must be sent along with the ciphertext.
+
+        let iv: [u8; 16] = random_iv(rng);
+        (Self::new(core, iv), iv)
+    }
+
+    pub fn with_iv(&self, iv: [u8; 16]) -> Self {
+        //! Creates a new cipher for another message with the same key.
+        //! The expanded key is reused, so the key schedule isn't computed again.
+        //! # Arguments
+        //! * `iv` - The initialization vector of the new message.
+
+        Self::new(self.core, iv)
+    }
+
+    pub fn reset(&mut self) {
+        //! Resets the cipher back to the initial IV, keeping the expanded key.
+        //! The same message can then be processed again. Encrypting a different message after the reset
+        //! reuses the keystream, so use `OfbCipher::with_iv` for new messages instead.
+
+        *self = Self::new(self.core, self.iv);
+    }
+
+    pub fn apply_keystream(&mut self, data: &mut [u8]) {
+        //! Encrypts or decrypts the next part of the message in place.
+
+        for byte in data {
+            if self.position == 16 {
+                self.core.encrypt_in_place(&mut self.block);
+                self.position = 0;
+            }
+            *byte ^= self.block[self.position];
+            self.position += 1;
+        }
+    }
+}
+
+/// Clearing the IV and the keystream block of the cipher.
+#[cfg(feature = "zeroize")]
+impl Zeroize for OfbCipher {
+    fn zeroize(&mut self) {
+        zeroize(&mut self.iv);
+        zeroize(&mut self.block);
+        self.position = 16;
+    }
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn ofb() {
+        //! Test with the NIST SP 800-38A F.4.1 OFB-AES128 vectors, processed in uneven parts
+
+        let core = AESCore::new(AESKey::AES128(hex("2b7e151628aed2a6abf7158809cf4f3c").try_into().unwrap()));
+        let iv: [u8; 16] = hex("000102030405060708090a0b0c0d0e0f").try_into().unwrap();
+        let plaintext = hex(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+        );
+        let ciphertext = hex(
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e"
+        );
+
+        let mut data = plaintext.clone();
+        let mut cipher = OfbCipher::new(core, iv);
+        let (first, rest) = data.split_at_mut(7);
+        cipher.apply_keystream(first);
+        cipher.apply_keystream(rest);
+        assert_eq!(data, ciphertext);
+
+        cipher.reset();
+        cipher.apply_keystream(&mut data);
+        assert_eq!(data, plaintext);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize() {
+        //! Test that clearing the cipher overwrites the IV and the keystream, and resets the cipher
+
+        let mut cipher = OfbCipher::new(AESCore::new(AESKey::AES128([0x2b; 16])), [0x01; 16]);
+        cipher.apply_keystream(&mut [0; 20]);
+        cipher.zeroize();
+        assert_eq!((cipher.iv, cipher.block, cipher.position), ([0; 16], [0; 16], 16));
+        assert_eq!(cipher, OfbCipher::new(AESCore::new(AESKey::AES128([0x2b; 16])), [0; 16]));
+    }
+}
@@ -0,0 +1,436 @@
+//! A module containing the AES-OCB3 authenticated encryption (RFC 7253).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aead::verify_or_wipe;
+use crate::aes_core::AESCore;
+use crate::gcm::AeadError;
+use crate::mac::double;
+use crate::util::blocks_mut;
+
+
+
+
+
+// STRUCTS
+
+/// The AES-OCB3 authenticated encryption.
+/// Each block is encrypted once, with an offset derived from the nonce and the block index,
+/// so the authentication comes almost for free (a checksum of the plaintext blocks).
+/// The nonce must never be reused with the same key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ocb {
+    /// The AES core used for encryption.
+    core: AESCore,
+    /// The length of the tag in bytes.
+    tag_len: usize,
+    /// L_* (the encrypted zero block), used for the final partial block.
+    l_star: [u8; 16],
+    /// L_$ (L_* doubled), used for the tag.
+    l_dollar: [u8; 16],
+    /// L_i (L_$ doubled i + 1 times), used for the offsets of the full blocks.
+    l: [[u8; 16]; 64],
+}
+
+/// Public functions for authenticated encryption with AES-OCB3.
+impl Ocb {
+    pub fn new(core: AESCore, tag_len: usize) -> Self {
+        //! Creates a new AES-OCB3 instance.
+        //! # Arguments
+        //! * `core` - The AES core used for encryption.
+        //! * `tag_len` - The length of the tag in bytes (16 is recommended).
+        //! # Panics
+        //! * If the tag length is not between 1 and 16 bytes.
+
+        assert!((1..=16).contains(&tag_len), "the tag length must be between 1 and 16 bytes");
+
+        let l_star: [u8; 16] = core.encrypt(&[0; 16]);
+        let l_dollar: [u8; 16] = double(&l_star);
+        let mut l: [[u8; 16]; 64] = [[0; 16]; 64];
+        l[0] = double(&l_dollar);
+        for i in 1..64 {
+            l[i] = double(&l[i - 1]);
+        }
+
+        Self {
+            core,
+            tag_len,
+            l_star,
+            l_dollar,
+            l,
+        }
+    }
+
+    pub fn tag_len(&self) -> usize {
+        //! Returns the length of the tag in bytes.
+
+        self.tag_len
+    }
+
+    pub fn encrypt(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts and authenticates the plaintext, and authenticates the associated data.
+        //! # Arguments
+        //! * `nonce` - The nonce (1 to 15 bytes, 12 is recommended), unique for each message.
+        //! * `aad` - The associated data, authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The ciphertext followed by the tag, or an error.
+        //! # Errors
+        //! * AeadError::InvalidNonceLength - The nonce is empty or longer than 15 bytes.
+
+        let mut offset: [u8; 16] = self.initial_offset(nonce)?;
+        let mut checksum: [u8; 16] = [0; 16];
+
+        let mut output = Vec::with_capacity(plaintext.len() + self.tag_len);
+        let mut blocks = plaintext.chunks_exact(16);
+        for (i, block) in (&mut blocks).enumerate() {
+            xor_into(&mut offset, &self.l[(i + 1).trailing_zeros() as usize]);
+            let mut buffer: [u8; 16] = block.try_into().unwrap();
+            xor_into(&mut checksum, &buffer);
+            xor_into(&mut buffer, &offset);
+            let mut encrypted = self.core.encrypt(&buffer);
+            xor_into(&mut encrypted, &offset);
+            output.extend_from_slice(&encrypted);
+        }
+
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            xor_into(&mut offset, &self.l_star);
+            let pad = self.core.encrypt(&offset);
+            output.extend(remainder.iter().zip(pad).map(|(byte, pad)| byte ^ pad));
+            xor_into(&mut checksum, &pad_block(remainder));
+        }
+
+        let tag = self.tag(&checksum, &offset, aad);
+        output.extend_from_slice(&tag[..self.tag_len]);
+        Ok(output)
+    }
+
+    pub fn decrypt(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Decrypts the ciphertext and verifies the tag in constant time.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `ciphertext` - The ciphertext followed by the tag.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidNonceLength - The nonce is empty or longer than 15 bytes.
+        //! * AeadError::InvalidLength - The ciphertext is shorter than the tag.
+        //! * AeadError::AuthenticationFailed - The tag doesn't match.
+
+        if ciphertext.len() < self.tag_len {
+            return Err(AeadError::InvalidLength);
+        }
+        let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - self.tag_len);
+
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt_in_place_detached(nonce, aad, &mut plaintext, tag)?;
+        Ok(plaintext)
+    }
+
+    pub fn decrypt_in_place_detached(&self, nonce: &[u8], aad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<(), AeadError> {
+        //! Decrypts the ciphertext in place and verifies the detached tag in constant time.
+        //! The checksum in the tag is computed over the plaintext, so the buffer is decrypted before the verification.
+        //! If the tag doesn't match, the buffer is wiped (filled with zeros), so no unverified plaintext is left in it.
+        //! # Arguments
+        //! * `nonce` - The nonce used for encryption.
+        //! * `aad` - The associated data used for encryption.
+        //! * `buffer` - The ciphertext, replaced with the plaintext (or zeros on failure).
+        //! * `tag` - The tag, `Ocb::tag_len` bytes long.
+        //! # Errors
+        //! * AeadError::InvalidNonceLength - The nonce is empty or longer than 15 bytes.
+        //! * AeadError::InvalidLength - The tag isn't `Ocb::tag_len` bytes long.
+        //! * AeadError::AuthenticationFailed - The tag doesn't match, the buffer was wiped.
+
+        if tag.len() != self.tag_len {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let mut offset: [u8; 16] = self.initial_offset(nonce)?;
+        let mut checksum: [u8; 16] = [0; 16];
+
+        let (blocks, remainder) = blocks_mut(buffer);
+        for (i, block) in blocks.enumerate() {
+            xor_into(&mut offset, &self.l[(i + 1).trailing_zeros() as usize]);
+            xor_into(block, &offset);
+            self.core.decrypt_in_place(block);
+            xor_into(block, &offset);
+            xor_into(&mut checksum, block);
+        }
+
+        if !remainder.is_empty() {
+            xor_into(&mut offset, &self.l_star);
+            let pad = self.core.encrypt(&offset);
+            for (byte, pad) in remainder.iter_mut().zip(pad) {
+                *byte ^= pad;
+            }
+            xor_into(&mut checksum, &pad_block(remainder));
+        }
+
+        verify_or_wipe(&self.tag(&checksum, &offset, aad)[..self.tag_len], tag, buffer)
+    }
+}
+
+/// Internal functions for authenticated encryption with AES-OCB3.
+impl Ocb {
+    pub(crate) fn with_full_tag(&self) -> Self {
+        //! Returns the instance with a 16-byte tag, reusing the precomputed offsets.
+
+        Self {
+            tag_len: 16,
+            ..*self
+        }
+    }
+
+    fn initial_offset(&self, nonce: &[u8]) -> Result<[u8; 16], AeadError> {
+        //! Computes the initial offset from the nonce (Offset_0).
+        //! The formatted nonce is split into Ktop (all but the last 6 bits, encrypted) and bottom (the last 6 bits),
+        //! and the offset is the 128 bits of Stretch starting at bit bottom.
+
+        if nonce.is_empty() || nonce.len() > 15 {
+            return Err(AeadError::InvalidNonceLength);
+        }
+
+        let mut formatted: [u8; 16] = [0; 16];
+        formatted[0] = (((self.tag_len * 8) % 128) as u8) << 1;
+        formatted[15 - nonce.len()] |= 1;
+        formatted[(16 - nonce.len())..].copy_from_slice(nonce);
+
+        let bottom = (formatted[15] & 0x3f) as u32;
+        formatted[15] &= 0xc0;
+        let ktop: [u8; 16] = self.core.encrypt(&formatted);
+
+        // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72])
+        let high = u128::from_be_bytes(ktop);
+        let low = u64::from_be_bytes(ktop[..8].try_into().unwrap()) ^ u64::from_be_bytes(ktop[1..9].try_into().unwrap());
+
+        let offset = if bottom == 0 {
+            high
+        } else {
+            (high << bottom) | ((low >> (64 - bottom)) as u128)
+        };
+        Ok(offset.to_be_bytes())
+    }
+
+    fn hash(&self, aad: &[u8]) -> [u8; 16] {
+        //! Computes the hash of the associated data (HASH).
+
+        let mut offset: [u8; 16] = [0; 16];
+        let mut sum: [u8; 16] = [0; 16];
+
+        let mut blocks = aad.chunks_exact(16);
+        for (i, block) in (&mut blocks).enumerate() {
+            xor_into(&mut offset, &self.l[(i + 1).trailing_zeros() as usize]);
+            let mut buffer: [u8; 16] = block.try_into().unwrap();
+            xor_into(&mut buffer, &offset);
+            xor_into(&mut sum, &self.core.encrypt(&buffer));
+        }
+
+        let remainder = blocks.remainder();
+        if !remainder.is_empty() {
+            xor_into(&mut offset, &self.l_star);
+            let mut buffer: [u8; 16] = pad_block(remainder);
+            xor_into(&mut buffer, &offset);
+            xor_into(&mut sum, &self.core.encrypt(&buffer));
+        }
+
+        sum
+    }
+
+    fn tag(&self, checksum: &[u8; 16], offset: &[u8; 16], aad: &[u8]) -> [u8; 16] {
+        //! Computes the full 128-bit tag from the final checksum and offset, and the associated data.
+
+        let mut buffer: [u8; 16] = *checksum;
+        xor_into(&mut buffer, offset);
+        xor_into(&mut buffer, &self.l_dollar);
+        let mut tag = self.core.encrypt(&buffer);
+        xor_into(&mut tag, &self.hash(aad));
+        tag
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+fn xor_into(block: &mut [u8; 16], other: &[u8; 16]) {
+    //! XORs the other block into the block.
+
+    for i in 0..16 {
+        block[i] ^= other[i];
+    }
+}
+
+fn pad_block(data: &[u8]) -> [u8; 16] {
+    //! Pads the partial block with a single 1 bit followed by zeros.
+
+    let mut block: [u8; 16] = [0; 16];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    block
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn rfc7253() {
+        //! Test with the RFC 7253 sample results (Appendix A)
+
+        let ocb = Ocb::new(AESCore::new(AESKey::try_from(&hex("000102030405060708090A0B0C0D0E0F")[..]).unwrap()), 16);
+        let bytes = |len: usize| (0..len).map(|i| i as u8).collect::<Vec<u8>>();
+
+        let vectors: [(&str, usize, usize, &str); 7] = [
+            ("BBAA99887766554433221100", 0, 0, "785407BFFFC8AD9EDCC5520AC9111EE6"),
+            ("BBAA99887766554433221101", 8, 8, "6820B3657B6F615A5725BDA0D3B4EB3A257C9AF1F8F03009"),
+            ("BBAA99887766554433221102", 8, 0, "81017F8203F081277152FADE694A0A00"),
+            ("BBAA99887766554433221103", 0, 8, "45DD69F8F5AAE72414054CD1F35D82760B2CD00D2F99BFA9"),
+            ("BBAA99887766554433221104", 16, 16, "571D535B60B277188BE5147170A9A22C3AD7A4FF3835B8C5701C1CCEC8FC3358"),
+            (
+                "BBAA99887766554433221105",
+                24,
+                24,
+                "9FFD50F147694CDE9654EC6E7CE7D40ADFE9205F4DCABF4CBFE47346550DF689FFC8DACDCCE6C657",
+            ),
+            (
+                "BBAA99887766554433221106",
+                40,
+                40,
+                "5CE88EC2E0692706A915C00AEB8B2396535D6E6B659CC8B3440E9D00E351C9413E3D01CE09B9AE6B50719DDBACFC3FB14F2051B00A71A049",
+            ),
+        ];
+
+        for (nonce, aad_len, plaintext_len, output) in vectors {
+            let nonce = hex(nonce);
+            let (aad, plaintext, output) = (bytes(aad_len), bytes(plaintext_len), hex(output));
+            assert_eq!(ocb.encrypt(&nonce, &aad, &plaintext).unwrap(), output);
+            assert_eq!(ocb.decrypt(&nonce, &aad, &output).unwrap(), plaintext);
+        }
+
+        // the sample with a 96-bit tag
+        let ocb = Ocb::new(AESCore::new(AESKey::try_from(&hex("0F0E0D0C0B0A09080706050403020100")[..]).unwrap()), 12);
+        let nonce = hex("BBAA9988776655443322110D");
+        let output = hex(
+            "1792A4E31E0755FB03E31B22116E6C2DDF9EFD6E33D536F1A0124B0A55BAE884\
+             ED93481529C76B6AD0C515F4D1CDD4FDAC4F02AA"
+        );
+        assert_eq!(ocb.encrypt(&nonce, &bytes(40), &bytes(40)).unwrap(), output);
+        assert_eq!(ocb.decrypt(&nonce, &bytes(40), &output).unwrap(), bytes(40));
+    }
+
+    #[test]
+    fn rfc7253_iterative() {
+        //! Test with the RFC 7253 iterative results for all key and tag lengths (Appendix A)
+
+        let vectors: [(usize, usize, &str); 9] = [
+            (16, 16, "67E944D23256C5E0B6C61FA22FDF1EA2"),
+            (24, 16, "F673F2C3E7174AAE7BAE986CA9F29E17"),
+            (32, 16, "D90EB8E9C977C88B79DD793D7FFA161C"),
+            (16, 12, "77A3D8E73589158D25D01209"),
+            (24, 12, "05D56EAD2752C86BE6932C5E"),
+            (32, 12, "5458359AC23B0CBA9E6330DD"),
+            (16, 8, "192C9B7BD90BA06A"),
+            (24, 8, "0066BC6E0EF34E24"),
+            (32, 8, "7D4EA5D445501CBE"),
+        ];
+
+        for (key_len, tag_len, result) in vectors {
+            let mut key = vec![0; key_len];
+            key[key_len - 1] = (tag_len * 8) as u8;
+            let ocb = Ocb::new(AESCore::new(AESKey::try_from(&key[..]).unwrap()), tag_len);
+            let nonce = |n: usize| (n as u128).to_be_bytes()[4..].to_vec();
+
+            let mut output = Vec::new();
+            for i in 0..128 {
+                let s = vec![0; i];
+                output.extend(ocb.encrypt(&nonce(3 * i + 1), &s, &s).unwrap());
+                output.extend(ocb.encrypt(&nonce(3 * i + 2), &[], &s).unwrap());
+                output.extend(ocb.encrypt(&nonce(3 * i + 3), &s, &[]).unwrap());
+            }
+            assert_eq!(ocb.encrypt(&nonce(385), &output, &[]).unwrap(), hex(result));
+        }
+    }
+
+    #[test]
+    fn ocb_errors() {
+        //! Test that tampered inputs and invalid nonces are rejected
+
+        let ocb = Ocb::new(AESCore::new(AESKey::AES128([0x42; 16])), 16);
+        let nonce = [0x24; 12];
+        let output = ocb.encrypt(&nonce, b"header", b"a message longer than a block").unwrap();
+
+        assert_eq!(ocb.encrypt(&[], b"", b""), Err(AeadError::InvalidNonceLength));
+        assert_eq!(ocb.encrypt(&[0; 16], b"", b""), Err(AeadError::InvalidNonceLength));
+        assert_eq!(ocb.decrypt(&nonce, b"header", &output[..15]), Err(AeadError::InvalidLength));
+        assert_eq!(ocb.decrypt(&nonce, b"header!", &output), Err(AeadError::AuthenticationFailed));
+        assert_eq!(ocb.decrypt(&[0x25; 12], b"header", &output), Err(AeadError::AuthenticationFailed));
+
+        for position in [0, 20, output.len() - 1] {
+            let mut tampered = output.clone();
+            tampered[position] ^= 0x01;
+            assert_eq!(ocb.decrypt(&nonce, b"header", &tampered), Err(AeadError::AuthenticationFailed));
+        }
+
+        // every nonce length and a truncated tag
+        let ocb = Ocb::new(AESCore::new(AESKey::AES256([0x42; 32])), 4);
+        for nonce_len in 1..=15 {
+            let nonce = vec![0x24; nonce_len];
+            let output = ocb.encrypt(&nonce, b"", b"message").unwrap();
+            assert_eq!(output.len(), 7 + 4);
+            assert_eq!(ocb.decrypt(&nonce, b"", &output).unwrap(), b"message");
+        }
+    }
+
+    #[test]
+    fn tampered_in_place() {
+        //! Test that a tampered ciphertext decrypted in place returns an error and leaves the buffer zeroed
+
+        let ocb = Ocb::new(AESCore::new(AESKey::AES128([0x42; 16])), 12);
+        let nonce = [0x24; 12];
+        let output = ocb.encrypt(&nonce, b"header", b"a message longer than a block").unwrap();
+        let (ciphertext, tag) = output.split_at(output.len() - 12);
+
+        let mut buffer = ciphertext.to_vec();
+        ocb.decrypt_in_place_detached(&nonce, b"header", &mut buffer, tag).unwrap();
+        assert_eq!(buffer, b"a message longer than a block");
+
+        let mut buffer = ciphertext.to_vec();
+        assert_eq!(ocb.decrypt_in_place_detached(&nonce, b"header", &mut buffer, &tag[1..]), Err(AeadError::InvalidLength));
+        assert_eq!(buffer, ciphertext);
+
+        // in the full block and in the final partial block
+        for position in [3, 20] {
+            let mut buffer = ciphertext.to_vec();
+            buffer[position] ^= 0x01;
+            assert_eq!(ocb.decrypt_in_place_detached(&nonce, b"header", &mut buffer, tag), Err(AeadError::AuthenticationFailed));
+            assert_eq!(buffer, [0; 29]);
+        }
+    }
+}
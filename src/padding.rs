@@ -12,6 +12,14 @@
 
 
 
+// IMPORTS
+
+use std::fmt;
+
+
+
+
+
 // ENUMS
 
 /// The enum with padding errors.
@@ -26,10 +34,28 @@ pub enum PaddingError {
     InvalidPaddedSize,
     /// Trying to pad/de-pad with `PaddingTypes::None`.
     NonePadding,
+    /// The padding can't be removed without knowing the length of the original input.
+    /// Returned when de-padding with `PaddingTypes::ZeroPadding`, see `Padding::de_pad_with_len`.
+    AmbiguousPadding,
 }
 
+impl fmt::Display for PaddingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaddingError::InvalidPadding => write!(f, "invalid padding"),
+            PaddingError::InvalidSize => write!(f, "input to be padded must be shorter than 16 bytes"),
+            PaddingError::InvalidPaddedSize => write!(f, "padded input must be 16 bytes long"),
+            PaddingError::NonePadding => write!(f, "cannot pad or de-pad without a padding type"),
+            PaddingError::AmbiguousPadding => write!(f, "padding is ambiguous without the original input length"),
+        }
+    }
+}
+
+impl std::error::Error for PaddingError {}
+
 /// The enum with padding types.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PaddingTypes {
     /// PKCS#7 padding.
     /// The value of each added byte is the total number of bytes that need to be added.
@@ -45,11 +71,24 @@ pub enum PaddingTypes {
     /// All other bytes of the padding are zeros.
     /// This padding scheme is defined in ANSI X9.23.
     X923,
+    /// Zero padding.
+    /// All bytes of the padding are 0x00.
+    /// Padding is only added when the input isn't a multiple of 16 bytes long, an empty input becomes a block of zeros.
+    /// The padding is ambiguous if the input itself ends with 0x00 bytes,
+    /// so it can only be removed when the length of the original input is known.
+    /// This padding scheme is defined in ISO/IEC 9797-1 as padding method 1.
+    ZeroPadding,
     /// Don't use padding.
     /// For use with certain cipher modes which don't require padding.
     None,
 }
 
+impl PaddingTypes {
+    /// ISO/IEC 9797-1 padding method 2.
+    /// A single 0x80 byte followed by 0x00 bytes, which is the same as ISO 7816-4 padding.
+    pub const ISO9797M2: PaddingTypes = PaddingTypes::ISO78164;
+}
+
 
 
 
@@ -58,6 +97,7 @@ pub enum PaddingTypes {
 
 /// The padding struct.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Padding {
     /// The padding type.
     padding_type: PaddingTypes,
@@ -124,6 +164,9 @@ impl Padding {
                 output[15] = (16 - input.len()) as u8;
                 output[input.len()..15].fill(0);
             }
+            PaddingTypes::ZeroPadding => {
+                output[input.len()..16].fill(0);
+            }
             PaddingTypes::None => panic!("This should not be possible to reach."),
         }
 
@@ -140,63 +183,220 @@ impl Padding {
         //! * PaddingError::InvalidPadding - The padding is invalid and cannot be removed.
         //! * PaddingError::InvalidPaddedSize - The input isn't 16 bytes long.
         //! * PaddingError::NonePadding - Trying to de-pad with `PaddingTypes::None`.
+        //! * PaddingError::AmbiguousPadding - Trying to de-pad with `PaddingTypes::ZeroPadding`, use `de_pad_with_len` instead.
 
         if self.padding_type == PaddingTypes::None {
             return Err(PaddingError::NonePadding);
         }
 
+        if self.padding_type == PaddingTypes::ZeroPadding {
+            return Err(PaddingError::AmbiguousPadding);
+        }
+
         if input.len() != 16 {
             return Err(PaddingError::InvalidPaddedSize);
         }
 
         let upper_bound = match self.padding_type {
             PaddingTypes::PKCS7 => {
-                let padding_length = input[input.len() - 1];
+                let (valid, padding_length) = check_length_padding(input, true);
 
-                if padding_length > 16 || padding_length as usize > input.len() {
+                if !valid {
                     return Err(PaddingError::InvalidPadding);
                 }
 
-                for i in (input.len() - padding_length as usize)..(input.len() - 1) {
-                    if input[i] != padding_length {
-                        return Err(PaddingError::InvalidPadding);
-                    }
-                }
-
-                input.len() - padding_length as usize
+                input.len() - padding_length
             }
             PaddingTypes::ISO78164 => {
-                let mut curr_index: usize = input.len() - 1;
-
-                while input[curr_index] == 0 {
-                    curr_index -= 1;
-                }
-
-                if input[curr_index] != 0x80 || input.len() - curr_index > 16{
-                    return Err(PaddingError::InvalidPadding);
+                // the marker is the last non-zero byte, an all-zero input has no marker
+                match input.iter().rposition(|&byte| byte != 0) {
+                    Some(marker_index) if input[marker_index] == 0x80 => marker_index,
+                    _ => return Err(PaddingError::InvalidPadding),
                 }
-
-                curr_index
             }
             PaddingTypes::X923 => {
-                let padding_length = input[input.len() - 1] as usize;
-                if padding_length > 16 {
-                    return Err(PaddingError::InvalidPadding);
-                }
+                let (valid, padding_length) = check_length_padding(input, false);
 
-                for i in (input.len() - padding_length)..(input.len() - 1) {
-                    if input[i] != 0 {
-                        return Err(PaddingError::InvalidPadding);
-                    }
+                if !valid {
+                    return Err(PaddingError::InvalidPadding);
                 }
 
                 input.len() - padding_length
             }
-            PaddingTypes::None => panic!("This should not be possible to reach."),
+            PaddingTypes::ZeroPadding | PaddingTypes::None => panic!("This should not be possible to reach."),
         };
 
         Ok(&input[..upper_bound])
     }
+
+    pub fn de_pad_with_len<'a>(&self, input: &'a [u8], len: usize) -> Result<&'a [u8], PaddingError> {
+        //! Removes the padding from the input, given the length of the original input.
+        //! This is the only way to remove `PaddingTypes::ZeroPadding`.
+        //! For other padding types, the padding is removed as in `de_pad` and the result must be `len` bytes long.
+        //! # Arguments
+        //! * `input` - The input to be de-padded. Should be 16 bytes long.
+        //! * `len` - The length of the original input. Should be less than 16.
+        //! # Returns
+        //! * Result<&[u8], PaddingError> - The de-padded input or an error.
+        //! # Errors
+        //! * PaddingError::InvalidPadding - The padding is invalid or doesn't match the given length.
+        //! * PaddingError::InvalidPaddedSize - The input isn't 16 bytes long.
+        //! * PaddingError::NonePadding - Trying to de-pad with `PaddingTypes::None`.
+
+        if self.padding_type == PaddingTypes::None {
+            return Err(PaddingError::NonePadding);
+        }
+
+        if input.len() != 16 {
+            return Err(PaddingError::InvalidPaddedSize);
+        }
+
+        if len >= 16 {
+            return Err(PaddingError::InvalidPadding);
+        }
+
+        if self.padding_type == PaddingTypes::ZeroPadding {
+            if input[len..].iter().any(|&byte| byte != 0) {
+                return Err(PaddingError::InvalidPadding);
+            }
+            return Ok(&input[..len]);
+        }
+
+        let output = self.de_pad(input)?;
+        if output.len() != len {
+            return Err(PaddingError::InvalidPadding);
+        }
+
+        Ok(output)
+    }
+
+    pub fn pad_buffer(&self, input: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        //! Pads the input of arbitrary length to the next multiple of 16 bytes.
+        //! Padding is always added, so an input which is already a multiple of 16 bytes long
+        //! gets a full block of padding appended.
+        //! The exception is `PaddingTypes::ZeroPadding`, which leaves a non-empty input of whole blocks unchanged.
+        //! # Arguments
+        //! * `input` - The input to be padded. Can be of any length, including zero.
+        //! # Returns
+        //! * `Result<Vec<u8>, PaddingError>` - The padded input or an error.
+        //! # Errors
+        //! * PaddingError::NonePadding - Trying to pad with `PaddingTypes::None`.
+
+        if self.padding_type == PaddingTypes::None {
+            return Err(PaddingError::NonePadding);
+        }
+
+        let full_blocks_len = input.len() - input.len() % 16;
+
+        if self.padding_type == PaddingTypes::ZeroPadding && full_blocks_len == input.len() && !input.is_empty() {
+            return Ok(input.to_vec());
+        }
+
+        let mut output: Vec<u8> = Vec::with_capacity(ciphertext_len(input.len(), self).expect("padding is added"));
+        output.extend_from_slice(&input[..full_blocks_len]);
+        output.extend_from_slice(&self.pad(&input[full_blocks_len..])?);
+
+        Ok(output)
+    }
+
+    pub fn de_pad_buffer<'a>(&self, input: &'a [u8]) -> Result<&'a [u8], PaddingError> {
+        //! Removes the padding from the input of arbitrary length.
+        //! Only the last block of the input is expected to contain the padding.
+        //! # Arguments
+        //! * `input` - The input to be de-padded. Should be a non-zero multiple of 16 bytes long.
+        //! # Returns
+        //! * Result<&[u8], PaddingError> - The de-padded input or an error.
+        //! # Errors
+        //! * PaddingError::InvalidPadding - The padding is invalid and cannot be removed.
+        //! * PaddingError::InvalidPaddedSize - The input isn't a non-zero multiple of 16 bytes long.
+        //! * PaddingError::NonePadding - Trying to de-pad with `PaddingTypes::None`.
+
+        if self.padding_type == PaddingTypes::None {
+            return Err(PaddingError::NonePadding);
+        }
+
+        if input.is_empty() || !input.len().is_multiple_of(16) {
+            return Err(PaddingError::InvalidPaddedSize);
+        }
+
+        let last_block_start = input.len() - 16;
+        let last_block = self.de_pad(&input[last_block_start..])?;
+
+        Ok(&input[..(last_block_start + last_block.len())])
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn ciphertext_len(plaintext_len: usize, padding: &Padding) -> Option<usize> {
+    //! Returns the length of the plaintext after padding, which is also the length of its ECB or CBC ciphertext.
+    //! Useful for sizing the output buffers in advance.
+    //! # Arguments
+    //! * `plaintext_len` - The length of the plaintext.
+    //! * `padding` - The padding, which is always added (a full block if the plaintext is a multiple of 16 bytes long).
+    //!   `PaddingTypes::ZeroPadding` isn't added to a non-empty plaintext of whole blocks.
+    //!   With `PaddingTypes::None` nothing is added, so the plaintext must already be a multiple of 16 bytes long.
+    //! # Returns
+    //! * `Option<usize>` - The length of the padded plaintext,
+    //!   or `None` if the plaintext isn't a multiple of 16 bytes long and the padding is `PaddingTypes::None`.
+
+    match padding.padding_type {
+        PaddingTypes::None if plaintext_len.is_multiple_of(16) => Some(plaintext_len),
+        PaddingTypes::None => None,
+        PaddingTypes::ZeroPadding if plaintext_len != 0 => Some(plaintext_len.div_ceil(16) * 16),
+        _ => Some((plaintext_len / 16 + 1) * 16),
+    }
+}
+
+fn check_length_padding(input: &[u8], fill_with_length: bool) -> (bool, usize) {
+    //! Checks the padding whose last byte is the padding length (PKCS#7 and ANSI X9.23).
+    //! All 16 bytes are always examined and the validity is accumulated without branching on the data,
+    //! so the time taken doesn't reveal whether or where the padding is invalid.
+    //! # Arguments
+    //! * `input` - The padded block. Must be 16 bytes long.
+    //! * `fill_with_length` - Whether the padding bytes hold the padding length (PKCS#7) or zeros (ANSI X9.23).
+    //! # Returns
+    //! * (bool, usize) - Whether the padding is valid and the padding length (zero if invalid).
+
+    check_length_padding_with(input, fill_with_length, |byte, expected_byte| byte ^ expected_byte)
+}
+
+fn check_length_padding_with(input: &[u8], fill_with_length: bool, mut compare: impl FnMut(u8, u8) -> u8) -> (bool, usize) {
+    //! Checks the padding whose last byte is the padding length, comparing the bytes with the given function.
+    //! The function returns the difference of a byte and the expected byte (zero if they are equal),
+    //! it's called exactly once for each of the first 15 bytes, so the tests can count the comparisons.
+
+    let padding_length: u8 = input[15];
+    let expected_byte: u8 = if fill_with_length { padding_length } else { 0 };
+
+    // the padding is 1 to 16 bytes long
+    let mut invalid: u8 = ct_less_than(16, padding_length) | !ct_non_zero(padding_length);
+
+    for i in 0..15 {
+        // the byte is a part of the padding if it is one of the last padding_length bytes
+        let in_padding: u8 = ct_less_than((15 - i) as u8, padding_length);
+        invalid |= in_padding & compare(input[i], expected_byte);
+    }
+
+    let valid_mask: u8 = !ct_non_zero(invalid);
+
+    (valid_mask == 0xFF, (padding_length & valid_mask) as usize)
+}
+
+fn ct_less_than(a: u8, b: u8) -> u8 {
+    //! Returns 0xFF if `a` is less than `b`, 0x00 otherwise, without branching.
+
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+fn ct_non_zero(a: u8) -> u8 {
+    //! Returns 0xFF if `a` isn't zero, 0x00 otherwise, without branching.
+
+    (0_u16.wrapping_sub(a as u16) >> 8) as u8
 }
 
 
@@ -208,6 +408,11 @@ impl Padding {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aes_core::{
+        AESCore,
+        AESKey,
+    };
+    use crate::modes::ecb_encrypt_padded;
 
     #[test]
     fn new() {
@@ -315,6 +520,13 @@ mod tests {
         let output3: &[u8] = padding.de_pad(&input3).unwrap();
         let wanted3: [u8; 15] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F];
         assert_eq!(output3, wanted3);
+
+        // the marker is missing
+        let input4: [u8; 16] = [0; 16];
+        assert_eq!(padding.de_pad(&input4), Err(PaddingError::InvalidPadding));
+
+        let input5: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x00];
+        assert_eq!(padding.de_pad(&input5), Err(PaddingError::InvalidPadding));
     }
 
     #[test]
@@ -363,6 +575,184 @@ mod tests {
         assert_eq!(output3, wanted3);
     }
 
+    #[test]
+    fn zero_padding() {
+        //! Tests the zero padding.
+
+        let padding: Padding = Padding::new(PaddingTypes::ZeroPadding);
+
+        let input1: [u8; 2] = [0b10100001, 0b10100000];
+        let output1: [u8; 16] = padding.pad(&input1).unwrap();
+        let wanted1: [u8; 16] = [0b10100001, 0b10100000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(output1, wanted1);
+
+        let input2: [u8; 0] = [];
+        let output2: [u8; 16] = padding.pad(&input2).unwrap();
+        let wanted2: [u8; 16] = [0; 16];
+        assert_eq!(output2, wanted2);
+
+        let input3: [u8; 15] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F];
+        let output3: [u8; 16] = padding.pad(&input3).unwrap();
+        let wanted3: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0];
+        assert_eq!(output3, wanted3);
+    }
+
+    #[test]
+    fn zero_de_padding() {
+        //! Tests the zero de-padding and its ambiguity.
+
+        let padding: Padding = Padding::new(PaddingTypes::ZeroPadding);
+
+        let input1: [u8; 16] = [0b10100001, 0b10100000, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(padding.de_pad(&input1), Err(PaddingError::AmbiguousPadding));
+        assert_eq!(padding.de_pad_with_len(&input1, 2).unwrap(), [0b10100001, 0b10100000]);
+
+        // the same padded block is produced by inputs ending with zero bytes
+        let input2: [u8; 4] = [0b10100001, 0b10100000, 0, 0];
+        assert_eq!(padding.pad(&input2).unwrap(), input1);
+        assert_eq!(padding.de_pad_with_len(&input1, 4).unwrap(), input2);
+        assert!(padding.de_pad_with_len(&[0; 16], 0).unwrap().is_empty());
+
+        // a non-zero byte after the given length can't be padding
+        assert_eq!(padding.de_pad_with_len(&input1, 1), Err(PaddingError::InvalidPadding));
+        assert_eq!(padding.de_pad_with_len(&input1, 16), Err(PaddingError::InvalidPadding));
+        assert_eq!(padding.de_pad_with_len(&[0; 15], 0), Err(PaddingError::InvalidPaddedSize));
+
+        // zero padding is only added to a partial block, or to an empty input
+        assert_eq!(padding.pad_buffer(&[0x61; 32]).unwrap(), [0x61; 32]);
+        assert_eq!(padding.pad_buffer(&[0x61; 17]).unwrap(), [[0x61; 17].as_slice(), &[0; 15]].concat());
+        assert_eq!(padding.pad_buffer(&[]).unwrap(), [0; 16]);
+
+        // the buffer de-padding can't remove zero padding either
+        assert_eq!(padding.de_pad_buffer(&[0; 32]), Err(PaddingError::AmbiguousPadding));
+    }
+
+    #[test]
+    fn de_padding_with_len() {
+        //! Tests the de-padding with a known length for non-ambiguous padding types.
+
+        let pkcs7: Padding = Padding::new(PaddingTypes::PKCS7);
+        let padded: [u8; 16] = pkcs7.pad(&[0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(pkcs7.de_pad_with_len(&padded, 3).unwrap(), [0x01, 0x02, 0x03]);
+        assert_eq!(pkcs7.de_pad_with_len(&padded, 4), Err(PaddingError::InvalidPadding));
+
+        let none: Padding = Padding::new(PaddingTypes::None);
+        assert_eq!(none.de_pad_with_len(&padded, 3), Err(PaddingError::NonePadding));
+    }
+
+    #[test]
+    fn iso9797m2_alias() {
+        //! Tests that ISO/IEC 9797-1 method 2 is the same as ISO 7816-4.
+
+        assert_eq!(PaddingTypes::ISO9797M2, PaddingTypes::ISO78164);
+
+        let iso9797m2: Padding = Padding::new(PaddingTypes::ISO9797M2);
+        let iso78164: Padding = Padding::new(PaddingTypes::ISO78164);
+        let input: [u8; 5] = [0x01, 0x02, 0x03, 0x04, 0x05];
+        assert_eq!(iso9797m2.pad(&input).unwrap(), iso78164.pad(&input).unwrap());
+    }
+
+    #[test]
+    fn buffer_padding() {
+        //! Tests the padding and de-padding of arbitrary length buffers.
+
+        let data: Vec<u8> = (0..32).collect();
+
+        for (len, padded_len) in [(0, 16), (1, 16), (15, 16), (16, 32), (31, 32), (32, 48)] {
+            let input: &[u8] = &data[..len];
+
+            let padding: Padding = Padding::new(PaddingTypes::PKCS7);
+            let output: Vec<u8> = padding.pad_buffer(input).unwrap();
+            assert_eq!(output.len(), padded_len);
+            assert_eq!(&output[..len], input);
+            assert!(output[len..].iter().all(|&byte| byte as usize == padded_len - len));
+            assert_eq!(padding.de_pad_buffer(&output).unwrap(), input);
+
+            for padding_type in [PaddingTypes::ISO78164, PaddingTypes::X923] {
+                let padding: Padding = Padding::new(padding_type);
+                let output: Vec<u8> = padding.pad_buffer(input).unwrap();
+                assert_eq!(output.len(), padded_len);
+                assert_eq!(&output[..len], input);
+                assert_eq!(padding.de_pad_buffer(&output).unwrap(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn buffer_padding_errors() {
+        //! Tests the errors of the buffer padding and de-padding.
+
+        let padding: Padding = Padding::new(PaddingTypes::PKCS7);
+        assert_eq!(padding.de_pad_buffer(&[]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(padding.de_pad_buffer(&[0x10; 17]), Err(PaddingError::InvalidPaddedSize));
+        assert_eq!(padding.de_pad_buffer(&[0x11; 32]), Err(PaddingError::InvalidPadding));
+
+        let padding: Padding = Padding::new(PaddingTypes::None);
+        assert_eq!(padding.pad_buffer(&[0; 20]), Err(PaddingError::NonePadding));
+        assert_eq!(padding.de_pad_buffer(&[0; 32]), Err(PaddingError::NonePadding));
+    }
+
+    #[test]
+    fn length_padding_check() {
+        //! Tests the PKCS#7 and ANSI X9.23 padding checks on valid blocks and on blocks invalid at different positions,
+        //! and that all of them examine the same number of bytes.
+
+        let valid_pkcs7: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x04, 0x04, 0x04, 0x04];
+        let invalid_pkcs7_first: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x05, 0x04, 0x04, 0x04];
+        let invalid_pkcs7_last: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x04, 0x04, 0x05, 0x04];
+        let invalid_pkcs7_length: [u8; 16] = [0x11; 16];
+
+        assert_eq!(check_length_padding(&valid_pkcs7, true), (true, 4));
+        assert_eq!(check_length_padding(&invalid_pkcs7_first, true), (false, 0));
+        assert_eq!(check_length_padding(&invalid_pkcs7_last, true), (false, 0));
+        assert_eq!(check_length_padding(&invalid_pkcs7_length, true), (false, 0));
+
+        let valid_x923: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0, 0, 0, 0x04];
+        let invalid_x923: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x01, 0, 0, 0x04];
+        let full_x923: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10];
+
+        assert_eq!(check_length_padding(&valid_x923, false), (true, 4));
+        assert_eq!(check_length_padding(&invalid_x923, false), (false, 0));
+        assert_eq!(check_length_padding(&full_x923, false), (true, 16));
+
+        // valid and invalid blocks take the same number of byte comparisons
+        let count_comparisons = |input: &[u8], fill_with_length: bool| {
+            let mut comparisons: usize = 0;
+            let result = check_length_padding_with(input, fill_with_length, |byte, expected_byte| {
+                comparisons += 1;
+                byte ^ expected_byte
+            });
+            assert_eq!(result, check_length_padding(input, fill_with_length));
+            comparisons
+        };
+        for input in [valid_pkcs7, invalid_pkcs7_first, invalid_pkcs7_last, invalid_pkcs7_length] {
+            assert_eq!(count_comparisons(&input, true), 15);
+        }
+        for input in [valid_x923, invalid_x923, full_x923] {
+            assert_eq!(count_comparisons(&input, false), 15);
+        }
+    }
+
+    #[test]
+    fn zero_padding_length() {
+        //! Tests that a final byte of zero (a padding length of zero) is rejected by PKCS#7 and ANSI X9.23.
+
+        let mut input: [u8; 16] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x00];
+        assert_eq!(check_length_padding(&input, true), (false, 0));
+        assert_eq!(check_length_padding(&input, false), (false, 0));
+
+        for padding_type in [PaddingTypes::PKCS7, PaddingTypes::X923] {
+            let padding: Padding = Padding::new(padding_type);
+            assert_eq!(padding.de_pad(&input), Err(PaddingError::InvalidPadding));
+            assert_eq!(padding.de_pad(&[0; 16]), Err(PaddingError::InvalidPadding));
+        }
+
+        // a padding length longer than the block is rejected as well
+        input[15] = 0x11;
+        assert_eq!(Padding::new(PaddingTypes::PKCS7).de_pad(&input), Err(PaddingError::InvalidPadding));
+        assert_eq!(Padding::new(PaddingTypes::X923).de_pad(&input), Err(PaddingError::InvalidPadding));
+    }
+
     #[test]
     fn padding_errors() {
         let padding_type = PaddingTypes::PKCS7;
@@ -394,4 +784,59 @@ mod tests {
         assert_eq!(new_padding.pad(&input), Err(PaddingError::NonePadding));
         assert_eq!(new_padding.de_pad(&padded_input), Err(PaddingError::NonePadding));
     }
+
+    #[test]
+    fn error_display() {
+        //! Test the error messages
+
+        assert_eq!(PaddingError::InvalidPadding.to_string(), "invalid padding");
+        assert_eq!(PaddingError::InvalidSize.to_string(), "input to be padded must be shorter than 16 bytes");
+        assert_eq!(PaddingError::InvalidPaddedSize.to_string(), "padded input must be 16 bytes long");
+        assert_eq!(PaddingError::NonePadding.to_string(), "cannot pad or de-pad without a padding type");
+        assert_eq!(PaddingError::AmbiguousPadding.to_string(), "padding is ambiguous without the original input length");
+
+        let boxed: Box<dyn std::error::Error> = Box::new(PaddingError::InvalidPadding);
+        assert_eq!(boxed.to_string(), "invalid padding");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_padding() {
+        //! Test serializing and deserializing the padding configuration
+
+        for padding_type in [PaddingTypes::PKCS7, PaddingTypes::ISO78164, PaddingTypes::X923, PaddingTypes::ZeroPadding, PaddingTypes::None] {
+            let json = serde_json::to_string(&padding_type).unwrap();
+            assert_eq!(serde_json::from_str::<PaddingTypes>(&json).unwrap(), padding_type);
+        }
+        assert_eq!(serde_json::to_string(&PaddingTypes::PKCS7).unwrap(), "\"PKCS7\"");
+
+        let padding = Padding::new(PaddingTypes::X923);
+        let json = serde_json::to_string(&padding).unwrap();
+        assert_eq!(json, "{\"padding_type\":\"X923\"}");
+        assert_eq!(serde_json::from_str::<Padding>(&json).unwrap(), padding);
+    }
+
+    #[test]
+    fn predicted_ciphertext_len() {
+        //! Test that the predicted length matches the padded length, and the ECB ciphertext length
+
+        let core = AESCore::new(AESKey::AES128([0x42; 16]));
+        let plaintext: Vec<u8> = vec![0x61; 100];
+
+        for padding_type in [PaddingTypes::PKCS7, PaddingTypes::ISO78164, PaddingTypes::X923, PaddingTypes::ZeroPadding] {
+            let padding = Padding::new(padding_type);
+            for len in [0, 1, 15, 16, 17, 31, 32, 33, 100] {
+                let padded = padding.pad_buffer(&plaintext[..len]).unwrap();
+                assert_eq!(ciphertext_len(len, &padding), Some(padded.len()));
+                assert_eq!(padded.capacity(), padded.len());
+                assert_eq!(ciphertext_len(len, &padding), Some(ecb_encrypt_padded(&core, &padding, &plaintext[..len]).unwrap().len()));
+            }
+        }
+
+        // without padding, only whole blocks can be encrypted in ECB or CBC
+        assert_eq!(ciphertext_len(0, &Padding::new(PaddingTypes::None)), Some(0));
+        assert_eq!(ciphertext_len(32, &Padding::new(PaddingTypes::None)), Some(32));
+        assert_eq!(ciphertext_len(17, &Padding::new(PaddingTypes::None)), None);
+        assert_eq!(ciphertext_len(15, &Padding::new(PaddingTypes::None)), None);
+    }
 }
@@ -0,0 +1,110 @@
+//! A module containing the SIMD round transformations (SSE2 on x86-64).
+//! The state is held in a single 128-bit register, one column word per 32-bit lane,
+//! so AddRoundKey is a single XOR and MixColumns processes all four columns at once.
+//! SSE2 is a part of the x86-64 baseline, so no runtime detection is needed.
+
+
+
+
+
+// IMPORTS
+
+use core::arch::x86_64::{
+    __m128i,
+    _mm_and_si128,
+    _mm_loadu_si128,
+    _mm_or_si128,
+    _mm_set1_epi32,
+    _mm_shufflehi_epi16,
+    _mm_shufflelo_epi16,
+    _mm_slli_epi16,
+    _mm_slli_epi32,
+    _mm_srli_epi16,
+    _mm_srli_epi32,
+    _mm_storeu_si128,
+    _mm_xor_si128,
+};
+
+
+
+
+
+// FUNCTIONS
+
+pub(crate) fn add_round_key(state: &mut [u32; 4], round_keys: &[[u8; 4]]) {
+    //! Adds the given round key to the state with a single 128-bit XOR.
+    //! # Panics
+    //! * If fewer than 4 round key words are given.
+
+    // SAFETY: SSE2 is a part of the x86-64 baseline, so it is always available
+    unsafe { add_round_key_sse2(state, round_keys) }
+}
+
+pub(crate) fn mix_columns(state: &mut [u32; 4]) {
+    //! Mixes the four columns of the state at once.
+
+    // SAFETY: SSE2 is a part of the x86-64 baseline, so it is always available
+    unsafe { mix_columns_sse2(state) }
+}
+
+#[target_feature(enable = "sse2")]
+fn add_round_key_sse2(state: &mut [u32; 4], round_keys: &[[u8; 4]]) {
+    //! Adds the given round key to the state.
+    //! The round key words are big-endian, so their bytes are swapped into the lanes first.
+
+    let round_key: &[u8] = round_keys[..4].as_flattened();
+    // SAFETY: the round key is exactly 16 bytes long, and the load doesn't require alignment
+    let round_key: __m128i = unsafe { _mm_loadu_si128(round_key.as_ptr() as *const __m128i) };
+
+    // swap the bytes in each 16-bit lane, then the 16-bit halves of each 32-bit lane
+    let swapped: __m128i = _mm_or_si128(_mm_slli_epi16::<8>(round_key), _mm_srli_epi16::<8>(round_key));
+    let swapped: __m128i = _mm_shufflehi_epi16::<0b10_11_00_01>(_mm_shufflelo_epi16::<0b10_11_00_01>(swapped));
+
+    store(state, _mm_xor_si128(load(state), swapped));
+}
+
+#[target_feature(enable = "sse2")]
+fn mix_columns_sse2(state: &mut [u32; 4]) {
+    //! Mixes the four columns of the state.
+    //! The same computation as the scalar version, 2 * (a0 + a1) + a1 + a2 + a3, done in each 32-bit lane.
+
+    let columns: __m128i = load(state);
+    let rotated: __m128i = _mm_or_si128(_mm_slli_epi32::<8>(columns), _mm_srli_epi32::<24>(columns));
+    let rotated_twice: __m128i = _mm_or_si128(_mm_slli_epi32::<16>(columns), _mm_srli_epi32::<16>(columns));
+    let rotated_thrice: __m128i = _mm_or_si128(_mm_slli_epi32::<24>(columns), _mm_srli_epi32::<8>(columns));
+
+    let mixed: __m128i = _mm_xor_si128(
+        _mm_xor_si128(xtime(_mm_xor_si128(columns, rotated)), rotated),
+        _mm_xor_si128(rotated_twice, rotated_thrice),
+    );
+    store(state, mixed);
+}
+
+#[target_feature(enable = "sse2")]
+fn xtime(words: __m128i) -> __m128i {
+    //! Multiplies each byte by 2 in GF(2^8), the reduction done with shifts of the carried out bits.
+
+    let carry: __m128i = _mm_and_si128(_mm_srli_epi32::<7>(words), _mm_set1_epi32(0x01010101));
+    let shifted: __m128i = _mm_slli_epi32::<1>(_mm_and_si128(words, _mm_set1_epi32(0x7f7f7f7f)));
+
+    _mm_xor_si128(
+        _mm_xor_si128(shifted, carry),
+        _mm_xor_si128(_mm_slli_epi32::<1>(carry), _mm_xor_si128(_mm_slli_epi32::<3>(carry), _mm_slli_epi32::<4>(carry))),
+    )
+}
+
+#[target_feature(enable = "sse2")]
+fn load(state: &[u32; 4]) -> __m128i {
+    //! Loads the state into a register, column c in lane c.
+
+    // SAFETY: the state is exactly 16 bytes long, and the load doesn't require alignment
+    unsafe { _mm_loadu_si128(state.as_ptr() as *const __m128i) }
+}
+
+#[target_feature(enable = "sse2")]
+fn store(state: &mut [u32; 4], register: __m128i) {
+    //! Stores the register back into the state, lane c into column c.
+
+    // SAFETY: the state is exactly 16 bytes long, and the store doesn't require alignment
+    unsafe { _mm_storeu_si128(state.as_mut_ptr() as *mut __m128i, register) }
+}
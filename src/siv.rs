@@ -0,0 +1,307 @@
+//! A module containing the AES-SIV deterministic authenticated encryption (RFC 5297).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aead::verify_or_wipe;
+use crate::aes_core::AESCore;
+use crate::gcm::AeadError;
+use crate::mac::{
+    double,
+    Cmac,
+};
+use crate::modes::CtrCipher;
+
+
+
+
+
+// STRUCTS
+
+/// The AES-SIV authenticated encryption.
+/// The synthetic IV is computed from the associated data and the plaintext, so encrypting the same input
+/// always gives the same output. Reusing a nonce (passed as the last associated data component) only reveals
+/// whether the same message was encrypted, it doesn't break the confidentiality of other messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Siv {
+    /// The AES core used for S2V (the first half of the SIV key).
+    mac_core: AESCore,
+    /// The AES core used for CTR encryption (the second half of the SIV key).
+    ctr_core: AESCore,
+}
+
+/// Public functions for authenticated encryption with AES-SIV.
+impl Siv {
+    pub fn new(mac_core: AESCore, ctr_core: AESCore) -> Self {
+        //! Creates a new AES-SIV instance.
+        //! # Arguments
+        //! * `mac_core` - The AES core keyed with the first half of the SIV key, used to compute the synthetic IV.
+        //! * `ctr_core` - The AES core keyed with the second half of the SIV key, used to encrypt the data.
+
+        Self {
+            mac_core,
+            ctr_core,
+        }
+    }
+
+    pub fn encrypt(&self, aad: &[&[u8]], plaintext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Encrypts and authenticates the plaintext, and authenticates the associated data.
+        //! # Arguments
+        //! * `aad` - The associated data components (at most `SIV_MAX_AAD_COMPONENTS`), authenticated but not encrypted.
+        //! * `plaintext` - The data to be encrypted.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The synthetic IV followed by the ciphertext, or an error.
+        //! # Errors
+        //! * AeadError::InvalidLength - There are more than `SIV_MAX_AAD_COMPONENTS` associated data components.
+
+        check_aad_components(aad)?;
+        let v = self.s2v(aad, plaintext);
+
+        let mut output = Vec::with_capacity(16 + plaintext.len());
+        output.extend_from_slice(&v);
+        output.extend_from_slice(plaintext);
+        self.ctr(&v, &mut output[16..]);
+        Ok(output)
+    }
+
+    pub fn decrypt(&self, aad: &[&[u8]], ciphertext: &[u8]) -> Result<Vec<u8>, AeadError> {
+        //! Decrypts the ciphertext and verifies the synthetic IV in constant time.
+        //! # Arguments
+        //! * `aad` - The associated data components used for encryption.
+        //! * `ciphertext` - The synthetic IV followed by the ciphertext.
+        //! # Returns
+        //! * `Result<Vec<u8>, AeadError>` - The plaintext or an error.
+        //! # Errors
+        //! * AeadError::InvalidLength - The ciphertext is shorter than the synthetic IV,
+        //!   or there are more than `SIV_MAX_AAD_COMPONENTS` associated data components.
+        //! * AeadError::AuthenticationFailed - The synthetic IV doesn't match.
+
+        if ciphertext.len() < 16 {
+            return Err(AeadError::InvalidLength);
+        }
+
+        let (v, ciphertext) = ciphertext.split_at(16);
+        let mut plaintext = ciphertext.to_vec();
+        self.decrypt_in_place_detached(aad, v.try_into().unwrap(), &mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    pub fn decrypt_in_place_detached(&self, aad: &[&[u8]], v: &[u8; 16], buffer: &mut [u8]) -> Result<(), AeadError> {
+        //! Decrypts the ciphertext in place and verifies the detached synthetic IV in constant time.
+        //! The synthetic IV is computed over the plaintext, so the buffer is decrypted before the verification.
+        //! If it doesn't match, the buffer is wiped (filled with zeros), so no unverified plaintext is left in it.
+        //! # Arguments
+        //! * `aad` - The associated data components used for encryption.
+        //! * `v` - The synthetic IV.
+        //! * `buffer` - The ciphertext, replaced with the plaintext (or zeros on failure).
+        //! # Errors
+        //! * AeadError::InvalidLength - There are more than `SIV_MAX_AAD_COMPONENTS` associated data components,
+        //!   the buffer is unchanged.
+        //! * AeadError::AuthenticationFailed - The synthetic IV doesn't match, the buffer was wiped.
+
+        check_aad_components(aad)?;
+        self.ctr(v, buffer);
+
+        verify_or_wipe(&self.s2v(aad, buffer), v, buffer)
+    }
+}
+
+/// Internal functions for authenticated encryption with AES-SIV.
+impl Siv {
+    fn s2v(&self, aad: &[&[u8]], plaintext: &[u8]) -> [u8; 16] {
+        //! Computes the synthetic IV from the associated data components and the plaintext (S2V).
+
+        let mut d: [u8; 16] = self.cmac(&[0; 16]);
+        for component in aad {
+            let mac = self.cmac(component);
+            d = double(&d);
+            for i in 0..16 {
+                d[i] ^= mac[i];
+            }
+        }
+
+        let mut cmac = Cmac::new(self.mac_core);
+        if plaintext.len() >= 16 {
+            // the last 16 bytes of the plaintext are XORed with D
+            let (head, tail) = plaintext.split_at(plaintext.len() - 16);
+            let mut last_block: [u8; 16] = tail.try_into().unwrap();
+            for i in 0..16 {
+                last_block[i] ^= d[i];
+            }
+            cmac.update(head);
+            cmac.update(&last_block);
+        } else {
+            let mut padded: [u8; 16] = [0; 16];
+            padded[..plaintext.len()].copy_from_slice(plaintext);
+            padded[plaintext.len()] = 0x80;
+            let d = double(&d);
+            for i in 0..16 {
+                padded[i] ^= d[i];
+            }
+            cmac.update(&padded);
+        }
+        cmac.finalize()
+    }
+
+    fn cmac(&self, data: &[u8]) -> [u8; 16] {
+        //! Computes the CMAC of the data with the S2V key.
+
+        let mut cmac = Cmac::new(self.mac_core);
+        cmac.update(data);
+        cmac.finalize()
+    }
+
+    fn ctr(&self, v: &[u8; 16], data: &mut [u8]) {
+        //! Encrypts or decrypts the data in CTR mode, starting from the synthetic IV with bits 31 and 63 cleared.
+
+        let mut counter: [u8; 16] = *v;
+        counter[8] &= 0x7f;
+        counter[12] &= 0x7f;
+
+        CtrCipher::new(self.ctr_core, counter)
+            .apply_keystream(data)
+            .expect("the full 128-bit counter is never exhausted");
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+fn check_aad_components(aad: &[&[u8]]) -> Result<(), AeadError> {
+    //! Checks the number of associated data components against the limit of S2V (RFC 5297, section 2.6).
+
+    if aad.len() > SIV_MAX_AAD_COMPONENTS {
+        return Err(AeadError::InvalidLength);
+    }
+
+    Ok(())
+}
+
+
+
+
+
+// CONSTANTS
+
+/// The maximum number of associated data components, S2V takes at most 127 inputs including the plaintext.
+pub const SIV_MAX_AAD_COMPONENTS: usize = 126;
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    fn siv(key: &str) -> Siv {
+        let key = hex(key);
+        Siv::new(
+            AESCore::new(AESKey::try_from(&key[..16]).unwrap()),
+            AESCore::new(AESKey::try_from(&key[16..]).unwrap()),
+        )
+    }
+
+    #[test]
+    fn rfc5297() {
+        //! Test with the RFC 5297 test vectors (deterministic and nonce-based)
+
+        let siv1 = siv("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let aad = hex("101112131415161718191a1b1c1d1e1f2021222324252627");
+        let plaintext = hex("112233445566778899aabbccddee");
+        let output = hex("85632d07c6e8f37f950acd320a2ecc9340c02b9690c4dc04daef7f6afe5c");
+
+        assert_eq!(siv1.encrypt(&[&aad], &plaintext).unwrap(), output);
+        assert_eq!(siv1.decrypt(&[&aad], &output).unwrap(), plaintext);
+
+        // the output is deterministic
+        assert_eq!(siv1.encrypt(&[&aad], &plaintext).unwrap(), siv1.encrypt(&[&aad], &plaintext).unwrap());
+
+        let siv2 = siv("7f7e7d7c7b7a79787776757473727170404142434445464748494a4b4c4d4e4f");
+        let aad1 = hex("00112233445566778899aabbccddeeffdeaddadadeaddadaffeeddccbbaa99887766554433221100");
+        let aad2 = hex("102030405060708090a0");
+        let nonce = hex("09f911029d74e35bd84156c5635688c0");
+        let plaintext = hex("7468697320697320736f6d6520706c61696e7465787420746f20656e6372797074207573696e67205349562d414553");
+        let output = hex(
+            "7bdb6e3b432667eb06f4d14bff2fbd0fcb900f2fddbe404326601965c889bf17\
+             dba77ceb094fa663b7a3f748ba8af829ea64ad544a272e9c485b62a3fd5c0d"
+        );
+
+        assert_eq!(siv2.encrypt(&[&aad1, &aad2, &nonce], &plaintext).unwrap(), output);
+        assert_eq!(siv2.decrypt(&[&aad1, &aad2, &nonce], &output).unwrap(), plaintext);
+
+        // empty plaintext, and no associated data
+        assert_eq!(siv2.encrypt(&[b"x"], &[]).unwrap(), hex("26f3bfde8dc23eb173a0ed621b9e0c13"));
+        assert_eq!(siv2.encrypt(&[], b"abc").unwrap(), hex("f3dc1a5dfd507ae8e007e139126d18f7b1a72a"));
+        assert!(siv2.decrypt(&[b"x"], &hex("26f3bfde8dc23eb173a0ed621b9e0c13")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn siv_errors() {
+        //! Test that tampered inputs are rejected
+
+        let siv = siv("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let output = siv.encrypt(&[b"header", b"nonce"], b"plaintext").unwrap();
+
+        assert_eq!(siv.decrypt(&[b"header", b"nonce"], &output[..15]), Err(AeadError::InvalidLength));
+        assert_eq!(siv.decrypt(&[b"header"], &output), Err(AeadError::AuthenticationFailed));
+        assert_eq!(siv.decrypt(&[b"nonce", b"header"], &output), Err(AeadError::AuthenticationFailed));
+
+        let mut tampered = output.clone();
+        tampered[20] ^= 0x01;
+        assert_eq!(siv.decrypt(&[b"header", b"nonce"], &tampered), Err(AeadError::AuthenticationFailed));
+
+        let mut tampered = output;
+        tampered[0] ^= 0x01;
+        assert_eq!(siv.decrypt(&[b"header", b"nonce"], &tampered), Err(AeadError::AuthenticationFailed));
+
+        // at most 126 associated data components
+        let components: Vec<&[u8]> = vec![b"component"; SIV_MAX_AAD_COMPONENTS + 1];
+        let output = siv.encrypt(&components[..SIV_MAX_AAD_COMPONENTS], b"plaintext").unwrap();
+        assert_eq!(siv.decrypt(&components[..SIV_MAX_AAD_COMPONENTS], &output).unwrap(), b"plaintext");
+        assert_eq!(siv.encrypt(&components, b"plaintext"), Err(AeadError::InvalidLength));
+        assert_eq!(siv.decrypt(&components, &output), Err(AeadError::InvalidLength));
+        let mut buffer = output[16..].to_vec();
+        assert_eq!(siv.decrypt_in_place_detached(&components, &output[..16].try_into().unwrap(), &mut buffer), Err(AeadError::InvalidLength));
+        assert_eq!(buffer, output[16..]);
+    }
+
+    #[test]
+    fn tampered_in_place() {
+        //! Test that a tampered ciphertext decrypted in place returns an error and leaves the buffer zeroed
+
+        let siv = siv("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff");
+        let output = siv.encrypt(&[b"header"], b"a plaintext longer than a block").unwrap();
+        let v: [u8; 16] = output[..16].try_into().unwrap();
+
+        let mut buffer = output[16..].to_vec();
+        siv.decrypt_in_place_detached(&[b"header"], &v, &mut buffer).unwrap();
+        assert_eq!(buffer, b"a plaintext longer than a block");
+
+        let mut buffer = output[16..].to_vec();
+        buffer[3] ^= 0x01;
+        assert_eq!(siv.decrypt_in_place_detached(&[b"header"], &v, &mut buffer), Err(AeadError::AuthenticationFailed));
+        assert_eq!(buffer, [0; 31]);
+    }
+}
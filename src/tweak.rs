@@ -0,0 +1,341 @@
+//! A module containing the building blocks of tweakable block ciphers (XEX, XTS).
+
+
+
+
+
+// DISABLED LINTS
+
+#![allow(clippy::needless_range_loop)]  // better readability
+
+
+
+
+
+// IMPORTS
+
+use crate::aes_core::{
+    AESCore,
+    LenError,
+};
+use crate::util::blocks_mut;
+
+
+
+
+
+// ENUMS
+
+/// The enum with encodings of the XTS sector number into the tweak block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum XtsTweakEncoding {
+    /// The sector number is encoded as a 128-bit little-endian integer (IEEE 1619).
+    #[default]
+    LittleEndian,
+    /// The sector number is encoded as a 128-bit big-endian integer, as used by some older disk encryption stacks.
+    BigEndian,
+}
+
+/// Public functions for the XTS tweak encoding.
+impl XtsTweakEncoding {
+    pub fn encode(&self, sector: u128) -> [u8; 16] {
+        //! Encodes the sector number into the tweak block (before it is encrypted with the tweak key).
+
+        match self {
+            XtsTweakEncoding::LittleEndian => sector.to_le_bytes(),
+            XtsTweakEncoding::BigEndian => sector.to_be_bytes(),
+        }
+    }
+}
+
+
+
+
+
+// TRAITS
+
+/// A value which can be used as a tweak.
+pub trait Tweak {
+    /// Returns the tweak as a block, before it is encrypted with the tweak key.
+    fn tweak_block(&self) -> [u8; 16];
+}
+
+impl Tweak for u128 {
+    /// The number is encoded as little-endian, as the sector number in IEEE 1619 (XTS).
+    fn tweak_block(&self) -> [u8; 16] {
+        self.to_le_bytes()
+    }
+}
+
+impl Tweak for [u8; 16] {
+    fn tweak_block(&self) -> [u8; 16] {
+        *self
+    }
+}
+
+
+
+
+
+// STRUCTS
+
+/// The XTS-AES tweakable cipher (IEEE 1619), for encrypting storage sectors (data units).
+/// Only data units of whole blocks are supported (no ciphertext stealing).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Xts {
+    /// The AES core used to encrypt the data (the first half of the XTS key).
+    core: AESCore,
+    /// The AES core used to encrypt the tweak (the second half of the XTS key).
+    tweak_core: AESCore,
+    /// The encoding of the sector number into the tweak block.
+    encoding: XtsTweakEncoding,
+}
+
+/// Public functions for encrypting and decrypting sectors with XTS.
+impl Xts {
+    pub fn new(core: AESCore, tweak_core: AESCore) -> Self {
+        //! Creates a new XTS cipher, encoding the sector numbers as little-endian (IEEE 1619).
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the data.
+        //! * `tweak_core` - The AES core used to encrypt the tweak, must be keyed differently from `core`.
+
+        Self::with_encoding(core, tweak_core, XtsTweakEncoding::default())
+    }
+
+    pub fn with_encoding(core: AESCore, tweak_core: AESCore, encoding: XtsTweakEncoding) -> Self {
+        //! Creates a new XTS cipher with the given encoding of the sector numbers.
+        //! # Arguments
+        //! * `core` - The AES core used to encrypt the data.
+        //! * `tweak_core` - The AES core used to encrypt the tweak, must be keyed differently from `core`.
+        //! * `encoding` - The encoding of the sector number into the tweak block.
+
+        Self {
+            core,
+            tweak_core,
+            encoding,
+        }
+    }
+
+    pub fn encoding(&self) -> XtsTweakEncoding {
+        //! Returns the encoding of the sector numbers.
+
+        self.encoding
+    }
+
+    pub fn encrypt_sector(&self, sector: u128, data: &mut [u8]) -> Result<(), LenError> {
+        //! Encrypts the sector in place.
+        //! # Arguments
+        //! * `sector` - The sector number (the data unit sequence number).
+        //! * `data` - The sector data, a multiple of 16 bytes long.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long.
+
+        self.process_sector(sector, data, tweaked_encrypt)
+    }
+
+    pub fn decrypt_sector(&self, sector: u128, data: &mut [u8]) -> Result<(), LenError> {
+        //! Decrypts the sector in place.
+        //! # Arguments
+        //! * `sector` - The sector number used for encryption.
+        //! * `data` - The encrypted sector data, a multiple of 16 bytes long.
+        //! # Errors
+        //! * LenError::NotBlockMultiple - The data isn't a multiple of 16 bytes long.
+
+        self.process_sector(sector, data, tweaked_decrypt)
+    }
+}
+
+/// Internal functions for encrypting and decrypting sectors with XTS.
+impl Xts {
+    fn process_sector(&self, sector: u128, data: &mut [u8], process_block: fn(&AESCore, &[u8; 16], &mut [u8; 16])) -> Result<(), LenError> {
+        //! Processes the blocks of the sector, doubling the tweak value after every block.
+
+        if !data.len().is_multiple_of(16) {
+            return Err(LenError::NotBlockMultiple);
+        }
+
+        let mut tweak_value = self.tweak_core.encrypt(&self.encoding.encode(sector));
+        for block in blocks_mut(data).0 {
+            process_block(&self.core, &tweak_value, block);
+            gf128_double(&mut tweak_value);
+        }
+
+        Ok(())
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn xex_encrypt<T: Tweak>(core: &AESCore, tweak_core: &AESCore, tweak: T, block: &mut [u8; 16]) {
+    //! Encrypts the block with the XEX (xor-encrypt-xor) construction.
+    //! The tweak is encrypted with the tweak key, XORed into the block, the block is encrypted
+    //! and the encrypted tweak is XORed into it again.
+    //! # Arguments
+    //! * `core` - The AES core used to encrypt the block.
+    //! * `tweak_core` - The AES core used to encrypt the tweak.
+    //! * `tweak` - The tweak, e.g. the sector number.
+    //! * `block` - The block to be encrypted in place.
+
+    tweaked_encrypt(core, &tweak_core.encrypt(&tweak.tweak_block()), block);
+}
+
+pub fn xex_decrypt<T: Tweak>(core: &AESCore, tweak_core: &AESCore, tweak: T, block: &mut [u8; 16]) {
+    //! Decrypts the block encrypted with `xex_encrypt`.
+    //! # Arguments
+    //! * `core` - The AES core used to decrypt the block.
+    //! * `tweak_core` - The AES core used to encrypt the tweak.
+    //! * `tweak` - The tweak used for encryption.
+    //! * `block` - The block to be decrypted in place.
+
+    tweaked_decrypt(core, &tweak_core.encrypt(&tweak.tweak_block()), block);
+}
+
+pub fn tweaked_encrypt(core: &AESCore, tweak_value: &[u8; 16], block: &mut [u8; 16]) {
+    //! Encrypts the block with an already encrypted (and possibly advanced) tweak value.
+    //! Used for the consecutive blocks of an XTS data unit, where the tweak value is advanced with `gf128_double`.
+
+    for i in 0..16 {
+        block[i] ^= tweak_value[i];
+    }
+    core.encrypt_in_place(block);
+    for i in 0..16 {
+        block[i] ^= tweak_value[i];
+    }
+}
+
+pub fn tweaked_decrypt(core: &AESCore, tweak_value: &[u8; 16], block: &mut [u8; 16]) {
+    //! Decrypts the block encrypted with `tweaked_encrypt`.
+
+    for i in 0..16 {
+        block[i] ^= tweak_value[i];
+    }
+    core.decrypt_in_place(block);
+    for i in 0..16 {
+        block[i] ^= tweak_value[i];
+    }
+}
+
+pub fn gf128_double(tweak_value: &mut [u8; 16]) {
+    //! Multiplies the tweak value by the primitive element α in GF(2^128) (IEEE 1619).
+    //! The block is interpreted as a little-endian number, reduced by x^128 + x^7 + x^2 + x + 1.
+
+    let value = u128::from_le_bytes(*tweak_value);
+    let carry = (value >> 127) as u8;
+    *tweak_value = ((value << 1) ^ (carry.wrapping_neg() as u128 & 0x87)).to_le_bytes();
+}
+
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aes_core::AESKey;
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn xex() {
+        //! Test that XEX encrypts the first block of an XTS data unit and decrypts back
+
+        let core = AESCore::new(AESKey::AES128([0x11; 16]));
+        let tweak_core = AESCore::new(AESKey::AES128([0x22; 16]));
+
+        let mut block: [u8; 16] = [0x44; 16];
+        xex_encrypt(&core, &tweak_core, 0x3333333333_u128, &mut block);
+        assert_eq!(block.to_vec(), hex("c454185e6a16936e39334038acef838b"));
+
+        let mut same_block: [u8; 16] = [0x44; 16];
+        xex_encrypt(&core, &tweak_core, (0x3333333333_u128).to_le_bytes(), &mut same_block);
+        assert_eq!(same_block, block);
+
+        xex_decrypt(&core, &tweak_core, 0x3333333333_u128, &mut block);
+        assert_eq!(block, [0x44; 16]);
+    }
+
+    #[test]
+    fn xts() {
+        //! Test that XEX composes into XTS with the IEEE 1619 test vectors 1 and 2
+
+        let zero_core = AESCore::new(AESKey::AES128([0x00; 16]));
+        let mut data: [u8; 32] = [0x00; 32];
+        Xts::new(zero_core, zero_core).encrypt_sector(0, &mut data).unwrap();
+        assert_eq!(data.to_vec(), hex("917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e"));
+
+        let core = AESCore::new(AESKey::AES128([0x11; 16]));
+        let tweak_core = AESCore::new(AESKey::AES128([0x22; 16]));
+        let xts = Xts::new(core, tweak_core);
+        let mut data: [u8; 32] = [0x44; 32];
+        xts.encrypt_sector(0x3333333333, &mut data).unwrap();
+        assert_eq!(data.to_vec(), hex("c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0"));
+
+        let mut tweak_value = tweak_core.encrypt(&0x3333333333_u128.tweak_block());
+        gf128_double(&mut tweak_value);
+        let mut block: [u8; 16] = data[16..].try_into().unwrap();
+        tweaked_decrypt(&core, &tweak_value, &mut block);
+        assert_eq!(block, [0x44; 16]);
+
+        xts.decrypt_sector(0x3333333333, &mut data).unwrap();
+        assert_eq!(data, [0x44; 32]);
+        assert_eq!(xts.encrypt_sector(0, &mut [0; 24]), Err(LenError::NotBlockMultiple));
+    }
+
+    #[test]
+    fn xts_tweak_encoding() {
+        //! Test the tweak blocks of both sector number encodings, and that the big-endian one is used for XTS
+
+        let sector: u128 = 0x0102030405;
+        assert_eq!(XtsTweakEncoding::default(), XtsTweakEncoding::LittleEndian);
+        assert_eq!(XtsTweakEncoding::LittleEndian.encode(sector).to_vec(), hex("05040302010000000000000000000000"));
+        assert_eq!(XtsTweakEncoding::BigEndian.encode(sector).to_vec(), hex("00000000000000000000000102030405"));
+        assert_eq!(XtsTweakEncoding::LittleEndian.encode(sector), sector.tweak_block());
+
+        let core = AESCore::new(AESKey::AES128([0x11; 16]));
+        let tweak_core = AESCore::new(AESKey::AES128([0x22; 16]));
+        let little_endian = Xts::new(core, tweak_core);
+        let big_endian = Xts::with_encoding(core, tweak_core, XtsTweakEncoding::BigEndian);
+        assert_eq!(little_endian.encoding(), XtsTweakEncoding::LittleEndian);
+        assert_eq!(big_endian.encoding(), XtsTweakEncoding::BigEndian);
+
+        let mut expected: [u8; 16] = [0x44; 16];
+        xex_encrypt(&core, &tweak_core, sector.to_be_bytes(), &mut expected);
+        let mut data: [u8; 16] = [0x44; 16];
+        big_endian.encrypt_sector(sector, &mut data).unwrap();
+        assert_eq!(data, expected);
+
+        let mut little_endian_data: [u8; 16] = [0x44; 16];
+        little_endian.encrypt_sector(sector, &mut little_endian_data).unwrap();
+        assert_ne!(little_endian_data, data);
+    }
+
+    #[test]
+    fn doubling() {
+        //! Test multiplying the tweak value by α, including the reduction
+
+        let mut tweak_value: [u8; 16] = [0; 16];
+        tweak_value[0] = 0x01;
+        gf128_double(&mut tweak_value);
+        assert_eq!(tweak_value[0], 0x02);
+
+        let mut tweak_value: [u8; 16] = [0; 16];
+        tweak_value[0] = 0x80;
+        gf128_double(&mut tweak_value);
+        assert_eq!(tweak_value[..2], [0x00, 0x01]);
+
+        let mut tweak_value: [u8; 16] = [0; 16];
+        tweak_value[15] = 0x80;
+        gf128_double(&mut tweak_value);
+        let mut expected: [u8; 16] = [0; 16];
+        expected[0] = 0x87;
+        assert_eq!(tweak_value, expected);
+    }
+}
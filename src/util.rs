@@ -0,0 +1,308 @@
+//! A module containing utility functions.
+
+
+
+
+
+// IMPORTS
+
+#[cfg(feature = "zeroize")]
+use std::ops::{
+    Deref,
+    DerefMut,
+};
+
+
+
+
+
+// TRAITS
+
+/// A type with secret state which can be cleared.
+/// Only the IVs, counters and buffers are cleared, the AES core holds the key and is managed separately.
+#[cfg(feature = "zeroize")]
+pub trait Zeroize {
+    /// Overwrites the secret state with zeros, which can't be optimized away.
+    /// The value is left reset, as if it was created with the same AES core and an all-zero IV (or counter block),
+    /// so it stays usable, but it must not be used to encrypt new data afterwards.
+    /// The GCM types also clear their hash subkey, so the tags they produce afterwards are invalid.
+    fn zeroize(&mut self);
+}
+
+
+
+
+
+// STRUCTS
+
+/// A wrapper which clears the value when it is dropped.
+///
+/// The modes of operation are `Copy`, so they can't implement `Drop` and clear themselves.
+/// Wrapping one moves it into a guard which isn't `Copy`, and it is used through `Deref` and `DerefMut`.
+/// Copies made from the wrapped value (e.g. with `*guard`) aren't cleared.
+#[cfg(feature = "zeroize")]
+#[derive(Debug)]
+pub struct Zeroizing<T: Zeroize>(T);
+
+/// Public functions for the clearing wrapper.
+#[cfg(feature = "zeroize")]
+impl<T: Zeroize> Zeroizing<T> {
+    pub fn new(value: T) -> Self {
+        //! Wraps the value, it is cleared once the wrapper is dropped.
+        //! # Arguments
+        //! * `value` - The value to be cleared on drop.
+
+        Self(value)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: Zeroize> Deref for Zeroizing<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: Zeroize> DerefMut for Zeroizing<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: Zeroize> Drop for Zeroizing<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+
+
+
+
+// FUNCTIONS
+
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    //! Compares the byte slices in constant time.
+    //! All bytes are always compared, so the time taken doesn't reveal where the slices differ.
+    //! Only the lengths aren't compared in constant time, slices of different lengths are unequal immediately.
+    //! It is the caller's responsibility to make sure the length isn't secret (e.g. by comparing fixed-size tags).
+    //! # Arguments
+    //! * `a` - The first slice.
+    //! * `b` - The second slice.
+    //! # Returns
+    //! * bool - Whether the slices are equal.
+
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let difference = a.iter()
+        .zip(b)
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+    difference == 0
+}
+
+pub fn wipe(buffer: &mut [u8]) {
+    //! Overwrites the buffer with zeros using volatile writes, so the compiler can't remove it as a dead store.
+    //! The modes of authenticated encryption call it on the failure path (through `verify_or_wipe`), so no unverified plaintext is left behind.
+    //! # Arguments
+    //! * `buffer` - The buffer to be cleared.
+
+    for byte in buffer.iter_mut() {
+        // SAFETY: the pointer comes from a mutable reference, so it is valid, aligned and exclusive
+        unsafe { core::ptr::write_volatile(byte, 0) };
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(feature = "zeroize")]
+pub fn zeroize<T: Copy + Default>(value: &mut T) {
+    //! Overwrites the value with its default (zeros for integers and byte arrays) using a volatile write,
+    //! so the compiler can't remove it as a dead store.
+    //! The value must be `Copy`, a heap allocation (e.g. of a `Vec`) would be leaked without being cleared.
+    //! # Arguments
+    //! * `value` - The value to be cleared.
+
+    // SAFETY: the pointer comes from a mutable reference, so it is valid, aligned and exclusive
+    unsafe { core::ptr::write_volatile(value, T::default()) };
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+pub fn blocks(data: &[u8]) -> (impl Iterator<Item = &[u8; 16]>, &[u8]) {
+    //! Splits the data into 16-byte blocks.
+    //! # Arguments
+    //! * `data` - The data to be split.
+    //! # Returns
+    //! * `impl Iterator<Item = &[u8; 16]>` - The iterator over the whole blocks.
+    //! * `&[u8]` - The trailing partial block (shorter than 16 bytes, empty if the data is a multiple of 16 bytes long).
+
+    let (blocks, remainder) = data.as_chunks::<16>();
+    (blocks.iter(), remainder)
+}
+
+pub fn blocks_mut(data: &mut [u8]) -> (impl Iterator<Item = &mut [u8; 16]>, &mut [u8]) {
+    //! Splits the data into mutable 16-byte blocks.
+    //! # Arguments
+    //! * `data` - The data to be split.
+    //! # Returns
+    //! * `impl Iterator<Item = &mut [u8; 16]>` - The iterator over the whole blocks.
+    //! * `&mut [u8]` - The trailing partial block (shorter than 16 bytes, empty if the data is a multiple of 16 bytes long).
+
+    let (blocks, remainder) = data.as_chunks_mut::<16>();
+    (blocks.iter_mut(), remainder)
+}
+
+
+#[cfg(all(test, feature = "zeroize"))]
+pub(crate) fn state_after_drop<T: Zeroize + Clone>(value: T) -> T {
+    //! Wraps the value in `Zeroizing`, drops the wrapper and returns the state the value was left in by the drop.
+    //! Used by the tests to check what the wrapper clears without reading the dropped value.
+
+    use std::cell::RefCell;
+
+    struct Recorder<'a, T: Zeroize + Clone> {
+        value: T,
+        dropped: &'a RefCell<Option<T>>,
+    }
+
+    impl<T: Zeroize + Clone> Zeroize for Recorder<'_, T> {
+        fn zeroize(&mut self) {
+            self.value.zeroize();
+            *self.dropped.borrow_mut() = Some(self.value.clone());
+        }
+    }
+
+    let dropped: RefCell<Option<T>> = RefCell::new(None);
+    drop(Zeroizing::new(Recorder { value, dropped: &dropped }));
+    dropped.into_inner().expect("the wrapper clears the value when it is dropped")
+}
+
+
+
+
+// TESTS
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq() {
+        //! Test comparing equal, unequal and different length slices
+
+        let a: [u8; 16] = [0x5a; 16];
+        let mut b: [u8; 16] = a;
+        assert!(ct_eq(&a, &b));
+        assert!(ct_eq(&[], &[]));
+
+        for i in 0..16 {
+            b[i] ^= 0x01;
+            assert!(!ct_eq(&a, &b));
+            b[i] ^= 0x01;
+        }
+        b[15] ^= 0x80;
+        assert!(!ct_eq(&a, &b));
+
+        assert!(!ct_eq(&a, &a[..15]));
+        assert!(!ct_eq(&a[..1], &[]));
+    }
+
+    #[test]
+    fn block_chunking() {
+        //! Test splitting exact multiples and slices with a trailing partial block
+
+        let data: Vec<u8> = (0..48).collect();
+        let (iter, remainder) = blocks(&data);
+        let whole_blocks: Vec<&[u8; 16]> = iter.collect();
+        assert_eq!(whole_blocks.len(), 3);
+        assert_eq!(whole_blocks[1][..], data[16..32]);
+        assert!(remainder.is_empty());
+
+        let (iter, remainder) = blocks(&data[..37]);
+        assert_eq!(iter.count(), 2);
+        assert_eq!(remainder, &data[32..37]);
+
+        let (mut iter, remainder) = blocks(&data[..15]);
+        assert!(iter.next().is_none());
+        assert_eq!(remainder.len(), 15);
+
+        let mut data: Vec<u8> = vec![0; 40];
+        let (iter, remainder) = blocks_mut(&mut data);
+        for (i, block) in iter.enumerate() {
+            block.fill(i as u8 + 1);
+        }
+        remainder.fill(0xff);
+        assert_eq!(data[..16], [1; 16]);
+        assert_eq!(data[16..32], [2; 16]);
+        assert_eq!(data[32..], [0xff; 8]);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroize_values() {
+        //! Test clearing byte arrays and integers
+
+        let mut block: [u8; 16] = [0xa5; 16];
+        zeroize(&mut block);
+        assert_eq!(block, [0; 16]);
+
+        let mut word: u128 = u128::MAX;
+        zeroize(&mut word);
+        assert_eq!(word, 0);
+    }
+
+    #[test]
+    fn wipe_buffer() {
+        //! Test that the whole buffer is overwritten with zeros
+
+        let mut buffer: Vec<u8> = (1..=40).collect();
+        wipe(&mut buffer[..10]);
+        assert_eq!(buffer[..10], [0; 10]);
+        assert_eq!(buffer[10], 11);
+        wipe(&mut buffer);
+        assert_eq!(buffer, [0; 40]);
+        wipe(&mut []);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn zeroizing_drop() {
+        //! Test that the wrapper clears the value when dropped, through a type recording its cleared state
+
+        use std::cell::Cell;
+
+        struct Secret<'a> {
+            key: [u8; 16],
+            cleared: &'a Cell<Option<[u8; 16]>>,
+        }
+
+        impl Zeroize for Secret<'_> {
+            fn zeroize(&mut self) {
+                zeroize(&mut self.key);
+                self.cleared.set(Some(self.key));
+            }
+        }
+
+        let cleared: Cell<Option<[u8; 16]>> = Cell::new(None);
+        {
+            let mut secret = Zeroizing::new(Secret { key: [0x2b; 16], cleared: &cleared });
+            secret.key[0] = 0x7e;
+            assert_eq!(secret.key[..2], [0x7e, 0x2b]);
+            assert_eq!(cleared.get(), None);
+        }
+        assert_eq!(cleared.get(), Some([0; 16]));
+
+        // a real mode of operation, cleared by dropping the wrapper
+        let core = crate::aes_core::AESCore::new(crate::aes_core::AESKey::AES128([0x2b; 16]));
+        let mut cipher = crate::modes::OfbCipher::new(core, [0x11; 16]);
+        let mut data: [u8; 20] = [0; 20];
+        cipher.apply_keystream(&mut data);
+        assert_ne!(data, [0; 20]);
+        assert_ne!(cipher, crate::modes::OfbCipher::new(core, [0; 16]));
+        assert_eq!(state_after_drop(cipher), crate::modes::OfbCipher::new(core, [0; 16]));
+    }
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use tinyaes::{
+    mct,
+    AESCore,
+    AESKey,
+    Cipher,
+    Direction,
+    Mode,
+};
+
+/// A test vector from a NIST CAVP response file.
+struct Vector {
+    /// The section of the file the vector is in, `Direction::Encrypt` for `[ENCRYPT]`.
+    direction: Direction,
+    /// The fields of the vector (e.g. `KEY`, `IV`, `PLAINTEXT`, `CIPHERTEXT`), keyed by name.
+    fields: HashMap<String, String>,
+}
+
+impl Vector {
+    fn hex(&self, name: &str) -> Vec<u8> {
+        //! Decodes the hex field with the given name.
+
+        let string = &self.fields[name];
+        (0..string.len()).step_by(2).map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap()).collect()
+    }
+
+    fn key(&self) -> AESKey {
+        //! Returns the key of the vector.
+
+        AESKey::try_from(self.hex("KEY").as_slice()).unwrap()
+    }
+
+    fn input_output(&self) -> (Vec<u8>, Vec<u8>) {
+        //! Returns the input and the expected output, the plaintext and the ciphertext in the direction of the vector.
+
+        match self.direction {
+            Direction::Encrypt => (self.hex("PLAINTEXT"), self.hex("CIPHERTEXT")),
+            Direction::Decrypt => (self.hex("CIPHERTEXT"), self.hex("PLAINTEXT")),
+        }
+    }
+}
+
+fn vectors_dir(test: &str) -> PathBuf {
+    //! Returns the directory with the response files of the test (KAT, MMT or MCT).
+
+    let name = match test {
+        "KAT" => "Known Answer Test (KAT)",
+        "MMT" => "Multiblock Message Test (MMT)",
+        "MCT" => "Monte Carlo Test (MCT)",
+        _ => unreachable!(),
+    };
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("test_vectors").join(name)
+}
+
+fn parse_rsp(path: &Path) -> Vec<Vector> {
+    //! Parses a CAVP response file: comments start with `#`, sections are `[ENCRYPT]` and `[DECRYPT]`,
+    //! and the vectors are blocks of `NAME = value` lines separated by empty lines, each starting with `COUNT`.
+
+    let mut vectors: Vec<Vector> = Vec::new();
+    let mut direction = Direction::Encrypt;
+
+    for line in fs::read_to_string(path).unwrap().lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line {
+            "[ENCRYPT]" => direction = Direction::Encrypt,
+            "[DECRYPT]" => direction = Direction::Decrypt,
+            _ => {
+                let (name, value) = line.split_once('=').unwrap_or_else(|| panic!("invalid line in {path:?}: {line}"));
+                let (name, value) = (name.trim(), value.trim());
+                if name == "COUNT" {
+                    vectors.push(Vector {
+                        direction,
+                        fields: HashMap::new(),
+                    });
+                }
+                vectors.last_mut().unwrap().fields.insert(name.to_string(), value.to_string());
+            }
+        }
+    }
+
+    vectors
+}
+
+fn rsp_files(test: &str, mode: &str) -> Vec<PathBuf> {
+    //! Returns the response files of the test for the mode (the file name prefix, e.g. `ECB`).
+    //! The prefix is followed by the test name (e.g. `GFSbox` or `MMT`),
+    //! so `CFB1` doesn't match the `CFB128` files.
+
+    let mut files: Vec<PathBuf> = fs::read_dir(vectors_dir(test))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap();
+            name.strip_prefix(mode).is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic()))
+        })
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no {test} files for {mode}");
+    files
+}
+
+fn run_mode(test: &str, file_prefix: &str, mode: Mode) -> usize {
+    //! Runs the vectors of all the response files of the test for the mode of operation.
+    //! Returns the number of vectors run.
+
+    let mut count = 0;
+    for path in rsp_files(test, file_prefix) {
+        for vector in parse_rsp(&path) {
+            let iv: Option<[u8; 16]> = vector.fields.contains_key("IV").then(|| vector.hex("IV").try_into().unwrap());
+            let (mut data, expected) = vector.input_output();
+
+            Cipher::new(AESCore::new(vector.key()), mode, vector.direction, iv).unwrap().process(&mut data).unwrap();
+            assert_eq!(data, expected, "{path:?}, {:?} COUNT = {}", vector.direction, vector.fields["COUNT"]);
+            count += 1;
+        }
+    }
+    count
+}
+
+fn ecb_mct(key: AESKey, block: [u8; 16], direction: Direction) -> (AESKey, [u8; 16]) {
+    //! Runs one outer iteration of the ECB Monte Carlo Test (AESAVS, section 6.4.1):
+    //! 1000 chained encryptions (or decryptions) with the key, and then the key update.
+    //! Returns the next key and the last output block, the input of the next iteration.
+
+    let core = AESCore::new(key);
+    let mut previous: [u8; 16] = [0; 16];
+    let mut block = block;
+    for _ in 0..1000 {
+        previous = block;
+        block = match direction {
+            Direction::Encrypt => core.encrypt(&block),
+            Direction::Decrypt => core.decrypt(&block),
+        };
+    }
+
+    (next_mct_key(key, previous, block), block)
+}
+
+fn chained_mct(key: AESKey, iv: [u8; 16], input: [u8; 16], mode: Mode, direction: Direction) -> (AESKey, [u8; 16], [u8; 16]) {
+    //! Runs one outer iteration of the Monte Carlo Test of a chained mode (CBC, OFB or CFB128, AESAVS, section 6.4.2):
+    //! 1000 blocks processed as one message, each input being the output before the previous one
+    //! (the IV for the second block), and then the key update.
+    //! Returns the next key, the last output block (also the next IV), and the output before it (the next input).
+
+    let mut cipher = Cipher::new(AESCore::new(key), mode, direction, Some(iv)).unwrap();
+    let mut input = input;
+    let mut previous: [u8; 16] = [0; 16];
+    let mut block: [u8; 16] = [0; 16];
+    for j in 0..1000 {
+        let mut output = input;
+        cipher.process(&mut output).unwrap();
+        input = if j == 0 { iv } else { block };
+        previous = block;
+        block = output;
+    }
+
+    (next_mct_key(key, previous, block), block, previous)
+}
+
+fn next_mct_key(key: AESKey, previous: [u8; 16], last: [u8; 16]) -> AESKey {
+    //! Updates the key between the outer iterations of the Monte Carlo Test:
+    //! the key is XORed with the last output bits, as many as the key has.
+
+    let mut outputs: Vec<u8> = previous.to_vec();
+    outputs.extend_from_slice(&last);
+    let mut key_bytes: Vec<u8> = match key {
+        AESKey::AES128(key) => key.to_vec(),
+        AESKey::AES192(key) => key.to_vec(),
+        AESKey::AES256(key) => key.to_vec(),
+    };
+    let outputs = &outputs[(32 - key_bytes.len())..];
+    for (key_byte, output) in key_bytes.iter_mut().zip(outputs) {
+        *key_byte ^= output;
+    }
+
+    AESKey::try_from(key_bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn parser() {
+    //! Test parsing the sections, the fields and the comments of a response file
+
+    let vectors = parse_rsp(&vectors_dir("KAT").join("ECBGFSbox128.rsp"));
+    assert_eq!(vectors.len(), 14);
+    assert_eq!(vectors.iter().filter(|vector| vector.direction == Direction::Encrypt).count(), 7);
+
+    let first = &vectors[0];
+    assert_eq!(first.fields.len(), 4);
+    assert_eq!(first.fields["COUNT"], "0");
+    assert_eq!(first.key(), AESKey::AES128([0; 16]));
+    assert_eq!(first.hex("PLAINTEXT"), [0xf3, 0x44, 0x81, 0xec, 0x3c, 0xc6, 0x27, 0xba, 0xcd, 0x5d, 0xc3, 0xfb, 0x08, 0xf2, 0x73, 0xe6]);
+
+    let last = &vectors[13];
+    assert_eq!(last.direction, Direction::Decrypt);
+    assert_eq!(last.fields["COUNT"], "6");
+    assert_eq!(last.input_output(), (last.hex("CIPHERTEXT"), last.hex("PLAINTEXT")));
+}
+
+#[test]
+fn known_answer() {
+    //! Test all the known answer vectors (GFSbox, KeySbox, VarKey and VarTxt) of the supported modes
+
+    for (prefix, mode) in [("ECB", Mode::Ecb), ("CBC", Mode::Cbc), ("OFB", Mode::Ofb), ("CFB128", Mode::Cfb)] {
+        assert!(run_mode("KAT", prefix, mode) > 1000);
+    }
+}
+
+#[test]
+fn multiblock_message() {
+    //! Test all the multiblock message vectors of the supported modes
+
+    for (prefix, mode) in [("ECB", Mode::Ecb), ("CBC", Mode::Cbc), ("OFB", Mode::Ofb), ("CFB128", Mode::Cfb)] {
+        assert_eq!(run_mode("MMT", prefix, mode), 60);
+    }
+}
+
+#[test]
+fn monte_carlo_ecb() {
+    //! Test the ECB Monte Carlo vectors, each the result of an outer iteration chained from the previous one
+
+    for path in rsp_files("MCT", "ECB") {
+        let vectors = parse_rsp(&path);
+        assert_eq!(vectors.len(), 200);
+
+        for direction in [Direction::Encrypt, Direction::Decrypt] {
+            let chain: Vec<&Vector> = vectors.iter().filter(|vector| vector.direction == direction).collect();
+            let (mut key, mut block) = (chain[0].key(), chain[0].input_output().0.try_into().unwrap());
+
+            for vector in chain {
+                assert_eq!(key, vector.key(), "{path:?}, {direction:?} COUNT = {}", vector.fields["COUNT"]);
+                (key, block) = ecb_mct(key, block, direction);
+                assert_eq!(block.to_vec(), vector.input_output().1, "{path:?}, {direction:?} COUNT = {}", vector.fields["COUNT"]);
+            }
+        }
+
+        // the whole encryption chain at once
+        let first = &vectors[0];
+        assert_eq!(mct(first.key(), first.hex("PLAINTEXT").try_into().unwrap()).to_vec(), vectors[99].hex("CIPHERTEXT"));
+    }
+}
+
+#[test]
+fn monte_carlo_chained() {
+    //! Test the Monte Carlo vectors of the chained modes, each the result of an outer iteration chained from the previous one
+
+    for (prefix, mode) in [("CBC", Mode::Cbc), ("OFB", Mode::Ofb), ("CFB128", Mode::Cfb)] {
+        for path in rsp_files("MCT", prefix) {
+            let vectors = parse_rsp(&path);
+            assert_eq!(vectors.len(), 200);
+
+            for direction in [Direction::Encrypt, Direction::Decrypt] {
+                let chain: Vec<&Vector> = vectors.iter().filter(|vector| vector.direction == direction).collect();
+                let mut key = chain[0].key();
+                let mut iv: [u8; 16] = chain[0].hex("IV").try_into().unwrap();
+                let mut input: [u8; 16] = chain[0].input_output().0.try_into().unwrap();
+
+                for vector in chain {
+                    let count = &vector.fields["COUNT"];
+                    assert_eq!(key, vector.key(), "{path:?}, {direction:?} COUNT = {count}");
+                    assert_eq!(iv.to_vec(), vector.hex("IV"), "{path:?}, {direction:?} COUNT = {count}");
+                    assert_eq!(input.to_vec(), vector.input_output().0, "{path:?}, {direction:?} COUNT = {count}");
+
+                    let output;
+                    (key, output, input) = chained_mct(key, iv, input, mode, direction);
+                    assert_eq!(output.to_vec(), vector.input_output().1, "{path:?}, {direction:?} COUNT = {count}");
+                    iv = output;
+                }
+            }
+        }
+    }
+}
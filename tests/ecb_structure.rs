@@ -0,0 +1,67 @@
+use std::collections::HashSet;
+use tinyaes::{
+    ecb_encrypt_padded,
+    AESCore,
+    AESKey,
+    Cipher,
+    Direction,
+    Mode,
+    Padding,
+    PaddingTypes,
+};
+
+fn count_repeated_blocks(data: &[u8]) -> usize {
+    //! Counts the 16-byte blocks which are repeats of an earlier block.
+    //! A high count in a ciphertext reveals the structure of the plaintext (the "ECB penguin").
+
+    let mut seen = HashSet::new();
+    data.chunks_exact(16).filter(|block| !seen.insert(*block)).count()
+}
+
+fn image() -> Vec<u8> {
+    //! Builds a tiny "image" of 64 rows, 4 blocks each, with large areas of the same color.
+
+    let background: [u8; 16] = [0xff; 16];
+    let figure: [u8; 16] = *b"penguin penguin!";
+    (0..64)
+        .flat_map(|row| (0..4).map(move |column| if (16..48).contains(&row) && (1..3).contains(&column) { figure } else { background }))
+        .flatten()
+        .collect()
+}
+
+#[test]
+fn repeated_blocks() {
+    //! Test counting the repeated blocks
+
+    assert_eq!(count_repeated_blocks(&[]), 0);
+    assert_eq!(count_repeated_blocks(&[0; 16]), 0);
+    assert_eq!(count_repeated_blocks(&[0; 48]), 2);
+    assert_eq!(count_repeated_blocks(&(0..48).collect::<Vec<u8>>()), 0);
+    assert_eq!(count_repeated_blocks(&image()), 256 - 2);
+}
+
+#[test]
+fn ecb_reveals_structure() {
+    //! Test that repeated plaintext blocks yield repeated ciphertext blocks in ECB mode, and not in CBC or CTR mode
+
+    let core = AESCore::new(AESKey::AES128(*b"YELLOW SUBMARINE"));
+    let image = image();
+
+    let mut ecb = image.clone();
+    Cipher::new(core, Mode::Ecb, Direction::Encrypt, None).unwrap().process(&mut ecb).unwrap();
+    assert_eq!(count_repeated_blocks(&ecb), count_repeated_blocks(&image));
+    for (plaintext, ciphertext) in image.chunks(16).zip(ecb.chunks(16)) {
+        assert_eq!(ciphertext, core.encrypt(plaintext.try_into().unwrap()));
+    }
+
+    // the padded variant gives the same blocks, plus the padding block
+    let padded = ecb_encrypt_padded(&core, &Padding::new(PaddingTypes::PKCS7), &image).unwrap();
+    assert_eq!(padded[..image.len()], ecb);
+    assert_eq!(count_repeated_blocks(&padded), count_repeated_blocks(&image));
+
+    for mode in [Mode::Cbc, Mode::Ctr] {
+        let mut ciphertext = image.clone();
+        Cipher::new(core, mode, Direction::Encrypt, Some([0x24; 16])).unwrap().process(&mut ciphertext).unwrap();
+        assert_eq!(count_repeated_blocks(&ciphertext), 0);
+    }
+}
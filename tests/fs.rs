@@ -0,0 +1,128 @@
+#![cfg(feature = "std")]
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tinyaes::fs::{
+    decrypt_file,
+    encrypt_file,
+};
+use tinyaes::{
+    AESCore,
+    AESKey,
+    Mode,
+    PaddingTypes,
+};
+
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("tinyaes-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn encrypt_decrypt_file() {
+    //! Test that encrypting and then decrypting a file restores its contents
+
+    let core = AESCore::new(AESKey::AES256([0x42; 32]));
+    let iv: [u8; 16] = [0x24; 16];
+
+    for (mode, padding_type) in [(Mode::Cbc, PaddingTypes::PKCS7), (Mode::Cbc, PaddingTypes::ISO78164), (Mode::Ctr, PaddingTypes::None), (Mode::Cfb, PaddingTypes::None), (Mode::Ofb, PaddingTypes::None)] {
+        for len in [0, 1, 16, 4095, 4096, 4097, 10_000] {
+            let plain_path = temp_path(&format!("{:?}-{}.plain", mode, len));
+            let encrypted_path = temp_path(&format!("{:?}-{}.enc", mode, len));
+            let decrypted_path = temp_path(&format!("{:?}-{}.dec", mode, len));
+
+            let contents: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+            fs::write(&plain_path, &contents).unwrap();
+
+            encrypt_file(core, mode, padding_type, iv, &plain_path, &encrypted_path).unwrap();
+            let encrypted = fs::read(&encrypted_path).unwrap();
+            assert_eq!(encrypted[..16], iv);
+            match mode {
+                Mode::Cbc => assert_eq!(encrypted.len(), 16 + (len / 16 + 1) * 16),
+                _ => assert_eq!(encrypted.len(), 16 + len),
+            }
+
+            decrypt_file(core, mode, padding_type, &encrypted_path, &decrypted_path).unwrap();
+            assert_eq!(fs::read(&decrypted_path).unwrap(), contents);
+
+            for path in [plain_path, encrypted_path, decrypted_path] {
+                fs::remove_file(path).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn encrypt_file_random_iv() {
+    //! Test that the generated IV is written to the file, differs between files, and the file decrypts
+
+    let core = AESCore::new(AESKey::AES128([0x42; 16]));
+    let mut rng = rand_core::UnwrapErr(getrandom::SysRng);
+    let plain_path = temp_path("random.plain");
+    let encrypted_path = temp_path("random.enc");
+    let decrypted_path = temp_path("random.dec");
+    let contents: Vec<u8> = (0..5000).map(|i| (i * 3 % 251) as u8).collect();
+    fs::write(&plain_path, &contents).unwrap();
+
+    for (mode, padding_type) in [(Mode::Cbc, PaddingTypes::PKCS7), (Mode::Ctr, PaddingTypes::None)] {
+        let iv = tinyaes::fs::encrypt_file_random_iv(core, mode, padding_type, &plain_path, &encrypted_path, &mut rng).unwrap();
+        let encrypted = fs::read(&encrypted_path).unwrap();
+        assert_eq!(encrypted[..16], iv);
+
+        // the same as encrypting with the generated IV
+        encrypt_file(core, mode, padding_type, iv, &plain_path, &decrypted_path).unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), encrypted);
+
+        let other_iv = tinyaes::fs::encrypt_file_random_iv(core, mode, padding_type, &plain_path, &encrypted_path, &mut rng).unwrap();
+        assert_ne!(other_iv, iv);
+
+        decrypt_file(core, mode, padding_type, &encrypted_path, &decrypted_path).unwrap();
+        assert_eq!(fs::read(&decrypted_path).unwrap(), contents);
+    }
+
+    let error = tinyaes::fs::encrypt_file_random_iv(core, Mode::Ecb, PaddingTypes::PKCS7, &plain_path, &encrypted_path, &mut rng).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    for path in [plain_path, encrypted_path, decrypted_path] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn invalid_files() {
+    //! Test that invalid configurations and malformed files are rejected
+
+    let core = AESCore::new(AESKey::AES128([0x42; 16]));
+    let plain_path = temp_path("invalid.plain");
+    let encrypted_path = temp_path("invalid.enc");
+    let decrypted_path = temp_path("invalid.dec");
+    fs::write(&plain_path, b"some data").unwrap();
+
+    for (mode, padding_type) in [(Mode::Ecb, PaddingTypes::PKCS7), (Mode::Cbc, PaddingTypes::None), (Mode::Cbc, PaddingTypes::ZeroPadding), (Mode::Ctr, PaddingTypes::PKCS7)] {
+        let error = encrypt_file(core, mode, padding_type, [0; 16], &plain_path, &encrypted_path).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+
+    // truncated ciphertext
+    encrypt_file(core, Mode::Cbc, PaddingTypes::PKCS7, [0; 16], &plain_path, &encrypted_path).unwrap();
+    let encrypted = fs::read(&encrypted_path).unwrap();
+    fs::write(&encrypted_path, &encrypted[..(encrypted.len() - 1)]).unwrap();
+    let error = decrypt_file(core, Mode::Cbc, PaddingTypes::PKCS7, &encrypted_path, &decrypted_path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // only the IV
+    fs::write(&encrypted_path, &encrypted[..16]).unwrap();
+    let error = decrypt_file(core, Mode::Cbc, PaddingTypes::PKCS7, &encrypted_path, &decrypted_path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // not even the IV
+    fs::write(&encrypted_path, &encrypted[..8]).unwrap();
+    let error = decrypt_file(core, Mode::Cbc, PaddingTypes::PKCS7, &encrypted_path, &decrypted_path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+    for path in [plain_path, encrypted_path, decrypted_path] {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,66 @@
+use proptest::prelude::*;
+use tinyaes::{
+    AESCore,
+    AESKey,
+    Padding,
+    PaddingTypes,
+};
+
+fn any_key() -> impl Strategy<Value = AESKey> {
+    prop_oneof![
+        any::<[u8; 16]>().prop_map(AESKey::AES128),
+        any::<[u8; 24]>().prop_map(AESKey::AES192),
+        any::<[u8; 32]>().prop_map(AESKey::AES256),
+    ]
+}
+
+fn any_padding_type() -> impl Strategy<Value = PaddingTypes> {
+    prop_oneof![
+        Just(PaddingTypes::PKCS7),
+        Just(PaddingTypes::ISO78164),
+        Just(PaddingTypes::X923),
+    ]
+}
+
+proptest! {
+    /// Test that decrypting the ciphertext restores the block
+    #[test]
+    fn encrypt_decrypt_round_trip(key in any_key(), block in any::<[u8; 16]>()) {
+        let core = AESCore::new(key);
+        let ciphertext = core.encrypt(&block);
+
+        prop_assert_eq!(core.decrypt(&ciphertext), block);
+        prop_assert_eq!(core.decrypt_eqinv(&ciphertext), block);
+    }
+
+    /// Test that removing the padding restores the input
+    #[test]
+    fn pad_de_pad_round_trip(padding_type in any_padding_type(), input in proptest::collection::vec(any::<u8>(), 0..16)) {
+        let padding = Padding::new(padding_type);
+        let padded = padding.pad(&input).unwrap();
+
+        prop_assert_eq!(&padded[..input.len()], &input[..]);
+        prop_assert_eq!(padding.de_pad(&padded).unwrap(), &input[..]);
+    }
+
+    /// Test that removing the zero padding with the known length restores the input
+    #[test]
+    fn zero_padding_round_trip(input in proptest::collection::vec(any::<u8>(), 0..16)) {
+        let padding = Padding::new(PaddingTypes::ZeroPadding);
+        let padded = padding.pad(&input).unwrap();
+
+        prop_assert_eq!(padding.de_pad_with_len(&padded, input.len()).unwrap(), &input[..]);
+    }
+
+    /// Test that two different keys don't produce the same ciphertext for the same block
+    /// (a collision is possible, but with probability 2^-128)
+    #[test]
+    fn distinct_keys_distinct_ciphertexts(key1 in any::<[u8; 16]>(), key2 in any::<[u8; 16]>(), block in any::<[u8; 16]>()) {
+        prop_assume!(key1 != key2);
+
+        let ciphertext1 = AESCore::new(AESKey::AES128(key1)).encrypt(&block);
+        let ciphertext2 = AESCore::new(AESKey::AES128(key2)).encrypt(&block);
+
+        prop_assert_ne!(ciphertext1, ciphertext2);
+    }
+}